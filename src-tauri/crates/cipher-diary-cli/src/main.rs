@@ -0,0 +1,277 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use time::format_description::well_known::Rfc3339;
+use uuid::Uuid;
+
+use cipher_diary_core::i18n::{self, Locale};
+use cipher_diary_core::validation;
+use cipher_diary_core::vault::{vault_file_path, NewEntry, UnlockResponse, VaultManager};
+
+// 供脚本和定时任务使用的命令行入口，与桌面端共用同一套日记库实现
+const PASSPHRASE_ENV: &str = "CIPHER_DIARY_PASSPHRASE";
+
+const USAGE: &str = "\
+usage: cipher-diary-cli <command> <vault-dir> [options]
+
+commands:
+  unlock <vault-dir>                    check the passphrase and print a summary
+  list <vault-dir> [--json]             list entries, newest first
+  show <vault-dir> <entry-id>           print the decrypted content of an entry
+  add <vault-dir> --title <title> [--folder <folder>] [--file <path>]
+                                        add an entry; content is read from --file or stdin
+  export <vault-dir> <output>           write a plaintext Markdown export
+  verify <vault-dir> [--json]           check the history chain and file permissions
+
+options:
+  --passphrase-file <path>              read the passphrase from a file
+
+The passphrase is taken from --passphrase-file, then the CIPHER_DIARY_PASSPHRASE
+environment variable, and otherwise from the first line of stdin.";
+
+enum Command {
+    Unlock,
+    List {
+        json: bool,
+    },
+    Show {
+        id: Uuid,
+    },
+    Add {
+        title: String,
+        folder: Option<String>,
+        file: Option<PathBuf>,
+    },
+    Export {
+        output: PathBuf,
+    },
+    Verify {
+        json: bool,
+    },
+}
+
+struct Invocation {
+    command: Command,
+    vault_dir: PathBuf,
+    passphrase_file: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    // 命令行默认跟随系统语言，非中文环境使用英文提示
+    let lang = env::var("LANG").unwrap_or_default();
+    if !lang.starts_with("zh") {
+        i18n::set_locale(Locale::En);
+    }
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    let result = parse(args).and_then(execute);
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse(args: Vec<String>) -> Result<Invocation> {
+    let mut args = args.into_iter();
+    let name = args.next().ok_or_else(|| anyhow!("missing command"))?;
+    let mut positional = Vec::new();
+    let mut json = false;
+    let mut title = None;
+    let mut folder = None;
+    let mut file = None;
+    let mut passphrase_file = None;
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .ok_or_else(|| anyhow!("{flag} requires a value"))
+        };
+        match arg.as_str() {
+            "--json" => json = true,
+            "--title" => title = Some(value("--title")?),
+            "--folder" => folder = Some(value("--folder")?),
+            "--file" => file = Some(PathBuf::from(value("--file")?)),
+            "--passphrase-file" => {
+                passphrase_file = Some(PathBuf::from(value("--passphrase-file")?))
+            }
+            flag if flag.starts_with("--") => bail!("unknown option {flag}"),
+            _ => positional.push(arg),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let vault_dir = positional
+        .next()
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow!("missing vault directory"))?;
+    let command = match name.as_str() {
+        "unlock" => Command::Unlock,
+        "list" => Command::List { json },
+        "show" => {
+            let id = positional
+                .next()
+                .ok_or_else(|| anyhow!("missing entry id"))?;
+            Command::Show {
+                id: Uuid::parse_str(&id).context("invalid entry id")?,
+            }
+        }
+        "add" => Command::Add {
+            title: title.ok_or_else(|| anyhow!("add requires --title"))?,
+            folder,
+            file,
+        },
+        "export" => Command::Export {
+            output: positional
+                .next()
+                .map(PathBuf::from)
+                .ok_or_else(|| anyhow!("missing output path"))?,
+        },
+        "verify" => Command::Verify { json },
+        other => bail!("unknown command {other}"),
+    };
+    if let Some(extra) = positional.next() {
+        bail!("unexpected argument {extra}");
+    }
+    Ok(Invocation {
+        command,
+        vault_dir,
+        passphrase_file,
+    })
+}
+
+fn execute(invocation: Invocation) -> Result<()> {
+    let mut stdin = io::stdin().lock();
+    let passphrase = read_passphrase(invocation.passphrase_file.as_deref(), &mut stdin)?;
+    let manager = VaultManager::default();
+    let response = manager.unlock(&passphrase, vault_file_path(invocation.vault_dir))?;
+    let result = dispatch(&manager, &response, invocation.command, &mut stdin);
+    // 无论命令是否成功都写回改动并清除内存中的密钥
+    manager.lock()?;
+    result
+}
+
+fn dispatch(
+    manager: &VaultManager,
+    response: &UnlockResponse,
+    command: Command,
+    stdin: &mut impl Read,
+) -> Result<()> {
+    match command {
+        Command::Unlock => {
+            println!(
+                "{}: {} entries, {}",
+                response.vault_root,
+                response.entries.len(),
+                response.last_saved.as_deref().unwrap_or("never saved")
+            );
+            Ok(())
+        }
+        Command::List { json } => list(manager, json),
+        Command::Show { id } => {
+            let entry = manager.load_entry(id)?;
+            let mut stdout = io::stdout().lock();
+            writeln!(stdout, "# {}\n", entry.title)?;
+            writeln!(stdout, "{}", entry.content)?;
+            Ok(())
+        }
+        Command::Add {
+            title,
+            folder,
+            file,
+        } => {
+            let content = match file {
+                Some(path) => fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read {}", path.display()))?,
+                None => {
+                    let mut content = String::new();
+                    stdin.read_to_string(&mut content)?;
+                    content
+                }
+            };
+            let item = NewEntry {
+                title,
+                content,
+                folder,
+                ..Default::default()
+            };
+            validation::new_entry(&item)?;
+            let created = manager
+                .create_entries(vec![item])?
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow!("entry was not created"))??;
+            println!("{}", created.id);
+            Ok(())
+        }
+        Command::Export { output } => {
+            for skipped in manager.export_plaintext_to(&output)? {
+                eprintln!("skipped {} ({}): {}", skipped.id, skipped.title, skipped.error);
+            }
+            Ok(())
+        }
+        Command::Verify { json } => {
+            let report = manager.verify_vault_integrity()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("history chain: {} records", report.history.length);
+                for issue in &report.history.issues {
+                    println!("history: {issue}");
+                }
+                for path in &report.world_readable {
+                    println!("world readable: {path}");
+                }
+                for entry in &report.unreadable_entries {
+                    println!("unreadable entry: {} ({}): {}", entry.id, entry.title, entry.error);
+                }
+            }
+            if report.valid {
+                Ok(())
+            } else {
+                Err(anyhow!("vault integrity check failed"))
+            }
+        }
+    }
+}
+
+fn list(manager: &VaultManager, json: bool) -> Result<()> {
+    let entries = manager.list()?;
+    let mut stdout = io::stdout().lock();
+    if json {
+        serde_json::to_writer_pretty(&mut stdout, &entries)?;
+        writeln!(stdout)?;
+        return Ok(());
+    }
+    for entry in entries {
+        let updated = entry.updated_at.format(&Rfc3339)?;
+        let folder = entry.folder.as_deref().unwrap_or("-");
+        writeln!(stdout, "{}\t{updated}\t{folder}\t{}", entry.id, entry.title)?;
+    }
+    Ok(())
+}
+
+fn read_passphrase(file: Option<&Path>, stdin: &mut impl BufRead) -> Result<String> {
+    let passphrase = if let Some(path) = file {
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?
+    } else if let Ok(value) = env::var(PASSPHRASE_ENV) {
+        value
+    } else {
+        let mut line = String::new();
+        stdin.read_line(&mut line)?;
+        line
+    };
+    let passphrase = passphrase.trim_end_matches(['\r', '\n']).to_string();
+    if passphrase.is_empty() {
+        bail!("no passphrase given");
+    }
+    Ok(passphrase)
+}
@@ -0,0 +1,145 @@
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Context, Result};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::envelope::{self, Kind};
+use crate::permissions;
+
+// 编辑器中尚未保存的内容：每篇正在编辑的日记一个加密暂存文件，编辑器按短间隔写入，
+// 不经过 update_entry。正常保存后删除，崩溃或断电后下次解锁时提示恢复
+pub const DRAFTS_DIR: &str = "drafts";
+const DRAFT_EXTENSION: &str = "draft";
+pub const MAX_DRAFT_BYTES: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct Draft {
+    pub entry_id: Uuid,
+    pub title: String,
+    pub content: String,
+    pub saved_at: OffsetDateTime,
+}
+
+pub fn drafts_dir(root: &Path) -> PathBuf {
+    root.join(DRAFTS_DIR)
+}
+
+fn draft_path(root: &Path, entry_id: &Uuid) -> PathBuf {
+    drafts_dir(root).join(format!("{entry_id}.{DRAFT_EXTENSION}"))
+}
+
+// 先写临时文件再改名，写到一半断电时保留上一份完整的暂存
+pub fn write(root: &Path, key: &[u8; 32], draft: &Draft) -> Result<()> {
+    let plaintext = serde_json::to_vec(draft).context("failed to serialize draft")?;
+    if plaintext.len() > MAX_DRAFT_BYTES {
+        return Err(anyhow!("draft too large"));
+    }
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| anyhow!("invalid key"))?;
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(&nonce_bytes);
+    // 日记 id 作为附加数据，暂存文件不能被改名冒充其他日记
+    let ciphertext = cipher
+        .encrypt(
+            nonce,
+            Payload {
+                msg: &plaintext,
+                aad: draft.entry_id.as_bytes(),
+            },
+        )
+        .map_err(|_| anyhow!("encryption failed"))?;
+
+    let dir = drafts_dir(root);
+    permissions::create_private_dir_all(&dir)?;
+    let path = draft_path(root, &draft.entry_id);
+    let temp = path.with_extension("tmp");
+    permissions::write_private(
+        &temp,
+        envelope::encode(Kind::Draft, &[&nonce_bytes, &ciphertext]),
+    )
+    .context("failed to write draft")?;
+    fs::rename(&temp, &path).context("failed to write draft")
+}
+
+fn read(path: &Path, key: &[u8; 32], entry_id: &Uuid) -> Result<Draft> {
+    let bytes = fs::read(path).context("failed to read draft")?;
+    let fields = envelope::decode(&bytes, Kind::Draft, 2).context("failed to parse draft")?;
+    if fields[0].len() != 12 {
+        return Err(anyhow!("invalid nonce length"));
+    }
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| anyhow!("invalid key"))?;
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(fields[0]);
+    let plaintext = cipher
+        .decrypt(
+            nonce,
+            Payload {
+                msg: fields[1],
+                aad: entry_id.as_bytes(),
+            },
+        )
+        .map_err(|_| anyhow!("decryption failed"))?;
+    let draft: Draft = serde_json::from_slice(&plaintext).context("failed to parse draft")?;
+    if draft.entry_id != *entry_id {
+        return Err(anyhow!("draft does not match its file"));
+    }
+    Ok(draft)
+}
+
+// 全部可读的暂存，无法解密的文件跳过
+pub fn list(root: &Path, key: &[u8; 32]) -> Result<Vec<Draft>> {
+    let mut drafts = Vec::new();
+    for (entry_id, path) in files(root)? {
+        match read(&path, key, &entry_id) {
+            Ok(draft) => drafts.push(draft),
+            Err(err) => tracing::warn!(%entry_id, error = %err, "skipping unreadable draft"),
+        }
+    }
+    drafts.sort_by_key(|draft| draft.saved_at);
+    Ok(drafts)
+}
+
+// 修改密码更换主密钥后用新密钥重新加密全部暂存
+pub fn rekey(root: &Path, old_key: &[u8; 32], new_key: &[u8; 32]) -> Result<()> {
+    for draft in list(root, old_key)? {
+        write(root, new_key, &draft)?;
+    }
+    Ok(())
+}
+
+pub fn remove(root: &Path, entry_id: &Uuid) -> Result<()> {
+    match fs::remove_file(draft_path(root, entry_id)) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err).context("failed to remove draft"),
+        _ => Ok(()),
+    }
+}
+
+fn files(root: &Path) -> Result<Vec<(Uuid, PathBuf)>> {
+    let dir = drafts_dir(root);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut found = Vec::new();
+    for item in fs::read_dir(&dir).context("failed to read drafts directory")? {
+        let path = item.context("failed to read drafts directory")?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(DRAFT_EXTENSION) {
+            continue;
+        }
+        let entry_id = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| Uuid::parse_str(stem).ok());
+        if let Some(entry_id) = entry_id {
+            found.push((entry_id, path));
+        }
+    }
+    Ok(found)
+}
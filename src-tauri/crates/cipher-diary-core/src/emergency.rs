@@ -0,0 +1,496 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use sha2::Sha256;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use time::macros::format_description;
+use time::{Duration, OffsetDateTime};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use crate::i18n::Message;
+use crate::permissions;
+use crate::quick_unlock::vault_id;
+use crate::vault::Entry;
+
+const EMERGENCY_DIR: &str = "emergency";
+const STATE_FILE: &str = "state.json";
+const STAGED_FILE: &str = "pending.cdea";
+const ARCHIVE_MAGIC: &[u8] = b"CDEMERG1";
+const STAGED_MAGIC: &[u8] = b"CDEMPND1";
+const HKDF_INFO: &[u8] = b"cipher-diary emergency archive v1";
+const RELEASE_KEY_INFO: &[u8] = b"cipher-diary emergency release v1";
+const STATE_KEY_INFO: &[u8] = b"cipher-diary emergency state v1";
+const KEYRING_SERVICE: &str = "cipher-diary-emergency";
+const MAX_DELAY_DAYS: u32 = 3650;
+// 锁定时重新生成归档需要解密整个日记库，最多每天一次
+const RESTAGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+// 保存在加密元数据中的配置，收件人公钥以此为准，防止被外部篡改
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmergencyAccess {
+    pub recipient_public_key: String,
+    pub delay_days: u32,
+    pub destination: String,
+    // 发布凭据，派生出待发布归档的外层密钥和计时状态的校验密钥；
+    // 只保存在加密元数据和本机钥匙串中，日记目录里的文件无法单独解开归档
+    #[serde(default)]
+    pub secret: String,
+}
+
+// 放在密文之外的计时状态，锁定时也需要读取以判断是否到期；带 HMAC，改动后不再放行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EmergencyState {
+    delay_days: u32,
+    destination: String,
+    last_check_in: OffsetDateTime,
+    released_at: Option<OffsetDateTime>,
+    #[serde(default)]
+    mac: String,
+}
+
+// 本机钥匙串中的发布凭据与最近一次签到时间。把 state.json 换回旧版本也不能提前发布
+#[derive(Serialize, Deserialize)]
+struct Gate {
+    secret: String,
+    last_check_in: OffsetDateTime,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct EmergencyStatus {
+    pub configured: bool,
+    pub delay_days: Option<u32>,
+    pub destination: Option<String>,
+    pub last_check_in: Option<OffsetDateTime>,
+    pub release_due_at: Option<OffsetDateTime>,
+    pub released_at: Option<OffsetDateTime>,
+    pub released_path: Option<String>,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct EmergencyKeypair {
+    pub public_key: String,
+    pub secret_key: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecoveryPayload {
+    pub created_at: OffsetDateTime,
+    pub entries: Vec<Entry>,
+    pub attachments: Vec<RecoveryAttachment>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecoveryAttachment {
+    pub path: String,
+    pub data: String,
+}
+
+impl EmergencyAccess {
+    pub fn new(recipient_public_key: &str, delay_days: u32, destination: &str) -> Result<Self> {
+        decode_key(recipient_public_key).context("invalid recipient public key")?;
+        if delay_days == 0 || delay_days > MAX_DELAY_DAYS {
+            return Err(anyhow!(Message::DelayDaysOutOfRange(MAX_DELAY_DAYS)));
+        }
+        let destination = destination.trim();
+        if destination.is_empty() {
+            return Err(anyhow!(Message::EmergencyDestinationMissing));
+        }
+        Ok(Self {
+            recipient_public_key: recipient_public_key.trim().to_string(),
+            delay_days,
+            destination: destination.to_string(),
+            secret: new_secret(),
+        })
+    }
+}
+
+pub fn new_secret() -> String {
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    general_purpose::STANDARD_NO_PAD.encode(secret)
+}
+
+pub fn emergency_dir(root: &Path) -> PathBuf {
+    root.join(EMERGENCY_DIR)
+}
+
+fn decode_key(encoded: &str) -> Result<[u8; 32]> {
+    let bytes = general_purpose::STANDARD_NO_PAD
+        .decode(encoded.trim().trim_end_matches('='))
+        .context("invalid key encoding")?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("invalid key length"))
+}
+
+fn read_state(root: &Path) -> Result<Option<EmergencyState>> {
+    let path = emergency_dir(root).join(STATE_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).context("failed to read emergency state")?;
+    let state = serde_json::from_str(&content).context("failed to parse emergency state")?;
+    Ok(Some(state))
+}
+
+fn write_state(root: &Path, secret: &str, state: &EmergencyState) -> Result<()> {
+    let dir = emergency_dir(root);
+    permissions::create_private_dir_all(&dir)?;
+    let state = EmergencyState {
+        mac: state_mac(secret, state)?,
+        ..state.clone()
+    };
+    let serialized =
+        serde_json::to_string_pretty(&state).context("failed to serialize emergency state")?;
+    permissions::write_private(&dir.join(STATE_FILE), serialized)
+}
+
+fn derive_subkey(secret: &str, info: &[u8]) -> Result<[u8; 32]> {
+    let seed = decode_key(secret).context("invalid emergency secret")?;
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(None, &seed)
+        .expand(info, &mut key)
+        .map_err(|_| anyhow!("failed to derive emergency key"))?;
+    Ok(key)
+}
+
+// 覆盖除 mac 以外的全部字段
+fn state_hmac(secret: &str, state: &EmergencyState) -> Result<Hmac<Sha256>> {
+    let key = derive_subkey(secret, STATE_KEY_INFO)?;
+    let fields = serde_json::to_vec(&(
+        state.delay_days,
+        &state.destination,
+        state.last_check_in,
+        state.released_at,
+    ))
+    .context("failed to serialize emergency state")?;
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(&key).map_err(|_| anyhow!("invalid key"))?;
+    mac.update(&fields);
+    Ok(mac)
+}
+
+fn state_mac(secret: &str, state: &EmergencyState) -> Result<String> {
+    let tag = state_hmac(secret, state)?.finalize().into_bytes();
+    Ok(general_purpose::STANDARD_NO_PAD.encode(tag))
+}
+
+fn verify_state(secret: &str, state: &EmergencyState) -> Result<()> {
+    let tag = general_purpose::STANDARD_NO_PAD
+        .decode(&state.mac)
+        .map_err(|_| anyhow!(Message::EmergencyStateTampered))?;
+    state_hmac(secret, state)?
+        .verify_slice(&tag)
+        .map_err(|_| anyhow!(Message::EmergencyStateTampered))
+}
+
+fn keyring_entry(root: &Path) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, &vault_id(root)).context(Message::KeychainUnavailable)
+}
+
+fn read_gate(root: &Path) -> Result<Gate> {
+    let secret = keyring_entry(root)?
+        .get_secret()
+        .map_err(|_| anyhow!(Message::EmergencyKeyUnavailable))?;
+    serde_json::from_slice(&secret).map_err(|_| anyhow!(Message::EmergencyKeyUnavailable))
+}
+
+// 写入后用新的条目读回：没有持久钥匙串的平台上紧急访问无法在锁定时发布，直接报错
+fn write_gate(root: &Path, gate: &Gate) -> Result<()> {
+    let serialized = serde_json::to_vec(gate).context("failed to serialize emergency key")?;
+    keyring_entry(root)?
+        .set_secret(&serialized)
+        .context(Message::KeychainWriteFailed)?;
+    match keyring_entry(root)?.get_secret() {
+        Ok(stored) if stored == serialized => Ok(()),
+        _ => Err(anyhow!(Message::KeychainUnavailable)),
+    }
+}
+
+pub fn generate_keypair() -> EmergencyKeypair {
+    let secret = StaticSecret::random_from_rng(OsRng);
+    let public = PublicKey::from(&secret);
+    EmergencyKeypair {
+        public_key: general_purpose::STANDARD_NO_PAD.encode(public.as_bytes()),
+        secret_key: general_purpose::STANDARD_NO_PAD.encode(secret.to_bytes()),
+    }
+}
+
+fn archive_key(shared: &[u8; 32], ephemeral: &[u8; 32], recipient: &[u8; 32]) -> Result<[u8; 32]> {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral);
+    salt[32..].copy_from_slice(recipient);
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(HKDF_INFO, &mut key)
+        .map_err(|_| anyhow!("failed to derive archive key"))?;
+    Ok(key)
+}
+
+// 归档格式：魔数 + 临时公钥(32) + nonce(12) + AES-256-GCM 密文
+fn seal(recipient_public_key: &str, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let recipient = decode_key(recipient_public_key)?;
+    let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+    let ephemeral_public = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(&PublicKey::from(recipient));
+    let key = archive_key(shared.as_bytes(), ephemeral_public.as_bytes(), &recipient)?;
+
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| anyhow!("invalid key"))?;
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(&nonce_bytes);
+    let ciphertext = cipher
+        .encrypt(nonce, plaintext)
+        .map_err(|_| anyhow!("encryption failed"))?;
+
+    let mut sealed = Vec::with_capacity(ARCHIVE_MAGIC.len() + 44 + ciphertext.len());
+    sealed.extend_from_slice(ARCHIVE_MAGIC);
+    sealed.extend_from_slice(ephemeral_public.as_bytes());
+    sealed.extend_from_slice(&nonce_bytes);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn unseal(secret_key: &str, sealed: &[u8]) -> Result<Vec<u8>> {
+    let header = ARCHIVE_MAGIC.len() + 32 + 12;
+    if sealed.len() < header || !sealed.starts_with(ARCHIVE_MAGIC) {
+        return Err(anyhow!("not an emergency archive"));
+    }
+    let secret = StaticSecret::from(decode_key(secret_key).context("invalid secret key")?);
+    let recipient = PublicKey::from(&secret);
+
+    let mut offset = ARCHIVE_MAGIC.len();
+    let mut ephemeral = [0u8; 32];
+    ephemeral.copy_from_slice(&sealed[offset..offset + 32]);
+    offset += 32;
+    let nonce_bytes = &sealed[offset..offset + 12];
+    offset += 12;
+
+    let shared = secret.diffie_hellman(&PublicKey::from(ephemeral));
+    let key = archive_key(shared.as_bytes(), &ephemeral, recipient.as_bytes())?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| anyhow!("invalid key"))?;
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(nonce_bytes);
+    cipher
+        .decrypt(nonce, &sealed[offset..])
+        .map_err(|_| anyhow!("decryption failed"))
+}
+
+// 待发布归档外再套一层发布凭据派生的密钥，到期前日记目录中只有无法解开的密文
+fn wrap_staged(secret: &str, sealed: &[u8]) -> Result<Vec<u8>> {
+    let key = derive_subkey(secret, RELEASE_KEY_INFO)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| anyhow!("invalid key"))?;
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(&nonce_bytes);
+    let ciphertext = cipher
+        .encrypt(nonce, sealed)
+        .map_err(|_| anyhow!("encryption failed"))?;
+
+    let mut staged = Vec::with_capacity(STAGED_MAGIC.len() + 12 + ciphertext.len());
+    staged.extend_from_slice(STAGED_MAGIC);
+    staged.extend_from_slice(&nonce_bytes);
+    staged.extend_from_slice(&ciphertext);
+    Ok(staged)
+}
+
+fn unwrap_staged(secret: &str, staged: &[u8]) -> Result<Vec<u8>> {
+    let header = STAGED_MAGIC.len() + 12;
+    if staged.len() < header || !staged.starts_with(STAGED_MAGIC) {
+        return Err(anyhow!("invalid staged emergency archive"));
+    }
+    let key = derive_subkey(secret, RELEASE_KEY_INFO)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| anyhow!("invalid key"))?;
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(&staged[STAGED_MAGIC.len()..header]);
+    cipher
+        .decrypt(nonce, &staged[header..])
+        .map_err(|_| anyhow!("invalid staged emergency archive"))
+}
+
+// 重新生成待发布的归档，并刷新计时状态（保留已有的签到时间）
+pub fn stage(root: &Path, access: &EmergencyAccess, payload: &RecoveryPayload) -> Result<()> {
+    let plaintext = serde_json::to_vec(payload).context("failed to serialize recovery payload")?;
+    let sealed = seal(&access.recipient_public_key, &plaintext)?;
+    let staged = wrap_staged(&access.secret, &sealed)?;
+
+    let dir = emergency_dir(root);
+    permissions::create_private_dir_all(&dir)?;
+    permissions::write_private(&dir.join(STAGED_FILE), staged)?;
+
+    // 校验不通过的旧状态不予采信
+    let previous = read_state(root)?.filter(|state| verify_state(&access.secret, state).is_ok());
+    let last_check_in = previous
+        .as_ref()
+        .map(|state| state.last_check_in)
+        .unwrap_or_else(OffsetDateTime::now_utc);
+    write_gate(
+        root,
+        &Gate {
+            secret: access.secret.clone(),
+            last_check_in,
+        },
+    )?;
+    write_state(
+        root,
+        &access.secret,
+        &EmergencyState {
+            delay_days: access.delay_days,
+            destination: access.destination.clone(),
+            last_check_in,
+            released_at: previous.and_then(|state| state.released_at),
+            mac: String::new(),
+        },
+    )
+}
+
+// 待发布的归档不存在，或距上次生成已超过一天
+pub fn restage_due(root: &Path) -> bool {
+    let staged = emergency_dir(root).join(STAGED_FILE);
+    fs::metadata(staged)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_none_or(|age| age >= RESTAGE_INTERVAL)
+}
+
+// 删除尚未套上发布密钥的旧版归档，下次锁定时重新生成
+pub fn discard_staged(root: &Path) -> Result<()> {
+    let staged = emergency_dir(root).join(STAGED_FILE);
+    if staged.exists() {
+        fs::remove_file(&staged).context("failed to remove staged emergency archive")?;
+    }
+    Ok(())
+}
+
+// 所有者解锁即视为签到，重置等待期；计时参数以加密元数据中的配置为准
+pub fn check_in(root: &Path, access: &EmergencyAccess) -> Result<()> {
+    let Some(mut state) = read_state(root)? else {
+        return Ok(());
+    };
+    let now = OffsetDateTime::now_utc();
+    state.delay_days = access.delay_days;
+    state.destination = access.destination.clone();
+    state.last_check_in = now;
+    state.released_at = None;
+    write_gate(
+        root,
+        &Gate {
+            secret: access.secret.clone(),
+            last_check_in: now,
+        },
+    )?;
+    write_state(root, &access.secret, &state)
+}
+
+pub fn cancel(root: &Path) -> Result<()> {
+    let dir = emergency_dir(root);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).context("failed to remove emergency access data")?;
+    }
+    if let Ok(entry) = keyring_entry(root) {
+        let _ = entry.delete_credential();
+    }
+    Ok(())
+}
+
+fn released_file_name(state: &EmergencyState) -> String {
+    let date_fmt = format_description!("[year]-[month]-[day]");
+    let date = state
+        .released_at
+        .and_then(|ts| ts.format(&date_fmt).ok())
+        .unwrap_or_else(|| "pending".into());
+    format!("cipher-diary-emergency-{date}.cdea")
+}
+
+// 仅供显示，发布前由 process 校验
+pub fn status(root: &Path) -> Result<EmergencyStatus> {
+    let state = read_state(root)?;
+    Ok(EmergencyStatus {
+        configured: state.is_some(),
+        delay_days: state.as_ref().map(|state| state.delay_days),
+        destination: state.as_ref().map(|state| state.destination.clone()),
+        last_check_in: state.as_ref().map(|state| state.last_check_in),
+        release_due_at: state
+            .as_ref()
+            .map(|state| state.last_check_in + Duration::days(state.delay_days.into())),
+        released_at: state.as_ref().and_then(|state| state.released_at),
+        released_path: state.as_ref().filter(|state| state.released_at.is_some()).map(|state| {
+            Path::new(&state.destination)
+                .join(released_file_name(state))
+                .to_string_lossy()
+                .into_owned()
+        }),
+    })
+}
+
+// 锁定状态下也可调用：等待期已过且尚未发布时，用钥匙串中的发布凭据解开外层，
+// 把交给受托人的归档写到指定目录。状态文件校验不通过或本机没有凭据时拒绝发布
+pub fn process(root: &Path) -> Result<EmergencyStatus> {
+    if let Some(mut state) = read_state(root)? {
+        let gate = read_gate(root)?;
+        verify_state(&gate.secret, &state)?;
+        let last_check_in = state.last_check_in.max(gate.last_check_in);
+        let due = last_check_in + Duration::days(state.delay_days.into());
+        if state.released_at.is_none() && OffsetDateTime::now_utc() >= due {
+            let staged = emergency_dir(root).join(STAGED_FILE);
+            if !staged.exists() {
+                return Err(anyhow!("emergency archive is missing"));
+            }
+            let staged = fs::read(&staged).context("failed to read emergency archive")?;
+            let sealed = unwrap_staged(&gate.secret, &staged)?;
+            let destination = PathBuf::from(&state.destination);
+            fs::create_dir_all(&destination)
+                .context("failed to prepare emergency destination")?;
+            state.released_at = Some(OffsetDateTime::now_utc());
+            fs::write(destination.join(released_file_name(&state)), sealed)
+                .context("failed to release emergency archive")?;
+            write_state(root, &gate.secret, &state)?;
+        }
+    }
+    status(root)
+}
+
+// 供受托人使用：用私钥解开归档，写出 Markdown 与附件
+pub fn open_archive(archive: &Path, secret_key: &str, output_dir: &Path) -> Result<PathBuf> {
+    let sealed = fs::read(archive).context("failed to read emergency archive")?;
+    let plaintext = unseal(secret_key, &sealed)?;
+    let payload: RecoveryPayload =
+        serde_json::from_slice(&plaintext).context("invalid recovery payload")?;
+
+    fs::create_dir_all(output_dir).context("failed to create output directory")?;
+    for attachment in payload.attachments.iter() {
+        let relative = Path::new(&attachment.path);
+        if relative.is_absolute() || relative.components().any(|part| part.as_os_str() == "..") {
+            return Err(anyhow!("invalid attachment path in archive"));
+        }
+        let target = output_dir.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).context("failed to create attachment directory")?;
+        }
+        let data = general_purpose::STANDARD_NO_PAD
+            .decode(&attachment.data)
+            .context("invalid attachment encoding")?;
+        fs::write(&target, data).context("failed to write attachment")?;
+    }
+
+    let markdown = payload
+        .entries
+        .iter()
+        .map(|entry| entry.to_markdown(None))
+        .collect::<Vec<_>>()
+        .join("\n---\n\n");
+    let target = output_dir.join("diary.md");
+    fs::write(&target, markdown).context("failed to write recovered entries")?;
+    Ok(target)
+}
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::i18n::Message;
+
+// 引导式日记：结构化内容与正文一起加密保存在条目文件中；
+// 正文由结构化内容生成，搜索、摘要和导出都直接使用正文
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryType {
+    // 普通日记，没有结构化内容
+    #[default]
+    Note,
+    Gratitude,
+    Dream,
+    Review,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewPeriod {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EntryPayload {
+    Gratitude {
+        items: Vec<String>,
+    },
+    Dream {
+        #[serde(default)]
+        story: String,
+        #[serde(default)]
+        lucid: bool,
+        #[serde(default)]
+        recurring: bool,
+        // 梦里出现的人、地点或意象
+        #[serde(default)]
+        symbols: Vec<String>,
+    },
+    Review {
+        period: ReviewPeriod,
+        #[serde(default)]
+        went_well: Vec<String>,
+        #[serde(default)]
+        to_improve: Vec<String>,
+        #[serde(default)]
+        next_steps: Vec<String>,
+    },
+}
+
+impl EntryPayload {
+    pub fn entry_type(&self) -> EntryType {
+        match self {
+            EntryPayload::Gratitude { .. } => EntryType::Gratitude,
+            EntryPayload::Dream { .. } => EntryType::Dream,
+            EntryPayload::Review { .. } => EntryType::Review,
+        }
+    }
+
+    // 未填写标题时使用
+    pub fn default_title(&self) -> String {
+        match self {
+            EntryPayload::Review { period, .. } => Message::ReviewTitle(*period).to_string(),
+            _ => Message::EntryTypeName(self.entry_type()).to_string(),
+        }
+    }
+
+    // 生成的正文按当前语言写入小标题，之后切换语言不会改写
+    pub fn to_markdown(&self) -> String {
+        let mut sections = Vec::new();
+        match self {
+            EntryPayload::Gratitude { items } => {
+                sections.push(format!("## {}", Message::GratitudeHeading));
+                sections.push(numbered(items));
+            }
+            EntryPayload::Dream {
+                story,
+                lucid,
+                recurring,
+                symbols,
+            } => {
+                if !story.trim().is_empty() {
+                    sections.push(story.trim().to_string());
+                }
+                let flags: Vec<String> = [
+                    (*lucid, Message::DreamLucid),
+                    (*recurring, Message::DreamRecurring),
+                ]
+                .into_iter()
+                .filter(|(set, _)| *set)
+                .map(|(_, label)| format!("- {label}"))
+                .collect();
+                if !flags.is_empty() {
+                    sections.push(flags.join("\n"));
+                }
+                if !symbols.is_empty() {
+                    sections.push(format!("{}{}", Message::DreamSymbols, symbols.join(", ")));
+                }
+            }
+            EntryPayload::Review {
+                went_well,
+                to_improve,
+                next_steps,
+                ..
+            } => {
+                let parts = [
+                    (Message::ReviewWentWell, went_well),
+                    (Message::ReviewToImprove, to_improve),
+                    (Message::ReviewNextSteps, next_steps),
+                ];
+                for (heading, items) in parts {
+                    if !items.is_empty() {
+                        sections.push(format!("## {heading}\n\n{}", bullets(items)));
+                    }
+                }
+            }
+        }
+        sections.join("\n\n")
+    }
+
+    // 校验时逐项检查的列表
+    pub fn items(&self) -> Vec<&String> {
+        match self {
+            EntryPayload::Gratitude { items } => items.iter().collect(),
+            EntryPayload::Dream { symbols, .. } => symbols.iter().collect(),
+            EntryPayload::Review {
+                went_well,
+                to_improve,
+                next_steps,
+                ..
+            } => went_well
+                .iter()
+                .chain(to_improve)
+                .chain(next_steps)
+                .collect(),
+        }
+    }
+}
+
+fn numbered(items: &[String]) -> String {
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| format!("{}. {}", index + 1, item.trim()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn bullets(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| format!("- {}", item.trim()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,77 @@
+use anyhow::{anyhow, Result};
+
+// 二进制存储封装：魔数 + 封装版本 + 类型 + 若干带长度前缀的字段，
+// 取代 JSON + base64，体积更小且无需解析文本
+const MAGIC: &[u8] = b"CDENV";
+const ENVELOPE_VERSION: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Kind {
+    Vault = 1,
+    Entry = 2,
+    SearchIndex = 3,
+    Draft = 4,
+    Image = 5,
+}
+
+// 旧格式是 JSON 文本，以 '{' 开头，不会与魔数冲突
+pub fn is_envelope(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+pub fn is_kind(bytes: &[u8], kind: Kind) -> bool {
+    is_envelope(bytes) && bytes.get(MAGIC.len() + 1) == Some(&(kind as u8))
+}
+
+pub fn encode(kind: Kind, fields: &[&[u8]]) -> Vec<u8> {
+    let body: usize = fields.iter().map(|field| 4 + field.len()).sum();
+    let mut out = Vec::with_capacity(MAGIC.len() + 2 + body);
+    out.extend_from_slice(MAGIC);
+    out.push(ENVELOPE_VERSION);
+    out.push(kind as u8);
+    for field in fields {
+        out.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        out.extend_from_slice(field);
+    }
+    out
+}
+
+pub fn decode(bytes: &[u8], kind: Kind, field_count: usize) -> Result<Vec<&[u8]>> {
+    let fields = decode_fields(bytes, kind)?;
+    if fields.len() != field_count {
+        return Err(anyhow!("invalid storage envelope"));
+    }
+    Ok(fields)
+}
+
+// 不检查字段数，供后来追加了字段、需要兼容旧文件的类型使用
+pub fn decode_fields(bytes: &[u8], kind: Kind) -> Result<Vec<&[u8]>> {
+    let header = MAGIC.len() + 2;
+    if bytes.len() < header || !is_envelope(bytes) {
+        return Err(anyhow!("invalid storage envelope"));
+    }
+    if bytes[MAGIC.len()] != ENVELOPE_VERSION {
+        return Err(anyhow!("unsupported storage envelope version"));
+    }
+    if bytes[MAGIC.len() + 1] != kind as u8 {
+        return Err(anyhow!("unexpected storage envelope type"));
+    }
+
+    let mut fields = Vec::new();
+    let mut rest = &bytes[header..];
+    while !rest.is_empty() {
+        if rest.len() < 4 {
+            return Err(anyhow!("truncated storage envelope"));
+        }
+        let (len, tail) = rest.split_at(4);
+        let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        if tail.len() < len {
+            return Err(anyhow!("truncated storage envelope"));
+        }
+        let (field, tail) = tail.split_at(len);
+        fields.push(field);
+        rest = tail;
+    }
+    Ok(fields)
+}
@@ -0,0 +1,111 @@
+use serde::Serialize;
+use specta::Type;
+use std::io::ErrorKind;
+
+use crate::i18n::Message;
+use crate::vault::{
+    Conflict, DecryptionFailed, EntryNotFound, QuotaExceeded, VaultLocked, VaultNotFound,
+};
+
+// 所有命令返回给前端的错误，序列化为 { code, message }：code 供程序判断，message 可直接显示
+#[derive(Debug, Serialize, thiserror::Error, Type)]
+#[serde(tag = "code", content = "message", rename_all = "snake_case")]
+pub enum VaultError {
+    #[error("{0}")]
+    Locked(String),
+    #[error("{0}")]
+    WrongPassphrase(String),
+    #[error("{0}")]
+    VaultNotFound(String),
+    #[error("{0}")]
+    EntryNotFound(String),
+    #[error("{0}")]
+    Io(String),
+    #[error("{0}")]
+    Corrupted(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    QuotaExceeded(String),
+    #[error("{0}")]
+    InvalidInput(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl VaultError {
+    pub fn invalid(message: Message) -> Self {
+        VaultError::InvalidInput(message.to_string())
+    }
+
+    pub fn conflict(message: Message) -> Self {
+        VaultError::Conflict(message.to_string())
+    }
+
+    pub fn other(message: impl Into<String>) -> Self {
+        VaultError::Other(message.into())
+    }
+
+    // 与序列化后的 code 相同；日志只记录它，消息可能带有标题等日记内容
+    pub fn code(&self) -> &'static str {
+        match self {
+            VaultError::Locked(_) => "locked",
+            VaultError::WrongPassphrase(_) => "wrong_passphrase",
+            VaultError::VaultNotFound(_) => "vault_not_found",
+            VaultError::EntryNotFound(_) => "entry_not_found",
+            VaultError::Io(_) => "io",
+            VaultError::Corrupted(_) => "corrupted",
+            VaultError::Conflict(_) => "conflict",
+            VaultError::QuotaExceeded(_) => "quota_exceeded",
+            VaultError::InvalidInput(_) => "invalid_input",
+            VaultError::Other(_) => "other",
+        }
+    }
+}
+
+// 按错误链中的标记类型归类。消息优先取链中的目录文字，其次是错误码的通用说明；
+// 没有目录文字的底层错误附上原始说明，便于排查
+impl From<anyhow::Error> for VaultError {
+    fn from(err: anyhow::Error) -> Self {
+        let localized = err.downcast_ref::<Message>().map(ToString::to_string);
+        let known = |fallback: &Message| localized.clone().unwrap_or_else(|| fallback.to_string());
+        let detailed = |fallback: Message| {
+            localized.clone().unwrap_or_else(|| format!("{fallback}: {err}"))
+        };
+        for cause in err.chain() {
+            if cause.is::<VaultLocked>() {
+                return VaultError::Locked(known(&Message::VaultLocked));
+            }
+            if cause.is::<DecryptionFailed>() {
+                return VaultError::WrongPassphrase(known(&Message::WrongPassphrase));
+            }
+            if cause.is::<VaultNotFound>() {
+                return VaultError::VaultNotFound(known(&Message::VaultNotFound));
+            }
+            if cause.is::<EntryNotFound>() {
+                return VaultError::EntryNotFound(known(&Message::EntryNotFound));
+            }
+            if let Some(Conflict(message)) = cause.downcast_ref::<Conflict>() {
+                return VaultError::Conflict(known(message));
+            }
+            if let Some(QuotaExceeded(message)) = cause.downcast_ref::<QuotaExceeded>() {
+                return VaultError::QuotaExceeded(known(message));
+            }
+            if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+                return match io.kind() {
+                    ErrorKind::StorageFull => {
+                        VaultError::QuotaExceeded(detailed(Message::StorageFull))
+                    }
+                    _ => VaultError::Io(detailed(Message::IoFailed)),
+                };
+            }
+            if cause.is::<serde_json::Error>()
+                || cause.is::<base64::DecodeError>()
+                || cause.is::<std::string::FromUtf8Error>()
+            {
+                return VaultError::Corrupted(detailed(Message::DataCorrupted));
+            }
+        }
+        VaultError::Other(detailed(Message::OperationFailed))
+    }
+}
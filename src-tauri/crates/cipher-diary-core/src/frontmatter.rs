@@ -0,0 +1,295 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use specta::Type;
+use std::fs;
+use std::path::Path;
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::{Date, OffsetDateTime};
+
+use crate::i18n::Message;
+use crate::validation;
+use crate::vault::{Location, NewEntry};
+
+// 导入 Markdown 文件时读取开头的 YAML frontmatter，写入对应的条目字段。
+// 只支持常见写法：key: value、[a, b] 行内列表、- 开头的列表和缩进的子字段
+const TITLE_KEYS: &[&str] = &["title"];
+const DATE_KEYS: &[&str] = &["date", "created"];
+const TAG_KEYS: &[&str] = &["tags", "tag"];
+const MOOD_KEYS: &[&str] = &["mood"];
+const LOCATION_KEYS: &[&str] = &["location"];
+
+// 其他应用使用的非标准键名，在内置键名之外额外识别，不区分大小写
+#[derive(Debug, Clone, Default, Deserialize, Type)]
+pub struct FrontmatterMapping {
+    #[serde(default)]
+    pub title: Vec<String>,
+    #[serde(default)]
+    pub date: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub mood: Vec<String>,
+    #[serde(default)]
+    pub location: Vec<String>,
+}
+
+enum Value {
+    Text(String),
+    List(Vec<String>),
+    Map(Vec<(String, String)>),
+}
+
+struct Field {
+    key: String,
+    value: Value,
+    // 原始行，未能识别的字段原样留在正文开头
+    lines: Vec<String>,
+}
+
+// 标题为空时使用文件名
+pub fn read(path: &Path, mapping: &FrontmatterMapping) -> Result<NewEntry> {
+    let text = fs::read_to_string(path).context(Message::ReadMarkdownFailed)?;
+    let mut entry = parse(&text, mapping);
+    if entry.title.trim().is_empty() {
+        if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+            entry.title = stem.to_string();
+        }
+    }
+    Ok(entry)
+}
+
+pub fn parse(text: &str, mapping: &FrontmatterMapping) -> NewEntry {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let Some((header, body)) = split(text) else {
+        return NewEntry {
+            content: text.to_string(),
+            ..Default::default()
+        };
+    };
+
+    let matches = |key: &str, builtin: &[&str], extra: &[String]| {
+        builtin.iter().any(|name| name.eq_ignore_ascii_case(key))
+            || extra
+                .iter()
+                .any(|name| name.trim().eq_ignore_ascii_case(key))
+    };
+    let mut entry = NewEntry::default();
+    let mut unknown = Vec::new();
+    for field in fields(header) {
+        let key = field.key.as_str();
+        let used = if matches(key, TITLE_KEYS, &mapping.title) {
+            text_value(&field.value)
+                .map(|title| entry.title = title)
+                .is_some()
+        } else if matches(key, DATE_KEYS, &mapping.date) {
+            text_value(&field.value)
+                .and_then(|text| date_value(&text))
+                .map(|(date, created_at)| {
+                    entry.entry_date = Some(date);
+                    entry.created_at = created_at;
+                })
+                .is_some()
+        } else if matches(key, TAG_KEYS, &mapping.tags) {
+            let tags = tag_values(&field.value);
+            let used = !tags.is_empty();
+            entry.tags = tags;
+            used
+        } else if matches(key, MOOD_KEYS, &mapping.mood) {
+            text_value(&field.value)
+                .and_then(|text| text.parse::<u8>().ok())
+                .filter(|mood| (validation::MIN_MOOD..=validation::MAX_MOOD).contains(mood))
+                .map(|mood| entry.mood = Some(mood))
+                .is_some()
+        } else if matches(key, LOCATION_KEYS, &mapping.location) {
+            location_value(&field.value)
+                .map(|location| entry.location = Some(location))
+                .is_some()
+        } else {
+            false
+        };
+        if !used {
+            unknown.extend(field.lines);
+        }
+    }
+
+    let body = body.trim_start_matches(['\r', '\n']);
+    entry.content = if unknown.is_empty() {
+        body.to_string()
+    } else {
+        format!("---\n{}\n---\n\n{body}", unknown.join("\n"))
+    };
+    entry
+}
+
+// frontmatter 必须从第一行的 --- 开始，到下一个单独的 --- 或 ... 结束
+fn split(text: &str) -> Option<(&str, &str)> {
+    let rest = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+fn fields(header: &str) -> Vec<Field> {
+    let mut fields: Vec<Field> = Vec::new();
+    for line in header.lines() {
+        let trimmed = line.trim();
+        let indented = line.starts_with([' ', '\t']);
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            if let Some(field) = fields.last_mut() {
+                field.lines.push(line.to_string());
+            }
+            continue;
+        }
+        if indented || trimmed.starts_with("- ") {
+            // 属于上一个字段的列表项或子字段
+            let Some(field) = fields.last_mut() else {
+                continue;
+            };
+            field.lines.push(line.to_string());
+            if let Some(item) = trimmed.strip_prefix("- ") {
+                match &mut field.value {
+                    Value::List(items) => items.push(unquote(item)),
+                    Value::Text(text) if text.is_empty() => {
+                        field.value = Value::List(vec![unquote(item)])
+                    }
+                    _ => {}
+                }
+            } else if let Some((key, value)) = trimmed.split_once(':') {
+                let pair = (key.trim().to_string(), unquote(value));
+                match &mut field.value {
+                    Value::Map(pairs) => pairs.push(pair),
+                    Value::Text(text) if text.is_empty() => field.value = Value::Map(vec![pair]),
+                    _ => {}
+                }
+            }
+            continue;
+        }
+        let Some((key, value)) = trimmed.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        let value = match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            Some(items) => Value::List(
+                items
+                    .split(',')
+                    .map(unquote)
+                    .filter(|item| !item.is_empty())
+                    .collect(),
+            ),
+            None => Value::Text(unquote(value)),
+        };
+        fields.push(Field {
+            key: key.trim().to_string(),
+            value,
+            lines: vec![line.to_string()],
+        });
+    }
+    fields
+}
+
+// 去掉行尾注释和成对的引号
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|v| v.strip_suffix(quote))
+        {
+            return inner.to_string();
+        }
+    }
+    match value.find(" #") {
+        Some(at) => value[..at].trim_end().to_string(),
+        None => value.to_string(),
+    }
+}
+
+fn text_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Text(text) if !text.is_empty() => Some(text.clone()),
+        _ => None,
+    }
+}
+
+// 带时间时同时作为新建时间；不带时区的时间无法确定时刻，只取日期
+fn date_value(text: &str) -> Option<(Date, Option<OffsetDateTime>)> {
+    if let Ok(at) = OffsetDateTime::parse(text, &Rfc3339) {
+        return Some((at.date(), Some(at)));
+    }
+    let date = text.get(..10)?;
+    let date = Date::parse(date, format_description!("[year]-[month]-[day]")).ok()?;
+    Some((date, None))
+}
+
+// 字符串形式的标签用逗号或空格分隔，Obsidian 风格的 # 前缀会被去掉
+fn tag_values(value: &Value) -> Vec<String> {
+    let items = match value {
+        Value::List(items) => items.clone(),
+        Value::Text(text) if text.contains(',') => text.split(',').map(String::from).collect(),
+        Value::Text(text) => text.split_whitespace().map(String::from).collect(),
+        Value::Map(_) => Vec::new(),
+    };
+    let mut tags: Vec<String> = Vec::new();
+    for item in items {
+        let tag = item.trim().trim_start_matches('#').trim();
+        if !tag.is_empty() && !tags.iter().any(|existing| existing == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+// 支持 "纬度, 经度"、[纬度, 经度] 和含 latitude/longitude/place 子字段的写法；
+// 只有地名没有坐标时无法保存为位置
+fn location_value(value: &Value) -> Option<Location> {
+    let coordinates = |latitude: &str, longitude: &str| -> Option<(f64, f64)> {
+        let latitude: f64 = latitude.trim().parse().ok()?;
+        let longitude: f64 = longitude.trim().parse().ok()?;
+        let valid = (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude);
+        valid.then_some((latitude, longitude))
+    };
+    let (latitude, longitude, place) = match value {
+        Value::Text(text) => {
+            let (latitude, longitude) = text.split_once(',')?;
+            let (latitude, longitude) = coordinates(latitude, longitude)?;
+            (latitude, longitude, None)
+        }
+        Value::List(items) => match items.as_slice() {
+            [latitude, longitude] => {
+                let (latitude, longitude) = coordinates(latitude, longitude)?;
+                (latitude, longitude, None)
+            }
+            _ => return None,
+        },
+        Value::Map(pairs) => {
+            let get = |names: &[&str]| {
+                pairs
+                    .iter()
+                    .find(|(key, _)| names.iter().any(|name| name.eq_ignore_ascii_case(key)))
+                    .map(|(_, value)| value.as_str())
+            };
+            let (latitude, longitude) = coordinates(
+                get(&["latitude", "lat"])?,
+                get(&["longitude", "lon", "lng"])?,
+            )?;
+            let place = get(&["place", "name"])
+                .filter(|place| !place.is_empty())
+                .map(String::from);
+            (latitude, longitude, place)
+        }
+    };
+    Some(Location {
+        latitude,
+        longitude,
+        place,
+    })
+}
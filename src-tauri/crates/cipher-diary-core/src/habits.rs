@@ -0,0 +1,187 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::BTreeMap;
+use time::Date;
+use uuid::Uuid;
+
+use crate::i18n::Message;
+use crate::vault::QuotaExceeded;
+
+// 习惯定义和每日打卡加密保存在元数据中，每个习惯每天至多一条记录
+pub const MAX_HABITS: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct Habit {
+    pub id: String,
+    pub name: String,
+    // 计量单位，如“杯”“分钟”；为空表示只记录是否完成
+    #[serde(default)]
+    pub unit: Option<String>,
+    // 当天的数值达到目标才算完成，只记录是否完成的习惯为 1
+    pub target: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HabitLog {
+    habit: String,
+    date: Date,
+    value: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HabitState {
+    #[serde(default)]
+    habits: Vec<Habit>,
+    #[serde(default)]
+    logs: Vec<HabitLog>,
+}
+
+// date 为 YYYY-MM-DD，只列出有打卡记录的日子
+#[derive(Debug, Serialize, Type)]
+pub struct HabitDay {
+    pub date: String,
+    pub value: f64,
+    pub done: bool,
+}
+
+// 连续天数按全部记录计算，不受查询范围影响；今天尚未完成时从昨天算起
+#[derive(Debug, Serialize, Type)]
+pub struct HabitHistory {
+    pub habit: Habit,
+    pub days: Vec<HabitDay>,
+    pub current_streak: u32,
+    pub longest_streak: u32,
+}
+
+impl HabitState {
+    pub fn habits(&self) -> Vec<Habit> {
+        self.habits.clone()
+    }
+
+    fn find(&self, id: &str) -> Result<&Habit> {
+        self.habits
+            .iter()
+            .find(|habit| habit.id == id)
+            .ok_or_else(|| anyhow!(Message::HabitNotFound))
+    }
+
+    // id 为 None 时新建；修改目标后历史记录按新目标判断是否完成
+    pub fn save(
+        &mut self,
+        id: Option<String>,
+        name: String,
+        unit: Option<String>,
+        target: f64,
+    ) -> Result<Habit> {
+        match id {
+            Some(id) => {
+                let habit = self
+                    .habits
+                    .iter_mut()
+                    .find(|habit| habit.id == id)
+                    .ok_or_else(|| anyhow!(Message::HabitNotFound))?;
+                habit.name = name;
+                habit.unit = unit;
+                habit.target = target;
+                Ok(habit.clone())
+            }
+            None => {
+                if self.habits.len() >= MAX_HABITS {
+                    return Err(QuotaExceeded(Message::TooManyHabits(MAX_HABITS)).into());
+                }
+                let habit = Habit {
+                    id: Uuid::new_v4().to_string(),
+                    name,
+                    unit,
+                    target,
+                };
+                self.habits.push(habit.clone());
+                Ok(habit)
+            }
+        }
+    }
+
+    pub fn delete(&mut self, id: &str) -> Result<()> {
+        self.find(id)?;
+        self.habits.retain(|habit| habit.id != id);
+        self.logs.retain(|log| log.habit != id);
+        Ok(())
+    }
+
+    // 同一天再次打卡时覆盖之前的数值，数值为 0 表示取消当天的打卡
+    pub fn log(&mut self, date: Date, id: &str, value: f64) -> Result<Habit> {
+        let habit = self.find(id)?.clone();
+        self.logs.retain(|log| log.habit != id || log.date != date);
+        if value > 0.0 {
+            self.logs.push(HabitLog {
+                habit: habit.id.clone(),
+                date,
+                value,
+            });
+        }
+        Ok(habit)
+    }
+
+    pub fn history(&self, id: &str, from: Date, to: Date, today: Date) -> Result<HabitHistory> {
+        if from > to {
+            return Err(anyhow!(Message::InvalidDate));
+        }
+        let habit = self.find(id)?.clone();
+        let values: BTreeMap<Date, f64> = self
+            .logs
+            .iter()
+            .filter(|log| log.habit == id)
+            .map(|log| (log.date, log.value))
+            .collect();
+        let done = |date: &Date| values.get(date).is_some_and(|value| *value >= habit.target);
+
+        let mut current_streak = 0;
+        let mut next = if done(&today) {
+            Some(today)
+        } else {
+            today.previous_day()
+        };
+        while let Some(date) = next.filter(&done) {
+            current_streak += 1;
+            next = date.previous_day();
+        }
+
+        let mut longest_streak = 0;
+        let mut run = 0;
+        let mut last: Option<Date> = None;
+        for date in values.keys().filter(|date| done(date)) {
+            run = if last.and_then(Date::next_day) == Some(*date) {
+                run + 1
+            } else {
+                1
+            };
+            longest_streak = longest_streak.max(run);
+            last = Some(*date);
+        }
+
+        let days = values
+            .range(from..=to)
+            .map(|(date, value)| HabitDay {
+                date: date.to_string(),
+                value: *value,
+                done: *value >= habit.target,
+            })
+            .collect();
+        Ok(HabitHistory {
+            habit,
+            days,
+            current_streak,
+            longest_streak,
+        })
+    }
+}
+
+// 追加到当天日记末尾的一行，用 Markdown 任务列表表示是否完成
+pub fn entry_line(habit: &Habit, value: f64) -> String {
+    let mark = if value >= habit.target { "x" } else { " " };
+    match &habit.unit {
+        Some(unit) => format!("- [{mark}] {}: {value} {unit}", habit.name),
+        None => format!("- [{mark}] {}", habit.name),
+    }
+}
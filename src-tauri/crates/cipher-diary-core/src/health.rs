@@ -0,0 +1,280 @@
+use anyhow::{anyhow, Context, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::{Date, OffsetDateTime, UtcOffset};
+
+use crate::i18n::Message;
+
+// 从健康应用导出文件中汇总每天的步数、睡眠和运动，附加到当天的日记上
+const APPLE_STEPS: &str = "HKQuantityTypeIdentifierStepCount";
+const APPLE_SLEEP: &str = "HKCategoryTypeIdentifierSleepAnalysis";
+const APPLE_ASLEEP: &str = "HKCategoryValueSleepAnalysisAsleep";
+const APPLE_WORKOUT_PREFIX: &str = "HKWorkoutActivityType";
+const GOOGLE_DAILY_METRICS: &str = "Daily activity metrics/Daily activity metrics.csv";
+const GOOGLE_SESSIONS: &str = "All Sessions";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthSource {
+    // 健康 App 导出后解压得到的 export.xml
+    AppleHealth,
+    // Google Takeout 中的 Fit 目录
+    GoogleFit,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Type)]
+pub struct HealthSummary {
+    #[serde(default)]
+    pub steps: Option<u32>,
+    #[serde(default)]
+    pub sleep_minutes: Option<u32>,
+    #[serde(default)]
+    pub workouts: Vec<Workout>,
+}
+
+// activity 为导出文件中的运动类型，统一为小写下划线形式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct Workout {
+    pub activity: String,
+    pub minutes: u32,
+}
+
+// skipped 为当天没有日记、未能附加的天数
+#[derive(Debug, Serialize, Type)]
+pub struct HealthImportReport {
+    pub days: usize,
+    pub attached: usize,
+    pub skipped: usize,
+}
+
+// 汇总后按本机日期归到某一天；睡眠算在醒来的那天
+#[derive(Default)]
+struct Totals {
+    // 多个设备会重复记录同一段时间，按来源分别累计后取最大值
+    steps: BTreeMap<(String, Date), f64>,
+    sleep: BTreeMap<(String, Date), i64>,
+    workouts: BTreeMap<Date, Vec<Workout>>,
+}
+
+impl Totals {
+    fn add_workout(&mut self, activity: String, start: OffsetDateTime, end: OffsetDateTime) {
+        let minutes = (end - start).whole_minutes();
+        if minutes > 0 {
+            self.workouts
+                .entry(start.date())
+                .or_default()
+                .push(Workout {
+                    activity,
+                    minutes: minutes as u32,
+                });
+        }
+    }
+
+    fn into_days(self) -> BTreeMap<Date, HealthSummary> {
+        let mut days: BTreeMap<Date, HealthSummary> = BTreeMap::new();
+        for ((_, date), steps) in self.steps {
+            let day = days.entry(date).or_default();
+            day.steps = day.steps.max(Some(steps.round() as u32));
+        }
+        for ((_, date), minutes) in self.sleep {
+            let day = days.entry(date).or_default();
+            day.sleep_minutes = day.sleep_minutes.max(Some(minutes as u32));
+        }
+        for (date, workouts) in self.workouts {
+            days.entry(date).or_default().workouts = workouts;
+        }
+        days
+    }
+}
+
+// offset 用于换算只带 UTC 时间的记录
+pub fn read(
+    source: HealthSource,
+    path: &Path,
+    offset: UtcOffset,
+) -> Result<BTreeMap<Date, HealthSummary>> {
+    match source {
+        HealthSource::AppleHealth => read_apple(path),
+        HealthSource::GoogleFit => read_google(path, offset),
+    }
+}
+
+// export.xml 可能有几百 MB，逐条读取而不整体载入
+fn read_apple(path: &Path) -> Result<BTreeMap<Date, HealthSummary>> {
+    let file = File::open(path).context("failed to open health export")?;
+    let mut reader = Reader::from_reader(BufReader::new(file));
+    let mut totals = Totals::default();
+    let mut buf = Vec::new();
+    loop {
+        match reader
+            .read_event_into(&mut buf)
+            .map_err(|_| anyhow!(Message::InvalidHealthData))?
+        {
+            Event::Start(element) | Event::Empty(element) => match element.name().as_ref() {
+                b"Record" => apple_record(&element, &mut totals),
+                b"Workout" => apple_workout(&element, &mut totals),
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(totals.into_days())
+}
+
+fn attribute(element: &BytesStart, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|attribute| attribute.key.as_ref() == name)
+        .and_then(|attribute| attribute.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+// 例如 2024-03-01 07:30:00 +0800，日期按记录自带的时区计
+fn apple_time(value: &str) -> Option<OffsetDateTime> {
+    let format = format_description!(
+        "[year]-[month]-[day] [hour]:[minute]:[second] [offset_hour sign:mandatory][offset_minute]"
+    );
+    OffsetDateTime::parse(value, format).ok()
+}
+
+fn apple_record(element: &BytesStart, totals: &mut Totals) {
+    let (Some(kind), Some(start), Some(end)) = (
+        attribute(element, b"type"),
+        attribute(element, b"startDate")
+            .as_deref()
+            .and_then(apple_time),
+        attribute(element, b"endDate")
+            .as_deref()
+            .and_then(apple_time),
+    ) else {
+        return;
+    };
+    let source = attribute(element, b"sourceName").unwrap_or_default();
+    let value = attribute(element, b"value").unwrap_or_default();
+    match kind.as_str() {
+        APPLE_STEPS => {
+            if let Ok(steps) = value.parse::<f64>() {
+                *totals.steps.entry((source, start.date())).or_default() += steps;
+            }
+        }
+        // 只计入睡着的时段，不含卧床和清醒
+        APPLE_SLEEP if value.starts_with(APPLE_ASLEEP) => {
+            *totals.sleep.entry((source, end.date())).or_default() += (end - start).whole_minutes();
+        }
+        _ => {}
+    }
+}
+
+fn apple_workout(element: &BytesStart, totals: &mut Totals) {
+    let (Some(kind), Some(start), Some(end)) = (
+        attribute(element, b"workoutActivityType"),
+        attribute(element, b"startDate")
+            .as_deref()
+            .and_then(apple_time),
+        attribute(element, b"endDate")
+            .as_deref()
+            .and_then(apple_time),
+    ) else {
+        return;
+    };
+    let name = kind.strip_prefix(APPLE_WORKOUT_PREFIX).unwrap_or(&kind);
+    totals.add_workout(snake_case(name), start, end);
+}
+
+// TraditionalStrengthTraining -> traditional_strength_training
+fn snake_case(name: &str) -> String {
+    let mut result = String::new();
+    for (index, ch) in name.chars().enumerate() {
+        if ch.is_uppercase() && index > 0 {
+            result.push('_');
+        }
+        result.extend(ch.to_lowercase());
+    }
+    result
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleSession {
+    fitness_activity: String,
+    start_time: String,
+    end_time: String,
+}
+
+// 步数取自每日汇总表，睡眠和运动取自各次活动记录；两者都没有时视为选错了目录
+fn read_google(dir: &Path, offset: UtcOffset) -> Result<BTreeMap<Date, HealthSummary>> {
+    let metrics = dir.join(GOOGLE_DAILY_METRICS);
+    let sessions = dir.join(GOOGLE_SESSIONS);
+    if !metrics.is_file() && !sessions.is_dir() {
+        return Err(anyhow!(Message::HealthDataNotFound));
+    }
+
+    let mut totals = Totals::default();
+    if metrics.is_file() {
+        let text = fs::read_to_string(&metrics).context("failed to read daily metrics")?;
+        google_metrics(&text, &mut totals)?;
+    }
+    if sessions.is_dir() {
+        for item in fs::read_dir(&sessions).context("failed to read sessions")? {
+            let path = item?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let text = fs::read_to_string(&path).context("failed to read session")?;
+            // 个别无法识别的记录直接跳过
+            let Ok(session) = serde_json::from_str::<GoogleSession>(&text) else {
+                continue;
+            };
+            let parse = |value: &str| {
+                OffsetDateTime::parse(value, &Rfc3339)
+                    .ok()
+                    .map(|at| at.to_offset(offset))
+            };
+            let (Some(start), Some(end)) = (parse(&session.start_time), parse(&session.end_time))
+            else {
+                continue;
+            };
+            if session.fitness_activity.starts_with("sleep") {
+                *totals.sleep.entry((String::new(), end.date())).or_default() +=
+                    (end - start).whole_minutes();
+            } else {
+                totals.add_workout(session.fitness_activity, start, end);
+            }
+        }
+    }
+    Ok(totals.into_days())
+}
+
+fn google_metrics(text: &str, totals: &mut Totals) -> Result<()> {
+    let mut lines = text.lines();
+    let header: Vec<&str> = lines.next().unwrap_or_default().split(',').collect();
+    let column = |name: &str| header.iter().position(|column| column.trim() == name);
+    let (Some(date_column), Some(steps_column)) = (column("Date"), column("Step count")) else {
+        return Err(anyhow!(Message::InvalidHealthData));
+    };
+    let format = format_description!("[year]-[month]-[day]");
+    for line in lines {
+        let fields: Vec<&str> = line.split(',').collect();
+        let date = fields
+            .get(date_column)
+            .and_then(|value| Date::parse(value.trim(), format).ok());
+        let steps = fields
+            .get(steps_column)
+            .and_then(|value| value.trim().parse::<f64>().ok());
+        if let (Some(date), Some(steps)) = (date, steps) {
+            *totals.steps.entry((String::new(), date)).or_default() += steps;
+        }
+    }
+    Ok(())
+}
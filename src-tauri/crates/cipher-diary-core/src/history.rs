@@ -0,0 +1,217 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+use crate::permissions;
+
+const HISTORY_FILE: &str = "vault.history";
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+// 哈希链中的一环：记录某次保存后 vault.json 的摘要以及上一环的哈希。
+// 日志追加也占一环，journal_len 为当时日志的长度，state_hash 为日志这部分内容的摘要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HistoryLink {
+    seq: u64,
+    saved_at: OffsetDateTime,
+    state_hash: String,
+    prev_hash: String,
+    hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    journal_len: Option<u64>,
+}
+
+impl HistoryLink {
+    fn compute_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.seq.to_le_bytes());
+        hasher.update(self.saved_at.unix_timestamp_nanos().to_le_bytes());
+        hasher.update(self.prev_hash.as_bytes());
+        hasher.update(self.state_hash.as_bytes());
+        if let Some(len) = self.journal_len {
+            hasher.update(len.to_le_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct HistoryReport {
+    pub valid: bool,
+    pub length: u64,
+    pub head: Option<String>,
+    pub broken_at: Option<u64>,
+    pub issues: Vec<String>,
+}
+
+pub fn history_file_path(root: &Path) -> PathBuf {
+    root.join(HISTORY_FILE)
+}
+
+fn hash_file(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).context("failed to read vault for hashing")?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+fn read_links(history_path: &Path) -> Result<Vec<HistoryLink>> {
+    if !history_path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(history_path).context("failed to read vault history")?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).context("failed to parse vault history"))
+        .collect()
+}
+
+// 追加时只需要最后一环，不必解析整条链
+fn last_link(history_path: &Path) -> Result<Option<HistoryLink>> {
+    if !history_path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(history_path).context("failed to read vault history")?;
+    content
+        .lines()
+        .rfind(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).context("failed to parse vault history"))
+        .transpose()
+}
+
+// 读取当前链头，用于解锁后继续追加
+pub fn current_head(history_path: &Path) -> Result<Option<String>> {
+    Ok(last_link(history_path)?.map(|link| link.hash))
+}
+
+// 链中第一环的时间，即日记库创建的时间
+pub fn first_saved_at(history_path: &Path) -> Result<Option<OffsetDateTime>> {
+    Ok(read_links(history_path)?.first().map(|link| link.saved_at))
+}
+
+// 在 vault.json 写入完成后追加一环，返回新的链头
+pub fn append(history_path: &Path, vault_path: &Path, timestamp: OffsetDateTime) -> Result<String> {
+    push_link(history_path, timestamp, hash_file(vault_path)?, None)
+}
+
+// 日志追加落盘后记下日志当前的长度与摘要，截掉已提交的记录能被发现
+pub fn append_journal(
+    history_path: &Path,
+    journal_path: &Path,
+    timestamp: OffsetDateTime,
+) -> Result<String> {
+    let bytes = fs::read(journal_path).context("failed to read metadata journal")?;
+    let state_hash = format!("{:x}", Sha256::digest(&bytes));
+    push_link(history_path, timestamp, state_hash, Some(bytes.len() as u64))
+}
+
+fn push_link(
+    history_path: &Path,
+    timestamp: OffsetDateTime,
+    state_hash: String,
+    journal_len: Option<u64>,
+) -> Result<String> {
+    let (seq, prev_hash) = match last_link(history_path)? {
+        Some(last) => (last.seq + 1, last.hash),
+        None => (0, GENESIS_HASH.to_string()),
+    };
+
+    let mut link = HistoryLink {
+        seq,
+        saved_at: timestamp,
+        state_hash,
+        prev_hash,
+        hash: String::new(),
+        journal_len,
+    };
+    link.hash = link.compute_hash();
+
+    let mut line = serde_json::to_string(&link).context("failed to serialize history link")?;
+    line.push('\n');
+
+    let mut file =
+        permissions::open_private_append(history_path).context("failed to open vault history")?;
+    file.write_all(line.as_bytes())
+        .context("failed to append vault history")?;
+    file.sync_all().context("failed to flush vault history")?;
+
+    Ok(link.hash)
+}
+
+// pinned_head 来自加密元数据，记录的是本次保存之前的链头，
+// 用于发现整条链被截断或重写的情况
+pub fn verify(
+    history_path: &Path,
+    vault_path: &Path,
+    journal_path: &Path,
+    pinned_head: Option<&str>,
+) -> Result<HistoryReport> {
+    let links = read_links(history_path)?;
+    let mut issues = Vec::new();
+    let mut broken_at = None;
+    let mut expected_prev = GENESIS_HASH.to_string();
+
+    for (index, link) in links.iter().enumerate() {
+        let index = index as u64;
+        if link.seq != index {
+            issues.push(format!("history link {index} has unexpected sequence {}", link.seq));
+        } else if link.prev_hash != expected_prev {
+            issues.push(format!("history link {index} does not follow the previous save"));
+        } else if link.compute_hash() != link.hash {
+            issues.push(format!("history link {index} has been modified"));
+        }
+        if !issues.is_empty() {
+            broken_at = Some(index);
+            break;
+        }
+        expected_prev = link.hash.clone();
+    }
+
+    if broken_at.is_none() {
+        let last_save = links.iter().rposition(|link| link.journal_len.is_none());
+        match last_save {
+            Some(position) => {
+                if hash_file(vault_path)? != links[position].state_hash {
+                    issues.push("vault.json does not match the latest recorded save".to_string());
+                }
+                let before_save = position.checked_sub(1).map(|idx| links[idx].hash.as_str());
+                if pinned_head != before_save {
+                    issues.push("history does not match the head recorded in the vault".to_string());
+                }
+                // 最近一次保存之后的日志追加：日志至少要保留最后一环记下的内容，
+                // 之后多出的部分是崩溃前没来得及记入链中的记录
+                if let Some((len, hash)) = links[position..]
+                    .last()
+                    .and_then(|link| link.journal_len.map(|len| (len, &link.state_hash)))
+                {
+                    let bytes = fs::read(journal_path).unwrap_or_default();
+                    let recorded = usize::try_from(len)
+                        .ok()
+                        .and_then(|len| bytes.get(..len))
+                        .map(|prefix| format!("{:x}", Sha256::digest(prefix)));
+                    if recorded.as_ref() != Some(hash) {
+                        issues.push(
+                            "vault.journal does not match the latest recorded changes".to_string(),
+                        );
+                    }
+                }
+            }
+            None => {
+                if pinned_head.is_some() {
+                    issues.push("vault history is missing".to_string());
+                }
+            }
+        }
+    }
+
+    Ok(HistoryReport {
+        valid: issues.is_empty(),
+        length: links.len() as u64,
+        head: links.last().map(|link| link.hash.clone()),
+        broken_at,
+        issues,
+    })
+}
@@ -0,0 +1,697 @@
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fmt;
+use time::Month;
+
+use crate::entry_types::{EntryType, ReviewPeriod};
+
+// 后端返回给前端的提示文字；错误码不随语言变化，前端应以 code 判断
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum Locale {
+    #[default]
+    #[serde(rename = "zh-CN", alias = "zh")]
+    ZhCn,
+    #[serde(rename = "en", alias = "en-US")]
+    En,
+}
+
+static LOCALE: RwLock<Locale> = RwLock::new(Locale::ZhCn);
+
+pub fn set_locale(locale: Locale) {
+    *LOCALE.write() = locale;
+}
+
+pub fn locale() -> Locale {
+    *LOCALE.read()
+}
+
+// 文字在显示时才按当前语言取出，可直接用于 anyhow!(..) 和 .context(..)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    // 各错误码的通用说明
+    VaultLocked,
+    WrongPassphrase,
+    VaultNotFound,
+    EntryNotFound,
+    IoFailed,
+    StorageFull,
+    DataCorrupted,
+    OperationFailed,
+    // 目录与日记库
+    InvalidDirectory,
+    NotADirectory,
+    CreateDirectoryFailed,
+    VaultAlreadyExists,
+    VaultAlreadyOpen,
+    VaultInUse,
+    LockVaultFirst,
+    ImportSourceOpen,
+    ImportIntoSelf,
+    ImportFailed,
+    ReadImportAttachmentFailed,
+    ReadMarkdownFailed,
+    SaveImportAttachmentFailed,
+    MoveFailed,
+    CopyFailed,
+    CreateDestinationFailed,
+    ResolveDestinationFailed,
+    DestinationOverlapsVault,
+    DestinationOccupied(String),
+    NoFilesSelected,
+    PlaintextExportDisabled,
+    // 密码与快速解锁
+    EmptyPassphrase,
+    PassphraseTooShort(usize),
+    WrongOldPassphrase,
+    PassphraseReused,
+    ChangePassphraseFailed,
+    ReplaceFilesFailed,
+    QuickUnlockExpired,
+    QuickUnlockDisabled,
+    DeviceKeyUnavailable,
+    KeychainUnavailable,
+    KeychainWriteFailed,
+    PinTooShort(usize),
+    PinLockedOut,
+    WrongPin(u32),
+    // 输入检查
+    TitleTooLong(usize),
+    TitleControlCharacters,
+    ContentTooLarge(u64),
+    ContentControlCharacters,
+    FolderNameTooLong(usize),
+    InvalidFolderName,
+    TooManyTags(usize),
+    TagTooLong(usize),
+    InvalidTag,
+    MoodOutOfRange(u8, u8),
+    InvalidCoordinates,
+    PlaceNameTooLong(usize),
+    InvalidPlaceName,
+    InvalidRadius(u32),
+    InvalidDate,
+    AttachmentTooLarge(u64),
+    // 图片
+    ImageNotFound,
+    ImageFileMissing,
+    EmptyPastedImage,
+    ReadImageFailed,
+    SaveImageFailed,
+    WriteImageFailed,
+    // 设置与资料
+    AutoLockTooShort,
+    SettingsTooLarge,
+    InvalidGoal,
+    InvalidTimezone,
+    WeatherApiKeyMissing,
+    ProfileNameTooLong(usize),
+    ProfileDescriptionTooLong(usize),
+    ProfileIconTooLong(usize),
+    // 紧急访问
+    DelayDaysOutOfRange(u32),
+    EmergencyDestinationMissing,
+    EmergencyStateTampered,
+    EmergencyKeyUnavailable,
+    // 提醒
+    InvalidReminderTime,
+    TooManyReminders(usize),
+    ReminderTitle,
+    ReminderBody,
+    InvalidMemoryYears(u32),
+    MemoryTitle,
+    MemoryEntry(u32, String),
+    MemoryEntries(u32, usize),
+    // 写作提示
+    PromptPackNotFound,
+    BuiltinPromptPackReadOnly,
+    TooManyPromptPacks(usize),
+    InvalidPromptPackName(usize),
+    TooManyPrompts(usize),
+    InvalidPrompt(usize),
+    // 习惯
+    HabitNotFound,
+    TooManyHabits(usize),
+    InvalidHabitName(usize),
+    InvalidHabitUnit(usize),
+    InvalidHabitTarget,
+    InvalidHabitValue,
+    // 健康数据
+    HealthDataNotFound,
+    InvalidHealthData,
+    // 日记类型
+    EntryTypeName(EntryType),
+    ReviewTitle(ReviewPeriod),
+    GratitudeHeading,
+    DreamLucid,
+    DreamRecurring,
+    DreamSymbols,
+    ReviewWentWell,
+    ReviewToImprove,
+    ReviewNextSteps,
+    EmptyEntryPayload,
+    InvalidEntryPayload(usize, usize),
+    // 月度回顾
+    RecapTitle(i32, u8),
+    RecapSummary(u32, u64, u32),
+    RecapMood(String),
+    RecapTags,
+    RecapPhotos,
+    RecapHighlights,
+    // 每日小结
+    DaySummaryTooLong(usize),
+    // 个人词典
+    InvalidDictionaryWord(usize),
+    TooManyDictionaryWords(usize),
+    // 分享包
+    ShareTitle,
+    SharePasswordPrompt,
+    ShareOpen,
+    ShareWrongPassword,
+    SharePasswordTooShort(usize),
+    NothingToShare,
+    InvalidSharedEntry,
+    SharedEntryNotFound,
+    // 格式升级
+    VaultTooNew,
+    // 损坏条目
+    EntryUnreadable(String),
+    QuarantineEntryFailed,
+    // 只读介质
+    VaultReadOnly,
+    // 多用户密钥槽
+    TooManyKeySlots(usize),
+    KeySlotNotFound,
+    KeySlotPassphraseInUse,
+    InvalidKeySlotLabel(usize),
+    AdminPassphraseRequired,
+    LastAdminKeySlot,
+    // Android 分区存储
+    DocumentTreeUnsupported,
+    DocumentTreeSyncFailed,
+    DocumentTreeUnsyncedChanges,
+}
+
+impl Message {
+    pub fn text(&self, locale: Locale) -> String {
+        match locale {
+            Locale::ZhCn => self.zh_cn(),
+            Locale::En => self.en(),
+        }
+    }
+
+    fn zh_cn(&self) -> String {
+        match self {
+            Message::VaultLocked => "日记库未打开或已锁定".into(),
+            Message::WrongPassphrase => "密码错误".into(),
+            Message::VaultNotFound => "所选目录中没有日记库".into(),
+            Message::EntryNotFound => "日记不存在或已被删除".into(),
+            Message::IoFailed => "读写文件失败".into(),
+            Message::StorageFull => "磁盘空间不足".into(),
+            Message::DataCorrupted => "日记数据已损坏".into(),
+            Message::OperationFailed => "操作失败".into(),
+            Message::InvalidDirectory => "所选目录无效".into(),
+            Message::NotADirectory => "所选路径不是文件夹".into(),
+            Message::CreateDirectoryFailed => "无法创建所选目录".into(),
+            Message::VaultAlreadyExists => "该目录中已存在日记库".into(),
+            Message::VaultAlreadyOpen => "该日记库已经打开".into(),
+            Message::VaultInUse => "该日记库正在被另一个程序使用".into(),
+            Message::LockVaultFirst => "请先锁定该日记库".into(),
+            Message::ImportSourceOpen => "要导入的日记库已经打开，请先锁定".into(),
+            Message::ImportIntoSelf => "不能把日记库导入到自身".into(),
+            Message::ImportFailed => "导入日记库失败，当前日记未被修改".into(),
+            Message::ReadImportAttachmentFailed => "无法读取要导入的附件".into(),
+            Message::ReadMarkdownFailed => "无法读取要导入的 Markdown 文件".into(),
+            Message::SaveImportAttachmentFailed => "无法保存导入的附件".into(),
+            Message::MoveFailed => "移动日记失败，原位置未被修改".into(),
+            Message::CopyFailed => "复制日记失败，已清理目标目录".into(),
+            Message::CreateDestinationFailed => "无法创建目标目录".into(),
+            Message::ResolveDestinationFailed => "无法解析目标目录".into(),
+            Message::DestinationOverlapsVault => {
+                "目标目录不能与当前日记目录相同或互相包含".into()
+            }
+            Message::DestinationOccupied(path) => format!("目标目录中已存在 {path}"),
+            Message::NoFilesSelected => "未选择任何文件".into(),
+            Message::PlaintextExportDisabled => "此日记已禁用明文导出".into(),
+            Message::EmptyPassphrase => "新密码不能为空".into(),
+            Message::PassphraseTooShort(min) => format!("新密码长度至少需要 {min} 个字符"),
+            Message::WrongOldPassphrase => "旧密码错误".into(),
+            Message::PassphraseReused => "新密码不能与最近使用过的密码相同".into(),
+            Message::ChangePassphraseFailed => "密码修改失败，原数据未被修改".into(),
+            Message::ReplaceFilesFailed => "文件替换失败，请检查数据完整性".into(),
+            Message::QuickUnlockExpired => "快速解锁已失效，请使用完整密码解锁".into(),
+            Message::QuickUnlockDisabled => "未启用快速解锁".into(),
+            Message::DeviceKeyUnavailable => "设备密钥不可用，请使用完整密码解锁".into(),
+            Message::KeychainUnavailable => "无法访问系统钥匙串".into(),
+            Message::KeychainWriteFailed => "无法写入系统钥匙串".into(),
+            Message::PinTooShort(min) => format!("PIN 需至少 {min} 位数字"),
+            Message::PinLockedOut => {
+                "PIN 错误次数过多，快速解锁已停用，请使用完整密码解锁".into()
+            }
+            Message::WrongPin(left) => format!("PIN 错误，还可尝试 {left} 次"),
+            Message::TitleTooLong(max) => format!("标题不能超过 {max} 个字符"),
+            Message::TitleControlCharacters => "标题包含不允许的控制字符".into(),
+            Message::ContentTooLarge(max) => format!("正文不能超过 {max} MB"),
+            Message::ContentControlCharacters => "正文包含不允许的控制字符".into(),
+            Message::FolderNameTooLong(max) => format!("文件夹名称不能超过 {max} 个字符"),
+            Message::InvalidFolderName => {
+                "文件夹名称无效：各级名称不能为空、不能以空格开头或结尾".into()
+            }
+            Message::TooManyTags(max) => format!("每篇日记最多 {max} 个标签"),
+            Message::TagTooLong(max) => format!("标签不能超过 {max} 个字符"),
+            Message::InvalidTag => "标签无效：不能为空、不能以空格开头或结尾".into(),
+            Message::MoodOutOfRange(min, max) => format!("心情评分需在 {min} 到 {max} 之间"),
+            Message::InvalidCoordinates => "经纬度超出范围".into(),
+            Message::PlaceNameTooLong(max) => format!("地点名称不能超过 {max} 个字符"),
+            Message::InvalidPlaceName => "地点名称不能为空或包含控制字符".into(),
+            Message::InvalidRadius(max) => format!("搜索半径需大于 0 且不超过 {max} 公里"),
+            Message::InvalidDate => "日期无效".into(),
+            Message::AttachmentTooLarge(max) => format!("附件不能超过 {max} MB"),
+            Message::ImageNotFound => "选定的图片不存在".into(),
+            Message::ImageFileMissing => "图片文件不存在".into(),
+            Message::EmptyPastedImage => "粘贴的图像为空".into(),
+            Message::ReadImageFailed => "无法读取图片文件".into(),
+            Message::SaveImageFailed => "无法保存加密图片".into(),
+            Message::WriteImageFailed => "无法写入加密图片数据".into(),
+            Message::AutoLockTooShort => "自动锁定时间至少为 1 分钟".into(),
+            Message::SettingsTooLarge => "日记库设置过大".into(),
+            Message::InvalidGoal => "写作目标必须大于 0".into(),
+            Message::InvalidTimezone => "时区需在 UTC-12:00 到 UTC+14:00 之间".into(),
+            Message::WeatherApiKeyMissing => "所选天气服务需要填写 API 密钥".into(),
+            Message::ProfileNameTooLong(max) => format!("日记库名称不能超过 {max} 个字符"),
+            Message::ProfileDescriptionTooLong(max) => {
+                format!("日记库简介不能超过 {max} 个字符")
+            }
+            Message::ProfileIconTooLong(max) => format!("日记库图标不能超过 {max} 个字符"),
+            Message::DelayDaysOutOfRange(max) => format!("等待期需在 1 到 {max} 天之间"),
+            Message::EmergencyDestinationMissing => "未指定紧急恢复文件的存放目录".into(),
+            Message::EmergencyStateTampered => "紧急访问的计时状态已被改动，拒绝发布".into(),
+            Message::EmergencyKeyUnavailable => "本机钥匙串中没有紧急访问的发布凭据".into(),
+            Message::InvalidReminderTime => "提醒时间无效或重复".into(),
+            Message::TooManyReminders(max) => format!("每天最多设置 {max} 个提醒"),
+            Message::ReminderTitle => "写日记的时间到了".into(),
+            Message::ReminderBody => "今天还没有写日记，花几分钟记录一下吧".into(),
+            Message::InvalidMemoryYears(max) => {
+                format!("回顾年数需在 1 到 {max} 之间且不能重复")
+            }
+            Message::MemoryTitle => "那年今日".into(),
+            Message::MemoryEntry(years, title) => format!("{years} 年前的今天，你写了《{title}》"),
+            Message::MemoryEntries(years, count) => {
+                format!("{years} 年前的今天，你写了 {count} 篇日记")
+            }
+            Message::PromptPackNotFound => "题库或题目不存在".into(),
+            Message::BuiltinPromptPackReadOnly => "内置题库不能修改或删除".into(),
+            Message::TooManyPromptPacks(max) => format!("最多创建 {max} 个自定义题库"),
+            Message::InvalidPromptPackName(max) => {
+                format!("题库名称不能为空，且不能超过 {max} 个字符")
+            }
+            Message::TooManyPrompts(max) => format!("每个题库需包含 1 到 {max} 道题目"),
+            Message::InvalidPrompt(max) => format!("题目不能为空，且不能超过 {max} 个字符"),
+            Message::HabitNotFound => "习惯不存在或已删除".into(),
+            Message::TooManyHabits(max) => format!("最多创建 {max} 个习惯"),
+            Message::InvalidHabitName(max) => {
+                format!("习惯名称不能为空，且不能超过 {max} 个字符")
+            }
+            Message::InvalidHabitUnit(max) => format!("单位不能为空，且不能超过 {max} 个字符"),
+            Message::InvalidHabitTarget => "每日目标必须是正数".into(),
+            Message::InvalidHabitValue => "打卡数值不能为负数".into(),
+            Message::HealthDataNotFound => "所选目录中没有找到健康数据".into(),
+            Message::InvalidHealthData => "无法识别健康数据导出文件".into(),
+            Message::EntryTypeName(entry_type) => match entry_type {
+                EntryType::Note => "日记",
+                EntryType::Gratitude => "感恩日记",
+                EntryType::Dream => "梦境记录",
+                EntryType::Review => "回顾",
+            }
+            .into(),
+            Message::ReviewTitle(period) => match period {
+                ReviewPeriod::Day => "每日回顾",
+                ReviewPeriod::Week => "每周回顾",
+                ReviewPeriod::Month => "每月回顾",
+                ReviewPeriod::Year => "年度回顾",
+            }
+            .into(),
+            Message::GratitudeHeading => "感谢的事".into(),
+            Message::DreamLucid => "清醒梦".into(),
+            Message::DreamRecurring => "反复出现的梦".into(),
+            Message::DreamSymbols => "意象：".into(),
+            Message::ReviewWentWell => "做得好的".into(),
+            Message::ReviewToImprove => "可以改进的".into(),
+            Message::ReviewNextSteps => "下一步".into(),
+            Message::EmptyEntryPayload => "至少需要填写一项内容".into(),
+            Message::InvalidEntryPayload(items, chars) => {
+                format!("最多填写 {items} 项，每项不能为空且不能超过 {chars} 个字符")
+            }
+            Message::RecapTitle(year, month) => format!("{year} 年 {month} 月回顾"),
+            Message::RecapSummary(entries, words, days) => {
+                format!("本月写了 {entries} 篇日记，共 {words} 字，{days} 天有记录")
+            }
+            Message::RecapMood(mood) => format!("平均心情：{mood}"),
+            Message::RecapTags => "常用标签：".into(),
+            Message::RecapPhotos => "本月照片".into(),
+            Message::RecapHighlights => "摘录".into(),
+            Message::DaySummaryTooLong(max) => format!("每日小结不能超过 {max} 个字符"),
+            Message::InvalidDictionaryWord(max) => {
+                format!("词典中的词不能为空或包含空白，且不能超过 {max} 个字符")
+            }
+            Message::TooManyDictionaryWords(max) => format!("个人词典最多收录 {max} 个词"),
+            Message::ShareTitle => "分享的日记".into(),
+            Message::SharePasswordPrompt => "请输入分享密码：".into(),
+            Message::ShareOpen => "查看".into(),
+            Message::ShareWrongPassword => "密码不正确，或文件已损坏".into(),
+            Message::SharePasswordTooShort(min) => format!("分享密码至少需要 {min} 个字符"),
+            Message::NothingToShare => "没有符合条件的日记可以分享".into(),
+            Message::InvalidSharedEntry => "不是有效的日记分享文件".into(),
+            Message::SharedEntryNotFound => "找不到分享文件".into(),
+            Message::VaultTooNew => "此日记库由更新版本的应用创建，请先升级应用".into(),
+            Message::EntryUnreadable(title) => {
+                format!("无法读取日记「{title}」，文件可能已损坏，可以将其隔离")
+            }
+            Message::QuarantineEntryFailed => "无法将日记文件移入隔离目录".into(),
+            Message::VaultReadOnly => "日记库所在位置不可写，只能以只读方式查看".into(),
+            Message::TooManyKeySlots(max) => format!("最多只能添加 {max} 位用户"),
+            Message::KeySlotNotFound => "该用户不存在或已被移除".into(),
+            Message::KeySlotPassphraseInUse => "该口令已被主口令或其他用户使用".into(),
+            Message::InvalidKeySlotLabel(max) => format!("用户名称不能为空，且不能超过 {max} 个字符"),
+            Message::AdminPassphraseRequired => "需要输入主口令或管理员口令".into(),
+            Message::LastAdminKeySlot => "至少需要保留一个管理员口令".into(),
+            Message::DocumentTreeUnsupported => "当前平台不支持以 content:// 地址打开日记库".into(),
+            Message::DocumentTreeSyncFailed => "无法与所选文件夹同步日记库文件".into(),
+            Message::DocumentTreeUnsyncedChanges => "工作副本中有尚未写回所选文件夹的改动".into(),
+        }
+    }
+
+    fn en(&self) -> String {
+        match self {
+            Message::VaultLocked => "The vault is not open or has been locked".into(),
+            Message::WrongPassphrase => "Incorrect passphrase".into(),
+            Message::VaultNotFound => "No vault was found in the selected folder".into(),
+            Message::EntryNotFound => "The entry does not exist or has been deleted".into(),
+            Message::IoFailed => "Failed to read or write files".into(),
+            Message::StorageFull => "Not enough disk space".into(),
+            Message::DataCorrupted => "The vault data is corrupted".into(),
+            Message::OperationFailed => "The operation failed".into(),
+            Message::InvalidDirectory => "The selected folder is invalid".into(),
+            Message::NotADirectory => "The selected path is not a folder".into(),
+            Message::CreateDirectoryFailed => "Could not create the selected folder".into(),
+            Message::VaultAlreadyExists => "A vault already exists in this folder".into(),
+            Message::VaultAlreadyOpen => "This vault is already open".into(),
+            Message::VaultInUse => "This vault is in use by another program".into(),
+            Message::LockVaultFirst => "Lock this vault first".into(),
+            Message::ImportSourceOpen => {
+                "The vault to import is open; lock it first".into()
+            }
+            Message::ImportIntoSelf => "A vault cannot be imported into itself".into(),
+            Message::ImportFailed => {
+                "Import failed; the current vault was not modified".into()
+            }
+            Message::ReadImportAttachmentFailed => {
+                "Could not read an attachment to import".into()
+            }
+            Message::ReadMarkdownFailed => "Could not read a Markdown file to import".into(),
+            Message::SaveImportAttachmentFailed => {
+                "Could not save an imported attachment".into()
+            }
+            Message::MoveFailed => {
+                "Moving the vault failed; the original location was not modified".into()
+            }
+            Message::CopyFailed => {
+                "Copying the vault failed; the destination has been cleaned up".into()
+            }
+            Message::CreateDestinationFailed => "Could not create the destination folder".into(),
+            Message::ResolveDestinationFailed => {
+                "Could not resolve the destination folder".into()
+            }
+            Message::DestinationOverlapsVault => {
+                "The destination must not be or overlap the current vault folder".into()
+            }
+            Message::DestinationOccupied(path) => {
+                format!("The destination already contains {path}")
+            }
+            Message::NoFilesSelected => "No files were selected".into(),
+            Message::PlaintextExportDisabled => {
+                "Plaintext export is disabled for this vault".into()
+            }
+            Message::EmptyPassphrase => "The new passphrase must not be empty".into(),
+            Message::PassphraseTooShort(min) => {
+                format!("The new passphrase must be at least {min} characters long")
+            }
+            Message::WrongOldPassphrase => "The current passphrase is incorrect".into(),
+            Message::PassphraseReused => {
+                "The new passphrase must differ from recently used passphrases".into()
+            }
+            Message::ChangePassphraseFailed => {
+                "Changing the passphrase failed; the data was not modified".into()
+            }
+            Message::ReplaceFilesFailed => {
+                "Replacing files failed; please check the vault integrity".into()
+            }
+            Message::QuickUnlockExpired => {
+                "Quick unlock is no longer valid; unlock with the full passphrase".into()
+            }
+            Message::QuickUnlockDisabled => "Quick unlock is not enabled".into(),
+            Message::DeviceKeyUnavailable => {
+                "The device key is unavailable; unlock with the full passphrase".into()
+            }
+            Message::KeychainUnavailable => "Could not access the system keychain".into(),
+            Message::KeychainWriteFailed => "Could not write to the system keychain".into(),
+            Message::PinTooShort(min) => format!("The PIN must have at least {min} digits"),
+            Message::PinLockedOut => {
+                "Too many wrong PINs; quick unlock has been disabled, \
+                 unlock with the full passphrase"
+                    .into()
+            }
+            Message::WrongPin(left) => format!("Wrong PIN, {left} attempts left"),
+            Message::TitleTooLong(max) => {
+                format!("The title must not exceed {max} characters")
+            }
+            Message::TitleControlCharacters => {
+                "The title contains control characters that are not allowed".into()
+            }
+            Message::ContentTooLarge(max) => format!("The entry must not exceed {max} MB"),
+            Message::ContentControlCharacters => {
+                "The entry contains control characters that are not allowed".into()
+            }
+            Message::FolderNameTooLong(max) => {
+                format!("The folder name must not exceed {max} characters")
+            }
+            Message::InvalidFolderName => {
+                "Invalid folder name: each level must be non-empty \
+                 and must not start or end with spaces"
+                    .into()
+            }
+            Message::TooManyTags(max) => format!("An entry can have at most {max} tags"),
+            Message::TagTooLong(max) => format!("Tags must not exceed {max} characters"),
+            Message::InvalidTag => {
+                "Invalid tag: it must be non-empty and must not start or end with spaces".into()
+            }
+            Message::MoodOutOfRange(min, max) => {
+                format!("The mood rating must be between {min} and {max}")
+            }
+            Message::InvalidCoordinates => "Latitude or longitude is out of range".into(),
+            Message::PlaceNameTooLong(max) => {
+                format!("Place names must not exceed {max} characters")
+            }
+            Message::InvalidPlaceName => {
+                "Place names must not be empty or contain control characters".into()
+            }
+            Message::InvalidRadius(max) => {
+                format!("The search radius must be greater than 0 and at most {max} km")
+            }
+            Message::InvalidDate => "The date is not valid".into(),
+            Message::AttachmentTooLarge(max) => {
+                format!("Attachments must not exceed {max} MB")
+            }
+            Message::ImageNotFound => "The selected image does not exist".into(),
+            Message::ImageFileMissing => "The image file does not exist".into(),
+            Message::EmptyPastedImage => "The pasted image is empty".into(),
+            Message::ReadImageFailed => "Could not read the image file".into(),
+            Message::SaveImageFailed => "Could not save the encrypted image".into(),
+            Message::WriteImageFailed => "Could not write the encrypted image data".into(),
+            Message::AutoLockTooShort => "Auto-lock must be at least 1 minute".into(),
+            Message::SettingsTooLarge => "The vault settings are too large".into(),
+            Message::InvalidGoal => "Writing goals must be greater than zero".into(),
+            Message::InvalidTimezone => {
+                "The time zone must be between UTC-12:00 and UTC+14:00".into()
+            }
+            Message::WeatherApiKeyMissing => {
+                "The selected weather provider requires an API key".into()
+            }
+            Message::ProfileNameTooLong(max) => {
+                format!("The vault name must not exceed {max} characters")
+            }
+            Message::ProfileDescriptionTooLong(max) => {
+                format!("The vault description must not exceed {max} characters")
+            }
+            Message::ProfileIconTooLong(max) => {
+                format!("The vault icon must not exceed {max} characters")
+            }
+            Message::DelayDaysOutOfRange(max) => {
+                format!("The waiting period must be between 1 and {max} days")
+            }
+            Message::EmergencyDestinationMissing => {
+                "No folder was chosen for the emergency recovery file".into()
+            }
+            Message::EmergencyStateTampered => {
+                "The emergency access timer was modified; release refused".into()
+            }
+            Message::EmergencyKeyUnavailable => {
+                "This device's keychain has no emergency release key".into()
+            }
+            Message::InvalidReminderTime => {
+                "Reminder times must be valid and must not repeat".into()
+            }
+            Message::TooManyReminders(max) => format!("At most {max} reminders per day"),
+            Message::ReminderTitle => "Time to write".into(),
+            Message::ReminderBody => {
+                "You haven't written today yet. Take a few minutes to jot something down".into()
+            }
+            Message::InvalidMemoryYears(max) => {
+                format!("Memory years must be between 1 and {max} and must not repeat")
+            }
+            Message::MemoryTitle => "On this day".into(),
+            Message::MemoryEntry(years, title) => {
+                format!("{} ago today you wrote about \u{201c}{title}\u{201d}", years_ago(*years))
+            }
+            Message::MemoryEntries(years, count) => {
+                let entries = if *count == 1 {
+                    "an entry".to_string()
+                } else {
+                    format!("{count} entries")
+                };
+                format!("You wrote {entries} on this day {} ago", years_ago(*years))
+            }
+            Message::PromptPackNotFound => "The prompt pack or prompt does not exist".into(),
+            Message::BuiltinPromptPackReadOnly => {
+                "Built-in prompt packs cannot be changed or deleted".into()
+            }
+            Message::TooManyPromptPacks(max) => {
+                format!("At most {max} custom prompt packs can be created")
+            }
+            Message::InvalidPromptPackName(max) => {
+                format!("Prompt pack names must be 1 to {max} characters long")
+            }
+            Message::TooManyPrompts(max) => {
+                format!("A prompt pack must contain 1 to {max} prompts")
+            }
+            Message::InvalidPrompt(max) => format!("Prompts must be 1 to {max} characters long"),
+            Message::HabitNotFound => "The habit does not exist or has been deleted".into(),
+            Message::TooManyHabits(max) => format!("At most {max} habits can be created"),
+            Message::InvalidHabitName(max) => {
+                format!("Habit names must be 1 to {max} characters long")
+            }
+            Message::InvalidHabitUnit(max) => format!("Units must be 1 to {max} characters long"),
+            Message::InvalidHabitTarget => "The daily target must be a positive number".into(),
+            Message::InvalidHabitValue => "Check-in values cannot be negative".into(),
+            Message::HealthDataNotFound => "No health data was found in the selected folder".into(),
+            Message::InvalidHealthData => "The health export file could not be read".into(),
+            Message::EntryTypeName(entry_type) => match entry_type {
+                EntryType::Note => "Note",
+                EntryType::Gratitude => "Gratitude",
+                EntryType::Dream => "Dream log",
+                EntryType::Review => "Review",
+            }
+            .into(),
+            Message::ReviewTitle(period) => match period {
+                ReviewPeriod::Day => "Daily review",
+                ReviewPeriod::Week => "Weekly review",
+                ReviewPeriod::Month => "Monthly review",
+                ReviewPeriod::Year => "Year in review",
+            }
+            .into(),
+            Message::GratitudeHeading => "Grateful for".into(),
+            Message::DreamLucid => "Lucid dream".into(),
+            Message::DreamRecurring => "Recurring dream".into(),
+            Message::DreamSymbols => "Symbols: ".into(),
+            Message::ReviewWentWell => "What went well".into(),
+            Message::ReviewToImprove => "What could be better".into(),
+            Message::ReviewNextSteps => "Next steps".into(),
+            Message::EmptyEntryPayload => "Fill in at least one item".into(),
+            Message::InvalidEntryPayload(items, chars) => {
+                format!("Up to {items} items of 1 to {chars} characters each are allowed")
+            }
+            Message::RecapTitle(year, month) => match Month::try_from(*month) {
+                Ok(month) => format!("{month} {year} recap"),
+                Err(_) => format!("{year}-{month:02} recap"),
+            },
+            Message::RecapSummary(entries, words, days) => {
+                format!("{entries} entries and {words} words, written on {days} days")
+            }
+            Message::RecapMood(mood) => format!("Average mood: {mood}"),
+            Message::RecapTags => "Top tags: ".into(),
+            Message::RecapPhotos => "Photos".into(),
+            Message::RecapHighlights => "Highlights".into(),
+            Message::DaySummaryTooLong(max) => {
+                format!("Day summaries cannot be longer than {max} characters")
+            }
+            Message::InvalidDictionaryWord(max) => {
+                format!("Dictionary words must be 1 to {max} characters long without spaces")
+            }
+            Message::TooManyDictionaryWords(max) => {
+                format!("The personal dictionary can hold at most {max} words")
+            }
+            Message::ShareTitle => "Shared diary entries".into(),
+            Message::SharePasswordPrompt => "Enter the share password:".into(),
+            Message::ShareOpen => "Open".into(),
+            Message::ShareWrongPassword => {
+                "The password is incorrect or the file is damaged".into()
+            }
+            Message::SharePasswordTooShort(min) => {
+                format!("Share passwords must be at least {min} characters long")
+            }
+            Message::NothingToShare => "No entries match the selection to share".into(),
+            Message::InvalidSharedEntry => "This is not a valid shared entry file".into(),
+            Message::SharedEntryNotFound => "The shared entry file was not found".into(),
+            Message::VaultTooNew => {
+                "This vault was created by a newer version of the app; please update first".into()
+            }
+            Message::EntryUnreadable(title) => {
+                format!(
+                    "Cannot read the entry \"{title}\"; its file may be damaged and can be \
+                     quarantined"
+                )
+            }
+            Message::QuarantineEntryFailed => {
+                "Failed to move the entry file into quarantine".into()
+            }
+            Message::VaultReadOnly => {
+                "The vault's location is not writable; it can only be viewed".into()
+            }
+            Message::TooManyKeySlots(max) => format!("At most {max} users can be added"),
+            Message::KeySlotNotFound => "The user does not exist or has been removed".into(),
+            Message::KeySlotPassphraseInUse => {
+                "This passphrase is already used by the main passphrase or another user".into()
+            }
+            Message::InvalidKeySlotLabel(max) => {
+                format!("User names must be non-empty and at most {max} characters")
+            }
+            Message::AdminPassphraseRequired => {
+                "This requires the main passphrase or an admin passphrase".into()
+            }
+            Message::LastAdminKeySlot => "At least one admin passphrase must remain".into(),
+            Message::DocumentTreeUnsupported => {
+                "content:// locations are not supported on this platform".into()
+            }
+            Message::DocumentTreeSyncFailed => {
+                "Failed to sync the vault files with the selected folder".into()
+            }
+            Message::DocumentTreeUnsyncedChanges => {
+                "The local copy has changes not yet written back to the selected folder".into()
+            }
+        }
+    }
+}
+
+fn years_ago(years: u32) -> String {
+    if years == 1 {
+        "1 year".into()
+    } else {
+        format!("{years} years")
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text(locale()))
+    }
+}
@@ -0,0 +1,164 @@
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Context, Result};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::permissions;
+use crate::vault::{AttachmentInfo, EntryInfo};
+
+// 条目索引的追加日志：两次整理之间的改动逐条加密追加，保存开销与日记规模无关。
+// 文件头记录所属的日志编号，与 vault.json 中的编号不符说明已整理过，整体忽略
+const JOURNAL_FILE: &str = "vault.journal";
+const JOURNAL_MAGIC: &[u8] = b"CDJRNL1\n";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum JournalChange {
+    Upsert { entry: Box<EntryInfo> },
+    Delete { id: Uuid },
+    Attachment { attachment: AttachmentInfo },
+}
+
+pub fn journal_file_path(root: &Path) -> PathBuf {
+    root.join(JOURNAL_FILE)
+}
+
+// 以新的日志编号开始一份空日志
+pub fn reset(path: &Path, journal_id: &Uuid) -> Result<()> {
+    let mut header = JOURNAL_MAGIC.to_vec();
+    header.extend_from_slice(journal_id.as_bytes());
+    // 已经是同一编号的空日志时不必重写，没有改动的解锁不写盘
+    if fs::read(path).is_ok_and(|bytes| bytes == header) {
+        return Ok(());
+    }
+    permissions::write_private(path, header).context("failed to reset metadata journal")
+}
+
+// 序号参与认证，记录被删改、重排或混入其他日志都会解密失败
+fn associated_data(journal_id: &Uuid, seq: u64) -> Vec<u8> {
+    let mut aad = journal_id.as_bytes().to_vec();
+    aad.extend_from_slice(&seq.to_le_bytes());
+    aad
+}
+
+// 追加若干改动并落盘，first_seq 为本批第一条记录的序号
+pub fn append(
+    path: &Path,
+    key: &[u8; 32],
+    journal_id: &Uuid,
+    first_seq: u64,
+    changes: &[JournalChange],
+) -> Result<()> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| anyhow!("invalid key"))?;
+    let mut buffer = Vec::new();
+    for (offset, change) in changes.iter().enumerate() {
+        let payload = serde_json::to_vec(change).context("failed to serialize journal record")?;
+        let aad = associated_data(journal_id, first_seq + offset as u64);
+        let mut nonce_bytes = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        #[allow(deprecated)]
+        let nonce = Nonce::from_slice(&nonce_bytes);
+        let ciphertext = cipher
+            .encrypt(
+                nonce,
+                Payload {
+                    msg: &payload,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| anyhow!("encryption failed"))?;
+
+        buffer.extend_from_slice(&((nonce_bytes.len() + ciphertext.len()) as u32).to_le_bytes());
+        buffer.extend_from_slice(&nonce_bytes);
+        buffer.extend_from_slice(&ciphertext);
+    }
+
+    // 一次写入整批记录，崩溃最多留下一条不完整的尾部记录
+    let mut file = permissions::open_private_append(path)?;
+    file.write_all(&buffer)
+        .context("failed to append metadata journal")?;
+    file.sync_data().context("failed to sync metadata journal")
+}
+
+// 读出属于 journal_id 的全部改动；末尾写了一半或补零的记录视为未提交而忽略
+pub fn replay(path: &Path, key: &[u8; 32], journal_id: &Uuid) -> Result<Vec<JournalChange>> {
+    let Ok(bytes) = fs::read(path) else {
+        return Ok(Vec::new());
+    };
+    let header_len = JOURNAL_MAGIC.len() + 16;
+    if bytes.len() < header_len
+        || !bytes.starts_with(JOURNAL_MAGIC)
+        || &bytes[JOURNAL_MAGIC.len()..header_len] != journal_id.as_bytes()
+    {
+        return Ok(Vec::new());
+    }
+
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| anyhow!("invalid key"))?;
+    let mut changes = Vec::new();
+    let mut rest = &bytes[header_len..];
+    while rest.len() >= 4 {
+        // 崩溃后文件可能已经变长而数据没有落盘，尾部全是零
+        if rest.iter().all(|byte| *byte == 0) {
+            break;
+        }
+        let len = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        if rest.len() < 4 + len {
+            break;
+        }
+        let record = &rest[4..4 + len];
+        rest = &rest[4 + len..];
+        let is_last = rest.iter().all(|byte| *byte == 0);
+
+        let aad = associated_data(journal_id, changes.len() as u64);
+        let plaintext = (record.len() >= 12)
+            .then(|| {
+                #[allow(deprecated)]
+                let nonce = Nonce::from_slice(&record[..12]);
+                cipher
+                    .decrypt(
+                        nonce,
+                        Payload {
+                            msg: &record[12..],
+                            aad: &aad,
+                        },
+                    )
+                    .ok()
+            })
+            .flatten();
+        // 只有最后一条记录可能是未写完的，中间的记录损坏说明日志被改动过
+        let Some(plaintext) = plaintext else {
+            if is_last {
+                break;
+            }
+            return Err(anyhow!("metadata journal is corrupted"));
+        };
+        changes.push(serde_json::from_slice(&plaintext).context("invalid journal record")?);
+    }
+
+    Ok(changes)
+}
+
+pub fn apply(
+    entries: &mut Vec<EntryInfo>,
+    attachments: &mut Vec<AttachmentInfo>,
+    change: &JournalChange,
+) {
+    match change {
+        JournalChange::Upsert { entry } => {
+            match entries.iter_mut().find(|existing| existing.id == entry.id) {
+                Some(existing) => *existing = (**entry).clone(),
+                None => entries.push((**entry).clone()),
+            }
+        }
+        JournalChange::Delete { id } => entries.retain(|existing| existing.id != *id),
+        JournalChange::Attachment { attachment } => {
+            attachments.retain(|existing| existing.path != attachment.path);
+            attachments.push(attachment.clone());
+        }
+    }
+}
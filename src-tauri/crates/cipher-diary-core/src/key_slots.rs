@@ -0,0 +1,339 @@
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::i18n::Message;
+use crate::permissions;
+use crate::vault::{derive_key, KdfParams};
+
+// 多用户密钥槽：每位用户的口令派生出包装密钥，解开同一把随机生成的主密钥，
+// 共用一本日记时各自使用自己的口令。新建日记时主口令也作为管理员槽位保存；
+// 旧日记的主密钥由主口令直接派生，没有管理员槽位，更换主密钥后才转为槽位
+const KEY_SLOTS_FILE: &str = "key_slots.json";
+const KEY_SLOTS_VERSION: u32 = 1;
+pub const MAX_KEY_SLOTS: usize = 8;
+pub const OWNER_LABEL: &str = "owner";
+
+#[derive(Serialize, Deserialize)]
+struct SlotFile {
+    version: u32,
+    slots: Vec<StoredSlot>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredSlot {
+    id: Uuid,
+    created_at: OffsetDateTime,
+    // 管理员可以添加、移除槽位和更换主密钥；该标记参与包装主密钥的认证，改动后槽位失效
+    #[serde(default)]
+    admin: bool,
+    salt: String,
+    // 用该用户口令派生的密钥加密的主密钥
+    wrapped_key: String,
+    // 用主密钥加密的标签，标签不以明文保存。包装密钥只能由用户口令派生，不随槽位保存，
+    // 否则持有主密钥的人能解出全部包装密钥，更换主密钥后仍可解开新的主密钥
+    sealed: String,
+}
+
+// 早期版本在其中还保存了包装密钥，读取时忽略
+#[derive(Serialize, Deserialize)]
+struct Sealed {
+    label: String,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct UserSlot {
+    pub id: Uuid,
+    pub label: String,
+    pub admin: bool,
+    pub created_at: OffsetDateTime,
+}
+
+pub fn key_slots_path(root: &Path) -> PathBuf {
+    root.join(KEY_SLOTS_FILE)
+}
+
+fn read(path: &Path) -> Result<Vec<StoredSlot>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).context("failed to read key slots")?;
+    let file: SlotFile = serde_json::from_str(&content).context("failed to parse key slots")?;
+    if file.version != KEY_SLOTS_VERSION {
+        return Err(anyhow!("unsupported key slot version"));
+    }
+    Ok(file.slots)
+}
+
+// 最后一个槽位删除后连同文件一起删除
+fn write(path: &Path, slots: Vec<StoredSlot>) -> Result<()> {
+    if slots.is_empty() {
+        if path.exists() {
+            fs::remove_file(path).context("failed to remove key slots")?;
+        }
+        return Ok(());
+    }
+    let file = SlotFile {
+        version: KEY_SLOTS_VERSION,
+        slots,
+    };
+    let serialized =
+        serde_json::to_string_pretty(&file).context("failed to serialize key slots")?;
+    permissions::write_private(path, serialized)
+}
+
+fn encode(bytes: &[u8]) -> String {
+    general_purpose::STANDARD_NO_PAD.encode(bytes)
+}
+
+fn decode(text: &str) -> Result<Vec<u8>> {
+    general_purpose::STANDARD_NO_PAD
+        .decode(text)
+        .context("invalid key slot encoding")
+}
+
+// 密文前 12 字节为 nonce；槽位编号作为附加数据，不同槽位之间不能互换内容
+fn seal(key: &[u8; 32], aad: &[u8], plaintext: &[u8]) -> Result<String> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| anyhow!("invalid key"))?;
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(&nonce_bytes);
+    let ciphertext = cipher
+        .encrypt(
+            nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| anyhow!("encryption failed"))?;
+    let mut sealed = nonce_bytes.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(encode(&sealed))
+}
+
+fn open(key: &[u8; 32], aad: &[u8], text: &str) -> Option<Vec<u8>> {
+    let bytes = decode(text).ok()?;
+    if bytes.len() < 12 {
+        return None;
+    }
+    let cipher = Aes256Gcm::new_from_slice(key).ok()?;
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(&bytes[..12]);
+    cipher
+        .decrypt(
+            nonce,
+            Payload {
+                msg: &bytes[12..],
+                aad,
+            },
+        )
+        .ok()
+}
+
+fn to_key(bytes: Vec<u8>) -> Result<[u8; 32]> {
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("invalid key slot data"))
+}
+
+// 早期槽位都不是管理员，附加数据只有槽位编号
+fn wrap_aad(id: &Uuid, admin: bool) -> Vec<u8> {
+    let mut aad = id.as_bytes().to_vec();
+    if admin {
+        aad.extend_from_slice(b"admin");
+    }
+    aad
+}
+
+fn unseal_label(master_key: &[u8; 32], slot: &StoredSlot) -> Result<String> {
+    let plaintext = open(master_key, slot.id.as_bytes(), &slot.sealed)
+        .ok_or_else(|| anyhow!("invalid key slot data"))?;
+    let sealed: Sealed = serde_json::from_slice(&plaintext).context("invalid key slot data")?;
+    Ok(sealed.label)
+}
+
+fn unwrap_slot(slot: &StoredSlot, passphrase: &str, kdf: &KdfParams) -> Result<Option<[u8; 32]>> {
+    let salt: [u8; 16] = decode(&slot.salt)?
+        .try_into()
+        .map_err(|_| anyhow!("invalid salt length"))?;
+    let wrapping_key = derive_key(passphrase, &salt, kdf)?;
+    open(
+        &wrapping_key,
+        &wrap_aad(&slot.id, slot.admin),
+        &slot.wrapped_key,
+    )
+    .map(to_key)
+    .transpose()
+}
+
+// 依次用各槽位的盐派生并尝试解开主密钥，返回匹配的槽位；没有匹配的槽位时返回 None
+pub fn unwrap_key(
+    root: &Path,
+    passphrase: &str,
+    kdf: &KdfParams,
+) -> Result<Option<(Uuid, [u8; 32])>> {
+    for slot in read(&key_slots_path(root))? {
+        if let Some(master_key) = unwrap_slot(&slot, passphrase, kdf)? {
+            return Ok(Some((slot.id, master_key)));
+        }
+    }
+    Ok(None)
+}
+
+// 只尝试指定的槽位；槽位已被移除时同样返回 None
+pub fn unwrap_key_with(
+    root: &Path,
+    id: Uuid,
+    passphrase: &str,
+    kdf: &KdfParams,
+) -> Result<Option<[u8; 32]>> {
+    match read(&key_slots_path(root))?
+        .iter()
+        .find(|slot| slot.id == id)
+    {
+        Some(slot) => unwrap_slot(slot, passphrase, kdf),
+        None => Ok(None),
+    }
+}
+
+// 有管理员槽位说明主密钥是随机生成的，不能再由主口令直接派生
+pub fn has_admin(root: &Path) -> Result<bool> {
+    Ok(read(&key_slots_path(root))?.iter().any(|slot| slot.admin))
+}
+
+pub fn is_admin(root: &Path, id: Uuid) -> Result<bool> {
+    Ok(read(&key_slots_path(root))?
+        .iter()
+        .any(|slot| slot.id == id && slot.admin))
+}
+
+pub fn list(root: &Path, master_key: &[u8; 32]) -> Result<Vec<UserSlot>> {
+    read(&key_slots_path(root))?
+        .iter()
+        .map(|slot| {
+            Ok(UserSlot {
+                id: slot.id,
+                label: unseal_label(master_key, slot)?,
+                admin: slot.admin,
+                created_at: slot.created_at,
+            })
+        })
+        .collect()
+}
+
+fn new_slot(
+    master_key: &[u8; 32],
+    kdf: &KdfParams,
+    label: &str,
+    passphrase: &str,
+    admin: bool,
+) -> Result<StoredSlot> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let wrapping_key = derive_key(passphrase, &salt, kdf)?;
+    let id = Uuid::new_v4();
+    let sealed = serde_json::to_vec(&Sealed {
+        label: label.to_string(),
+    })?;
+    Ok(StoredSlot {
+        id,
+        created_at: OffsetDateTime::now_utc(),
+        admin,
+        salt: encode(&salt),
+        wrapped_key: seal(&wrapping_key, &wrap_aad(&id, admin), master_key)?,
+        sealed: seal(master_key, id.as_bytes(), &sealed)?,
+    })
+}
+
+fn info(slot: &StoredSlot, label: &str) -> UserSlot {
+    UserSlot {
+        id: slot.id,
+        label: label.to_string(),
+        admin: slot.admin,
+        created_at: slot.created_at,
+    }
+}
+
+// 写入只有一个管理员槽位的新文件，覆盖原有槽位：新建日记和更换主密钥时使用
+pub fn create(
+    root: &Path,
+    master_key: &[u8; 32],
+    kdf: &KdfParams,
+    label: &str,
+    passphrase: &str,
+) -> Result<UserSlot> {
+    let slot = new_slot(master_key, kdf, label, passphrase, true)?;
+    let created = info(&slot, label);
+    write(&key_slots_path(root), vec![slot])?;
+    Ok(created)
+}
+
+// 槽位与主口令使用 vault.json 头部记录的同一组派生参数
+pub fn add(
+    root: &Path,
+    master_key: &[u8; 32],
+    kdf: &KdfParams,
+    label: &str,
+    passphrase: &str,
+    admin: bool,
+) -> Result<UserSlot> {
+    let path = key_slots_path(root);
+    let mut slots = read(&path)?;
+    if slots.len() >= MAX_KEY_SLOTS {
+        return Err(anyhow!(Message::TooManyKeySlots(MAX_KEY_SLOTS)));
+    }
+    let slot = new_slot(master_key, kdf, label, passphrase, admin)?;
+    let added = info(&slot, label);
+    slots.push(slot);
+    write(&path, slots)?;
+    Ok(added)
+}
+
+// 只换该槽位的盐和包装，主密钥不变，日记文件无需重新加密
+pub fn rewrap(
+    root: &Path,
+    id: Uuid,
+    master_key: &[u8; 32],
+    kdf: &KdfParams,
+    passphrase: &str,
+) -> Result<()> {
+    let path = key_slots_path(root);
+    let mut slots = read(&path)?;
+    let slot = slots
+        .iter_mut()
+        .find(|slot| slot.id == id)
+        .ok_or_else(|| anyhow!(Message::KeySlotNotFound))?;
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let wrapping_key = derive_key(passphrase, &salt, kdf)?;
+    slot.salt = encode(&salt);
+    slot.wrapped_key = seal(&wrapping_key, &wrap_aad(&id, slot.admin), master_key)?;
+    write(&path, slots)
+}
+
+// 删除后该口令无法再解开主密钥；此前已取得主密钥的人仍可解密现有文件，
+// 需要彻底撤销时再更换主密钥。至少保留一个管理员槽位
+pub fn remove(root: &Path, id: Uuid) -> Result<()> {
+    let path = key_slots_path(root);
+    let mut slots = read(&path)?;
+    let before = slots.len();
+    let removed_admin = slots.iter().any(|slot| slot.id == id && slot.admin);
+    slots.retain(|slot| slot.id != id);
+    if slots.len() == before {
+        return Err(anyhow!(Message::KeySlotNotFound));
+    }
+    if removed_admin && !slots.iter().any(|slot| slot.admin) {
+        return Err(anyhow!(Message::LastAdminKeySlot));
+    }
+    write(&path, slots)
+}
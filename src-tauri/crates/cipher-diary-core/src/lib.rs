@@ -0,0 +1,34 @@
+// 日记库的存储格式与加解密实现，不依赖 Tauri，桌面端、命令行等前端共用
+mod envelope;
+mod journal;
+mod migrations;
+mod search;
+mod sentiment;
+mod shards;
+mod vault_lock;
+
+pub mod drafts;
+pub mod emergency;
+pub mod entry_types;
+pub mod error;
+pub mod frontmatter;
+pub mod habits;
+pub mod health;
+pub mod history;
+pub mod i18n;
+pub mod key_slots;
+pub mod permissions;
+pub mod profile;
+pub mod prompts;
+pub mod quick_unlock;
+pub mod recap;
+pub mod share;
+pub mod storage;
+pub mod undo;
+pub mod unlock_log;
+pub mod validation;
+pub mod vault;
+pub mod wordfreq;
+
+pub use error::VaultError;
+pub use vault::{vault_file_path, VaultManager};
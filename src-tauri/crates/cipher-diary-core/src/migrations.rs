@@ -0,0 +1,77 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+use crate::i18n::Message;
+use crate::vault::StoredVault;
+
+// 格式的逐级升级：先把旧数据从原版本一步步改写到当前版本，再按当前结构解析。
+// vault.json 文件头、元数据和条目文件各自计数版本，以后修改哪一种格式就在对应的表末尾
+// 追加一步，并提高 vault.rs 中对应的版本常量
+pub struct Step<T: 'static> {
+    // 升级到 from + 1
+    pub from: u32,
+    pub description: &'static str,
+    pub apply: fn(&mut T) -> Result<()>,
+}
+
+// 解密出的元数据 JSON
+const METADATA_STEPS: &[Step<Value>] = &[Step {
+    from: 1,
+    // 条目列表仍在元数据中，解锁时按年份写入索引分片后随整理一起去掉
+    description: "move the entry list into yearly index shards",
+    apply: |_| Ok(()),
+}];
+
+// vault.json 的明文头部，在派生密钥之前升级；升级后的头部在下次保存时写回
+pub const VAULT_FILE_STEPS: &[Step<StoredVault>] = &[];
+
+// 条目文件解密、解压后的明文，升级到不带结构化内容的版本；读取时在内存中升级，
+// 条目下次保存时写成当前版本
+pub const ENTRY_FILE_STEPS: &[Step<Vec<u8>>] = &[];
+
+// 返回从 version 升级到 current 需要依次执行的步骤；版本相同时为空
+pub fn plan<T>(
+    steps: &'static [Step<T>],
+    version: u32,
+    current: u32,
+    unsupported: &'static str,
+) -> Result<Vec<&'static Step<T>>> {
+    if version > current {
+        return Err(anyhow!(Message::VaultTooNew));
+    }
+    (version..current)
+        .map(|from| {
+            steps
+                .iter()
+                .find(|step| step.from == from)
+                .ok_or_else(|| anyhow!(unsupported))
+        })
+        .collect()
+}
+
+pub fn plan_metadata(version: u32, current: u32) -> Result<Vec<&'static Step<Value>>> {
+    plan(METADATA_STEPS, version, current, "unsupported metadata version")
+}
+
+pub fn apply<T>(value: &mut T, steps: &[&Step<T>]) -> Result<()> {
+    for step in steps {
+        (step.apply)(value)?;
+    }
+    Ok(())
+}
+
+pub fn apply_metadata(metadata: &mut Value, steps: &[&Step<Value>]) -> Result<()> {
+    for step in steps {
+        (step.apply)(metadata)?;
+        metadata["version"] = Value::from(step.from + 1);
+    }
+    Ok(())
+}
+
+pub fn version(metadata: &Value) -> Result<u32> {
+    metadata
+        .get("version")
+        .and_then(Value::as_u64)
+        .and_then(|version| u32::try_from(version).ok())
+        .ok_or_else(|| anyhow!("invalid metadata"))
+}
@@ -0,0 +1,146 @@
+use anyhow::{Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+#[cfg(unix)]
+const PRIVATE_DIR_MODE: u32 = 0o700;
+#[cfg(unix)]
+const PRIVATE_FILE_MODE: u32 = 0o600;
+
+// 创建仅当前用户可访问的目录（Unix 下为 0700，Windows 下收紧 ACL）
+pub fn create_private_dir_all(path: &Path) -> Result<()> {
+    fs::create_dir_all(path)
+        .with_context(|| format!("failed to create directory: {:?}", path))?;
+    restrict_dir(path)
+}
+
+// 在目录中试建一个临时文件判断能否写入；只读挂载的文件系统上权限位仍可能显示可写
+pub fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".write-test-{}", std::process::id()));
+    match OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+// 写入仅当前用户可读写的文件，Unix 下创建时即为 0600，避免短暂的可读窗口
+pub fn write_private(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(PRIVATE_FILE_MODE);
+
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("failed to open file: {:?}", path))?;
+    file.write_all(contents.as_ref())
+        .with_context(|| format!("failed to write file: {:?}", path))?;
+    // 已存在的文件不会因 mode 改变权限，这里显式收紧一次
+    restrict_file(path)
+}
+
+// 以 0600 新建（或截断）文件并返回句柄，供流式写入
+pub fn create_private(path: &Path) -> Result<fs::File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(PRIVATE_FILE_MODE);
+    let file = options
+        .open(path)
+        .with_context(|| format!("failed to open file: {:?}", path))?;
+    restrict_file(path)?;
+    Ok(file)
+}
+
+pub fn copy_private(from: &Path, to: &Path) -> Result<()> {
+    fs::copy(from, to).with_context(|| format!("failed to copy file: {:?}", from))?;
+    restrict_file(to)
+}
+
+pub fn open_private_append(path: &Path) -> Result<fs::File> {
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    options.mode(PRIVATE_FILE_MODE);
+    let file = options
+        .open(path)
+        .with_context(|| format!("failed to open file: {:?}", path))?;
+    restrict_file(path)?;
+    Ok(file)
+}
+
+#[cfg(unix)]
+pub fn restrict_file(path: &Path) -> Result<()> {
+    fs::set_permissions(path, fs::Permissions::from_mode(PRIVATE_FILE_MODE))
+        .with_context(|| format!("failed to restrict permissions: {:?}", path))
+}
+
+// Windows 下文件继承所在目录的 ACL，只需收紧目录
+#[cfg(not(unix))]
+pub fn restrict_file(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn restrict_dir(path: &Path) -> Result<()> {
+    fs::set_permissions(path, fs::Permissions::from_mode(PRIVATE_DIR_MODE))
+        .with_context(|| format!("failed to restrict permissions: {:?}", path))
+}
+
+#[cfg(windows)]
+fn restrict_dir(path: &Path) -> Result<()> {
+    use std::process::Command;
+
+    let Ok(user) = std::env::var("USERNAME") else {
+        return Ok(());
+    };
+    // 移除继承的权限，仅保留当前用户的完全控制（失败时不阻断正常使用）
+    let _ = Command::new("icacls")
+        .arg(path)
+        .arg("/inheritance:r")
+        .arg("/grant:r")
+        .arg(format!("{user}:(OI)(CI)F"))
+        .output();
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn restrict_dir(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+// 递归查找其他用户可读的文件或目录
+#[cfg(unix)]
+pub fn find_world_readable(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    collect_world_readable(root, &mut found)?;
+    Ok(found)
+}
+
+#[cfg(unix)]
+fn collect_world_readable(path: &Path, found: &mut Vec<PathBuf>) -> Result<()> {
+    let metadata = fs::symlink_metadata(path)
+        .with_context(|| format!("failed to inspect permissions: {:?}", path))?;
+    if metadata.permissions().mode() & 0o004 != 0 {
+        found.push(path.to_path_buf());
+    }
+    if metadata.is_dir() {
+        for entry in fs::read_dir(path).context("failed to read directory")? {
+            let entry = entry.context("failed to read directory entry")?;
+            collect_world_readable(&entry.path(), found)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn find_world_readable(_root: &Path) -> Result<Vec<PathBuf>> {
+    Ok(Vec::new())
+}
@@ -0,0 +1,81 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::i18n::Message;
+use crate::permissions;
+
+// 名称、简介和图标放在密文之外，选择日记库时无需解锁即可显示。不要在这里写入敏感内容
+const PROFILE_FILE: &str = "profile.json";
+const MAX_NAME_CHARS: usize = 64;
+const MAX_DESCRIPTION_CHARS: usize = 500;
+const MAX_ICON_CHARS: usize = 8;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct VaultProfile {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+pub fn profile_file_path(root: &Path) -> PathBuf {
+    root.join(PROFILE_FILE)
+}
+
+// 文件缺失或损坏时返回空资料，不影响解锁
+pub fn read(root: &Path) -> VaultProfile {
+    fs::read_to_string(profile_file_path(root))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn write(root: &Path, profile: VaultProfile) -> Result<VaultProfile> {
+    let profile = normalize(profile)?;
+    let path = profile_file_path(root);
+    if profile == VaultProfile::default() {
+        if path.exists() {
+            fs::remove_file(&path).context("failed to remove vault profile")?;
+        }
+        return Ok(profile);
+    }
+    let serialized =
+        serde_json::to_string_pretty(&profile).context("failed to serialize vault profile")?;
+    permissions::write_private(&path, serialized).context("failed to write vault profile")?;
+    Ok(profile)
+}
+
+fn normalize(profile: VaultProfile) -> Result<VaultProfile> {
+    Ok(VaultProfile {
+        name: clean(profile.name, MAX_NAME_CHARS, Message::ProfileNameTooLong)?,
+        description: clean(
+            profile.description,
+            MAX_DESCRIPTION_CHARS,
+            Message::ProfileDescriptionTooLong,
+        )?,
+        icon: clean(profile.icon, MAX_ICON_CHARS, Message::ProfileIconTooLong)?,
+    })
+}
+
+fn clean(
+    value: Option<String>,
+    max_chars: usize,
+    too_long: fn(usize) -> Message,
+) -> Result<Option<String>> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+    if trimmed.chars().count() > max_chars {
+        return Err(anyhow!(too_long(max_chars)));
+    }
+    Ok(Some(trimmed.to_string()))
+}
@@ -0,0 +1,292 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::BTreeSet;
+use time::Date;
+use uuid::Uuid;
+
+use crate::i18n::{self, Locale, Message};
+use crate::vault::{Conflict, QuotaExceeded};
+
+// 写作提示：内置题库随程序发布，按当前语言显示；自定义题库和作答记录加密保存在元数据中
+pub const MAX_CUSTOM_PACKS: usize = 32;
+// 只保留最近的作答记录，够判断一年内答过哪些题
+const MAX_ANSWERED: usize = 1000;
+
+struct BuiltinPack {
+    id: &'static str,
+    name: (&'static str, &'static str),
+    prompts: &'static [(&'static str, &'static str)],
+}
+
+const BUILTIN_PACKS: &[BuiltinPack] = &[
+    BuiltinPack {
+        id: "builtin.reflection",
+        name: ("每日回顾", "Daily reflection"),
+        prompts: &[
+            (
+                "今天最让你印象深刻的一件事是什么？",
+                "What stood out most today?",
+            ),
+            (
+                "今天有什么事情没有按计划进行？",
+                "What didn't go as planned today?",
+            ),
+            ("今天你学到了什么？", "What did you learn today?"),
+            (
+                "今天你和谁聊得最多？聊了什么？",
+                "Who did you talk to most today, and about what?",
+            ),
+            (
+                "如果能重来，今天你会做哪件不同的事？",
+                "If you could redo today, what would you change?",
+            ),
+            ("今天是什么让你分心？", "What distracted you today?"),
+            (
+                "明天最想完成的一件事是什么？",
+                "What is the one thing you want to finish tomorrow?",
+            ),
+        ],
+    },
+    BuiltinPack {
+        id: "builtin.gratitude",
+        name: ("感恩", "Gratitude"),
+        prompts: &[
+            (
+                "今天有哪三件值得感谢的小事？",
+                "Name three small things you're grateful for today.",
+            ),
+            ("最近谁帮助过你？", "Who has helped you recently?"),
+            (
+                "你身边有什么常被忽略的好东西？",
+                "What good thing around you do you often overlook?",
+            ),
+            (
+                "今天哪个瞬间让你笑了？",
+                "What moment made you smile today?",
+            ),
+            (
+                "你最近为自己做了什么好事？",
+                "What have you done for yourself lately?",
+            ),
+        ],
+    },
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct PromptPack {
+    pub id: String,
+    pub name: String,
+    pub prompts: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct PromptPackInfo {
+    pub id: String,
+    pub name: String,
+    pub builtin: bool,
+    pub enabled: bool,
+    pub prompts: Vec<String>,
+}
+
+// date 为作答当天（本机时区），每天至多一条
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AnsweredPrompt {
+    pack: String,
+    index: usize,
+    date: Date,
+    entry_id: Uuid,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptState {
+    #[serde(default)]
+    custom: Vec<PromptPack>,
+    // 记录停用而不是启用的题库，以后新增的内置题库默认启用
+    #[serde(default)]
+    disabled: BTreeSet<String>,
+    #[serde(default)]
+    answered: Vec<AnsweredPrompt>,
+}
+
+// entry_id 不为空表示今天已经作答
+#[derive(Debug, Serialize, Type)]
+pub struct TodaysPrompt {
+    pub date: String,
+    pub pack_id: String,
+    pub pack_name: String,
+    pub index: u32,
+    pub text: String,
+    pub entry_id: Option<Uuid>,
+}
+
+fn builtin_pack(pack: &BuiltinPack, locale: Locale) -> PromptPack {
+    let pick = |(zh, en): (&str, &str)| match locale {
+        Locale::ZhCn => zh.to_string(),
+        Locale::En => en.to_string(),
+    };
+    PromptPack {
+        id: pack.id.to_string(),
+        name: pick(pack.name),
+        prompts: pack.prompts.iter().map(|prompt| pick(*prompt)).collect(),
+    }
+}
+
+fn is_builtin(id: &str) -> bool {
+    BUILTIN_PACKS.iter().any(|pack| pack.id == id)
+}
+
+impl PromptState {
+    // 内置题库在前，自定义题库按创建顺序
+    fn all_packs(&self) -> Vec<PromptPack> {
+        let locale = i18n::locale();
+        BUILTIN_PACKS
+            .iter()
+            .map(|pack| builtin_pack(pack, locale))
+            .chain(self.custom.iter().cloned())
+            .collect()
+    }
+
+    pub fn packs(&self) -> Vec<PromptPackInfo> {
+        self.all_packs()
+            .into_iter()
+            .map(|pack| PromptPackInfo {
+                builtin: is_builtin(&pack.id),
+                enabled: !self.disabled.contains(&pack.id),
+                id: pack.id,
+                name: pack.name,
+                prompts: pack.prompts,
+            })
+            .collect()
+    }
+
+    // id 为 None 时新建；修改题目会改变编号，旧的作答记录可能指向别的题目
+    pub fn save_pack(
+        &mut self,
+        id: Option<String>,
+        name: String,
+        prompts: Vec<String>,
+    ) -> Result<PromptPack> {
+        match id {
+            Some(id) if is_builtin(&id) => Err(Conflict(Message::BuiltinPromptPackReadOnly).into()),
+            Some(id) => {
+                let pack = self
+                    .custom
+                    .iter_mut()
+                    .find(|pack| pack.id == id)
+                    .ok_or_else(|| anyhow!(Message::PromptPackNotFound))?;
+                pack.name = name;
+                pack.prompts = prompts;
+                Ok(pack.clone())
+            }
+            None => {
+                if self.custom.len() >= MAX_CUSTOM_PACKS {
+                    return Err(QuotaExceeded(Message::TooManyPromptPacks(MAX_CUSTOM_PACKS)).into());
+                }
+                let pack = PromptPack {
+                    id: Uuid::new_v4().to_string(),
+                    name,
+                    prompts,
+                };
+                self.custom.push(pack.clone());
+                Ok(pack)
+            }
+        }
+    }
+
+    pub fn delete_pack(&mut self, id: &str) -> Result<()> {
+        if is_builtin(id) {
+            return Err(Conflict(Message::BuiltinPromptPackReadOnly).into());
+        }
+        let before = self.custom.len();
+        self.custom.retain(|pack| pack.id != id);
+        if self.custom.len() == before {
+            return Err(anyhow!(Message::PromptPackNotFound));
+        }
+        self.disabled.remove(id);
+        self.answered.retain(|answer| answer.pack != id);
+        Ok(())
+    }
+
+    pub fn set_enabled(&mut self, id: &str, enabled: bool) -> Result<()> {
+        if !is_builtin(id) && !self.custom.iter().any(|pack| pack.id == id) {
+            return Err(anyhow!(Message::PromptPackNotFound));
+        }
+        if enabled {
+            self.disabled.remove(id);
+        } else {
+            self.disabled.insert(id.to_string());
+        }
+        Ok(())
+    }
+
+    // 按日期在所有启用的题目中轮换，跳过答过的题；全部答过后从头再来。
+    // 当天已作答时固定返回那道题。关联的日记已删除的作答记录视为未作答
+    pub fn today(&self, date: Date, exists: impl Fn(&Uuid) -> bool) -> Option<TodaysPrompt> {
+        let answered: Vec<&AnsweredPrompt> = self
+            .answered
+            .iter()
+            .filter(|answer| exists(&answer.entry_id))
+            .collect();
+        let candidates: Vec<(PromptPack, usize)> = self
+            .all_packs()
+            .into_iter()
+            .filter(|pack| !self.disabled.contains(&pack.id))
+            .flat_map(|pack| (0..pack.prompts.len()).map(move |index| (pack.clone(), index)))
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        let prompt = |(pack, index): &(PromptPack, usize), entry_id| TodaysPrompt {
+            date: date.to_string(),
+            pack_id: pack.id.clone(),
+            pack_name: pack.name.clone(),
+            index: *index as u32,
+            text: pack.prompts[*index].clone(),
+            entry_id,
+        };
+
+        if let Some(answer) = answered.iter().rev().find(|answer| answer.date == date) {
+            let answered = candidates
+                .iter()
+                .find(|(pack, index)| pack.id == answer.pack && *index == answer.index);
+            if let Some(candidate) = answered {
+                return Some(prompt(candidate, Some(answer.entry_id)));
+            }
+        }
+
+        let start = date.to_julian_day().rem_euclid(candidates.len() as i32) as usize;
+        let rotated = || candidates.iter().cycle().skip(start).take(candidates.len());
+        let chosen = rotated()
+            .find(|(pack, index)| {
+                !answered
+                    .iter()
+                    .any(|answer| answer.pack == pack.id && answer.index == *index)
+            })
+            .or_else(|| rotated().next())?;
+        Some(prompt(chosen, None))
+    }
+
+    // 同一天再次作答时覆盖之前的记录
+    pub fn answer(&mut self, date: Date, pack: &str, index: usize, entry_id: Uuid) -> Result<()> {
+        let exists = self
+            .all_packs()
+            .iter()
+            .any(|candidate| candidate.id == pack && index < candidate.prompts.len());
+        if !exists {
+            return Err(anyhow!(Message::PromptPackNotFound));
+        }
+        self.answered.retain(|answer| answer.date != date);
+        self.answered.push(AnsweredPrompt {
+            pack: pack.to_string(),
+            index,
+            date,
+            entry_id,
+        });
+        if self.answered.len() > MAX_ANSWERED {
+            let excess = self.answered.len() - MAX_ANSWERED;
+            self.answered.drain(..excess);
+        }
+        Ok(())
+    }
+}
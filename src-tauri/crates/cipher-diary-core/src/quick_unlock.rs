@@ -0,0 +1,240 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::i18n::Message;
+use crate::permissions;
+use crate::vault::{derive_key, original_kdf_params};
+
+const KEYRING_SERVICE: &str = "cipher-diary-quick-unlock";
+const QUICK_UNLOCK_VERSION: u32 = 1;
+const MIN_PIN_LENGTH: usize = 4;
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+// 主密钥的包装副本：包装密钥由 PIN 派生值与系统钥匙串中的设备密钥共同决定，
+// 单独拿到这个文件无法离线暴力破解 PIN
+#[derive(Serialize, Deserialize)]
+struct WrappedKey {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+// 钥匙串中的设备密钥和错误计数。计数不放在普通文件里，改写或换回旧文件都不能重置
+#[derive(Serialize, Deserialize)]
+struct DeviceKey {
+    secret: String,
+    failed_attempts: u32,
+    max_attempts: u32,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct QuickUnlockStatus {
+    pub enabled: bool,
+    pub remaining_attempts: Option<u32>,
+}
+
+// 以日记目录的规范路径区分不同日记
+pub(crate) fn vault_id(root: &Path) -> String {
+    let canonical = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let digest = Sha256::digest(canonical.to_string_lossy().as_bytes());
+    format!("{:x}", digest)[..32].to_string()
+}
+
+fn wrapped_key_path(store_dir: &Path, root: &Path) -> PathBuf {
+    store_dir.join(format!("{}.json", vault_id(root)))
+}
+
+fn keyring_entry(root: &Path) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, &vault_id(root)).context(Message::KeychainUnavailable)
+}
+
+// 读不到或格式不对都视为没有设备密钥
+fn read_device_key(root: &Path) -> Result<Option<DeviceKey>> {
+    let entry = keyring_entry(root)?;
+    Ok(entry
+        .get_secret()
+        .ok()
+        .and_then(|secret| serde_json::from_slice(&secret).ok()))
+}
+
+fn write_device_key(root: &Path, device: &DeviceKey) -> Result<()> {
+    let serialized = serde_json::to_vec(device).context("failed to serialize device key")?;
+    keyring_entry(root)?
+        .set_secret(&serialized)
+        .context(Message::KeychainWriteFailed)
+}
+
+fn wrapping_key(device_secret: &[u8], pin: &str, salt: &[u8; 16]) -> Result<[u8; 32]> {
+    let pin_key = derive_key(pin, salt, &original_kdf_params())?;
+    let mut hasher = Sha256::new();
+    hasher.update(device_secret);
+    hasher.update(pin_key);
+    Ok(hasher.finalize().into())
+}
+
+fn read_wrapped(path: &Path) -> Result<Option<WrappedKey>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).context("failed to read quick unlock data")?;
+    let wrapped: WrappedKey =
+        serde_json::from_str(&content).context("failed to parse quick unlock data")?;
+    if wrapped.version != QUICK_UNLOCK_VERSION {
+        return Err(anyhow!("unsupported quick unlock version"));
+    }
+    Ok(Some(wrapped))
+}
+
+fn write_wrapped(path: &Path, wrapped: &WrappedKey) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        permissions::create_private_dir_all(parent)?;
+    }
+    let serialized =
+        serde_json::to_string_pretty(wrapped).context("failed to serialize quick unlock data")?;
+    permissions::write_private(path, serialized)
+}
+
+pub fn enable(
+    store_dir: &Path,
+    root: &Path,
+    pin: &str,
+    master_key: &[u8; 32],
+    max_attempts: u32,
+) -> Result<()> {
+    if pin.chars().count() < MIN_PIN_LENGTH || !pin.chars().all(|ch| ch.is_ascii_digit()) {
+        return Err(anyhow!(Message::PinTooShort(MIN_PIN_LENGTH)));
+    }
+
+    let mut device_secret = [0u8; 32];
+    OsRng.fill_bytes(&mut device_secret);
+    write_device_key(
+        root,
+        &DeviceKey {
+            secret: general_purpose::STANDARD_NO_PAD.encode(device_secret),
+            failed_attempts: 0,
+            max_attempts: if max_attempts == 0 {
+                DEFAULT_MAX_ATTEMPTS
+            } else {
+                max_attempts
+            },
+        },
+    )?;
+    // 没有持久钥匙串的平台上读不回刚写入的密钥
+    if read_device_key(root)?.is_none() {
+        return Err(anyhow!(Message::KeychainUnavailable));
+    }
+
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let key = wrapping_key(&device_secret, pin, &salt)?;
+
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| anyhow!("invalid key"))?;
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(&nonce_bytes);
+    let ciphertext = cipher
+        .encrypt(nonce, master_key.as_ref())
+        .map_err(|_| anyhow!("encryption failed"))?;
+
+    write_wrapped(
+        &wrapped_key_path(store_dir, root),
+        &WrappedKey {
+            version: QUICK_UNLOCK_VERSION,
+            salt: general_purpose::STANDARD_NO_PAD.encode(salt),
+            nonce: general_purpose::STANDARD_NO_PAD.encode(nonce_bytes),
+            ciphertext: general_purpose::STANDARD_NO_PAD.encode(ciphertext),
+        },
+    )
+}
+
+pub fn disable(store_dir: &Path, root: &Path) -> Result<()> {
+    let path = wrapped_key_path(store_dir, root);
+    if path.exists() {
+        fs::remove_file(&path).context("failed to remove quick unlock data")?;
+    }
+    if let Ok(entry) = keyring_entry(root) {
+        let _ = entry.delete_credential();
+    }
+    Ok(())
+}
+
+pub fn status(store_dir: &Path, root: &Path) -> Result<QuickUnlockStatus> {
+    let enabled = read_wrapped(&wrapped_key_path(store_dir, root))?.is_some();
+    let device = if enabled { read_device_key(root)? } else { None };
+    Ok(QuickUnlockStatus {
+        enabled,
+        remaining_attempts: device
+            .map(|device| device.max_attempts.saturating_sub(device.failed_attempts)),
+    })
+}
+
+// 错误次数达到上限后删除包装密钥，之后只能使用完整密码
+pub fn unwrap_key(store_dir: &Path, root: &Path, pin: &str) -> Result<[u8; 32]> {
+    let path = wrapped_key_path(store_dir, root);
+    let wrapped = read_wrapped(&path)?.ok_or_else(|| anyhow!(Message::QuickUnlockDisabled))?;
+
+    let Some(mut device) = read_device_key(root)? else {
+        disable(store_dir, root)?;
+        return Err(anyhow!(Message::DeviceKeyUnavailable));
+    };
+    if device.failed_attempts >= device.max_attempts {
+        disable(store_dir, root)?;
+        return Err(anyhow!(Message::PinLockedOut));
+    }
+    // 先把这次尝试记为失败再解密，中途结束进程也会计入
+    device.failed_attempts += 1;
+    write_device_key(root, &device)?;
+    let device_secret = general_purpose::STANDARD_NO_PAD
+        .decode(&device.secret)
+        .context("invalid device key encoding")?;
+
+    let salt: [u8; 16] = general_purpose::STANDARD_NO_PAD
+        .decode(&wrapped.salt)
+        .context("invalid salt encoding")?
+        .try_into()
+        .map_err(|_| anyhow!("invalid salt length"))?;
+    let nonce_bytes = general_purpose::STANDARD_NO_PAD
+        .decode(&wrapped.nonce)
+        .context("invalid nonce encoding")?;
+    if nonce_bytes.len() != 12 {
+        return Err(anyhow!("invalid nonce length"));
+    }
+    let ciphertext = general_purpose::STANDARD_NO_PAD
+        .decode(&wrapped.ciphertext)
+        .context("invalid ciphertext encoding")?;
+
+    let key = wrapping_key(&device_secret, pin, &salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| anyhow!("invalid key"))?;
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(&nonce_bytes);
+
+    match cipher.decrypt(nonce, ciphertext.as_ref()) {
+        Ok(plaintext) => {
+            let master_key: [u8; 32] = plaintext
+                .try_into()
+                .map_err(|_| anyhow!("invalid quick unlock data"))?;
+            device.failed_attempts = 0;
+            write_device_key(root, &device)?;
+            Ok(master_key)
+        }
+        Err(_) => {
+            if device.failed_attempts >= device.max_attempts {
+                disable(store_dir, root)?;
+                return Err(anyhow!(Message::PinLockedOut));
+            }
+            Err(anyhow!(Message::WrongPin(
+                device.max_attempts - device.failed_attempts
+            )))
+        }
+    }
+}
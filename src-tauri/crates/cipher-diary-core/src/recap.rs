@@ -0,0 +1,87 @@
+use serde::Serialize;
+use specta::Type;
+use uuid::Uuid;
+
+use crate::i18n::Message;
+use crate::vault::{EntryInfo, TagCount};
+
+// 月度回顾：统计由日记库计算，这里只负责结构和写成日记时的正文
+pub const MAX_RECAP_TAGS: usize = 5;
+pub const MAX_RECAP_PHOTOS: usize = 9;
+pub const MAX_RECAP_LINES: usize = 5;
+
+// photos 为当月保存的图片附件，按保存时间排列；
+// highlights 取当月字数最多的几篇，按日期排列
+#[derive(Debug, Serialize, Type)]
+pub struct MonthRecap {
+    pub year: i32,
+    pub month: u8,
+    pub entries: u32,
+    pub words: u64,
+    pub active_days: u32,
+    pub average_mood: Option<f64>,
+    pub top_tags: Vec<TagCount>,
+    pub photos: Vec<String>,
+    pub highlights: Vec<RecapLine>,
+    // 要求写成日记时为新建或更新后的回顾日记
+    pub entry: Option<EntryInfo>,
+}
+
+// date 为 YYYY-MM-DD，preview 为正文首行摘要
+#[derive(Debug, Serialize, Type)]
+pub struct RecapLine {
+    pub id: Uuid,
+    pub date: String,
+    pub title: String,
+    pub preview: String,
+}
+
+impl MonthRecap {
+    pub fn title(&self) -> String {
+        Message::RecapTitle(self.year, self.month).to_string()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut overview = vec![format!(
+            "- {}",
+            Message::RecapSummary(self.entries, self.words, self.active_days)
+        )];
+        if let Some(mood) = self.average_mood {
+            overview.push(format!("- {}", Message::RecapMood(format!("{mood:.1}"))));
+        }
+        if !self.top_tags.is_empty() {
+            let tags: Vec<String> = self
+                .top_tags
+                .iter()
+                .map(|tag| format!("#{}", tag.tag))
+                .collect();
+            overview.push(format!("- {}{}", Message::RecapTags, tags.join(" ")));
+        }
+        let mut sections = vec![overview.join("\n")];
+        if !self.photos.is_empty() {
+            let photos: Vec<String> = self
+                .photos
+                .iter()
+                .map(|path| format!("![]({path})"))
+                .collect();
+            sections.push(format!(
+                "## {}\n\n{}",
+                Message::RecapPhotos,
+                photos.join("\n")
+            ));
+        }
+        if !self.highlights.is_empty() {
+            let lines: Vec<String> = self
+                .highlights
+                .iter()
+                .map(|line| format!("- {} **{}**: {}", line.date, line.title, line.preview))
+                .collect();
+            sections.push(format!(
+                "## {}\n\n{}",
+                Message::RecapHighlights,
+                lines.join("\n")
+            ));
+        }
+        sections.join("\n\n")
+    }
+}
@@ -0,0 +1,260 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Context, Result};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::envelope::{self, Kind};
+use crate::permissions;
+
+// 全文检索的倒排索引，加密保存在磁盘上，条目保存/删除时增量更新。
+// 每篇文档记录建索引时的 updated_at，解锁时只为不一致的条目重建
+const SEARCH_INDEX_FILE: &str = "search.idx";
+const SEARCH_INDEX_VERSION: u32 = 1;
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct SearchIndex {
+    docs: BTreeMap<Uuid, IndexedDoc>,
+    postings: BTreeMap<String, BTreeSet<Uuid>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexedDoc {
+    updated_at: OffsetDateTime,
+    terms: Vec<String>,
+}
+
+pub fn index_file_path(root: &Path) -> PathBuf {
+    root.join(SEARCH_INDEX_FILE)
+}
+
+// 中日韩文字没有空格分词，按单字和相邻两字建索引
+fn is_cjk(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF
+    )
+}
+
+enum Token {
+    Word(String),
+    Cjk(Vec<char>),
+}
+
+fn split_tokens(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut run = Vec::new();
+    for ch in text.chars().flat_map(char::to_lowercase) {
+        if is_cjk(ch) {
+            if !word.is_empty() {
+                tokens.push(Token::Word(std::mem::take(&mut word)));
+            }
+            run.push(ch);
+        } else {
+            if !run.is_empty() {
+                tokens.push(Token::Cjk(std::mem::take(&mut run)));
+            }
+            if ch.is_alphanumeric() {
+                word.push(ch);
+            } else if !word.is_empty() {
+                tokens.push(Token::Word(std::mem::take(&mut word)));
+            }
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(Token::Word(word));
+    }
+    if !run.is_empty() {
+        tokens.push(Token::Cjk(run));
+    }
+    tokens
+}
+
+fn document_terms(text: &str) -> BTreeSet<String> {
+    let mut terms = BTreeSet::new();
+    for token in split_tokens(text) {
+        match token {
+            Token::Word(word) => {
+                terms.insert(word);
+            }
+            Token::Cjk(run) => {
+                terms.extend(run.iter().map(char::to_string));
+                terms.extend(run.windows(2).map(|pair| pair.iter().collect::<String>()));
+            }
+        }
+    }
+    terms
+}
+
+// 词频统计用：其他语言按词；中日韩文字没有词典可用，先在 stop_chars 处断开，
+// 再取相邻两字作为词，单独剩下的一个字不计
+pub fn frequency_terms(text: &str, stop_chars: &[char]) -> Vec<String> {
+    let mut terms = Vec::new();
+    for token in split_tokens(text) {
+        match token {
+            Token::Word(word) => terms.push(word),
+            Token::Cjk(run) => {
+                for part in run.split(|ch| stop_chars.contains(ch)) {
+                    terms.extend(part.windows(2).map(|pair| pair.iter().collect::<String>()));
+                }
+            }
+        }
+    }
+    terms
+}
+
+// 字数与分词规则一致：中日韩文字按字计，其余按词计
+pub fn word_count(text: &str) -> u32 {
+    split_tokens(text)
+        .iter()
+        .map(|token| match token {
+            Token::Word(_) => 1,
+            Token::Cjk(run) => run.len() as u32,
+        })
+        .sum()
+}
+
+// 列表中显示的摘要：第一行非空文字，去掉 Markdown 的标题、引用和列表标记
+pub fn preview(text: &str) -> String {
+    const PREVIEW_CHARS: usize = 80;
+    let line = text
+        .lines()
+        .map(|line| line.trim_start_matches(['#', '>', '-', '*', ' ', '\t']).trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    if line.chars().count() > PREVIEW_CHARS {
+        let mut short: String = line.chars().take(PREVIEW_CHARS).collect();
+        short.push('…');
+        short
+    } else {
+        line.to_string()
+    }
+}
+
+impl SearchIndex {
+    pub fn is_current(&self, id: &Uuid, updated_at: OffsetDateTime) -> bool {
+        self.docs
+            .get(id)
+            .is_some_and(|doc| doc.updated_at == updated_at)
+    }
+
+    pub fn upsert(&mut self, id: Uuid, updated_at: OffsetDateTime, title: &str, content: &str) {
+        self.remove(&id);
+        let mut terms = document_terms(title);
+        terms.extend(document_terms(content));
+        for term in terms.iter() {
+            self.postings.entry(term.clone()).or_default().insert(id);
+        }
+        self.docs.insert(
+            id,
+            IndexedDoc {
+                updated_at,
+                terms: terms.into_iter().collect(),
+            },
+        );
+    }
+
+    // 标题和正文都没变，只需同步更新时间，避免下次解锁时重新分词
+    pub fn refresh(&mut self, id: &Uuid, updated_at: OffsetDateTime) {
+        if let Some(doc) = self.docs.get_mut(id) {
+            doc.updated_at = updated_at;
+        }
+    }
+
+    pub fn remove(&mut self, id: &Uuid) {
+        let Some(doc) = self.docs.remove(id) else {
+            return;
+        };
+        for term in doc.terms {
+            if let Some(ids) = self.postings.get_mut(&term) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+
+    // 移除已不在日记中的文档，返回是否有文档被移除
+    pub fn retain(&mut self, keep: impl Fn(&Uuid) -> bool) -> bool {
+        let stale: Vec<Uuid> = self.docs.keys().filter(|id| !keep(id)).copied().collect();
+        for id in stale.iter() {
+            self.remove(id);
+        }
+        !stale.is_empty()
+    }
+
+    // 所有查询词都要命中；英文等按词前缀匹配，中日韩按相邻两字匹配
+    pub fn search(&self, query: &str) -> BTreeSet<Uuid> {
+        let mut result: Option<BTreeSet<Uuid>> = None;
+        for token in split_tokens(query) {
+            let terms: Vec<(String, bool)> = match token {
+                Token::Word(word) => vec![(word, true)],
+                Token::Cjk(run) if run.len() == 1 => vec![(run[0].to_string(), false)],
+                Token::Cjk(run) => run
+                    .windows(2)
+                    .map(|pair| (pair.iter().collect(), false))
+                    .collect(),
+            };
+            for (term, prefix) in terms {
+                let matches: BTreeSet<Uuid> = if prefix {
+                    self.postings
+                        .range(term.clone()..)
+                        .take_while(|(key, _)| key.starts_with(&term))
+                        .flat_map(|(_, ids)| ids.iter().copied())
+                        .collect()
+                } else {
+                    self.postings.get(&term).cloned().unwrap_or_default()
+                };
+                result = Some(match result {
+                    None => matches,
+                    Some(found) => found.intersection(&matches).copied().collect(),
+                });
+            }
+        }
+        result.unwrap_or_default()
+    }
+}
+
+// 索引文件缺失、损坏或用旧密钥加密时返回 None，由调用方重建
+pub fn load(path: &Path, key: &[u8; 32]) -> Option<SearchIndex> {
+    let bytes = fs::read(path).ok()?;
+    let fields = envelope::decode(&bytes, Kind::SearchIndex, 3).ok()?;
+    if fields[0] != SEARCH_INDEX_VERSION.to_le_bytes() || fields[1].len() != 12 {
+        return None;
+    }
+    let cipher = Aes256Gcm::new_from_slice(key).ok()?;
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(fields[1]);
+    let compressed = cipher.decrypt(nonce, fields[2]).ok()?;
+    let plaintext = zstd::decode_all(compressed.as_slice()).ok()?;
+    serde_json::from_slice(&plaintext).ok()
+}
+
+pub fn save(path: &Path, key: &[u8; 32], index: &SearchIndex) -> Result<()> {
+    let payload = serde_json::to_vec(index).context("failed to serialize search index")?;
+    let compressed =
+        zstd::encode_all(payload.as_slice(), ZSTD_LEVEL).context("failed to compress data")?;
+
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| anyhow!("invalid key"))?;
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(&nonce_bytes);
+    let ciphertext = cipher
+        .encrypt(nonce, compressed.as_ref())
+        .map_err(|_| anyhow!("encryption failed"))?;
+
+    let bytes = envelope::encode(
+        Kind::SearchIndex,
+        &[&SEARCH_INDEX_VERSION.to_le_bytes(), &nonce_bytes, &ciphertext],
+    );
+    permissions::write_private(path, bytes).context("failed to write search index")
+}
@@ -0,0 +1,188 @@
+// 本机情感打分：只用内置的中英文情感词典，正文不会离开本机。
+// 结果在 -1（消极）到 1（积极）之间，没有命中任何情感词时为 0
+const NORMALIZE: f64 = 15.0;
+// 否定词之后的情感词反向并减弱，“不坏”不等于“很好”
+const NEGATION_FACTOR: f64 = -0.5;
+// 英文否定词影响之后的几个词，如 not very happy
+const NEGATION_WINDOW: u8 = 3;
+
+const EN_NEGATIONS: &[&str] = &["not", "no", "never", "without", "hardly", "nothing"];
+const ZH_NEGATIONS: &[char] = &['不', '没', '别', '未', '无', '非'];
+
+const EN_WORDS: &[(&str, i8)] = &[
+    ("amazing", 3),
+    ("awesome", 3),
+    ("beautiful", 2),
+    ("blessed", 2),
+    ("calm", 1),
+    ("cheerful", 2),
+    ("comfortable", 1),
+    ("confident", 2),
+    ("delighted", 3),
+    ("enjoy", 2),
+    ("enjoyed", 2),
+    ("excited", 2),
+    ("fantastic", 3),
+    ("fun", 2),
+    ("glad", 2),
+    ("good", 1),
+    ("grateful", 2),
+    ("great", 2),
+    ("happy", 2),
+    ("hope", 1),
+    ("hopeful", 2),
+    ("joy", 3),
+    ("love", 3),
+    ("loved", 3),
+    ("lucky", 2),
+    ("nice", 1),
+    ("peaceful", 2),
+    ("proud", 2),
+    ("relaxed", 2),
+    ("relieved", 2),
+    ("satisfied", 2),
+    ("success", 2),
+    ("thankful", 2),
+    ("wonderful", 3),
+    ("afraid", -2),
+    ("angry", -3),
+    ("annoyed", -2),
+    ("anxious", -2),
+    ("awful", -3),
+    ("bad", -2),
+    ("bored", -1),
+    ("depressed", -3),
+    ("disappointed", -2),
+    ("exhausted", -2),
+    ("failed", -2),
+    ("frustrated", -2),
+    ("hate", -3),
+    ("hurt", -2),
+    ("lonely", -2),
+    ("lost", -1),
+    ("miserable", -3),
+    ("nervous", -2),
+    ("pain", -2),
+    ("regret", -2),
+    ("sad", -2),
+    ("scared", -2),
+    ("sick", -2),
+    ("stressed", -2),
+    ("terrible", -3),
+    ("tired", -1),
+    ("upset", -2),
+    ("worried", -2),
+    ("worse", -2),
+    ("worst", -3),
+];
+
+// 只收两个字以上的词，避免单字在其他词里误命中
+const ZH_WORDS: &[(&str, i8)] = &[
+    ("开心", 2),
+    ("高兴", 2),
+    ("快乐", 2),
+    ("幸福", 3),
+    ("满足", 2),
+    ("满意", 2),
+    ("感激", 2),
+    ("感谢", 2),
+    ("感动", 2),
+    ("温暖", 2),
+    ("轻松", 1),
+    ("放松", 2),
+    ("顺利", 2),
+    ("成功", 2),
+    ("兴奋", 2),
+    ("期待", 1),
+    ("喜欢", 2),
+    ("美好", 2),
+    ("平静", 1),
+    ("安心", 1),
+    ("自豪", 2),
+    ("欣慰", 2),
+    ("愉快", 2),
+    ("舒服", 1),
+    ("精彩", 2),
+    ("有趣", 1),
+    ("甜蜜", 2),
+    ("难过", -2),
+    ("伤心", -2),
+    ("痛苦", -3),
+    ("焦虑", -2),
+    ("担心", -2),
+    ("害怕", -2),
+    ("生气", -2),
+    ("愤怒", -3),
+    ("失望", -2),
+    ("沮丧", -2),
+    ("孤独", -2),
+    ("疲惫", -2),
+    ("烦躁", -2),
+    ("压力", -1),
+    ("崩溃", -3),
+    ("后悔", -2),
+    ("委屈", -2),
+    ("紧张", -1),
+    ("郁闷", -2),
+    ("难受", -2),
+    ("糟糕", -2),
+    ("失败", -2),
+    ("无聊", -1),
+    ("烦恼", -2),
+    ("讨厌", -2),
+    ("绝望", -3),
+    ("失眠", -1),
+];
+
+pub fn score(text: &str) -> f64 {
+    let total = english(text) + chinese(text);
+    if total == 0.0 {
+        return 0.0;
+    }
+    total / (total * total + NORMALIZE).sqrt()
+}
+
+fn english(text: &str) -> f64 {
+    let mut total = 0.0;
+    let mut negated = 0u8;
+    let words = text
+        .split(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '\''))
+        .filter(|word| !word.is_empty());
+    for word in words {
+        let word = word.to_ascii_lowercase();
+        if let Some((_, weight)) = EN_WORDS.iter().find(|(item, _)| *item == word) {
+            let weight = f64::from(*weight);
+            total += if negated > 0 {
+                weight * NEGATION_FACTOR
+            } else {
+                weight
+            };
+            negated = 0;
+        } else if EN_NEGATIONS.contains(&word.as_str()) || word.ends_with("n't") {
+            negated = NEGATION_WINDOW;
+        } else {
+            negated = negated.saturating_sub(1);
+        }
+    }
+    total
+}
+
+// 中文不分词，直接在正文中查找词典里的词，看紧挨着的前一个字是否为否定词
+fn chinese(text: &str) -> f64 {
+    ZH_WORDS
+        .iter()
+        .map(|(word, weight)| {
+            let weight = f64::from(*weight);
+            text.match_indices(word)
+                .map(|(at, _)| {
+                    let before = text[..at].chars().next_back();
+                    if before.is_some_and(|ch| ZH_NEGATIONS.contains(&ch)) {
+                        weight * NEGATION_FACTOR
+                    } else {
+                        weight
+                    }
+                })
+                .sum::<f64>()
+        })
+        .sum()
+}
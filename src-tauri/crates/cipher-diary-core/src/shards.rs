@@ -0,0 +1,167 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::permissions;
+use crate::vault::EntryInfo;
+
+const SHARD_VERSION: u32 = 1;
+pub const INDEX_DIR: &str = "index";
+
+// 条目索引按创建年份分片保存，每次保存只重写发生变化的年份。
+// 分片文件名带内容摘要，新分片写好且 vault.json 落盘后才清理旧分片，中途崩溃不会破坏索引
+#[derive(Serialize, Deserialize)]
+struct StoredShard {
+    version: u32,
+    year: i32,
+    nonce: String,
+    ciphertext: String,
+}
+
+pub fn shard_year(info: &EntryInfo) -> i32 {
+    info.created_at.year()
+}
+
+fn shard_path(index_dir: &Path, year: i32, hash: &str) -> PathBuf {
+    index_dir.join(shard_file_name(year, hash))
+}
+
+fn shard_file_name(year: i32, hash: &str) -> String {
+    format!("{year}-{}.json", &hash[..hash.len().min(16)])
+}
+
+fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+// 写入某一年的分片并返回文件摘要；该年已无条目时不写文件
+pub fn write_shard(
+    index_dir: &Path,
+    key: &[u8; 32],
+    year: i32,
+    entries: &[&EntryInfo],
+) -> Result<Option<String>> {
+    if entries.is_empty() {
+        return Ok(None);
+    }
+
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| anyhow!("invalid key"))?;
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(&nonce_bytes);
+    let payload = serde_json::to_vec(entries).context("failed to serialize index shard")?;
+    let ciphertext = cipher
+        .encrypt(nonce, payload.as_ref())
+        .map_err(|_| anyhow!("encryption failed"))?;
+
+    let stored = StoredShard {
+        version: SHARD_VERSION,
+        year,
+        nonce: general_purpose::STANDARD_NO_PAD.encode(nonce_bytes),
+        ciphertext: general_purpose::STANDARD_NO_PAD.encode(ciphertext),
+    };
+    let serialized = serde_json::to_vec(&stored).context("failed to serialize index shard")?;
+    let hash = hash_bytes(&serialized);
+
+    permissions::create_private_dir_all(index_dir).context("failed to prepare index directory")?;
+    permissions::write_private(&shard_path(index_dir, year, &hash), &serialized)
+        .context("failed to write index shard")?;
+    Ok(Some(hash))
+}
+
+// 按元数据中记录的摘要读取全部分片，摘要不符说明分片被替换或回滚
+pub fn load_shards(
+    index_dir: &Path,
+    key: &[u8; 32],
+    hashes: &BTreeMap<i32, String>,
+) -> Result<Vec<EntryInfo>> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| anyhow!("invalid key"))?;
+    let mut entries = Vec::new();
+
+    for (year, expected_hash) in hashes {
+        let path = shard_path(index_dir, *year, expected_hash);
+        let bytes = fs::read(&path)
+            .with_context(|| format!("index shard for {year} is missing"))?;
+        if &hash_bytes(&bytes) != expected_hash {
+            return Err(anyhow!("index shard for {year} does not match the vault"));
+        }
+
+        let stored: StoredShard =
+            serde_json::from_slice(&bytes).context("failed to parse index shard")?;
+        if stored.version != SHARD_VERSION || stored.year != *year {
+            return Err(anyhow!("unsupported index shard"));
+        }
+        let nonce_bytes = general_purpose::STANDARD_NO_PAD
+            .decode(&stored.nonce)
+            .context("invalid nonce encoding")?;
+        if nonce_bytes.len() != 12 {
+            return Err(anyhow!("invalid nonce length"));
+        }
+        let ciphertext = general_purpose::STANDARD_NO_PAD
+            .decode(&stored.ciphertext)
+            .context("invalid ciphertext encoding")?;
+        #[allow(deprecated)]
+        let nonce = Nonce::from_slice(&nonce_bytes);
+        let plaintext = cipher
+            .decrypt(nonce, ciphertext.as_ref())
+            .map_err(|_| anyhow!("decryption failed"))?;
+
+        let shard: Vec<EntryInfo> =
+            serde_json::from_slice(&plaintext).context("invalid index shard")?;
+        entries.extend(shard);
+    }
+
+    Ok(entries)
+}
+
+// 把指定年份的分片写入 index_dir，返回更新后的摘要表
+pub fn write_years(
+    index_dir: &Path,
+    key: &[u8; 32],
+    entries: &[EntryInfo],
+    years: impl IntoIterator<Item = i32>,
+    hashes: &mut BTreeMap<i32, String>,
+) -> Result<()> {
+    for year in years {
+        let shard: Vec<&EntryInfo> = entries
+            .iter()
+            .filter(|info| shard_year(info) == year)
+            .collect();
+        match write_shard(index_dir, key, year, &shard)? {
+            Some(hash) => {
+                hashes.insert(year, hash);
+            }
+            None => {
+                hashes.remove(&year);
+            }
+        }
+    }
+    Ok(())
+}
+
+// 删除不再被元数据引用的分片，须在 vault.json 写入之后调用
+pub fn prune(index_dir: &Path, hashes: &BTreeMap<i32, String>) -> Result<()> {
+    if !index_dir.is_dir() {
+        return Ok(());
+    }
+    let live: Vec<String> = hashes
+        .iter()
+        .map(|(year, hash)| shard_file_name(*year, hash))
+        .collect();
+    for entry in fs::read_dir(index_dir).context("failed to read index directory")? {
+        let path = entry.context("failed to read directory entry")?.path();
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if path.is_file() && !live.iter().any(|live| live == name) {
+            fs::remove_file(&path).context("failed to remove index shard")?;
+        }
+    }
+    Ok(())
+}
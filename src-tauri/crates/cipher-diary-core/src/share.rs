@@ -0,0 +1,329 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use specta::Type;
+
+use crate::i18n::{self, Locale, Message};
+use crate::vault::{Entry, EntryError};
+
+// 分享包：选中的日记加密后嵌入一个独立的 HTML 文件，对方在浏览器中输入密码后用
+// WebCrypto 解密查看。密钥派生用浏览器原生支持的 PBKDF2-SHA256，加密用 AES-256-GCM
+pub const SHARE_ITERATIONS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, Serialize, Type)]
+pub struct ShareReport {
+    pub path: String,
+    pub entries: usize,
+    // 读不出而未打包的日记
+    pub skipped: Vec<EntryError>,
+}
+
+// 只包含文字；附件不打包，正文中的图片引用在浏览器里不会显示
+#[derive(Debug, Serialize)]
+pub struct ShareEntry {
+    pub title: String,
+    // YYYY-MM-DD
+    pub date: String,
+    pub tags: Vec<String>,
+    pub mood: Option<u8>,
+    pub content: String,
+}
+
+// 单篇日记的一次性分享文件：连同引用的附件加密写入 .cdshare 文件，
+// 密码由程序随机生成、只显示一次，对方在自己的应用中导入后用自己的密钥重新加密
+pub const ENTRY_SHARE_EXTENSION: &str = "cdshare";
+const ENTRY_SHARE_FORMAT: &str = "cipher-diary-entry";
+const ENTRY_SHARE_VERSION: u32 = 1;
+// Crockford Base32：32 个字符每个 5 位，20 个字符共 100 位
+const PASSCODE_ALPHABET: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const PASSCODE_GROUPS: usize = 4;
+const PASSCODE_GROUP_LEN: usize = 5;
+
+#[derive(Debug, Serialize, Type)]
+pub struct EntryShareReport {
+    pub path: String,
+    pub passcode: String,
+    pub attachments: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SharedAttachment {
+    // 发送方日记库中的相对路径，导入时据此改写正文中的引用
+    pub path: String,
+    pub data: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SharedEntry {
+    pub entry: Entry,
+    pub attachments: Vec<SharedAttachment>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EntryShareFile {
+    format: String,
+    version: u32,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    data: String,
+}
+
+pub fn generate_passcode() -> String {
+    let mut bytes = [0u8; PASSCODE_GROUPS * PASSCODE_GROUP_LEN];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+        .chunks(PASSCODE_GROUP_LEN)
+        .map(|group| {
+            group
+                .iter()
+                .map(|byte| PASSCODE_ALPHABET[*byte as usize % PASSCODE_ALPHABET.len()] as char)
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// 输入时不区分大小写，分隔符和空白可有可无，易混淆的 O、I、L 按 0 和 1 处理
+fn normalize_passcode(passcode: &str) -> String {
+    passcode
+        .chars()
+        .filter(|ch| ch.is_ascii_alphanumeric())
+        .map(|ch| match ch.to_ascii_uppercase() {
+            'O' => '0',
+            'I' | 'L' => '1',
+            ch => ch,
+        })
+        .collect()
+}
+
+pub fn seal_entry(shared: &SharedEntry, passcode: &str) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let key = pbkdf2_sha256(
+        normalize_passcode(passcode).as_bytes(),
+        &salt,
+        SHARE_ITERATIONS,
+    )?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| anyhow!("invalid key"))?;
+    #[allow(deprecated)]
+    let nonce_ref = Nonce::from_slice(&nonce);
+    let plaintext = serde_json::to_vec(shared)?;
+    let ciphertext = cipher
+        .encrypt(nonce_ref, plaintext.as_ref())
+        .map_err(|_| anyhow!("encryption failed"))?;
+    let file = EntryShareFile {
+        format: ENTRY_SHARE_FORMAT.to_string(),
+        version: ENTRY_SHARE_VERSION,
+        iterations: SHARE_ITERATIONS,
+        salt: general_purpose::STANDARD.encode(salt),
+        nonce: general_purpose::STANDARD.encode(nonce),
+        data: general_purpose::STANDARD.encode(ciphertext),
+    };
+    Ok(serde_json::to_vec(&file)?)
+}
+
+pub fn open_entry(bytes: &[u8], passcode: &str) -> Result<SharedEntry> {
+    let file: EntryShareFile =
+        serde_json::from_slice(bytes).map_err(|_| anyhow!(Message::InvalidSharedEntry))?;
+    if file.format != ENTRY_SHARE_FORMAT || file.version != ENTRY_SHARE_VERSION {
+        return Err(anyhow!(Message::InvalidSharedEntry));
+    }
+    // 迭代次数来自文件，限制上限以免被构造的文件拖住
+    if file.iterations == 0 || file.iterations > SHARE_ITERATIONS * 10 {
+        return Err(anyhow!(Message::InvalidSharedEntry));
+    }
+    let decode = |text: &str| {
+        general_purpose::STANDARD
+            .decode(text)
+            .map_err(|_| anyhow!(Message::InvalidSharedEntry))
+    };
+    let salt = decode(&file.salt)?;
+    let nonce = decode(&file.nonce)?;
+    if nonce.len() != NONCE_LEN {
+        return Err(anyhow!(Message::InvalidSharedEntry));
+    }
+    let key = pbkdf2_sha256(
+        normalize_passcode(passcode).as_bytes(),
+        &salt,
+        file.iterations,
+    )?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| anyhow!("invalid key"))?;
+    #[allow(deprecated)]
+    let nonce_ref = Nonce::from_slice(&nonce);
+    let plaintext = cipher
+        .decrypt(nonce_ref, decode(&file.data)?.as_ref())
+        .map_err(|_| anyhow!(Message::ShareWrongPassword))?;
+    serde_json::from_slice(&plaintext).context(Message::InvalidSharedEntry)
+}
+
+pub fn bundle(entries: &[ShareEntry], password: &str) -> Result<String> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let key = pbkdf2_sha256(password.as_bytes(), &salt, SHARE_ITERATIONS)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| anyhow!("invalid key"))?;
+    #[allow(deprecated)]
+    let nonce_ref = Nonce::from_slice(&nonce);
+    let plaintext = serde_json::to_vec(entries)?;
+    let ciphertext = cipher
+        .encrypt(nonce_ref, plaintext.as_ref())
+        .map_err(|_| anyhow!("encryption failed"))?;
+
+    let encode = |bytes: &[u8]| general_purpose::STANDARD.encode(bytes);
+    let data = json!({
+        "iterations": SHARE_ITERATIONS,
+        "salt": encode(&salt),
+        "nonce": encode(&nonce),
+        "data": encode(&ciphertext),
+        "text": {
+            "prompt": Message::SharePasswordPrompt.to_string(),
+            "open": Message::ShareOpen.to_string(),
+            "wrong": Message::ShareWrongPassword.to_string(),
+        },
+    });
+    // 嵌入 <script> 时不能出现 </script>
+    let data = data.to_string().replace("</", "<\\/");
+    let lang = match i18n::locale() {
+        Locale::ZhCn => "zh-CN",
+        Locale::En => "en",
+    };
+    Ok(TEMPLATE
+        .replace("{{lang}}", lang)
+        .replace("{{title}}", &escape_html(&Message::ShareTitle.to_string()))
+        .replace("{{data}}", &data))
+}
+
+// 单个输出块即可得到 32 字节密钥
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> Result<[u8; 32]> {
+    let mac = <Hmac<Sha256> as Mac>::new_from_slice(password)
+        .map_err(|_| anyhow!("failed to derive share key"))?;
+    let mut block = mac.clone();
+    block.update(salt);
+    block.update(&1u32.to_be_bytes());
+    let mut u: [u8; 32] = block.finalize().into_bytes().into();
+    let mut key = u;
+    for _ in 1..iterations {
+        let mut next = mac.clone();
+        next.update(&u);
+        u = next.finalize().into_bytes().into();
+        for (k, byte) in key.iter_mut().zip(u) {
+            *k ^= byte;
+        }
+    }
+    Ok(key)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// 解密后的内容只通过 textContent 写入页面，正文不会被当作 HTML 执行
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="{{lang}}">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="referrer" content="no-referrer">
+<meta http-equiv="Content-Security-Policy"
+  content="default-src 'none'; script-src 'unsafe-inline'; style-src 'unsafe-inline'">
+<title>{{title}}</title>
+<style>
+body {
+  font-family: system-ui, sans-serif; max-width: 720px; margin: 2rem auto; padding: 0 1rem;
+  color: #222; line-height: 1.6;
+}
+form { display: flex; gap: .5rem; flex-wrap: wrap; align-items: center; }
+input { padding: .4rem .6rem; font-size: 1rem; }
+button { padding: .4rem 1rem; font-size: 1rem; }
+.error { color: #b00020; }
+article { border-top: 1px solid #ddd; padding: 1rem 0; }
+.meta { color: #666; font-size: .9rem; }
+.content { white-space: pre-wrap; }
+</style>
+</head>
+<body>
+<h1>{{title}}</h1>
+<form id="unlock">
+<label for="password"></label>
+<input id="password" type="password" autocomplete="off" autofocus>
+<button type="submit"></button>
+<p class="error" id="error" hidden></p>
+</form>
+<main id="entries"></main>
+<script id="bundle" type="application/json">{{data}}</script>
+<script>
+(function () {
+  const bundle = JSON.parse(document.getElementById('bundle').textContent);
+  const form = document.getElementById('unlock');
+  const error = document.getElementById('error');
+  form.querySelector('label').textContent = bundle.text.prompt;
+  form.querySelector('button').textContent = bundle.text.open;
+  const bytes = (text) => Uint8Array.from(atob(text), (ch) => ch.charCodeAt(0));
+
+  async function decrypt(password) {
+    const material = await crypto.subtle.importKey(
+      'raw', new TextEncoder().encode(password), 'PBKDF2', false, ['deriveKey']);
+    const key = await crypto.subtle.deriveKey(
+      {
+        name: 'PBKDF2',
+        salt: bytes(bundle.salt),
+        iterations: bundle.iterations,
+        hash: 'SHA-256'
+      },
+      material, { name: 'AES-GCM', length: 256 }, false, ['decrypt']);
+    const plain = await crypto.subtle.decrypt(
+      { name: 'AES-GCM', iv: bytes(bundle.nonce) }, key, bytes(bundle.data));
+    return JSON.parse(new TextDecoder().decode(plain));
+  }
+
+  function render(entries) {
+    const main = document.getElementById('entries');
+    for (const entry of entries) {
+      const article = document.createElement('article');
+      const title = document.createElement('h2');
+      title.textContent = entry.title;
+      const meta = document.createElement('p');
+      meta.className = 'meta';
+      const parts = [entry.date];
+      if (entry.mood !== null) parts.push('★'.repeat(entry.mood));
+      if (entry.tags.length) parts.push(entry.tags.map((tag) => '#' + tag).join(' '));
+      meta.textContent = parts.join(' · ');
+      const content = document.createElement('div');
+      content.className = 'content';
+      content.textContent = entry.content;
+      article.append(title, meta, content);
+      main.append(article);
+    }
+  }
+
+  form.addEventListener('submit', async (event) => {
+    event.preventDefault();
+    error.hidden = true;
+    try {
+      render(await decrypt(document.getElementById('password').value));
+      form.remove();
+    } catch (_) {
+      error.textContent = bundle.text.wrong;
+      error.hidden = false;
+    }
+  });
+})();
+</script>
+</body>
+</html>
+"#;
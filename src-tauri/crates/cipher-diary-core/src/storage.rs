@@ -0,0 +1,351 @@
+use anyhow::{anyhow, Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::i18n::Message;
+use crate::permissions;
+use crate::vault::vault_file_path;
+use crate::vault_lock;
+
+// 日记库文件的存放位置。桌面平台直接读写目录；Android 的分区存储中，用户选择的文件夹
+// （SD 卡、同步盘）只能通过 SAF 的 content:// 地址访问，由应用层实现
+pub trait DocumentTree: Send + Sync {
+    // 显示给用户、记入最近列表的位置
+    fn location(&self) -> String;
+    // 全部文件，路径相对于根目录、以 / 分隔
+    fn list(&self) -> Result<Vec<TreeFile>>;
+    fn read(&self, path: &str) -> Result<Vec<u8>>;
+    // 返回写入后的文件信息，缺少的上级目录一并创建
+    fn write(&self, path: &str, data: &[u8]) -> Result<TreeFile>;
+    // 文件不存在时视为成功
+    fn remove(&self, path: &str) -> Result<()>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeFile {
+    pub path: String,
+    pub size: u64,
+    // 毫秒时间戳，只用来判断文件是否变化
+    pub modified: i64,
+}
+
+pub fn is_document_uri(text: &str) -> bool {
+    text.trim_start().starts_with("content://")
+}
+
+// 同一远端位置总是对应 base 下的同一个工作副本目录
+pub fn mirror_path(base: &Path, location: &str) -> PathBuf {
+    let digest = Sha256::digest(location.trim().as_bytes());
+    base.join(&format!("{:x}", digest)[..32])
+}
+
+// 普通目录，桌面平台和测试使用
+pub struct DirectoryTree {
+    root: PathBuf,
+}
+
+impl DirectoryTree {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        DirectoryTree { root: root.into() }
+    }
+}
+
+impl DocumentTree for DirectoryTree {
+    fn location(&self) -> String {
+        self.root.to_string_lossy().into_owned()
+    }
+
+    fn list(&self) -> Result<Vec<TreeFile>> {
+        walk(&self.root)?
+            .into_iter()
+            .map(|(path, file)| {
+                let meta = fs::metadata(&file).context("failed to read file metadata")?;
+                Ok(TreeFile {
+                    path,
+                    size: meta.len(),
+                    modified: meta.modified().map(millis).unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>> {
+        fs::read(resolve(&self.root, path)?).with_context(|| format!("failed to read {path}"))
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> Result<TreeFile> {
+        let target = resolve(&self.root, path)?;
+        if let Some(parent) = target.parent() {
+            permissions::create_private_dir_all(parent)?;
+        }
+        permissions::write_private(&target, data)?;
+        let meta = fs::metadata(&target).context("failed to read file metadata")?;
+        Ok(TreeFile {
+            path: path.to_string(),
+            size: meta.len(),
+            modified: meta.modified().map(millis).unwrap_or_default(),
+        })
+    }
+
+    fn remove(&self, path: &str) -> Result<()> {
+        match fs::remove_file(resolve(&self.root, path)?) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                Err(err).with_context(|| format!("failed to remove {path}"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SyncReport {
+    pub transferred: usize,
+    pub removed: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Synced {
+    remote: TreeFile,
+    local: Stamp,
+}
+
+// 远端文件夹在应用私有目录中的工作副本：日记库照常读写本地文件，改动再由 push 写回。
+// 只比较大小和修改时间，两边都没有变化的文件不会重复传输。
+// 同步状态保存在工作副本旁边，应用重启后仍能分辨哪些本地改动还没有写回
+pub struct Mirror {
+    tree: Box<dyn DocumentTree>,
+    local: PathBuf,
+    // 从未同步过时为 None，此时工作副本只是缓存，可以整体覆盖
+    synced: Mutex<Option<BTreeMap<String, Synced>>>,
+}
+
+impl Mirror {
+    pub fn new(tree: Box<dyn DocumentTree>, local: PathBuf) -> Self {
+        let synced = load_state(&state_path(&local));
+        Mirror {
+            tree,
+            local,
+            synced: Mutex::new(synced),
+        }
+    }
+
+    pub fn location(&self) -> String {
+        self.tree.location()
+    }
+
+    pub fn local_root(&self) -> &Path {
+        &self.local
+    }
+
+    // 以远端为准刷新工作副本，远端没有的本地文件删除。只能在日记库未打开时调用。
+    // 上次同步之后在本地改过的文件还没有写回，此时拒绝覆盖，需先 push
+    pub fn pull(&self) -> Result<SyncReport> {
+        let mut synced = self.synced.lock();
+        permissions::create_private_dir_all(&self.local)?;
+        let remote: Vec<TreeFile> = self
+            .tree
+            .list()?
+            .into_iter()
+            .filter(|file| !is_local_only(&file.path))
+            .collect();
+        let listed: BTreeSet<&str> = remote.iter().map(|file| file.path.as_str()).collect();
+        let stale: Vec<(String, PathBuf)> = walk(&self.local)?
+            .into_iter()
+            .filter(|(path, _)| !is_local_only(path) && !listed.contains(path.as_str()))
+            .collect();
+
+        // 先检查全部文件再动手，不会只覆盖一部分
+        if let Some(known) = synced.as_ref() {
+            let targets = remote
+                .iter()
+                .map(|file| resolve(&self.local, &file.path).map(|target| (&file.path, target)))
+                .collect::<Result<Vec<_>>>()?;
+            let unsynced = targets
+                .iter()
+                .map(|(path, target)| (path.as_str(), target))
+                .chain(stale.iter().map(|(path, file)| (path.as_str(), file)))
+                .find(|(path, target)| {
+                    stamp(target).is_some_and(|local| {
+                        known.get(*path).is_none_or(|entry| entry.local != local)
+                    })
+                });
+            if let Some((path, _)) = unsynced {
+                tracing::warn!(path, "local copy changed since the last sync");
+                return Err(anyhow!(Message::DocumentTreeUnsyncedChanges));
+            }
+        }
+
+        let mut report = SyncReport::default();
+        let mut next = BTreeMap::new();
+        for file in remote {
+            let target = resolve(&self.local, &file.path)?;
+            let unchanged = synced
+                .as_ref()
+                .and_then(|known| known.get(&file.path))
+                .is_some_and(|known| known.remote == file && stamp(&target) == Some(known.local));
+            if !unchanged {
+                let data = self.tree.read(&file.path)?;
+                if let Some(parent) = target.parent() {
+                    permissions::create_private_dir_all(parent)?;
+                }
+                permissions::write_private(&target, data)?;
+                report.transferred += 1;
+            }
+            let local = stamp(&target).ok_or_else(|| anyhow!("failed to read {}", file.path))?;
+            next.insert(
+                file.path.clone(),
+                Synced {
+                    remote: file,
+                    local,
+                },
+            );
+        }
+        for (path, file) in stale {
+            fs::remove_file(&file).with_context(|| format!("failed to remove {path}"))?;
+            report.removed += 1;
+        }
+        save_state(&state_path(&self.local), &next)?;
+        *synced = Some(next);
+        Ok(report)
+    }
+
+    // 把工作副本的改动写回远端。vault.json 引用其他文件，最后写入；
+    // 上次同步过、之后在本地被删掉的文件等 vault.json 写好后才删除，
+    // 中途失败时远端仍是一份完整的日记库。尚未 pull 过的工作副本不会清空远端
+    pub fn push(&self) -> Result<SyncReport> {
+        let mut guard = self.synced.lock();
+        let synced = guard.get_or_insert_with(BTreeMap::new);
+        let metadata = vault_file_path(PathBuf::new());
+        let mut files: Vec<(String, PathBuf)> = walk(&self.local)?
+            .into_iter()
+            .filter(|(path, _)| !is_local_only(path))
+            .collect();
+        files.sort_by_key(|(path, _)| Path::new(path) == metadata);
+
+        let mut report = SyncReport::default();
+        let mut present = BTreeSet::new();
+        let result = (|| -> Result<()> {
+            for (path, file) in files {
+                present.insert(path.clone());
+                // 先取时间戳再读取：读取期间文件又被改写时，下一轮会再上传一次
+                let Some(local) = stamp(&file) else {
+                    continue;
+                };
+                if synced.get(&path).is_some_and(|known| known.local == local) {
+                    continue;
+                }
+                let data = match fs::read(&file) {
+                    Ok(data) => data,
+                    Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                    Err(err) => return Err(err).with_context(|| format!("failed to read {path}")),
+                };
+                let remote = self.tree.write(&path, &data)?;
+                synced.insert(path, Synced { remote, local });
+                report.transferred += 1;
+            }
+            let deleted: Vec<String> = synced
+                .keys()
+                .filter(|path| !present.contains(*path))
+                .cloned()
+                .collect();
+            for path in deleted {
+                self.tree.remove(&path)?;
+                synced.remove(&path);
+                report.removed += 1;
+            }
+            Ok(())
+        })();
+        // 中途失败时已写回的文件也要记下，避免重启后误判为未同步的改动
+        if report != SyncReport::default() {
+            save_state(&state_path(&self.local), synced)?;
+        }
+        result.map(|()| report)
+    }
+}
+
+// 同步状态放在工作副本目录之外，不会被当成日记库文件同步
+fn state_path(local: &Path) -> PathBuf {
+    let mut name = local.file_name().unwrap_or_default().to_os_string();
+    name.push(".sync.json");
+    local.with_file_name(name)
+}
+
+// 状态文件损坏时按从未同步处理，下次 pull 以远端为准
+fn load_state(path: &Path) -> Option<BTreeMap<String, Synced>> {
+    let bytes = fs::read(path).ok()?;
+    serde_json::from_slice(&bytes)
+        .inspect_err(|err| tracing::warn!(error = %err, "ignoring unreadable mirror sync state"))
+        .ok()
+}
+
+fn save_state(path: &Path, synced: &BTreeMap<String, Synced>) -> Result<()> {
+    let serialized = serde_json::to_vec(synced).context("failed to serialize sync state")?;
+    permissions::write_private(path, serialized).context("failed to save sync state")
+}
+
+// 锁文件只对本机的进程有意义，不在两边之间同步
+fn is_local_only(path: &str) -> bool {
+    Path::new(path) == vault_lock::lock_file_path(Path::new(""))
+}
+
+// 远端给出的路径不能跳出根目录
+fn resolve(root: &Path, path: &str) -> Result<PathBuf> {
+    if path
+        .split('/')
+        .any(|part| part.is_empty() || part == "." || part == ".." || part.contains('\\'))
+    {
+        return Err(anyhow!("invalid document path: {path}"));
+    }
+    Ok(path
+        .split('/')
+        .fold(root.to_path_buf(), |dir, part| dir.join(part)))
+}
+
+fn walk(root: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    if root.is_dir() {
+        walk_into(root, "", &mut files)?;
+    }
+    Ok(files)
+}
+
+fn walk_into(dir: &Path, prefix: &str, files: &mut Vec<(String, PathBuf)>) -> Result<()> {
+    for entry in fs::read_dir(dir).context("failed to read directory")? {
+        let entry = entry.context("failed to read directory entry")?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = format!("{prefix}{name}");
+        let path = entry.path();
+        if path.is_dir() {
+            walk_into(&path, &format!("{relative}/"), files)?;
+        } else if path.is_file() {
+            files.push((relative, path));
+        }
+    }
+    Ok(())
+}
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let meta = fs::metadata(path).ok()?;
+    Some(Stamp {
+        size: meta.len(),
+        modified: meta.modified().ok(),
+    })
+}
+
+fn millis(time: SystemTime) -> i64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or_default()
+}
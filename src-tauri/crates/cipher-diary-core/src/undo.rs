@@ -0,0 +1,99 @@
+use serde::Serialize;
+use specta::Type;
+use std::collections::VecDeque;
+use uuid::Uuid;
+
+use crate::vault::Entry;
+
+// 只保存在内存中，锁定后随解锁状态一起清空
+const MAX_UNDO_RECORDS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum UndoKind {
+    Delete,
+    Move,
+    // 移除标签，包括合并或改名时去掉旧标签
+    Retag,
+}
+
+#[derive(Debug, Clone)]
+pub enum UndoStep {
+    // 写回完整日记（正文和元数据）
+    Restore(Box<Entry>),
+    Remove(Uuid),
+    SetFolder { id: Uuid, folder: Option<String> },
+    SetTags { id: Uuid, tags: Vec<String> },
+}
+
+impl UndoStep {
+    pub fn id(&self) -> Uuid {
+        match self {
+            UndoStep::Restore(entry) => entry.id,
+            UndoStep::Remove(id) => *id,
+            UndoStep::SetFolder { id, .. } => *id,
+            UndoStep::SetTags { id, .. } => *id,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UndoRecord {
+    pub kind: UndoKind,
+    pub undo: Vec<UndoStep>,
+    pub redo: Vec<UndoStep>,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct UndoOutcome {
+    pub kind: UndoKind,
+    pub ids: Vec<Uuid>,
+    pub can_undo: bool,
+    pub can_redo: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct UndoHistory {
+    undo: VecDeque<UndoRecord>,
+    redo: Vec<UndoRecord>,
+}
+
+impl UndoHistory {
+    // 新操作会让重做记录失效
+    pub fn record(&mut self, record: UndoRecord) {
+        self.redo.clear();
+        self.push_undo(record);
+    }
+
+    // 不可撤销的改动同样让重做记录失效，否则重做会覆盖之后的修改
+    pub fn clear_redo(&mut self) {
+        self.redo.clear();
+    }
+
+    pub fn push_undo(&mut self, record: UndoRecord) {
+        self.undo.push_back(record);
+        while self.undo.len() > MAX_UNDO_RECORDS {
+            self.undo.pop_front();
+        }
+    }
+
+    pub fn pop_undo(&mut self) -> Option<UndoRecord> {
+        self.undo.pop_back()
+    }
+
+    pub fn push_redo(&mut self, record: UndoRecord) {
+        self.redo.push(record);
+    }
+
+    pub fn pop_redo(&mut self) -> Option<UndoRecord> {
+        self.redo.pop()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
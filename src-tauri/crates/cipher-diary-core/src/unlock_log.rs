@@ -0,0 +1,78 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+use crate::permissions;
+
+// 放在密文之外，这样输错密码时也能记录
+const UNLOCK_LOG_FILE: &str = "unlock_attempts.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UnlockLog {
+    failed_count: u32,
+    last_failed_at: Option<OffsetDateTime>,
+    last_unlocked_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Type)]
+pub struct LastUnlockInfo {
+    pub failed_attempts: u32,
+    pub last_failed_at: Option<OffsetDateTime>,
+    pub previous_unlock_at: Option<OffsetDateTime>,
+}
+
+pub fn log_file_path(root: &Path) -> PathBuf {
+    root.join(UNLOCK_LOG_FILE)
+}
+
+fn read_log(root: &Path) -> UnlockLog {
+    // 记录损坏时从零开始，不应因此阻止解锁
+    fs::read_to_string(log_file_path(root))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_log(root: &Path, log: &UnlockLog) -> Result<()> {
+    let serialized = serde_json::to_string_pretty(log).context("failed to serialize unlock log")?;
+    permissions::write_private(&log_file_path(root), serialized)
+        .context("failed to write unlock log")
+}
+
+pub fn record_failure(root: &Path) -> Result<()> {
+    let mut log = read_log(root);
+    log.failed_count = log.failed_count.saturating_add(1);
+    log.last_failed_at = Some(OffsetDateTime::now_utc());
+    write_log(root, &log)
+}
+
+// 解锁成功：返回自上次解锁以来的失败记录并清零
+pub fn record_success(root: &Path) -> Result<LastUnlockInfo> {
+    let log = read_log(root);
+    let info = last_unlock_info(&log);
+    write_log(
+        root,
+        &UnlockLog {
+            failed_count: 0,
+            last_failed_at: log.last_failed_at,
+            last_unlocked_at: Some(OffsetDateTime::now_utc()),
+        },
+    )?;
+    Ok(info)
+}
+
+// 只读取不更新，用于以只读方式打开的日记库
+pub fn last_unlock(root: &Path) -> LastUnlockInfo {
+    last_unlock_info(&read_log(root))
+}
+
+fn last_unlock_info(log: &UnlockLog) -> LastUnlockInfo {
+    LastUnlockInfo {
+        failed_attempts: log.failed_count,
+        last_failed_at: log.last_failed_at,
+        previous_unlock_at: log.last_unlocked_at,
+    }
+}
@@ -0,0 +1,329 @@
+use std::fs;
+use std::path::Path;
+
+use time::macros::format_description;
+use time::Date;
+
+use crate::entry_types::EntryPayload;
+use crate::error::VaultError;
+use crate::i18n::Message;
+use crate::vault::{Entry, Location, NewEntry, PatchFields};
+
+// 命令入口处的输入检查，避免超大或畸形的数据进入加密和索引流程
+pub const MAX_TITLE_CHARS: usize = 200;
+pub const MAX_CONTENT_BYTES: usize = 8 * 1024 * 1024;
+pub const MAX_FOLDER_CHARS: usize = 120;
+pub const MAX_TAGS: usize = 32;
+pub const MAX_TAG_CHARS: usize = 40;
+pub const MIN_MOOD: u8 = 1;
+pub const MAX_MOOD: u8 = 5;
+pub const MAX_PLACE_CHARS: usize = 120;
+// list_entries_near 的最大搜索半径，约为地球周长的一半
+pub const MAX_RADIUS_KM: f64 = 20_000.0;
+pub const MAX_PACK_NAME_CHARS: usize = 60;
+pub const MAX_PACK_PROMPTS: usize = 366;
+pub const MAX_PROMPT_CHARS: usize = 300;
+pub const MAX_HABIT_NAME_CHARS: usize = 60;
+pub const MAX_HABIT_UNIT_CHARS: usize = 20;
+pub const MAX_PAYLOAD_ITEMS: usize = 50;
+pub const MAX_PAYLOAD_ITEM_CHARS: usize = 300;
+pub const MAX_DAY_SUMMARY_CHARS: usize = 500;
+pub const MAX_DICTIONARY_WORD_CHARS: usize = 60;
+pub const MIN_SHARE_PASSWORD_CHARS: usize = 8;
+pub const MAX_KEY_SLOT_LABEL_CHARS: usize = 40;
+pub const MAX_ATTACHMENT_BYTES: u64 = 50 * 1024 * 1024;
+
+const MEGABYTE: u64 = 1024 * 1024;
+
+pub fn title(title: &str) -> Result<(), VaultError> {
+    if title.chars().count() > MAX_TITLE_CHARS {
+        return Err(VaultError::invalid(Message::TitleTooLong(MAX_TITLE_CHARS)));
+    }
+    if title.chars().any(char::is_control) {
+        return Err(VaultError::invalid(Message::TitleControlCharacters));
+    }
+    Ok(())
+}
+
+// 正文允许换行和制表符，其余控制字符一律拒绝
+pub fn content(content: &str) -> Result<(), VaultError> {
+    if content.len() > MAX_CONTENT_BYTES {
+        return Err(too_large(Message::ContentTooLarge(
+            MAX_CONTENT_BYTES as u64 / MEGABYTE,
+        )));
+    }
+    if content
+        .chars()
+        .any(|ch| ch.is_control() && !matches!(ch, '\n' | '\r' | '\t'))
+    {
+        return Err(VaultError::invalid(Message::ContentControlCharacters));
+    }
+    Ok(())
+}
+
+// 文件夹用 / 分隔层级，每一级都不能为空或是 . / ..，也不能带首尾空白
+pub fn folder(folder: Option<&str>) -> Result<(), VaultError> {
+    let Some(folder) = folder else {
+        return Ok(());
+    };
+    if folder.chars().count() > MAX_FOLDER_CHARS {
+        return Err(VaultError::invalid(Message::FolderNameTooLong(MAX_FOLDER_CHARS)));
+    }
+    let valid = folder.split('/').all(|segment| {
+        !segment.is_empty()
+            && segment.trim() == segment
+            && segment != "."
+            && segment != ".."
+            && !segment.chars().any(|ch| ch.is_control() || ch == '\\')
+    });
+    if !valid {
+        return Err(VaultError::invalid(Message::InvalidFolderName));
+    }
+    Ok(())
+}
+
+// 标签不分层级，不能为空、带首尾空白或包含控制字符
+pub fn tags(tags: &[String]) -> Result<(), VaultError> {
+    if tags.len() > MAX_TAGS {
+        return Err(VaultError::invalid(Message::TooManyTags(MAX_TAGS)));
+    }
+    for tag in tags {
+        if tag.chars().count() > MAX_TAG_CHARS {
+            return Err(VaultError::invalid(Message::TagTooLong(MAX_TAG_CHARS)));
+        }
+        if tag.is_empty() || tag.trim() != tag || tag.chars().any(char::is_control) {
+            return Err(VaultError::invalid(Message::InvalidTag));
+        }
+    }
+    Ok(())
+}
+
+pub fn mood(mood: Option<u8>) -> Result<(), VaultError> {
+    match mood {
+        Some(value) if !(MIN_MOOD..=MAX_MOOD).contains(&value) => Err(VaultError::invalid(
+            Message::MoodOutOfRange(MIN_MOOD, MAX_MOOD),
+        )),
+        _ => Ok(()),
+    }
+}
+
+// 题库名称和题目都是单行文本
+pub fn prompt_pack(name: &str, prompts: &[String]) -> Result<(), VaultError> {
+    let single_line = |text: &str, max: usize| {
+        !text.trim().is_empty()
+            && text.chars().count() <= max
+            && !text.chars().any(char::is_control)
+    };
+    if !single_line(name, MAX_PACK_NAME_CHARS) {
+        return Err(VaultError::invalid(Message::InvalidPromptPackName(
+            MAX_PACK_NAME_CHARS,
+        )));
+    }
+    if prompts.is_empty() || prompts.len() > MAX_PACK_PROMPTS {
+        return Err(VaultError::invalid(Message::TooManyPrompts(MAX_PACK_PROMPTS)));
+    }
+    if !prompts.iter().all(|prompt| single_line(prompt, MAX_PROMPT_CHARS)) {
+        return Err(VaultError::invalid(Message::InvalidPrompt(MAX_PROMPT_CHARS)));
+    }
+    Ok(())
+}
+
+pub fn habit(name: &str, unit: Option<&str>, target: f64) -> Result<(), VaultError> {
+    let single_line = |text: &str, max: usize| {
+        !text.trim().is_empty()
+            && text.chars().count() <= max
+            && !text.chars().any(char::is_control)
+    };
+    if !single_line(name, MAX_HABIT_NAME_CHARS) {
+        return Err(VaultError::invalid(Message::InvalidHabitName(
+            MAX_HABIT_NAME_CHARS,
+        )));
+    }
+    if unit.is_some_and(|unit| !single_line(unit, MAX_HABIT_UNIT_CHARS)) {
+        return Err(VaultError::invalid(Message::InvalidHabitUnit(
+            MAX_HABIT_UNIT_CHARS,
+        )));
+    }
+    if !target.is_finite() || target <= 0.0 {
+        return Err(VaultError::invalid(Message::InvalidHabitTarget));
+    }
+    Ok(())
+}
+
+pub fn habit_value(value: f64) -> Result<(), VaultError> {
+    if !value.is_finite() || value < 0.0 {
+        return Err(VaultError::invalid(Message::InvalidHabitValue));
+    }
+    Ok(())
+}
+
+// 各列表项合计计数；梦境正文与普通正文同样限制大小
+pub fn entry_payload(payload: Option<&EntryPayload>) -> Result<(), VaultError> {
+    let Some(payload) = payload else {
+        return Ok(());
+    };
+    let items = payload.items();
+    let story = match payload {
+        EntryPayload::Dream { story, .. } => story.trim(),
+        _ => "",
+    };
+    if items.is_empty() && story.is_empty() {
+        return Err(VaultError::invalid(Message::EmptyEntryPayload));
+    }
+    content(story)?;
+    let invalid = |item: &&String| {
+        item.trim().is_empty()
+            || item.chars().count() > MAX_PAYLOAD_ITEM_CHARS
+            || item.chars().any(char::is_control)
+    };
+    if items.len() > MAX_PAYLOAD_ITEMS || items.iter().any(invalid) {
+        return Err(VaultError::invalid(Message::InvalidEntryPayload(
+            MAX_PAYLOAD_ITEMS,
+            MAX_PAYLOAD_ITEM_CHARS,
+        )));
+    }
+    Ok(())
+}
+
+// 每日小结允许换行，空白内容等同于清除
+pub fn day_summary(summary: &str) -> Result<(), VaultError> {
+    if summary.chars().count() > MAX_DAY_SUMMARY_CHARS {
+        return Err(VaultError::invalid(Message::DaySummaryTooLong(
+            MAX_DAY_SUMMARY_CHARS,
+        )));
+    }
+    Ok(())
+}
+
+// 拼写检查按单词匹配，词典中的词不能含空白
+pub fn dictionary_word(word: &str) -> Result<(), VaultError> {
+    let word = word.trim();
+    if word.is_empty()
+        || word.chars().count() > MAX_DICTIONARY_WORD_CHARS
+        || word.chars().any(|ch| ch.is_whitespace() || ch.is_control())
+    {
+        return Err(VaultError::invalid(Message::InvalidDictionaryWord(
+            MAX_DICTIONARY_WORD_CHARS,
+        )));
+    }
+    Ok(())
+}
+
+// 分享包离开本机后可被离线暴力破解，密码不能太短
+pub fn share_password(password: &str) -> Result<(), VaultError> {
+    if password.chars().count() < MIN_SHARE_PASSWORD_CHARS {
+        return Err(VaultError::invalid(Message::SharePasswordTooShort(
+            MIN_SHARE_PASSWORD_CHARS,
+        )));
+    }
+    Ok(())
+}
+
+pub fn key_slot_label(label: &str) -> Result<(), VaultError> {
+    let label = label.trim();
+    if label.is_empty() || label.chars().count() > MAX_KEY_SLOT_LABEL_CHARS {
+        return Err(VaultError::invalid(Message::InvalidKeySlotLabel(
+            MAX_KEY_SLOT_LABEL_CHARS,
+        )));
+    }
+    Ok(())
+}
+
+// 命令参数中的日期统一为 YYYY-MM-DD
+pub fn date(text: &str) -> Result<Date, VaultError> {
+    Date::parse(text, format_description!("[year]-[month]-[day]"))
+        .map_err(|_| VaultError::invalid(Message::InvalidDate))
+}
+
+pub fn coordinates(latitude: f64, longitude: f64) -> Result<(), VaultError> {
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return Err(VaultError::invalid(Message::InvalidCoordinates));
+    }
+    Ok(())
+}
+
+pub fn location(location: Option<&Location>) -> Result<(), VaultError> {
+    let Some(location) = location else {
+        return Ok(());
+    };
+    coordinates(location.latitude, location.longitude)?;
+    if let Some(place) = &location.place {
+        if place.chars().count() > MAX_PLACE_CHARS {
+            return Err(VaultError::invalid(Message::PlaceNameTooLong(MAX_PLACE_CHARS)));
+        }
+        if place.trim().is_empty() || place.chars().any(char::is_control) {
+            return Err(VaultError::invalid(Message::InvalidPlaceName));
+        }
+    }
+    Ok(())
+}
+
+pub fn radius(radius_km: f64) -> Result<(), VaultError> {
+    if radius_km.is_nan() || radius_km <= 0.0 || radius_km > MAX_RADIUS_KM {
+        return Err(VaultError::invalid(Message::InvalidRadius(MAX_RADIUS_KM as u32)));
+    }
+    Ok(())
+}
+
+pub fn attachment_size(bytes: u64) -> Result<(), VaultError> {
+    if bytes > MAX_ATTACHMENT_BYTES {
+        return Err(too_large(Message::AttachmentTooLarge(
+            MAX_ATTACHMENT_BYTES / MEGABYTE,
+        )));
+    }
+    Ok(())
+}
+
+// 只看文件大小，不读取内容；读取失败交给后续存储步骤报告
+pub fn attachment_file(path: &Path) -> Result<(), VaultError> {
+    match fs::metadata(path) {
+        Ok(metadata) => attachment_size(metadata.len()),
+        Err(_) => Ok(()),
+    }
+}
+
+pub fn entry(entry: &Entry) -> Result<(), VaultError> {
+    title(&entry.title)?;
+    content(&entry.content)?;
+    folder(entry.folder.as_deref())?;
+    tags(&entry.tags)?;
+    mood(entry.mood)?;
+    location(entry.location.as_ref())?;
+    entry_payload(entry.payload.as_ref())
+}
+
+pub fn new_entry(entry: &NewEntry) -> Result<(), VaultError> {
+    title(&entry.title)?;
+    content(&entry.content)?;
+    folder(entry.folder.as_deref())?;
+    tags(&entry.tags)?;
+    mood(entry.mood)?;
+    location(entry.location.as_ref())?;
+    entry_payload(entry.payload.as_ref())
+}
+
+pub fn patch(patch: &PatchFields) -> Result<(), VaultError> {
+    if let Some(value) = &patch.title {
+        title(value)?;
+    }
+    if let Some(value) = &patch.content {
+        content(value)?;
+    }
+    if let Some(value) = &patch.folder {
+        folder(value.as_deref())?;
+    }
+    if let Some(value) = &patch.tags {
+        tags(value)?;
+    }
+    if let Some(value) = patch.mood {
+        mood(value)?;
+    }
+    if let Some(value) = &patch.location {
+        location(value.as_ref())?;
+    }
+    Ok(())
+}
+
+fn too_large(message: Message) -> VaultError {
+    VaultError::QuotaExceeded(message.to_string())
+}
@@ -0,0 +1,6410 @@
+use aes::Aes256;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce as ChaChaNonce};
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use parking_lot::{Mutex, RwLock};
+use rand::{rngs::OsRng, RngCore};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use time::{format_description::well_known::Rfc3339, Date, Month, OffsetDateTime, UtcOffset};
+use uuid::Uuid;
+
+use crate::drafts::{self, Draft};
+use crate::emergency::{self, EmergencyAccess, EmergencyStatus, RecoveryAttachment, RecoveryPayload};
+use crate::entry_types::{EntryPayload, EntryType};
+use crate::envelope::{self, Kind};
+use crate::history::{self, HistoryReport};
+use crate::i18n::{Locale, Message};
+use crate::journal::{self, JournalChange};
+use crate::key_slots::{self, UserSlot};
+use crate::migrations;
+use crate::permissions;
+use crate::profile::{self, VaultProfile};
+use crate::habits::{self, Habit, HabitHistory, HabitState};
+use crate::health::{self, HealthImportReport, HealthSource, HealthSummary};
+use crate::prompts::{PromptPack, PromptPackInfo, PromptState, TodaysPrompt};
+use crate::quick_unlock;
+use crate::recap::{self, MonthRecap, RecapLine};
+use crate::search::{self, SearchIndex};
+use crate::sentiment;
+use crate::share::{
+    self, EntryShareReport, ShareEntry, ShareReport, SharedAttachment, SharedEntry,
+};
+use crate::shards;
+use crate::undo::{UndoHistory, UndoKind, UndoOutcome, UndoRecord, UndoStep};
+use crate::unlock_log::{self, LastUnlockInfo};
+use crate::vault_lock;
+use crate::wordfreq::{WordCounter, WordFrequency};
+
+type Aes256Ctr = ctr::Ctr128BE<Aes256>;
+
+const VAULT_VERSION: u32 = 1;
+const METADATA_VERSION: u32 = 2;
+// 版本 1 将全部条目索引直接存放在 vault.json 中，打开时迁移为分片
+const LEGACY_METADATA_VERSION: u32 = 1;
+// 升级格式前的元数据备份，位于日记根目录下，只保留最近几份
+const MIGRATION_BACKUP_DIR: &str = "migration_backups";
+const MIGRATION_BACKUPS_KEPT: usize = 3;
+// 被隔离的损坏条目文件，保留原样便于以后手动恢复
+const QUARANTINE_DIR: &str = "quarantine";
+const ENTRY_VERSION: u32 = 1;
+// 带结构化内容的条目：明文为正文与结构化内容的 JSON
+const TYPED_ENTRY_VERSION: u32 = 2;
+// 新建日记库和修改密码时使用的 Argon2id 参数，记录在 vault.json 头部；
+// 解锁时按头部记录的参数派生，修改这里不影响已有的日记库
+const KDF_MEMORY_KIB: u32 = 32768;
+const KDF_ITERATIONS: u32 = 2;
+const KDF_PARALLELISM: u32 = 4;
+// 头部没有记录参数的旧文件、历史密码校验值和快速解锁 PIN 都按最初的参数派生，不能改动
+const ORIGINAL_KDF: [u32; 3] = [32768, 2, 4];
+const IMAGE_MAGIC_PREFIX: &[u8] = b"VAULTIMG"; // 加密图片的固定前缀
+const IMAGE_ZSTD_MARKER: &[u8] = b":ZSTD:"; // 紧跟算法标记，表示密文前已压缩
+const ZSTD_LEVEL: i32 = 3;
+// 解压结果的上限，不小于任何合法的正文或附件；超出说明数据损坏或被构造成解压炸弹
+const MAX_DECOMPRESSED_BYTES: u64 = 64 * 1024 * 1024;
+const DEFAULT_PASSPHRASE_HISTORY_DEPTH: usize = 3;
+const MAX_PASSPHRASE_HISTORY_DEPTH: usize = 20;
+const MAX_SETTINGS_BYTES: usize = 64 * 1024;
+const MAX_DICTIONARY_WORDS: usize = 10_000;
+// 现行时区的范围为 UTC-12:00 到 UTC+14:00
+const MIN_OFFSET_MINUTES: i16 = -12 * 60;
+const MAX_OFFSET_MINUTES: i16 = 14 * 60;
+// 复制或移动日记后在新位置抽样解密的条目数
+const COPY_SAMPLE_ENTRIES: usize = 16;
+const SHRED_CHUNK_BYTES: usize = 64 * 1024;
+// 日志累积这么多条记录后整理为分片并重写 vault.json
+const JOURNAL_COMPACT_THRESHOLD: u64 = 256;
+// 流式导出时每批并行解密的条目数，限制内存中同时存在的明文
+const EXPORT_BATCH_SIZE: usize = 64;
+// 性能测试使用的合成数据规模
+const BENCHMARK_ENTRY_COUNT: usize = 200;
+const BENCHMARK_ENTRY_BYTES: usize = 4 * 1024;
+const BENCHMARK_ATTACHMENT_BYTES: usize = 8 * 1024 * 1024;
+const BENCHMARK_WORDS: [&str; 12] = [
+    "今天", "天气", "很好", "散步", "心情", "工作", "the", "morning", "coffee", "walk",
+    "meeting", "notes",
+];
+const SELF_TEST_RANDOM_BYTES: usize = 4096;
+const SELF_TEST_ENTRY_BYTES: usize = 16 * 1024;
+const SELF_TEST_ATTACHMENT_BYTES: usize = 256 * 1024;
+// 自动保存的元数据改动在停顿这么久后才写盘，连续编辑最多推迟到上限
+const METADATA_FLUSH_DELAY: Duration = Duration::from_secs(2);
+const METADATA_FLUSH_MAX_DELAY: Duration = Duration::from_secs(10);
+
+const SUPPORTED_TEXT_ENCRYPTIONS: [TextEncryption; 2] = [
+    TextEncryption::Aes256Gcm,
+    TextEncryption::ChaCha20Poly1305,
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Default, Type)]
+pub enum TextEncryption {
+    #[serde(rename = "aes256_gcm")]
+    #[default]
+    Aes256Gcm,
+    #[serde(rename = "chacha20_poly1305")]
+    ChaCha20Poly1305,
+}
+
+impl TextEncryption {
+    fn as_byte(self) -> u8 {
+        match self {
+            TextEncryption::Aes256Gcm => 1,
+            TextEncryption::ChaCha20Poly1305 => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self> {
+        match byte {
+            1 => Ok(TextEncryption::Aes256Gcm),
+            2 => Ok(TextEncryption::ChaCha20Poly1305),
+            _ => Err(anyhow!("unsupported entry encryption")),
+        }
+    }
+}
+
+// 认证解密失败（密钥错误或数据被篡改），解锁时据此判断为密码错误
+#[derive(Debug, thiserror::Error)]
+#[error("decryption failed")]
+pub struct DecryptionFailed;
+
+// 所选目录中没有日记库；解锁不再自动创建，由前端引导用户确认后调用 create
+#[derive(Debug, thiserror::Error)]
+#[error("vault not found")]
+pub struct VaultNotFound;
+
+#[derive(Debug, thiserror::Error)]
+#[error("vault is locked")]
+pub struct VaultLocked;
+
+#[derive(Debug, thiserror::Error)]
+#[error("entry not found")]
+pub struct EntryNotFound;
+
+// 目标位置已被占用，需要用户换一个位置或先处理冲突
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct Conflict(pub Message);
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct QuotaExceeded(pub Message);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageEncryption {
+    Aes256Ctr,
+}
+
+impl ImageEncryption {
+    const MARKER_AES256_CTR: &'static [u8] = b":AES256CTR:";
+
+    fn detect(bytes: &[u8]) -> Option<(Self, usize)> {
+        if bytes.starts_with(Self::MARKER_AES256_CTR) {
+            return Some((Self::Aes256Ctr, Self::MARKER_AES256_CTR.len()));
+        }
+        None
+    }
+}
+
+// 加密前的压缩方式，记录在条目文件头中；旧文件没有该字段，视为未压缩
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum Compression {
+    #[default]
+    None,
+    Zstd,
+}
+
+impl Compression {
+    fn as_byte(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Zstd => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self> {
+        match byte {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Zstd),
+            _ => Err(anyhow!("unsupported compression")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct EntryInfo {
+    pub id: Uuid,
+    pub title: String,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+    // 日记记述的日期，可以早于新建时间；未设置时取新建时间的日期。
+    // created_at/updated_at 只作为写入记录，日历、那年今日和按日期排序都用这个字段
+    #[serde(default, with = "iso_date")]
+    pub entry_date: Option<Date>,
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // 心情评分 1-5，未填写为 None
+    #[serde(default)]
+    pub mood: Option<u8>,
+    #[serde(default)]
+    pub location: Option<Location>,
+    #[serde(default)]
+    pub weather: Option<Weather>,
+    // 从健康数据导入的当天汇总
+    #[serde(default)]
+    pub health: Option<HealthSummary>,
+    // 正文字数，随正文保存时更新；旧数据在解锁时补算
+    #[serde(default)]
+    pub word_count: Option<u32>,
+    // 正文首行摘要，更新与补算方式同字数
+    #[serde(default)]
+    pub preview: Option<String>,
+    // 本机情感打分，-1 到 1；未开启情感分析时保存的条目为 None
+    #[serde(default)]
+    pub sentiment: Option<f64>,
+    #[serde(default)]
+    pub entry_type: EntryType,
+    #[serde(default)]
+    pub encryption: TextEncryption,
+}
+
+impl EntryInfo {
+    fn touch(&mut self, now: OffsetDateTime) {
+        self.updated_at = now;
+    }
+
+    // 新建时间带有写入时的本机时区，直接取其日期；
+    // 旧数据只记录了 UTC 时间，改按 fallback 时区换算
+    pub fn created_day(&self, fallback: UtcOffset) -> Date {
+        if self.created_at.offset().is_utc() {
+            self.created_at.to_offset(fallback).date()
+        } else {
+            self.created_at.date()
+        }
+    }
+
+    // 未设置 entry_date 的条目按新建日期计
+    pub fn entry_day(&self, fallback: UtcOffset) -> Date {
+        self.entry_date.unwrap_or_else(|| self.created_day(fallback))
+    }
+
+    fn with_body(self, content: String, payload: Option<EntryPayload>) -> Entry {
+        Entry {
+            id: self.id,
+            title: self.title,
+            content,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            entry_date: self.entry_date,
+            folder: self.folder,
+            tags: self.tags,
+            mood: self.mood,
+            location: self.location,
+            weather: self.weather,
+            health: self.health,
+            payload,
+            encryption: self.encryption,
+        }
+    }
+}
+
+// 新建日记时的天气，由应用层向天气服务查询后写入
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct Weather {
+    pub condition: WeatherCondition,
+    pub temperature_c: f64,
+    #[serde(default)]
+    pub humidity: Option<u8>,
+    #[serde(default)]
+    pub wind_kph: Option<f64>,
+    pub provider: WeatherProvider,
+}
+
+// 各天气服务的天气代码归并为以下几类，文字由前端按语言显示
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum WeatherCondition {
+    Clear,
+    Cloudy,
+    Fog,
+    Drizzle,
+    Rain,
+    Snow,
+    Thunderstorm,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum WeatherProvider {
+    // 无需 API 密钥
+    #[default]
+    OpenMeteo,
+    OpenWeatherMap,
+}
+
+// 默认关闭；API 密钥随日记库设置加密保存，不写入应用设置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Type)]
+pub struct WeatherSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub provider: WeatherProvider,
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+// 写日记时所在的位置，与其他元数据一起加密保存
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+    #[serde(default)]
+    pub place: Option<String>,
+}
+
+impl Location {
+    // 球面距离（公里）
+    pub fn distance_km(&self, latitude: f64, longitude: f64) -> f64 {
+        const EARTH_RADIUS_KM: f64 = 6371.0;
+        let (lat1, lat2) = (self.latitude.to_radians(), latitude.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (longitude - self.longitude).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+    }
+}
+
+// create_entries 的单条输入；导入时可带上原始创建时间
+#[derive(Debug, Clone, Default, Deserialize, Type)]
+pub struct NewEntry {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub mood: Option<u8>,
+    #[serde(default)]
+    pub location: Option<Location>,
+    #[serde(default)]
+    pub weather: Option<Weather>,
+    // 提供时正文由结构化内容生成，content 被忽略；标题为空时按类型取默认标题
+    #[serde(default)]
+    pub payload: Option<EntryPayload>,
+    #[serde(default)]
+    pub encryption: Option<TextEncryption>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub created_at: Option<OffsetDateTime>,
+    #[serde(default, with = "iso_date")]
+    pub entry_date: Option<Date>,
+}
+
+// patch_entry 的参数：缺省的字段保持不变
+#[derive(Debug, Clone, Default, Deserialize, Type)]
+pub struct PatchFields {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub content: Option<String>,
+    // 字段缺省表示不修改，显式传 null 表示移出文件夹
+    #[serde(default, deserialize_with = "present")]
+    pub folder: Option<Option<String>>,
+    // 整体替换标签列表，传空数组表示清除
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    // 与 folder 相同，显式传 null 表示清除心情
+    #[serde(default, deserialize_with = "present")]
+    pub mood: Option<Option<u8>>,
+    // 显式传 null 表示清除位置
+    #[serde(default, deserialize_with = "present")]
+    pub location: Option<Option<Location>>,
+    #[serde(default, deserialize_with = "present")]
+    pub weather: Option<Option<Weather>>,
+    // 显式传 null 表示恢复为新建日期
+    #[serde(default, deserialize_with = "present_date")]
+    pub entry_date: Option<Option<Date>>,
+    #[serde(default)]
+    pub encryption: Option<TextEncryption>,
+}
+
+fn present<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+fn present_date<'de, D>(deserializer: D) -> std::result::Result<Option<Option<Date>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    iso_date::deserialize(deserializer).map(Some)
+}
+
+// 日期在前后端之间统一用 YYYY-MM-DD。time 自带的 option 格式在日志记录
+// （按 op 标记的枚举）里无法解析 null，这里先按字符串读取
+mod iso_date {
+    use serde::{de, ser, Deserialize, Deserializer, Serializer};
+    use time::format_description::FormatItem;
+    use time::macros::format_description;
+    use time::Date;
+
+    const FORMAT: &[FormatItem<'_>] = format_description!("[year]-[month]-[day]");
+
+    pub fn serialize<S>(date: &Option<Date>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match date {
+            Some(date) => {
+                let text = date.format(FORMAT).map_err(ser::Error::custom)?;
+                serializer.serialize_str(&text)
+            }
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Date>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|text| Date::parse(&text, FORMAT).map_err(de::Error::custom))
+            .transpose()
+    }
+}
+
+// 附件的大小、尺寸和保存时间缓存在加密元数据中，列出附件时无需逐个读取解密
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct AttachmentInfo {
+    pub path: String,
+    pub size: u64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub stored_at: OffsetDateTime,
+}
+
+impl AttachmentInfo {
+    fn describe(relative: &Path, data: &[u8], stored_at: OffsetDateTime) -> Self {
+        let dimensions = imagesize::blob_size(data).ok();
+        AttachmentInfo {
+            path: display_path(relative),
+            size: data.len() as u64,
+            width: dimensions.map(|size| size.width as u32),
+            height: dimensions.map(|size| size.height as u32),
+            stored_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct Entry {
+    pub id: Uuid,
+    pub title: String,
+    pub content: String,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+    #[serde(default, with = "iso_date")]
+    pub entry_date: Option<Date>,
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub mood: Option<u8>,
+    #[serde(default)]
+    pub location: Option<Location>,
+    #[serde(default)]
+    pub weather: Option<Weather>,
+    #[serde(default)]
+    pub health: Option<HealthSummary>,
+    // 引导式日记的结构化内容，普通日记为 None
+    #[serde(default)]
+    pub payload: Option<EntryPayload>,
+    #[serde(default)]
+    pub encryption: TextEncryption,
+}
+
+impl Entry {
+    pub fn new(
+        title: impl Into<String>,
+        content: impl Into<String>,
+        encryption: TextEncryption,
+    ) -> Self {
+        let now = OffsetDateTime::now_utc();
+        Self {
+            id: Uuid::new_v4(),
+            title: title.into(),
+            content: content.into(),
+            created_at: now,
+            updated_at: now,
+            entry_date: None,
+            folder: None,
+            tags: Vec::new(),
+            mood: None,
+            location: None,
+            weather: None,
+            health: None,
+            payload: None,
+            encryption,
+        }
+    }
+
+    // offset 为 None 时按写入时记录的时区输出；引导式日记额外注明类型
+    pub fn to_markdown(&self, offset: Option<UtcOffset>) -> String {
+        let format = |at: OffsetDateTime| {
+            offset
+                .map_or(at, |offset| at.to_offset(offset))
+                .format(&Rfc3339)
+                .unwrap_or_else(|_| String::new())
+        };
+        let kind = match self.entry_type() {
+            EntryType::Note => String::new(),
+            other => format!("类型：{}\n", Message::EntryTypeName(other)),
+        };
+        format!(
+            "# {title}\n{kind}创建：{created}\n更新：{updated}\n\n{content}\n",
+            title = self.title,
+            created = format(self.created_at),
+            updated = format(self.updated_at),
+            content = self.content
+        )
+    }
+
+    pub fn entry_type(&self) -> EntryType {
+        self.payload
+            .as_ref()
+            .map_or(EntryType::Note, EntryPayload::entry_type)
+    }
+
+    fn at(mut self, now: OffsetDateTime) -> Self {
+        self.created_at = now;
+        self.updated_at = now;
+        self
+    }
+
+    fn metadata(&self, sentiment: bool) -> EntryInfo {
+        EntryInfo {
+            id: self.id,
+            title: self.title.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            entry_date: self.entry_date,
+            folder: self.folder.clone(),
+            tags: self.tags.clone(),
+            mood: self.mood,
+            location: self.location.clone(),
+            weather: self.weather.clone(),
+            health: self.health.clone(),
+            word_count: Some(search::word_count(&self.content)),
+            preview: Some(search::preview(&self.content)),
+            sentiment: sentiment.then(|| sentiment::score(&self.content)),
+            entry_type: self.entry_type(),
+            encryption: self.encryption,
+        }
+    }
+}
+
+// 保存在加密元数据中的日记策略，修改需要重新验证密码
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Type)]
+pub struct VaultPolicy {
+    #[serde(default)]
+    pub disable_plaintext_export: bool,
+}
+
+// 随日记库加密保存的偏好，换设备后依然生效；修改不需要重新验证密码
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+pub struct VaultSettings {
+    #[serde(default)]
+    pub auto_lock_minutes: Option<u64>,
+    #[serde(default)]
+    pub goals: WritingGoals,
+    // 导出时统一换算到的时区（相对 UTC 的分钟数），None 表示保留各条目写入时的时区
+    #[serde(default)]
+    pub timezone_offset_minutes: Option<i16>,
+    #[serde(default)]
+    pub weather: WeatherSettings,
+    // 保存时用内置词典给正文打情感分，开启时补算已有日记
+    #[serde(default)]
+    pub sentiment_analysis: bool,
+    // 其余偏好（导出默认值、图片压缩、保留策略等）由前端解释，后端只负责加密保存
+    #[serde(flatten)]
+    pub preferences: serde_json::Map<String, serde_json::Value>,
+}
+
+// 写作目标；未设置的目标不计算连续达成
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct WritingGoals {
+    #[serde(default)]
+    pub words_per_day: Option<u32>,
+    #[serde(default)]
+    pub entries_per_week: Option<u32>,
+}
+
+// 按新建时间统计，周从周一开始。当前周期尚未达成时，连续记录从上一周期算起
+#[derive(Debug, Serialize, Type)]
+pub struct GoalProgress {
+    pub goals: WritingGoals,
+    pub words_today: u32,
+    pub entries_this_week: u32,
+    pub day_streak: u32,
+    pub week_streak: u32,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct UnlockResponse {
+    pub entries: Vec<EntryInfo>,
+    pub created: bool,
+    pub last_saved: Option<String>,
+    pub vault_root: String,
+    pub text_encryption: TextEncryption,
+    pub available_text_encryptions: Vec<TextEncryption>,
+    pub policy: VaultPolicy,
+    pub settings: VaultSettings,
+    // 本次解锁时升级了旧版本格式
+    pub migration: Option<MigrationReport>,
+    // 日记目录不可写，已自动以只读方式打开
+    pub read_only: bool,
+}
+
+// steps 为依次执行的升级步骤说明；backup 为升级前备份所在目录，预检时为 None
+#[derive(Debug, Serialize, Type)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub steps: Vec<String>,
+    pub backup: Option<String>,
+}
+
+// 只读取 vault.json 的明文头部，不派生密钥，供前端决定显示解锁还是创建界面
+#[derive(Debug, Serialize, Type)]
+pub struct VaultProbe {
+    pub exists: bool,
+    pub compatible: bool,
+    pub version: Option<u32>,
+    pub legacy_format: bool,
+    pub updated_at: Option<String>,
+    pub kdf: Option<KdfParams>,
+    pub profile: Option<VaultProfile>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Type)]
+pub struct KdfParams {
+    pub algorithm: &'static str,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+// 本机性能测试结果，用于反馈"解锁很慢"之类的问题
+#[derive(Debug, Serialize, Type)]
+pub struct BenchmarkReport {
+    pub platform: String,
+    pub threads: usize,
+    pub kdf: KdfParams,
+    pub kdf_ms: f64,
+    pub entries: Vec<EntryBenchmark>,
+    pub attachment: AttachmentBenchmark,
+    pub metadata_entries: usize,
+    pub metadata_save_ms: f64,
+    pub journal_append_ms: f64,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct EntryBenchmark {
+    pub encryption: TextEncryption,
+    pub entry_count: usize,
+    pub entry_bytes: usize,
+    pub encrypt_ms_per_entry: f64,
+    pub decrypt_ms_per_entry: f64,
+    pub encrypt_mb_per_sec: f64,
+    pub decrypt_mb_per_sec: f64,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct AttachmentBenchmark {
+    pub bytes: usize,
+    pub encrypt_mb_per_sec: f64,
+    pub decrypt_mb_per_sec: f64,
+}
+
+// 自检结果，附在问题反馈中用于判断是本机环境还是日记本身的问题
+#[derive(Debug, Serialize, Type)]
+pub struct SelfTestReport {
+    pub platform: String,
+    pub passed: bool,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfTestKind {
+    Random,
+    TextEncryption,
+    Attachment,
+    WrongKeyRejected,
+    VaultRoundtrip,
+    VaultDirectory,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct SelfTestCheck {
+    pub kind: SelfTestKind,
+    // 只有文本加密检查填写
+    pub encryption: Option<TextEncryption>,
+    pub passed: bool,
+    // 通过时是补充说明，失败时是错误原因
+    pub detail: Option<String>,
+    pub duration_ms: f64,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct CloneReport {
+    pub vault_root: String,
+    pub files: usize,
+    pub bytes: u64,
+    // 在副本中实际解密验证过的条目数
+    pub verified_entries: usize,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct DeleteReport {
+    pub backup: Option<CloneReport>,
+    pub removed_files: usize,
+    // 未能删除的文件，需要用户手动清理
+    pub leftover: Vec<String>,
+    pub directory_removed: bool,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct ImportReport {
+    pub entries: usize,
+    pub attachments: usize,
+    // 与当前日记 id 冲突、换用新 id 的条目数
+    pub renamed: usize,
+    pub folders: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct MoveReport {
+    pub vault_root: String,
+    // 旧位置中未能删除的文件，需要用户手动清理
+    pub leftover: Vec<String>,
+}
+
+// 设置和关于页面一次取得当前日记库的概况
+#[derive(Debug, Serialize, Type)]
+pub struct VaultInfo {
+    pub vault_root: String,
+    pub created_at: Option<String>,
+    pub entry_count: usize,
+    pub attachment_count: usize,
+    pub attachment_bytes: u64,
+    pub last_saved: Option<String>,
+    pub text_encryption: TextEncryption,
+    pub kdf: KdfParams,
+    pub auto_lock_minutes: Option<u64>,
+    // 距离自动锁定还剩多少秒，未启用自动锁定时为 None
+    pub auto_lock_in_secs: Option<u64>,
+}
+
+// query_entries 的条件，所有字段都可省略；多个条件同时满足才会命中
+#[derive(Debug, Clone, Default, Deserialize, Type)]
+pub struct QuerySpec {
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub folder: Option<String>,
+    // 为 true 时 folder 也匹配其下的子文件夹
+    #[serde(default)]
+    pub include_subfolders: bool,
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub entry_type: Option<EntryType>,
+    // 心情评分范围（含两端）；设置任一端时不包含未打分的条目
+    #[serde(default)]
+    pub min_mood: Option<u8>,
+    #[serde(default)]
+    pub max_mood: Option<u8>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub from: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub to: Option<OffsetDateTime>,
+    #[serde(default)]
+    pub date_field: QueryDateField,
+    // 需要解密正文判断，放在其他条件之后执行
+    #[serde(default)]
+    pub has_attachments: Option<bool>,
+    #[serde(default)]
+    pub sort: QuerySort,
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+// list_places 的一项，坐标取该地点各条目的平均值
+#[derive(Debug, Serialize, Type)]
+pub struct PlaceCount {
+    pub place: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryDateField {
+    Created,
+    #[default]
+    Updated,
+    // 按 entry_date 筛选，from/to 按各自时区取日期后比较
+    Entry,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum QuerySort {
+    #[default]
+    UpdatedDesc,
+    UpdatedAsc,
+    CreatedDesc,
+    CreatedAsc,
+    // 按 entry_date 排序，同一天的条目按新建时间
+    DateDesc,
+    DateAsc,
+    Title,
+}
+
+// list_folders 的一项；count 只统计直接位于该文件夹中的条目
+#[derive(Debug, Serialize, Type)]
+pub struct FolderCount {
+    pub folder: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsGroup {
+    Folder,
+    Tag,
+}
+
+// get_writing_stats 的一组。group 为 None 表示没有文件夹或标签的日记；
+// 按标签统计时一篇日记计入它的每个标签
+#[derive(Debug, Serialize, Type)]
+pub struct WritingStats {
+    pub group: Option<String>,
+    pub entries: u32,
+    pub words: u64,
+    pub average_words: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum TrendBucket {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+// 按日记日期筛选，两端都可省略
+#[derive(Debug, Clone, Default, Deserialize, Type)]
+pub struct DateRange {
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub from: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub to: Option<OffsetDateTime>,
+}
+
+impl DateRange {
+    fn contains(&self, info: &EntryInfo) -> bool {
+        self.from
+            .is_none_or(|from| info.entry_day(from.offset()) >= from.date())
+            && self
+                .to
+                .is_none_or(|to| info.entry_day(to.offset()) <= to.date())
+    }
+}
+
+// start 为该时间段第一天（YYYY-MM-DD），周从周一开始
+#[derive(Debug, Serialize, Type)]
+pub struct MoodPoint {
+    pub start: String,
+    pub average: f64,
+    pub entries: u32,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct TagMood {
+    pub tag: String,
+    pub average: f64,
+    pub entries: u32,
+}
+
+// 日历中一天的日记数，date 为 YYYY-MM-DD
+#[derive(Debug, Serialize, Type)]
+pub struct CalendarDay {
+    pub date: String,
+    pub count: usize,
+}
+
+// 某一天的全部日记，按新建时间排列；attachments 为这些日记正文引用的附件
+#[derive(Debug, Serialize, Type)]
+pub struct DayView {
+    pub date: String,
+    pub entries: Vec<EntryInfo>,
+    pub words: u64,
+    pub average_mood: Option<f64>,
+    pub attachments: Vec<AttachmentInfo>,
+    pub summary: Option<String>,
+}
+
+// 侧边栏按年、月分组的时间线，新的在前
+#[derive(Debug, Serialize, Type)]
+pub struct TimelineYear {
+    pub year: i32,
+    pub count: usize,
+    pub months: Vec<TimelineMonth>,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct TimelineMonth {
+    pub month: u8,
+    pub count: usize,
+    pub entries: Vec<EntryInfo>,
+}
+
+// 某一天保存的附件，按保存时间排列；cover 为当天像素最多的图片，作为照片日历的封面
+#[derive(Debug, Serialize, Type)]
+pub struct MediaDay {
+    pub date: String,
+    pub cover: Option<String>,
+    pub attachments: Vec<AttachmentInfo>,
+}
+
+// 只统计填写了心情的条目；样本少于两条或没有变化时相关系数为 None
+#[derive(Debug, Serialize, Type)]
+pub struct MoodTrends {
+    pub average: Option<f64>,
+    pub points: Vec<MoodPoint>,
+    pub tags: Vec<TagMood>,
+    pub word_count_correlation: Option<f64>,
+}
+
+// 只统计已打分的日记；mood_correlation 为情感分与心情评分的相关系数
+#[derive(Debug, Serialize, Type)]
+pub struct SentimentTrends {
+    pub average: Option<f64>,
+    pub points: Vec<MoodPoint>,
+    pub mood_correlation: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct QueryPage {
+    pub entries: Vec<EntryInfo>,
+    // 分页前的命中总数
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct IntegrityReport {
+    pub valid: bool,
+    pub history: HistoryReport,
+    pub world_readable: Vec<String>,
+    pub unreadable_entries: Vec<EntryError>,
+}
+
+// 无法解密或解析的日记，列表、导出和校验遇到时跳过并记录在这里
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct EntryError {
+    pub id: Uuid,
+    pub title: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct ExportReport {
+    pub path: String,
+    pub skipped: Vec<EntryError>,
+}
+
+pub const VAULT_EVENT: &str = "vault://lifecycle";
+
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VaultEvent {
+    Unlocked { vault_root: String },
+    Moved { vault_root: String },
+    ProfileChanged { profile: VaultProfile },
+    Locked,
+    AutoLocked,
+    PassphraseChanged,
+}
+
+type EventListener = Box<dyn Fn(&VaultEvent) + Send + Sync>;
+
+pub const UNLOCK_PROGRESS_EVENT: &str = "unlock://progress";
+
+// 解锁各阶段，供解锁界面显示进度（Argon2 派生在旧设备上可能需要数秒）
+#[derive(Debug, Clone, Copy, Serialize, Type)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum UnlockProgress {
+    Started,
+    Deriving,
+    Decrypting,
+    LoadingEntries,
+}
+
+type ProgressListener = Box<dyn Fn(UnlockProgress) + Send + Sync>;
+
+pub const ENTRIES_CHANGED_EVENT: &str = "entries://changed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryChange {
+    Created,
+    Updated,
+    Deleted,
+}
+
+// 条目增删改后通知所有窗口，免得各自轮询 list_entries
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct EntriesChanged {
+    pub change: EntryChange,
+    pub ids: Vec<Uuid>,
+}
+
+type EntriesListener = Box<dyn Fn(&EntriesChanged) + Send + Sync>;
+
+// 返回带本机时区偏移的当前时间。core 不自行读取系统时区，未设置时按 UTC 记录
+type LocalClock = Box<dyn Fn() -> OffsetDateTime + Send + Sync>;
+
+#[derive(Default)]
+pub struct VaultManager {
+    inner: RwLock<Option<UnlockedVault>>,
+    listener: Mutex<Option<EventListener>>,
+    progress_listener: Mutex<Option<ProgressListener>>,
+    entries_listener: Mutex<Option<EntriesListener>>,
+    local_clock: Mutex<Option<LocalClock>>,
+    auto_lock_after: Mutex<Option<Duration>>,
+    last_activity: Mutex<Option<Instant>>,
+}
+
+impl VaultManager {
+    pub fn set_event_listener(&self, listener: impl Fn(&VaultEvent) + Send + Sync + 'static) {
+        *self.listener.lock() = Some(Box::new(listener));
+    }
+
+    pub fn set_progress_listener(&self, listener: impl Fn(UnlockProgress) + Send + Sync + 'static) {
+        *self.progress_listener.lock() = Some(Box::new(listener));
+    }
+
+    pub fn set_entries_listener(
+        &self,
+        listener: impl Fn(&EntriesChanged) + Send + Sync + 'static,
+    ) {
+        *self.entries_listener.lock() = Some(Box::new(listener));
+    }
+
+    pub fn set_local_clock(&self, clock: impl Fn() -> OffsetDateTime + Send + Sync + 'static) {
+        *self.local_clock.lock() = Some(Box::new(clock));
+    }
+
+    fn now(&self) -> OffsetDateTime {
+        match self.local_clock.lock().as_ref() {
+            Some(clock) => clock(),
+            None => OffsetDateTime::now_utc(),
+        }
+    }
+
+    fn report(&self, stage: UnlockProgress) {
+        if let Some(listener) = self.progress_listener.lock().as_ref() {
+            listener(stage);
+        }
+    }
+
+    fn emit(&self, event: VaultEvent) {
+        if let Some(listener) = self.listener.lock().as_ref() {
+            listener(&event);
+        }
+    }
+
+    // 调用前需释放日记锁，监听器可能回调进来
+    fn notify_entries(&self, change: EntryChange, ids: Vec<Uuid>) {
+        if ids.is_empty() {
+            return;
+        }
+        if let Some(listener) = self.entries_listener.lock().as_ref() {
+            listener(&EntriesChanged { change, ids });
+        }
+    }
+
+    fn touch(&self) {
+        *self.last_activity.lock() = Some(Instant::now());
+    }
+
+    pub fn set_auto_lock_after(&self, timeout: Option<Duration>) {
+        *self.auto_lock_after.lock() = timeout;
+        self.touch();
+    }
+
+    // 由后台定时调用：超过空闲时间后自动锁定，返回是否执行了锁定
+    pub fn lock_if_idle(&self) -> bool {
+        let Some(timeout) = *self.auto_lock_after.lock() else {
+            return false;
+        };
+        let idle = self
+            .last_activity
+            .lock()
+            .map(|last| last.elapsed() >= timeout)
+            .unwrap_or(false);
+        if !idle {
+            return false;
+        }
+
+        self.close(VaultEvent::AutoLocked)
+    }
+
+    // 锁定前整理元数据日志；若本次会话有改动，则刷新待发布的紧急归档。
+    // 重新生成要解密整个日记库，距上次生成不到一天时跳过
+    fn close(&self, event: VaultEvent) -> bool {
+        let Some(mut vault) = self.inner.write().take() else {
+            return false;
+        };
+        if let Err(err) = compact_metadata(&mut vault) {
+            tracing::warn!(error = %err, "failed to compact metadata before locking");
+        }
+        let root = vault_root_of(&vault);
+        if vault.modified && !vault.read_only && emergency::restage_due(&root) {
+            if let Some(access) = vault.emergency.as_ref() {
+                let staged = recovery_payload(&vault)
+                    .and_then(|payload| emergency::stage(&root, access, &payload));
+                if let Err(err) = staged {
+                    tracing::warn!(error = %err, "failed to stage emergency archive");
+                }
+            }
+        }
+        self.emit(event);
+        true
+    }
+
+    // 在所选目录中新建日记库并直接解锁；目录中已有日记库时拒绝，避免覆盖
+    pub fn create(
+        &self,
+        passphrase: &str,
+        metadata_path: PathBuf,
+        preferred_encryption: Option<TextEncryption>,
+        policy: Option<VaultPolicy>,
+    ) -> Result<UnlockResponse> {
+        self.report(UnlockProgress::Started);
+        let root_path = metadata_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| metadata_path.clone());
+        if metadata_path.exists() {
+            return Err(Conflict(Message::VaultAlreadyExists).into());
+        }
+
+        let entries_dir = root_path.join("entries");
+        let attachments_dir = root_path.join("attachments");
+        permissions::create_private_dir_all(&entries_dir)
+            .context("failed to prepare entries directory")?;
+        permissions::create_private_dir_all(&attachments_dir)
+            .context("failed to prepare attachments directory")?;
+        let lock = vault_lock::acquire(&root_path)?;
+
+        let text_encryption = preferred_encryption.unwrap_or_default();
+        if !SUPPORTED_TEXT_ENCRYPTIONS.contains(&text_encryption) {
+            return Err(anyhow!("unsupported text encryption method"));
+        }
+
+        // 主密钥随机生成，主口令作为管理员槽位包装主密钥，修改口令时只需重新包装
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        self.report(UnlockProgress::Deriving);
+        let kdf = kdf_params();
+        let owner = key_slots::create(&root_path, &key, &kdf, key_slots::OWNER_LABEL, passphrase)?;
+
+        let journal_id = Uuid::new_v4();
+        let metadata = VaultMetadata {
+            version: METADATA_VERSION,
+            entries: Vec::new(),
+            shards: BTreeMap::new(),
+            journal_id: Some(journal_id),
+            text_encryption,
+            history_head: None,
+            passphrase_history: Vec::new(),
+            passphrase_history_depth: DEFAULT_PASSPHRASE_HISTORY_DEPTH,
+            emergency: None,
+            policy: policy.unwrap_or_default(),
+            settings: VaultSettings::default(),
+            prompts: PromptState::default(),
+            habits: HabitState::default(),
+            recaps: BTreeMap::new(),
+            day_summaries: BTreeMap::new(),
+            dictionary: BTreeSet::new(),
+            attachments: Some(Vec::new()),
+        };
+        let now = OffsetDateTime::now_utc();
+        save_vault(&metadata_path, &salt, &kdf, &key, &metadata, now)?;
+        let journal_path = journal::journal_file_path(&root_path);
+        journal::reset(&journal_path, &journal_id)?;
+        let history_head =
+            history::append(&history::history_file_path(&root_path), &metadata_path, now)?;
+        let last_unlock = record_unlock(&root_path);
+
+        let unlocked = UnlockedVault {
+            key,
+            salt,
+            kdf,
+            slot: Some(owner.id),
+            metadata: Vec::new(),
+            path: metadata_path,
+            entries_dir,
+            attachments_dir,
+            attachments: Vec::new(),
+            index_dir: root_path.join(shards::INDEX_DIR),
+            shards: BTreeMap::new(),
+            dirty_years: BTreeSet::new(),
+            journal_path,
+            journal_id,
+            journal_len: 0,
+            search: SearchIndex::default(),
+            search_path: search::index_file_path(&root_path),
+            search_dirty: false,
+            text_encryption,
+            last_saved: now,
+            history_head: Some(history_head),
+            last_unlock,
+            passphrase_history: Vec::new(),
+            passphrase_history_depth: DEFAULT_PASSPHRASE_HISTORY_DEPTH,
+            emergency: None,
+            policy: policy.unwrap_or_default(),
+            settings: VaultSettings::default(),
+            prompts: PromptState::default(),
+            habits: HabitState::default(),
+            recaps: BTreeMap::new(),
+            day_summaries: BTreeMap::new(),
+            dictionary: BTreeSet::new(),
+            modified: false,
+            read_only: false,
+            pending_flush: None,
+            pending_updates: BTreeSet::new(),
+            undo: UndoHistory::default(),
+            _lock: lock,
+        };
+
+        *self.inner.write() = Some(unlocked);
+        self.touch();
+        self.emit(VaultEvent::Unlocked {
+            vault_root: display_path(&root_path),
+        });
+
+        Ok(UnlockResponse {
+            entries: Vec::new(),
+            created: true,
+            last_saved: Some(now.format(&Rfc3339).unwrap_or_default()),
+            vault_root: display_path(&root_path),
+            text_encryption,
+            available_text_encryptions: SUPPORTED_TEXT_ENCRYPTIONS.to_vec(),
+            policy: policy.unwrap_or_default(),
+            settings: VaultSettings::default(),
+            migration: None,
+            read_only: false,
+        })
+    }
+
+    pub fn unlock(&self, passphrase: &str, metadata_path: PathBuf) -> Result<UnlockResponse> {
+        self.report(UnlockProgress::Started);
+        let root_path = metadata_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| metadata_path.clone());
+        if !metadata_path.exists() {
+            return Err(VaultNotFound.into());
+        }
+
+        let stored = load_vault(&metadata_path)?;
+        let salt = stored_salt(&stored)?;
+        self.report(UnlockProgress::Deriving);
+        master_key(&root_path, &stored, passphrase)
+            .and_then(|(key, slot)| {
+                self.open_with_key(stored, salt, key, slot, metadata_path, false)
+            })
+            .inspect_err(|err| {
+                if err.is::<DecryptionFailed>() {
+                    tracing::warn!("unlock rejected: wrong passphrase");
+                    let _ = unlock_log::record_failure(&root_path);
+                }
+            })
+    }
+
+    // 使用快速解锁 PIN 取回的主密钥打开已有日记；PIN 相关数据全部位于日记目录之外
+    pub fn unlock_with_pin(
+        &self,
+        store_dir: &Path,
+        pin: &str,
+        metadata_path: PathBuf,
+    ) -> Result<UnlockResponse> {
+        self.report(UnlockProgress::Started);
+        let root_path = metadata_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| metadata_path.clone());
+        if !metadata_path.exists() {
+            return Err(VaultNotFound.into());
+        }
+
+        self.report(UnlockProgress::Deriving);
+        let key = quick_unlock::unwrap_key(store_dir, &root_path, pin)?;
+        let stored = load_vault(&metadata_path)?;
+        let salt = stored_salt(&stored)?;
+        self.open_with_key(stored, salt, key, None, metadata_path, false)
+            .map_err(|err| {
+                if err.is::<DecryptionFailed>() {
+                    // 密码已修改，保存的主密钥失效
+                    let _ = quick_unlock::disable(store_dir, &root_path);
+                    return anyhow!(Message::QuickUnlockExpired);
+                }
+                err
+            })
+    }
+
+    // 以只读方式解锁，不整理、不记录解锁，密码错误也不写日志；用于读取导入来源
+    fn unlock_read_only(&self, passphrase: &str, metadata_path: PathBuf) -> Result<UnlockResponse> {
+        let root_path = metadata_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| metadata_path.clone());
+        if !metadata_path.exists() {
+            return Err(VaultNotFound.into());
+        }
+        let stored = load_vault(&metadata_path)?;
+        let salt = stored_salt(&stored)?;
+        let (key, slot) = master_key(&root_path, &stored, passphrase)?;
+        self.open_with_key(stored, salt, key, slot, metadata_path, true)
+    }
+
+    pub fn enable_quick_unlock(&self, store_dir: &Path, pin: &str, max_attempts: u32) -> Result<()> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        quick_unlock::enable(store_dir, &vault_root_of(vault), pin, &vault.key, max_attempts)
+    }
+
+    fn open_with_key(
+        &self,
+        stored: StoredVault,
+        salt: [u8; 16],
+        key: [u8; 32],
+        slot: Option<Uuid>,
+        metadata_path: PathBuf,
+        read_only: bool,
+    ) -> Result<UnlockResponse> {
+        let root_path = metadata_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| metadata_path.clone());
+        let kdf = stored_kdf(&stored);
+        // 不可写的目录自动以只读方式打开，解锁过程中不写入任何文件；
+        // 可写时也只创建缺少的目录
+        let read_only = read_only || !permissions::is_writable(&root_path);
+        // 另一个进程已经打开时拒绝，两边同时写日志会互相覆盖；只读打开不写任何文件，不加锁
+        let lock = if read_only {
+            None
+        } else {
+            vault_lock::acquire(&root_path)?
+        };
+        let entries_dir = root_path.join("entries");
+        let attachments_dir = root_path.join("attachments");
+        for dir in [&entries_dir, &attachments_dir] {
+            if !read_only && !dir.exists() {
+                permissions::create_private_dir_all(dir)
+                    .context("failed to prepare vault directory")?;
+            }
+        }
+
+        self.report(UnlockProgress::Decrypting);
+        let mut raw = decrypt_metadata_value(&stored, &key)?;
+        let version = migrations::version(&raw)?;
+        let file_steps = vault_file_steps(stored.version)?;
+        let steps = migrations::plan_metadata(version, METADATA_VERSION)?;
+        let migration = if steps.is_empty() && file_steps.is_empty() {
+            None
+        } else {
+            tracing::info!(from = version, to = METADATA_VERSION, "migrating vault metadata");
+            // 只读时只在内存中升级，文件保持原样
+            let backup = if read_only {
+                None
+            } else {
+                Some(backup_before_migration(&root_path, version)?)
+            };
+            migrations::apply_metadata(&mut raw, &steps)?;
+            Some(MigrationReport {
+                from_version: version,
+                to_version: METADATA_VERSION,
+                steps: step_descriptions(&file_steps, &steps),
+                backup: backup.as_deref().map(display_path),
+            })
+        };
+        let metadata: VaultMetadata = serde_json::from_value(raw).context("invalid metadata")?;
+        let VaultMetadata {
+            entries,
+            shards: shard_hashes,
+            journal_id,
+            text_encryption,
+            passphrase_history,
+            passphrase_history_depth,
+            mut emergency,
+            policy,
+            settings,
+            prompts,
+            habits,
+            recaps,
+            day_summaries,
+            dictionary,
+            attachments: cached_attachments,
+            ..
+        } = metadata;
+        if !SUPPORTED_TEXT_ENCRYPTIONS.contains(&text_encryption) {
+            return Err(anyhow!("unsupported text encryption method"));
+        }
+
+        self.report(UnlockProgress::LoadingEntries);
+        let index_dir = root_path.join(shards::INDEX_DIR);
+        let (mut entries, mut dirty_years) = if version == LEGACY_METADATA_VERSION {
+            let years = entries.iter().map(shards::shard_year).collect();
+            (entries, years)
+        } else {
+            (
+                shards::load_shards(&index_dir, &key, &shard_hashes)?,
+                BTreeSet::new(),
+            )
+        };
+
+        // 重放上次整理之后的日志；有内容（或刚升级过格式、旧版本没有日志）时解锁后立即整理
+        let journal_path = journal::journal_file_path(&root_path);
+        let mut compact = migration.is_some() || journal_id.is_none();
+        let has_attachment_cache = cached_attachments.is_some();
+        let mut attachments = cached_attachments.unwrap_or_default();
+        if let Some(journal_id) = journal_id {
+            let changes = journal::replay(&journal_path, &key, &journal_id)?;
+            for change in &changes {
+                let year = match change {
+                    JournalChange::Upsert { entry } => Some(shards::shard_year(entry)),
+                    JournalChange::Delete { id } => entries
+                        .iter()
+                        .find(|entry| entry.id == *id)
+                        .map(shards::shard_year),
+                    JournalChange::Attachment { .. } => None,
+                };
+                dirty_years.extend(year);
+                journal::apply(&mut entries, &mut attachments, change);
+            }
+            tracing::debug!(changes = changes.len(), "replayed metadata journal");
+            compact |= !changes.is_empty();
+        }
+        // 旧版本没有保存字数和摘要，补算一次后随元数据写回
+        let counted = fill_content_stats(
+            &mut entries,
+            &entries_dir,
+            &key,
+            settings.sentiment_analysis,
+        );
+        if !counted.is_empty() {
+            dirty_years.extend(counted);
+            compact = true;
+        }
+        if !has_attachment_cache {
+            tracing::info!("rebuilding attachment cache");
+            attachments = scan_attachments(&attachments_dir, &root_path, &key)?;
+            compact = true;
+        }
+
+        let last_saved = stored.updated_at.unwrap_or_else(OffsetDateTime::now_utc);
+        let history_head = history::current_head(&history::history_file_path(&root_path))?;
+        // 早期的紧急访问配置没有发布凭据：补上，并丢弃未套外层的归档，锁定时重新生成
+        if let Some(access) = emergency.as_mut().filter(|access| access.secret.is_empty()) {
+            if !read_only {
+                access.secret = emergency::new_secret();
+                emergency::discard_staged(&root_path)?;
+                compact = true;
+            }
+        }
+        let last_unlock = if read_only {
+            unlock_log::last_unlock(&root_path)
+        } else {
+            let last_unlock = record_unlock(&root_path);
+            if let Some(access) = emergency.as_ref() {
+                if let Err(err) = emergency::check_in(&root_path, access) {
+                    tracing::warn!(error = %err, "failed to check in for emergency access");
+                }
+            }
+            last_unlock
+        };
+
+        let search_path = search::index_file_path(&root_path);
+        let mut search_index = search::load(&search_path, &key).unwrap_or_default();
+        if refresh_search_index(&mut search_index, &entries, &entries_dir, &key) && !read_only {
+            search::save(&search_path, &key, &search_index)?;
+        }
+
+        let entries_clone = entries.clone();
+
+        let mut unlocked = UnlockedVault {
+            key,
+            salt,
+            kdf,
+            slot,
+            metadata: entries,
+            path: metadata_path,
+            entries_dir,
+            attachments_dir,
+            attachments,
+            index_dir,
+            shards: shard_hashes,
+            dirty_years,
+            journal_path,
+            journal_id: journal_id.unwrap_or_else(Uuid::new_v4),
+            journal_len: 0,
+            search: search_index,
+            search_path,
+            search_dirty: false,
+            text_encryption,
+            last_saved,
+            history_head,
+            last_unlock,
+            passphrase_history,
+            passphrase_history_depth,
+            emergency,
+            policy,
+            settings: settings.clone(),
+            prompts,
+            habits,
+            recaps,
+            day_summaries,
+            dictionary,
+            modified: false,
+            read_only,
+            pending_flush: None,
+            pending_updates: BTreeSet::new(),
+            undo: UndoHistory::default(),
+            _lock: lock,
+        };
+        if read_only {
+            tracing::info!("vault directory is not writable, opened read-only");
+        } else if compact {
+            save_metadata(&mut unlocked).context("failed to compact vault metadata")?;
+        } else {
+            journal::reset(&unlocked.journal_path, &unlocked.journal_id)?;
+        }
+
+        *self.inner.write() = Some(unlocked);
+        self.set_auto_lock_after(auto_lock_duration(&settings));
+        self.emit(VaultEvent::Unlocked {
+            vault_root: display_path(&root_path),
+        });
+
+        Ok(UnlockResponse {
+            entries: entries_clone,
+            created: false,
+            last_saved: stored.updated_at.and_then(|ts| ts.format(&Rfc3339).ok()),
+            vault_root: display_path(&root_path),
+            text_encryption,
+            available_text_encryptions: SUPPORTED_TEXT_ENCRYPTIONS.to_vec(),
+            policy,
+            settings,
+            migration,
+            read_only,
+        })
+    }
+
+    // 主动锁定时先整理元数据，写入失败则保持解锁以便重试
+    pub fn lock(&self) -> Result<()> {
+        if let Some(vault) = self.inner.write().as_mut() {
+            compact_metadata(vault)?;
+        }
+        self.close(VaultEvent::Locked);
+        Ok(())
+    }
+
+    // 立即写回推迟的元数据改动，返回是否执行了写入
+    pub fn flush(&self) -> Result<bool> {
+        let mut guard = self.inner.write();
+        let Some(vault) = guard.as_mut() else {
+            return Ok(false);
+        };
+        if vault.pending_flush.is_none() {
+            return Ok(false);
+        }
+        flush_pending(vault)?;
+        Ok(true)
+    }
+
+    // 由后台定时调用：改动停顿足够久或积压过久时写回
+    pub fn flush_if_due(&self) -> Result<bool> {
+        let mut guard = self.inner.write();
+        let Some(vault) = guard.as_mut() else {
+            return Ok(false);
+        };
+        match vault.pending_flush {
+            Some(pending) if pending.is_due(Instant::now()) => {
+                flush_pending(vault)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    pub fn last_unlock_info(&self) -> Result<LastUnlockInfo> {
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        Ok(vault.last_unlock.clone())
+    }
+
+    pub fn list(&self) -> Result<Vec<EntryInfo>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let mut entries = vault.metadata.clone();
+        entries.sort_by_key(|info| std::cmp::Reverse(info.updated_at));
+        Ok(entries)
+    }
+
+    pub fn search(&self, query: &str) -> Result<Vec<EntryInfo>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let matched = vault.search.search(query);
+        let mut entries: Vec<EntryInfo> = vault
+            .metadata
+            .iter()
+            .filter(|info| matched.contains(&info.id))
+            .cloned()
+            .collect();
+        entries.sort_by_key(|info| std::cmp::Reverse(info.updated_at));
+        Ok(entries)
+    }
+
+    // 选择器和自动补全使用，直接从内存中的元数据统计，不解密正文
+    pub fn list_folders(&self) -> Result<Vec<FolderCount>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for folder in vault.metadata.iter().filter_map(|info| info.folder.as_deref()) {
+            *counts.entry(folder).or_default() += 1;
+        }
+        Ok(counts
+            .into_iter()
+            .map(|(folder, count)| FolderCount {
+                folder: folder.to_string(),
+                count,
+            })
+            .collect())
+    }
+
+    pub fn list_tags(&self) -> Result<Vec<TagCount>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for tag in vault.metadata.iter().flat_map(|info| &info.tags) {
+            *counts.entry(tag).or_default() += 1;
+        }
+        Ok(counts
+            .into_iter()
+            .map(|(tag, count)| TagCount {
+                tag: tag.to_string(),
+                count,
+            })
+            .collect())
+    }
+
+    // 按文件夹或标签汇总日记日期在 range 内的字数；按分组名排序
+    pub fn writing_stats(
+        &self,
+        group_by: StatsGroup,
+        range: DateRange,
+    ) -> Result<Vec<WritingStats>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let mut groups: BTreeMap<Option<&str>, (u32, u64)> = BTreeMap::new();
+        for info in vault.metadata.iter().filter(|info| range.contains(info)) {
+            let keys: Vec<Option<&str>> = match group_by {
+                StatsGroup::Folder => vec![info.folder.as_deref()],
+                StatsGroup::Tag if info.tags.is_empty() => vec![None],
+                StatsGroup::Tag => info.tags.iter().map(|tag| Some(tag.as_str())).collect(),
+            };
+            for key in keys {
+                let (entries, words) = groups.entry(key).or_default();
+                *entries += 1;
+                *words += u64::from(info.word_count.unwrap_or_default());
+            }
+        }
+        Ok(groups
+            .into_iter()
+            .map(|(group, (entries, words))| WritingStats {
+                group: group.map(str::to_string),
+                entries,
+                words,
+                average_words: words as f64 / f64::from(entries),
+            })
+            .collect())
+    }
+
+    // 解密 range 内的日记统计词频，只返回前 limit 个词
+    pub fn word_frequencies(
+        &self,
+        range: DateRange,
+        stopwords: Option<Locale>,
+        limit: usize,
+    ) -> Result<Vec<WordFrequency>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let entries: Vec<&EntryInfo> =
+            vault.metadata.iter().filter(|info| range.contains(info)).collect();
+        // 读不出的条目不计入
+        let counter = entries
+            .par_iter()
+            .filter_map(|info| {
+                load_entry_content(&vault.entries_dir, &vault.key, info.encryption, &info.id)
+                    .ok()
+            })
+            .map(|content| {
+                let mut counter = WordCounter::new(stopwords);
+                counter.add(&content);
+                counter
+            })
+            .reduce(|| WordCounter::new(stopwords), WordCounter::merge);
+        Ok(counter.top(limit))
+    }
+
+    // 按距离由近到远返回 radius_km 范围内带位置的条目
+    pub fn list_entries_near(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        radius_km: f64,
+    ) -> Result<Vec<EntryInfo>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let mut near: Vec<(f64, &EntryInfo)> = vault
+            .metadata
+            .iter()
+            .filter_map(|info| {
+                let distance = info.location.as_ref()?.distance_km(latitude, longitude);
+                (distance <= radius_km).then_some((distance, info))
+            })
+            .collect();
+        near.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(near.into_iter().map(|(_, info)| info.clone()).collect())
+    }
+
+    // 只统计填写了地点名称的条目
+    pub fn list_places(&self) -> Result<Vec<PlaceCount>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let mut places: BTreeMap<&str, Vec<&Location>> = BTreeMap::new();
+        for location in vault.metadata.iter().filter_map(|info| info.location.as_ref()) {
+            if let Some(place) = &location.place {
+                places.entry(place).or_default().push(location);
+            }
+        }
+        Ok(places
+            .into_iter()
+            .map(|(place, locations)| {
+                let latitudes: Vec<f64> = locations.iter().map(|at| at.latitude).collect();
+                let longitudes: Vec<f64> = locations.iter().map(|at| at.longitude).collect();
+                PlaceCount {
+                    place: place.to_string(),
+                    latitude: mean(&latitudes).unwrap_or_default(),
+                    longitude: mean(&longitudes).unwrap_or_default(),
+                    count: locations.len(),
+                }
+            })
+            .collect())
+    }
+
+    // 提醒用来判断当天是否已经写过：按给定时区比较新建或修改日期。
+    // 后台检查不算用户操作，不刷新自动锁定计时
+    pub fn has_entry_on(&self, date: Date, offset: UtcOffset) -> Result<bool> {
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        Ok(vault.metadata.iter().any(|info| {
+            info.created_at.to_offset(offset).date() == date
+                || info.updated_at.to_offset(offset).date() == date
+        }))
+    }
+
+    // now 决定“今天”和“本周”的范围，由调用方按本机时区给出
+    pub fn goal_progress(&self, now: OffsetDateTime) -> Result<GoalProgress> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let mut words: BTreeMap<Date, u32> = BTreeMap::new();
+        let mut weeks: BTreeMap<Date, u32> = BTreeMap::new();
+        for info in &vault.metadata {
+            let day = info.created_day(now.offset());
+            *words.entry(day).or_default() += info.word_count.unwrap_or(0);
+            *weeks.entry(week_start(day)).or_default() += 1;
+        }
+        let today = now.date();
+        let this_week = week_start(today);
+        let goals = vault.settings.goals;
+        Ok(GoalProgress {
+            goals,
+            words_today: words.get(&today).copied().unwrap_or(0),
+            entries_this_week: weeks.get(&this_week).copied().unwrap_or(0),
+            day_streak: goals.words_per_day.map_or(0, |goal| {
+                streak(&words, today, goal, Date::previous_day)
+            }),
+            week_streak: goals.entries_per_week.map_or(0, |goal| {
+                streak(&weeks, this_week, goal, |week| {
+                    week.checked_sub(time::Duration::WEEK)
+                })
+            }),
+        })
+    }
+
+    // 按日记日期统计一个月中每天的条目数，没有日记的日子不返回
+    pub fn calendar_month(
+        &self,
+        year: i32,
+        month: u8,
+        offset: UtcOffset,
+    ) -> Result<Vec<CalendarDay>> {
+        let month = time::Month::try_from(month).map_err(|_| anyhow!(Message::InvalidDate))?;
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let mut days: BTreeMap<Date, usize> = BTreeMap::new();
+        for info in &vault.metadata {
+            let day = info.entry_day(offset);
+            if day.year() == year && day.month() == month {
+                *days.entry(day).or_default() += 1;
+            }
+        }
+        Ok(days
+            .into_iter()
+            .map(|(date, count)| CalendarDay {
+                date: date.to_string(),
+                count,
+            })
+            .collect())
+    }
+
+    // 未设置日记日期的条目按 offset 时区的新建日期计
+    pub fn day(&self, date: Date, offset: UtcOffset) -> Result<DayView> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let mut entries: Vec<EntryInfo> = vault
+            .metadata
+            .iter()
+            .filter(|info| info.entry_day(offset) == date)
+            .cloned()
+            .collect();
+        entries.sort_by_key(|info| info.created_at);
+
+        let contents = entries
+            .par_iter()
+            .filter_map(|info| {
+                load_entry_content(&vault.entries_dir, &vault.key, info.encryption, &info.id)
+                    .ok()
+            })
+            .collect::<Vec<String>>();
+        let mut attachments: Vec<AttachmentInfo> = vault
+            .attachments
+            .iter()
+            .filter(|attachment| {
+                contents
+                    .iter()
+                    .any(|content| references_attachment(content, &[attachment.path.as_str()]))
+            })
+            .cloned()
+            .collect();
+        attachments.sort_by_key(|attachment| attachment.stored_at);
+
+        let moods: Vec<f64> = entries
+            .iter()
+            .filter_map(|info| info.mood.map(f64::from))
+            .collect();
+        let key = date.to_string();
+        Ok(DayView {
+            words: entries
+                .iter()
+                .map(|info| u64::from(info.word_count.unwrap_or_default()))
+                .sum(),
+            average_mood: mean(&moods),
+            summary: vault.day_summaries.get(&key).cloned(),
+            date: key,
+            entries,
+            attachments,
+        })
+    }
+
+    // summary 为 None 或空白时清除当天的小结
+    pub fn set_day_summary(&self, date: Date, summary: Option<String>) -> Result<()> {
+        let summary = summary
+            .map(|summary| summary.trim().to_string())
+            .filter(|summary| !summary.is_empty());
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        let key = date.to_string();
+        match summary {
+            Some(summary) => vault.day_summaries.insert(key, summary),
+            None => vault.day_summaries.remove(&key),
+        };
+        save_metadata(vault)
+    }
+
+    // 按日记日期分组；同一天的条目按新建时间倒序
+    pub fn timeline(&self, offset: UtcOffset) -> Result<Vec<TimelineYear>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let mut grouped: BTreeMap<(i32, u8), Vec<(Date, &EntryInfo)>> = BTreeMap::new();
+        for info in &vault.metadata {
+            let day = info.entry_day(offset);
+            grouped
+                .entry((day.year(), u8::from(day.month())))
+                .or_default()
+                .push((day, info));
+        }
+
+        let mut years: Vec<TimelineYear> = Vec::new();
+        for ((year, month), mut entries) in grouped.into_iter().rev() {
+            entries.sort_by(|(a_day, a), (b_day, b)| {
+                b_day.cmp(a_day).then(b.created_at.cmp(&a.created_at))
+            });
+            let month = TimelineMonth {
+                month,
+                count: entries.len(),
+                entries: entries.into_iter().map(|(_, info)| info.clone()).collect(),
+            };
+            match years.last_mut() {
+                Some(last) if last.year == year => {
+                    last.count += month.count;
+                    last.months.push(month);
+                }
+                _ => years.push(TimelineYear {
+                    year,
+                    count: month.count,
+                    months: vec![month],
+                }),
+            }
+        }
+        Ok(years)
+    }
+
+    // 往年同月同日的日记，年份近的在前；平年的 2 月 28 日同时列出闰年 2 月 29 日的日记
+    pub fn on_this_day(&self, today: Date, offset: UtcOffset) -> Result<Vec<EntryInfo>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        Ok(same_day_entries(vault, today, offset, |_| true))
+    }
+
+    // 汇总某月的日记；create_entry 为 true 时把回顾写成当月最后一天的日记。
+    // 同一个月再次生成时更新之前的回顾日记，回顾日记本身不计入统计
+    pub fn month_recap(
+        &self,
+        year: i32,
+        month: Month,
+        offset: UtcOffset,
+        create_entry: bool,
+    ) -> Result<MonthRecap> {
+        let key = format!("{year:04}-{:02}", month as u8);
+        let (mut summary, previous) = {
+            self.touch();
+            let guard = self.inner.read();
+            let vault = guard.as_ref().ok_or(VaultLocked)?;
+            let previous = vault
+                .recaps
+                .get(&key)
+                .copied()
+                .filter(|id| vault.metadata.iter().any(|info| info.id == *id));
+            (month_summary(vault, year, month, offset), previous)
+        };
+        if !create_entry {
+            return Ok(summary);
+        }
+
+        let title = summary.title();
+        let content = summary.to_markdown();
+        let entry = match previous {
+            Some(id) => {
+                let mut entry = self.load_entry(id)?;
+                entry.title = title;
+                entry.content = content;
+                self.update_entry(entry)?
+            }
+            None => self.create_entry_from(NewEntry {
+                title,
+                content,
+                entry_date: Some(Date::from_calendar_date(year, month, month.length(year))?),
+                ..Default::default()
+            })?,
+        };
+        {
+            let mut guard = self.inner.write();
+            let vault = guard.as_mut().ok_or(VaultLocked)?;
+            vault.recaps.insert(key, entry.id);
+            save_metadata(vault)?;
+            summary.entry = vault.metadata.iter().find(|info| info.id == entry.id).cloned();
+        }
+        Ok(summary)
+    }
+
+    // 那年今日通知用：只返回恰好 years 年前的日记。后台检查不刷新自动锁定计时
+    pub fn anniversaries(
+        &self,
+        today: Date,
+        offset: UtcOffset,
+        years: &[u32],
+    ) -> Result<Vec<EntryInfo>> {
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        Ok(same_day_entries(vault, today, offset, |year| {
+            u32::try_from(today.year() - year).is_ok_and(|ago| years.contains(&ago))
+        }))
+    }
+
+    // 只用元数据中的心情、标签和字数，不解密正文；offset 决定日期的分界
+    pub fn mood_trends(
+        &self,
+        range: DateRange,
+        bucket: TrendBucket,
+        offset: UtcOffset,
+    ) -> Result<MoodTrends> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let rated: Vec<(&EntryInfo, f64)> = vault
+            .metadata
+            .iter()
+            .filter(|info| range.contains(info))
+            .filter_map(|info| info.mood.map(|mood| (info, f64::from(mood))))
+            .collect();
+
+        let mut points: BTreeMap<Date, Vec<f64>> = BTreeMap::new();
+        let mut tags: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+        let mut words = Vec::new();
+        for (info, mood) in &rated {
+            let day = info.entry_day(offset);
+            points.entry(bucket_start(day, bucket)).or_default().push(*mood);
+            for tag in &info.tags {
+                tags.entry(tag).or_default().push(*mood);
+            }
+            if let Some(count) = info.word_count {
+                words.push((f64::from(count), *mood));
+            }
+        }
+
+        let moods: Vec<f64> = rated.iter().map(|(_, mood)| *mood).collect();
+        Ok(MoodTrends {
+            average: mean(&moods),
+            points: points
+                .into_iter()
+                .map(|(start, moods)| MoodPoint {
+                    start: start.to_string(),
+                    average: mean(&moods).unwrap_or_default(),
+                    entries: moods.len() as u32,
+                })
+                .collect(),
+            tags: tags
+                .into_iter()
+                .map(|(tag, moods)| TagMood {
+                    tag: tag.to_string(),
+                    average: mean(&moods).unwrap_or_default(),
+                    entries: moods.len() as u32,
+                })
+                .collect(),
+            word_count_correlation: correlation(&words),
+        })
+    }
+
+    pub fn sentiment_trends(
+        &self,
+        range: DateRange,
+        bucket: TrendBucket,
+        offset: UtcOffset,
+    ) -> Result<SentimentTrends> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let mut points: BTreeMap<Date, Vec<f64>> = BTreeMap::new();
+        let mut scores = Vec::new();
+        let mut moods = Vec::new();
+        for info in vault.metadata.iter().filter(|info| range.contains(info)) {
+            let Some(score) = info.sentiment else {
+                continue;
+            };
+            let day = info.entry_day(offset);
+            points.entry(bucket_start(day, bucket)).or_default().push(score);
+            scores.push(score);
+            if let Some(mood) = info.mood {
+                moods.push((score, f64::from(mood)));
+            }
+        }
+        Ok(SentimentTrends {
+            average: mean(&scores),
+            points: points
+                .into_iter()
+                .map(|(start, scores)| MoodPoint {
+                    start: start.to_string(),
+                    average: mean(&scores).unwrap_or_default(),
+                    entries: scores.len() as u32,
+                })
+                .collect(),
+            mood_correlation: correlation(&moods),
+        })
+    }
+
+    pub fn query(&self, spec: QuerySpec) -> Result<QueryPage> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        Ok(query_in(vault, spec))
+    }
+
+    pub fn load_entry(&self, id: Uuid) -> Result<Entry> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let meta = vault
+            .metadata
+            .iter()
+            .find(|entry| entry.id == id)
+            .cloned()
+            .ok_or(EntryNotFound)?;
+        let (content, payload) =
+            load_entry_body(&vault.entries_dir, &vault.key, meta.encryption, &meta.id)
+                .context(Message::EntryUnreadable(meta.title.clone()))?;
+        Ok(meta.with_body(content, payload))
+    }
+
+    // 只取元数据、不解密正文；条目不存在时返回 None 而不是错误
+    pub fn entry_info(&self, id: Uuid) -> Result<Option<EntryInfo>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        Ok(vault.metadata.iter().find(|entry| entry.id == id).cloned())
+    }
+
+    pub fn create_entry(
+        &self,
+        title: &str,
+        content: &str,
+        encryption: Option<TextEncryption>,
+    ) -> Result<Entry> {
+        self.create_entry_from(NewEntry {
+            title: title.to_string(),
+            content: content.to_string(),
+            encryption,
+            ..Default::default()
+        })
+    }
+
+    // 单条新建，连同位置、天气等附加字段一次写入
+    pub fn create_entry_from(&self, item: NewEntry) -> Result<Entry> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        let entry = build_entry(item, vault.text_encryption, self.now())?;
+        save_entry_content(&vault.entries_dir, &vault.key, entry.encryption, &entry)?;
+        let info = entry.metadata(vault.settings.sentiment_analysis);
+        vault.dirty_years.insert(shards::shard_year(&info));
+        vault
+            .search
+            .upsert(entry.id, entry.updated_at, &entry.title, &entry.content);
+        vault.search_dirty = true;
+        vault.metadata.push(info.clone());
+        vault.undo.clear_redo();
+        append_journal(
+            vault,
+            &[JournalChange::Upsert {
+                entry: Box::new(info),
+            }],
+        )?;
+        drop(guard);
+        self.notify_entries(EntryChange::Created, vec![entry.id]);
+        Ok(entry)
+    }
+
+    // 批量新建：正文并行加密写入，最后只保存一次元数据。
+    // 返回值与输入一一对应，单条失败不影响其他条目
+    pub fn create_entries(&self, items: Vec<NewEntry>) -> Result<Vec<Result<EntryInfo>>> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        let default_method = vault.text_encryption;
+        let now = self.now();
+        let results: Vec<Result<Entry>> = items
+            .into_par_iter()
+            .map(|item| {
+                let entry = build_entry(item, default_method, now)?;
+                save_entry_content(&vault.entries_dir, &vault.key, entry.encryption, &entry)?;
+                Ok(entry)
+            })
+            .collect();
+
+        let mut created = Vec::new();
+        let results: Vec<Result<EntryInfo>> = results
+            .into_iter()
+            .map(|result| {
+                result.map(|entry| {
+                    let info = entry.metadata(vault.settings.sentiment_analysis);
+                    vault.dirty_years.insert(shards::shard_year(&info));
+                    vault
+                        .search
+                        .upsert(entry.id, entry.updated_at, &entry.title, &entry.content);
+                    vault.metadata.push(info.clone());
+                    created.push(entry.id);
+                    info
+                })
+            })
+            .collect();
+        if !created.is_empty() {
+            vault.search_dirty = true;
+            vault.undo.clear_redo();
+            save_metadata(vault)?;
+        }
+        drop(guard);
+        self.notify_entries(EntryChange::Created, created);
+        Ok(results)
+    }
+
+    pub fn update_entry(&self, entry: Entry) -> Result<Entry> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        let info = vault
+            .metadata
+            .iter_mut()
+            .find(|item| item.id == entry.id)
+            .ok_or(EntryNotFound)?;
+
+        if !SUPPORTED_TEXT_ENCRYPTIONS.contains(&entry.encryption) {
+            return Err(anyhow!("unsupported text encryption method"));
+        }
+
+        let previous = (info.folder.clone(), info.tags.clone());
+        info.entry_type = entry.entry_type();
+        info.title = entry.title.clone();
+        info.folder = entry.folder.clone();
+        info.tags = unique_tags(entry.tags);
+        info.mood = entry.mood;
+        info.entry_date = entry.entry_date;
+        info.location = entry.location;
+        info.weather = entry.weather;
+        info.health = entry.health;
+        info.word_count = Some(search::word_count(&entry.content));
+        info.preview = Some(search::preview(&entry.content));
+        info.sentiment = score_sentiment(&vault.settings, &entry.content);
+        info.encryption = entry.encryption;
+        info.touch(self.now());
+        vault.dirty_years.insert(shards::shard_year(info));
+
+        let updated = Entry {
+            id: entry.id,
+            title: entry.title,
+            content: entry.content,
+            created_at: info.created_at,
+            updated_at: info.updated_at,
+            entry_date: info.entry_date,
+            folder: info.folder.clone(),
+            tags: info.tags.clone(),
+            mood: info.mood,
+            location: info.location.clone(),
+            weather: info.weather.clone(),
+            health: info.health.clone(),
+            payload: entry.payload,
+            encryption: info.encryption,
+        };
+
+        save_entry_content(
+            &vault.entries_dir,
+            &vault.key,
+            info.encryption,
+            &updated,
+        )?;
+        vault
+            .search
+            .upsert(updated.id, updated.updated_at, &updated.title, &updated.content);
+        vault.search_dirty = true;
+        vault.pending_updates.insert(updated.id);
+        defer_metadata_save(vault);
+        let current = vault
+            .metadata
+            .iter()
+            .find(|item| item.id == updated.id)
+            .cloned()
+            .ok_or(EntryNotFound)?;
+        record_reorganize(&mut vault.undo, previous, &current);
+        drop(guard);
+        self.notify_entries(EntryChange::Updated, vec![updated.id]);
+        Ok(updated)
+    }
+
+    // 引导式编辑器保存：按新的结构化内容重新生成正文，其余字段保持不变
+    pub fn update_entry_payload(&self, id: Uuid, payload: EntryPayload) -> Result<Entry> {
+        let mut entry = self.load_entry(id)?;
+        entry.content = payload.to_markdown();
+        entry.payload = Some(payload);
+        self.update_entry(entry)
+    }
+
+    // 只应用提供的字段；正文与加密方式都没变时不重写正文文件，
+    // 仅标题变化时解密一次正文用于重建搜索索引
+    pub fn patch_entry(&self, id: Uuid, patch: PatchFields) -> Result<EntryInfo> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        let position = vault
+            .metadata
+            .iter()
+            .position(|item| item.id == id)
+            .ok_or(EntryNotFound)?;
+        let mut info = vault.metadata[position].clone();
+        let previous_encryption = info.encryption;
+
+        if let Some(method) = patch.encryption {
+            if !SUPPORTED_TEXT_ENCRYPTIONS.contains(&method) {
+                return Err(anyhow!("unsupported text encryption method"));
+            }
+            info.encryption = method;
+        }
+        let title_changed = patch.title.as_ref().is_some_and(|title| *title != info.title);
+        if let Some(title) = patch.title {
+            info.title = title;
+        }
+        let previous = (info.folder.clone(), info.tags.clone());
+        if let Some(folder) = patch.folder {
+            info.folder = folder;
+        }
+        if let Some(tags) = patch.tags {
+            info.tags = unique_tags(tags);
+        }
+        if let Some(mood) = patch.mood {
+            info.mood = mood;
+        }
+        if let Some(entry_date) = patch.entry_date {
+            info.entry_date = entry_date;
+        }
+        if let Some(location) = patch.location {
+            info.location = location;
+        }
+        if let Some(weather) = patch.weather {
+            info.weather = weather;
+        }
+        info.touch(self.now());
+
+        let rewrite = patch.content.is_some() || info.encryption != previous_encryption;
+        // 结构化内容不随 patch 修改，重写正文文件时原样保留
+        let load = || load_entry_body(&vault.entries_dir, &vault.key, previous_encryption, &id);
+        let (content, payload) = match patch.content {
+            Some(content) if info.entry_type == EntryType::Note => (Some(content), None),
+            Some(content) => (Some(content), load()?.1),
+            None if rewrite || title_changed => {
+                let (content, payload) = load()?;
+                (Some(content), payload)
+            }
+            None => (None, None),
+        };
+        match content {
+            Some(content) => {
+                info.word_count = Some(search::word_count(&content));
+                info.preview = Some(search::preview(&content));
+                info.sentiment = score_sentiment(&vault.settings, &content);
+                let entry = info.clone().with_body(content, payload);
+                if rewrite {
+                    save_entry_content(&vault.entries_dir, &vault.key, entry.encryption, &entry)?;
+                }
+                vault
+                    .search
+                    .upsert(entry.id, entry.updated_at, &entry.title, &entry.content);
+            }
+            None => vault.search.refresh(&id, info.updated_at),
+        }
+        vault.search_dirty = true;
+
+        vault.dirty_years.insert(shards::shard_year(&info));
+        vault.metadata[position] = info.clone();
+        vault.pending_updates.insert(id);
+        defer_metadata_save(vault);
+        record_reorganize(&mut vault.undo, previous, &info);
+        drop(guard);
+        self.notify_entries(EntryChange::Updated, vec![id]);
+        Ok(info)
+    }
+
+    pub fn delete_entry(&self, id: Uuid) -> Result<()> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        let info = vault
+            .metadata
+            .iter()
+            .find(|entry| entry.id == id)
+            .cloned()
+            .ok_or(EntryNotFound)?;
+        // 先取出正文留作撤销；正文已丢失的日记仍允许删除，只是无法撤销
+        let body = load_entry_body(&vault.entries_dir, &vault.key, info.encryption, &id);
+        remove_entry(vault, id)?;
+        match body {
+            Ok((content, payload)) => vault.undo.record(UndoRecord {
+                kind: UndoKind::Delete,
+                undo: vec![UndoStep::Restore(Box::new(info.with_body(content, payload)))],
+                redo: vec![UndoStep::Remove(id)],
+            }),
+            Err(_) => vault.undo.clear_redo(),
+        }
+        drop(guard);
+        self.notify_entries(EntryChange::Deleted, vec![id]);
+        Ok(())
+    }
+
+    // 编辑器的暂存内容，按短间隔写入；不修改元数据，也不计入撤销历史
+    pub fn save_draft(&self, entry_id: Uuid, title: String, content: String) -> Result<()> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        if !vault.metadata.iter().any(|entry| entry.id == entry_id) {
+            return Err(EntryNotFound.into());
+        }
+        let draft = Draft {
+            entry_id,
+            title,
+            content,
+            saved_at: self.now(),
+        };
+        drafts::write(&vault_root_of(vault), &vault.key, &draft)
+    }
+
+    pub fn discard_draft(&self, entry_id: Uuid) -> Result<()> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        drafts::remove(&vault_root_of(vault), &entry_id)
+    }
+
+    // 解锁后检查上次未正常保存的暂存。日记已被删除、暂存之后又正常保存过，
+    // 或内容与已保存的相同时，暂存已经没有用处，顺带删除
+    pub fn recover_unsaved_changes(&self) -> Result<Vec<Draft>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let root = vault_root_of(vault);
+        let mut unsaved = Vec::new();
+        for draft in drafts::list(&root, &vault.key)? {
+            let info = vault.metadata.iter().find(|entry| entry.id == draft.entry_id);
+            let pending = info.is_some_and(|info| {
+                let saved = || {
+                    load_entry_content(&vault.entries_dir, &vault.key, info.encryption, &info.id)
+                        .ok()
+                };
+                info.updated_at < draft.saved_at
+                    && (info.title != draft.title || saved().as_ref() != Some(&draft.content))
+            });
+            if pending {
+                unsaved.push(draft);
+            } else if !vault.read_only {
+                drafts::remove(&root, &draft.entry_id)?;
+            }
+        }
+        Ok(unsaved)
+    }
+
+    // 把读不出的条目文件移到隔离目录，并从列表中去掉，返回文件的新位置。
+    // 不会记录撤销，需要恢复时手动把文件放回 entries 目录
+    pub fn quarantine_entry(&self, id: Uuid) -> Result<String> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        if !vault.metadata.iter().any(|entry| entry.id == id) {
+            return Err(EntryNotFound.into());
+        }
+        let quarantine_dir = vault_root_of(vault).join(QUARANTINE_DIR);
+        permissions::create_private_dir_all(&quarantine_dir)
+            .context(Message::QuarantineEntryFailed)?;
+        let source = entry_file_path(&vault.entries_dir, &id);
+        let target = entry_file_path(&quarantine_dir, &id);
+        if source.exists() {
+            fs::rename(&source, &target).context(Message::QuarantineEntryFailed)?;
+        }
+        remove_entry(vault, id)?;
+        vault.undo.clear_redo();
+        drop(guard);
+        self.notify_entries(EntryChange::Deleted, vec![id]);
+        Ok(display_path(&target))
+    }
+
+    pub fn undo_last(&self) -> Result<Option<UndoOutcome>> {
+        self.replay(true)
+    }
+
+    pub fn redo_last(&self) -> Result<Option<UndoOutcome>> {
+        self.replay(false)
+    }
+
+    // 撤销和重做共用：取出一条记录执行对应步骤，再放入另一侧的栈
+    fn replay(&self, undo: bool) -> Result<Option<UndoOutcome>> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        let record = if undo {
+            vault.undo.pop_undo()
+        } else {
+            vault.undo.pop_redo()
+        };
+        let Some(record) = record else {
+            return Ok(None);
+        };
+        let steps = if undo { &record.undo } else { &record.redo };
+        let changes = match apply_undo_steps(vault, steps, self.now()) {
+            Ok(changes) => changes,
+            Err(err) => {
+                // 执行失败时放回原处，用户可以重试
+                if undo {
+                    vault.undo.push_undo(record);
+                } else {
+                    vault.undo.push_redo(record);
+                }
+                return Err(err);
+            }
+        };
+        let kind = record.kind;
+        let mut ids: Vec<Uuid> = steps.iter().map(UndoStep::id).collect();
+        ids.dedup();
+        if undo {
+            vault.undo.push_redo(record);
+        } else {
+            vault.undo.push_undo(record);
+        }
+        let outcome = UndoOutcome {
+            kind,
+            ids,
+            can_undo: vault.undo.can_undo(),
+            can_redo: vault.undo.can_redo(),
+        };
+        drop(guard);
+        for change in [EntryChange::Created, EntryChange::Updated, EntryChange::Deleted] {
+            let ids = changes
+                .iter()
+                .filter(|(kind, _)| *kind == change)
+                .map(|(_, id)| *id)
+                .collect();
+            self.notify_entries(change, ids);
+        }
+        Ok(Some(outcome))
+    }
+
+    pub fn export_plaintext(&self) -> Result<String> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        ensure_plaintext_export_allowed(vault)?;
+        let mut buffer = Vec::new();
+        let skipped = write_plaintext(vault, &mut buffer)?;
+        if !skipped.is_empty() {
+            tracing::warn!(count = skipped.len(), "skipped unreadable entries in export");
+        }
+        String::from_utf8(buffer).context("invalid export content")
+    }
+
+    // 边解密边写入文件，不在内存中拼接整份导出；返回跳过的条目
+    pub fn export_plaintext_to(&self, path: &Path) -> Result<Vec<EntryError>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        ensure_plaintext_export_allowed(vault)?;
+        if let Some(parent) = path.parent() {
+            permissions::create_private_dir_all(parent)?;
+        }
+        let mut writer = BufWriter::new(permissions::create_private(path)?);
+        let skipped = write_plaintext(vault, &mut writer)?;
+        writer.flush().context("failed to write export")?;
+        Ok(skipped)
+    }
+
+    // 按查询条件选出日记，用分享密码加密后写成独立的 HTML 文件。
+    // 内容会交给他人，与明文导出一样受禁用明文导出的策略约束
+    pub fn export_share_bundle(
+        &self,
+        spec: QuerySpec,
+        password: &str,
+        path: &Path,
+    ) -> Result<ShareReport> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        ensure_plaintext_export_allowed(vault)?;
+        // 在同一把读锁下筛选和读取，期间删除的条目不会导致导出失败
+        let selected = query_in(vault, spec).entries;
+        if selected.is_empty() {
+            return Err(anyhow!(Message::NothingToShare));
+        }
+        let offset = export_offset(&vault.settings);
+        // 与导出明文一样，读不出的条目跳过并在结果中列出
+        let (entries, skipped): (Vec<_>, Vec<_>) = selected
+            .par_iter()
+            .map(|info| {
+                load_entry_content(&vault.entries_dir, &vault.key, info.encryption, &info.id)
+                    .map(|content| ShareEntry {
+                        title: info.title.clone(),
+                        date: info
+                            .entry_day(offset.unwrap_or(info.created_at.offset()))
+                            .to_string(),
+                        tags: info.tags.clone(),
+                        mood: info.mood,
+                        content,
+                    })
+                    .map_err(|err| entry_error(info, err))
+            })
+            .partition_map(|result| match result {
+                Ok(entry) => rayon::iter::Either::Left(entry),
+                Err(error) => rayon::iter::Either::Right(error),
+            });
+        if entries.is_empty() {
+            return Err(anyhow!(Message::NothingToShare));
+        }
+        let html = share::bundle(&entries, password)?;
+        if let Some(parent) = path.parent() {
+            permissions::create_private_dir_all(parent)?;
+        }
+        permissions::write_private(path, html).context("failed to write share bundle")?;
+        Ok(ShareReport {
+            path: display_path(path),
+            entries: entries.len(),
+            skipped,
+        })
+    }
+
+    // 把一篇日记连同它引用的附件写成一次性分享文件，密码随机生成，只在返回值中出现一次
+    pub fn share_entry(&self, id: Uuid, path: &Path) -> Result<EntryShareReport> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        ensure_plaintext_export_allowed(vault)?;
+        let info = vault
+            .metadata
+            .iter()
+            .find(|entry| entry.id == id)
+            .ok_or(EntryNotFound)?;
+        let (content, payload) =
+            load_entry_body(&vault.entries_dir, &vault.key, info.encryption, &info.id)
+                .context(Message::EntryUnreadable(info.title.clone()))?;
+        let root = vault_root_of(vault);
+        let attachments = vault
+            .attachments
+            .iter()
+            .filter(|attachment| references_attachment(&content, &[attachment.path.as_str()]))
+            .map(|attachment| -> Result<SharedAttachment> {
+                let encrypted = fs::read(root.join(&attachment.path))
+                    .context(Message::ReadImageFailed)?;
+                let data = decrypt_image_data(&vault.key, &encrypted)?;
+                Ok(SharedAttachment {
+                    path: attachment.path.clone(),
+                    data: general_purpose::STANDARD.encode(data),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let shared = SharedEntry {
+            entry: info.clone().with_body(content, payload),
+            attachments,
+        };
+        let passcode = share::generate_passcode();
+        let sealed = share::seal_entry(&shared, &passcode)?;
+        if let Some(parent) = path.parent() {
+            permissions::create_private_dir_all(parent)?;
+        }
+        permissions::write_private(path, sealed).context("failed to write shared entry")?;
+        Ok(EntryShareReport {
+            path: display_path(path),
+            passcode,
+            attachments: shared.attachments.len(),
+        })
+    }
+
+    // 导入别人分享的日记：换用新 id，用当前日记的密钥重新加密，附件换用新路径并改写正文引用。
+    // 文件夹属于发送方自己的整理方式，不随之导入
+    pub fn import_shared_entry(&self, path: &Path, passcode: &str) -> Result<Entry> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Err(anyhow!(Message::SharedEntryNotFound));
+            }
+            Err(err) => return Err(err).context("failed to read shared entry"),
+        };
+        let shared = share::open_entry(&bytes, passcode)?;
+
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+
+        let mut written = Vec::new();
+        let prepared = (|| -> Result<(Entry, Vec<AttachmentInfo>)> {
+            let mut entry = shared.entry;
+            let mut attachments = Vec::new();
+            for attachment in &shared.attachments {
+                let data = general_purpose::STANDARD
+                    .decode(&attachment.data)
+                    .map_err(|_| anyhow!(Message::InvalidSharedEntry))?;
+                let extension = Path::new(&attachment.path)
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .unwrap_or("bin");
+                let (target_path, relative) = attachment_target(vault, extension)?;
+                let encrypted = encrypt_image_data(&vault.key, vault.text_encryption, &data)?;
+                permissions::write_private(&target_path, encrypted)
+                    .context(Message::SaveImportAttachmentFailed)?;
+                written.push(target_path);
+                let new_path = display_path(&relative);
+                let old = attachment.path.as_str();
+                entry.content = entry.content.replace(old, &new_path);
+                entry.content = entry
+                    .content
+                    .replace(&old.replace('\\', "/"), &new_path.replace('\\', "/"));
+                attachments.push(AttachmentInfo::describe(
+                    &relative,
+                    &data,
+                    OffsetDateTime::now_utc(),
+                ));
+            }
+            entry.id = Uuid::new_v4();
+            entry.folder = None;
+            if !SUPPORTED_TEXT_ENCRYPTIONS.contains(&entry.encryption) {
+                entry.encryption = vault.text_encryption;
+            }
+            save_entry_content(&vault.entries_dir, &vault.key, entry.encryption, &entry)?;
+            written.push(entry_file_path(&vault.entries_dir, &entry.id));
+            Ok((entry, attachments))
+        })();
+        let (entry, attachments) = match prepared {
+            Ok(prepared) => prepared,
+            Err(err) => {
+                for path in &written {
+                    let _ = fs::remove_file(path);
+                }
+                return Err(err.context(Message::ImportFailed));
+            }
+        };
+
+        let info = entry.metadata(vault.settings.sentiment_analysis);
+        vault.dirty_years.insert(shards::shard_year(&info));
+        vault
+            .search
+            .upsert(entry.id, entry.updated_at, &entry.title, &entry.content);
+        vault.search_dirty = true;
+        vault.metadata.push(info.clone());
+        vault.undo.clear_redo();
+        vault.attachments.extend(attachments.iter().cloned());
+        let mut changes: Vec<JournalChange> = attachments
+            .into_iter()
+            .map(|attachment| JournalChange::Attachment { attachment })
+            .collect();
+        changes.push(JournalChange::Upsert {
+            entry: Box::new(info),
+        });
+        append_journal(vault, &changes)?;
+        drop(guard);
+        self.notify_entries(EntryChange::Created, vec![entry.id]);
+        Ok(entry)
+    }
+
+    // 只读查询，不计入活动时间，否则剩余的自动锁定时间总是满的
+    pub fn info(&self) -> Result<VaultInfo> {
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let root = vault_root_of(vault);
+        // 旧日记可能没有历史链，退而使用最早的条目时间
+        let created_at = history::first_saved_at(&history::history_file_path(&root))
+            .ok()
+            .flatten()
+            .or_else(|| vault.metadata.iter().map(|entry| entry.created_at).min());
+        let timeout = *self.auto_lock_after.lock();
+        let auto_lock_in_secs = timeout.map(|timeout| {
+            let idle = self
+                .last_activity
+                .lock()
+                .map(|last| last.elapsed())
+                .unwrap_or_default();
+            timeout.saturating_sub(idle).as_secs()
+        });
+
+        Ok(VaultInfo {
+            vault_root: display_path(&root),
+            created_at: created_at.and_then(|ts| ts.format(&Rfc3339).ok()),
+            entry_count: vault.metadata.len(),
+            attachment_count: vault.attachments.len(),
+            attachment_bytes: vault.attachments.iter().map(|info| info.size).sum(),
+            last_saved: vault.last_saved.format(&Rfc3339).ok(),
+            text_encryption: vault.text_encryption,
+            kdf: vault.kdf,
+            auto_lock_minutes: timeout.map(|timeout| timeout.as_secs() / 60),
+            auto_lock_in_secs,
+        })
+    }
+
+    pub fn vault_root(&self) -> Result<PathBuf> {
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        Ok(vault_root_of(vault))
+    }
+
+    // 资料文件不加密，只允许在解锁状态下修改，避免随手改掉别人日记库的名称
+    pub fn set_profile(&self, profile: VaultProfile) -> Result<VaultProfile> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let profile = profile::write(&vault_root_of(vault), profile)?;
+        drop(guard);
+        self.emit(VaultEvent::ProfileChanged {
+            profile: profile.clone(),
+        });
+        Ok(profile)
+    }
+
+    // 把日记整体移到新目录：先复制并逐个比对，确认新位置可以解密后再切换路径，最后删除旧文件。
+    // 切换之前任何一步失败都会清理目标目录，原位置保持不变
+    pub fn move_vault(&self, destination: &Path) -> Result<MoveReport> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        compact_metadata(vault)?;
+
+        let old_root = vault_root_of(vault);
+        let sources: Vec<PathBuf> = managed_paths(&old_root)
+            .into_iter()
+            .filter(|path| path.exists())
+            .collect();
+        let targets = plan_copy(&old_root, destination, &sources)?;
+        copy_and_verify(vault, destination, &sources, &targets)
+            .map_err(|err| err.context(Message::MoveFailed))?;
+
+        let new_root = destination.to_path_buf();
+        // 换到新目录的锁，旧目录的锁随之释放
+        vault._lock = vault_lock::acquire(&new_root)?;
+        vault.path = vault_file_path(new_root.clone());
+        vault.entries_dir = new_root.join("entries");
+        vault.attachments_dir = new_root.join("attachments");
+        vault.index_dir = new_root.join(shards::INDEX_DIR);
+        vault.journal_path = journal::journal_file_path(&new_root);
+        vault.search_path = search::index_file_path(&new_root);
+        drop(guard);
+
+        // 新位置已经生效，旧文件删除失败只记录下来交给用户处理
+        let leftover = sources
+            .iter()
+            .filter(|source| remove_path(source).is_err())
+            .map(|source| display_path(source))
+            .collect();
+        let _ = fs::remove_file(vault_lock::lock_file_path(&old_root));
+        // 目录已空时一并删除，相当于重命名
+        let _ = fs::remove_dir(&old_root);
+
+        let vault_root = display_path(&new_root);
+        self.emit(VaultEvent::Moved {
+            vault_root: vault_root.clone(),
+        });
+        Ok(MoveReport {
+            vault_root,
+            leftover,
+        })
+    }
+
+    // 复制一份完整的加密日记作为离线副本，当前日记继续使用原位置。导出的明文文件不会被复制
+    pub fn clone_vault(&self, destination: &Path) -> Result<CloneReport> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        compact_metadata(vault)?;
+
+        let root = vault_root_of(vault);
+        let exports = root.join("exports");
+        let sources: Vec<PathBuf> = managed_paths(&root)
+            .into_iter()
+            .filter(|path| path.exists() && *path != exports)
+            .collect();
+        let targets = plan_copy(&root, destination, &sources)?;
+        let stats = copy_and_verify(vault, destination, &sources, &targets)
+            .map_err(|err| err.context(Message::CopyFailed))?;
+        Ok(CloneReport {
+            vault_root: display_path(destination),
+            files: stats.files,
+            bytes: stats.bytes,
+            verified_entries: stats.verified_entries,
+        })
+    }
+
+    // 把另一个日记库合并进来：用它自己的密码解锁，逐条解密后用当前日记的密钥重新加密。
+    // 条目 id 冲突时换用新 id，附件换用新路径并同步改写正文中的引用，文件夹按名称合并
+    pub fn import_vault(
+        &self,
+        other_path: PathBuf,
+        other_passphrase: &str,
+    ) -> Result<ImportReport> {
+        // 必须在解锁对方之前检查：解锁会重置对方的日志，同一目录会破坏当前日记
+        let own_root = self.vault_root()?;
+        let other_root = other_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| other_path.clone());
+        if let (Ok(own), Ok(other)) = (own_root.canonicalize(), other_root.canonicalize()) {
+            if own == other {
+                return Err(Conflict(Message::ImportIntoSelf).into());
+            }
+        }
+
+        // 来源只读取，不整理日志也不留下解锁记录
+        let other = VaultManager::default();
+        other.unlock_read_only(other_passphrase, other_path)?;
+        let result = self.import_from(&other);
+        let _ = other.lock();
+        result
+    }
+
+    fn import_from(&self, other: &VaultManager) -> Result<ImportReport> {
+        self.touch();
+        let other_guard = other.inner.read();
+        let source = other_guard.as_ref().ok_or(VaultLocked)?;
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+
+        let source_root = vault_root_of(source);
+        let mut written = Vec::new();
+        let prepared = (|| -> Result<(Vec<AttachmentInfo>, Vec<Entry>, usize)> {
+            // 先写入全部文件，确认无误后再修改元数据；中途失败时删除已写入的文件
+            let mut renames = Vec::new();
+            let mut attachments = Vec::new();
+            for info in &source.attachments {
+                let encrypted = fs::read(source_root.join(&info.path))
+                    .context(Message::ReadImportAttachmentFailed)?;
+                let data = decrypt_image_data(&source.key, &encrypted)?;
+                let extension = Path::new(&info.path)
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .unwrap_or("bin");
+                let (target_path, relative) = attachment_target(vault, extension)?;
+                let encrypted = encrypt_image_data(&vault.key, vault.text_encryption, &data)?;
+                permissions::write_private(&target_path, encrypted)
+                    .context(Message::SaveImportAttachmentFailed)?;
+                written.push(target_path);
+                let path = display_path(&relative);
+                renames.push((info.path.clone(), path.clone()));
+                attachments.push(AttachmentInfo {
+                    path,
+                    ..info.clone()
+                });
+            }
+
+            let mut taken: BTreeSet<Uuid> = vault.metadata.iter().map(|info| info.id).collect();
+            let mut renamed = 0;
+            let mut entries = Vec::new();
+            for info in &source.metadata {
+                let (mut content, payload) = load_entry_body(
+                    &source.entries_dir,
+                    &source.key,
+                    info.encryption,
+                    &info.id,
+                )?;
+                for (old, new) in &renames {
+                    content = content.replace(old.as_str(), new);
+                    content = content.replace(&old.replace('\\', "/"), &new.replace('\\', "/"));
+                }
+                let mut entry = info.clone().with_body(content, payload);
+                if !taken.insert(entry.id) {
+                    entry.id = Uuid::new_v4();
+                    taken.insert(entry.id);
+                    renamed += 1;
+                }
+                if !SUPPORTED_TEXT_ENCRYPTIONS.contains(&entry.encryption) {
+                    entry.encryption = vault.text_encryption;
+                }
+                save_entry_content(&vault.entries_dir, &vault.key, entry.encryption, &entry)?;
+                written.push(entry_file_path(&vault.entries_dir, &entry.id));
+                entries.push(entry);
+            }
+            Ok((attachments, entries, renamed))
+        })();
+        let (attachments, entries, renamed) = match prepared {
+            Ok(prepared) => prepared,
+            Err(err) => {
+                for path in &written {
+                    let _ = fs::remove_file(path);
+                }
+                return Err(err.context(Message::ImportFailed));
+            }
+        };
+
+        let folders: BTreeSet<String> = entries
+            .iter()
+            .filter_map(|entry| entry.folder.clone())
+            .collect();
+        for entry in &entries {
+            let info = entry.metadata(vault.settings.sentiment_analysis);
+            vault.dirty_years.insert(shards::shard_year(&info));
+            vault
+                .search
+                .upsert(entry.id, entry.updated_at, &entry.title, &entry.content);
+            vault.metadata.push(info);
+        }
+        vault.search_dirty = true;
+        vault.undo.clear_redo();
+        let attachment_count = attachments.len();
+        vault.attachments.extend(attachments);
+        save_metadata(vault)?;
+        drop(guard);
+        drop(other_guard);
+        self.notify_entries(
+            EntryChange::Created,
+            entries.iter().map(|entry| entry.id).collect(),
+        );
+
+        Ok(ImportReport {
+            entries: entries.len(),
+            attachments: attachment_count,
+            renamed,
+            folders: folders.into_iter().collect(),
+        })
+    }
+
+    pub fn store_image(&self, source: PathBuf) -> Result<String> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+
+        if !source.exists() {
+            return Err(anyhow!(Message::ImageNotFound));
+        }
+
+        let extension = source
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.trim_start_matches('.'))
+            .filter(|ext| !ext.is_empty())
+            .unwrap_or("bin");
+        let (target_path, relative) = attachment_target(vault, extension)?;
+
+        // 读取并加密图片
+        let data = fs::read(&source).context(Message::ReadImageFailed)?;
+        let encrypted = encrypt_image_data(&vault.key, vault.text_encryption, &data)?;
+        permissions::write_private(&target_path, encrypted).context(Message::SaveImageFailed)?;
+        let attachment = AttachmentInfo::describe(&relative, &data, OffsetDateTime::now_utc());
+        record_attachment(vault, attachment)?;
+
+        Ok(display_path(&relative))
+    }
+
+    pub fn store_image_bytes(
+        &self,
+        name: Option<String>,
+        mime: Option<String>,
+        data: Vec<u8>,
+    ) -> Result<String> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+
+        if data.is_empty() {
+            return Err(anyhow!(Message::EmptyPastedImage));
+        }
+
+        let extension = infer_image_extension(name.as_deref(), mime.as_deref());
+        let (target_path, relative) = attachment_target(vault, &extension)?;
+
+        // 加密图片数据
+        let encrypted = encrypt_image_data(&vault.key, vault.text_encryption, &data)?;
+        permissions::write_private(&target_path, encrypted).context(Message::WriteImageFailed)?;
+        let attachment = AttachmentInfo::describe(&relative, &data, OffsetDateTime::now_utc());
+        record_attachment(vault, attachment)?;
+
+        Ok(display_path(&relative))
+    }
+
+    pub fn decrypt_image(&self, path: &str) -> Result<Vec<u8>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+
+        // 解析路径（可能是相对路径或绝对路径）
+        let image_path = if Path::new(path).is_absolute() {
+            PathBuf::from(path)
+        } else {
+            let root = vault
+                .path
+                .parent()
+                .map(PathBuf::from)
+                .unwrap_or_else(|| vault.path.clone());
+            root.join(path.trim_start_matches('/').trim_start_matches('\\'))
+        };
+
+        if !image_path.exists() {
+            return Err(anyhow!(Message::ImageFileMissing));
+        }
+
+        let encrypted = fs::read(&image_path).context(Message::ReadImageFailed)?;
+        decrypt_image_data(&vault.key, &encrypted)
+    }
+
+    // 按保存时间倒序返回附件缓存，不读取附件文件
+    pub fn list_attachments(&self) -> Result<Vec<AttachmentInfo>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let mut attachments = vault.attachments.clone();
+        attachments.sort_by_key(|attachment| std::cmp::Reverse(attachment.stored_at));
+        Ok(attachments)
+    }
+
+    // 按附件的保存日期分组，只读缓存的附件信息，不解密日记或图片
+    pub fn media_timeline(&self, range: DateRange, offset: UtcOffset) -> Result<Vec<MediaDay>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let mut days: BTreeMap<Date, Vec<AttachmentInfo>> = BTreeMap::new();
+        for attachment in &vault.attachments {
+            let day = attachment.stored_at.to_offset(offset).date();
+            let within = range.from.is_none_or(|from| day >= from.date())
+                && range.to.is_none_or(|to| day <= to.date());
+            if within {
+                days.entry(day).or_default().push(attachment.clone());
+            }
+        }
+        Ok(days
+            .into_iter()
+            .map(|(date, mut attachments)| {
+                attachments.sort_by_key(|attachment| attachment.stored_at);
+                let cover = attachments
+                    .iter()
+                    .filter_map(|attachment| {
+                        let pixels = u64::from(attachment.width?) * u64::from(attachment.height?);
+                        Some((pixels, &attachment.path))
+                    })
+                    .max_by_key(|(pixels, _)| *pixels)
+                    .map(|(_, path)| path.clone());
+                MediaDay {
+                    date: date.to_string(),
+                    cover,
+                    attachments,
+                }
+            })
+            .collect())
+    }
+
+    // 只修改解锁所用槽位的口令，主密钥不变，日记文件无需重新加密。
+    // 旧日记的主密钥由主口令直接派生，只换口令撤销不了旧口令，此时同时更换主密钥
+    pub fn change_passphrase(&self, old_passphrase: &str, new_passphrase: &str) -> Result<()> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+
+        // 验证旧密码
+        let caller = own_caller(vault, old_passphrase)?.ok_or_else(|| {
+            anyhow::Error::new(DecryptionFailed).context(Message::WrongOldPassphrase)
+        })?;
+
+        // 拒绝重复使用当前或最近用过的密码
+        if vault.passphrase_history_depth > 0
+            && (new_passphrase == old_passphrase
+                || passphrase_in_history(new_passphrase, &vault.passphrase_history)?)
+        {
+            return Err(anyhow!(Message::PassphraseReused));
+        }
+        // 与其他用户的口令相同时解锁无法区分槽位
+        if new_passphrase != old_passphrase && find_caller(vault, new_passphrase)?.is_some() {
+            return Err(anyhow!(Message::KeySlotPassphraseInUse));
+        }
+        let mut new_history = vault.passphrase_history.clone();
+        if vault.passphrase_history_depth > 0 {
+            new_history.insert(0, PassphraseVerifier::new(old_passphrase)?);
+            new_history.truncate(vault.passphrase_history_depth);
+        }
+
+        match caller.slot {
+            Some(id) => {
+                let root = vault_root_of(vault);
+                key_slots::rewrap(&root, id, &vault.key, &vault.kdf, new_passphrase)?;
+                vault.passphrase_history = new_history;
+                save_metadata(vault)?;
+            }
+            None => {
+                let voided =
+                    rekey_vault(vault, key_slots::OWNER_LABEL, new_passphrase, new_history)?;
+                if voided > 0 {
+                    tracing::info!(count = voided, "voided user key slots with the derived key");
+                }
+            }
+        }
+        drop(guard);
+
+        self.emit(VaultEvent::PassphraseChanged);
+        Ok(())
+    }
+
+    // 更换主密钥并重新加密全部文件，已移除的用户即使留有旧主密钥也无法解密。
+    // 其他槽位没有各自的口令无法重新包装，全部作废，只保留调用者的槽位；返回作废的槽位数
+    pub fn rotate_master_key(&self, passphrase: &str) -> Result<usize> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        let caller = admin_caller(vault, passphrase)?;
+        let label = match caller.slot {
+            Some(id) => key_slots::list(&vault_root_of(vault), &vault.key)?
+                .into_iter()
+                .find(|slot| slot.id == id)
+                .map(|slot| slot.label),
+            None => None,
+        };
+        let label = label.unwrap_or_else(|| key_slots::OWNER_LABEL.to_string());
+        let history = vault.passphrase_history.clone();
+        let voided = rekey_vault(vault, &label, passphrase, history)?;
+        drop(guard);
+
+        self.emit(VaultEvent::PassphraseChanged);
+        Ok(voided)
+    }
+
+    pub fn user_slots(&self) -> Result<Vec<UserSlot>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        key_slots::list(&vault_root_of(vault), &vault.key)
+    }
+
+    // 为另一位用户添加口令，解锁后与主口令看到的内容完全相同，需要主口令或管理员口令。
+    // 口令不能与主口令或已有槽位相同，否则无法区分和撤销
+    pub fn add_user_slot(
+        &self,
+        admin_passphrase: &str,
+        label: &str,
+        passphrase: &str,
+        admin: bool,
+    ) -> Result<UserSlot> {
+        self.touch();
+        // 持有写锁，同时添加或删除槽位时不会互相覆盖
+        let guard = self.inner.write();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        admin_caller(vault, admin_passphrase)?;
+        if find_caller(vault, passphrase)?.is_some() {
+            return Err(anyhow!(Message::KeySlotPassphraseInUse));
+        }
+        key_slots::add(&vault_root_of(vault), &vault.key, &vault.kdf, label, passphrase, admin)
+    }
+
+    pub fn remove_user_slot(&self, admin_passphrase: &str, id: Uuid) -> Result<()> {
+        self.touch();
+        let guard = self.inner.write();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        admin_caller(vault, admin_passphrase)?;
+        key_slots::remove(&vault_root_of(vault), id)
+    }
+
+    // 深度为 0 会清空历史，与修改策略一样需要重新输入密码
+    pub fn set_passphrase_history_depth(&self, passphrase: &str, depth: usize) -> Result<()> {
+        self.touch();
+        if depth > MAX_PASSPHRASE_HISTORY_DEPTH {
+            return Err(anyhow!(
+                "passphrase history depth cannot exceed {MAX_PASSPHRASE_HISTORY_DEPTH}"
+            ));
+        }
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        if own_caller(vault, passphrase)?.is_none() {
+            return Err(DecryptionFailed.into());
+        }
+        vault.passphrase_history_depth = depth;
+        vault.passphrase_history.truncate(depth);
+        save_metadata(vault)
+    }
+
+    pub fn configure_emergency_access(&self, access: EmergencyAccess) -> Result<EmergencyStatus> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        let root = vault_root_of(vault);
+
+        // 先写归档和钥匙串凭据，本机不支持时不留下半截配置
+        let payload = recovery_payload(vault)?;
+        emergency::stage(&root, &access, &payload)?;
+        emergency::check_in(&root, &access)?;
+        vault.emergency = Some(access);
+        save_metadata(vault)?;
+        emergency::status(&root)
+    }
+
+    pub fn cancel_emergency_access(&self) -> Result<()> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        vault.emergency = None;
+        save_metadata(vault)?;
+        emergency::cancel(&vault_root_of(vault))
+    }
+
+    pub fn emergency_access_status(&self) -> Result<EmergencyStatus> {
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        emergency::status(&vault_root_of(vault))
+    }
+
+    pub fn policy(&self) -> Result<VaultPolicy> {
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        Ok(vault.policy)
+    }
+
+    pub fn set_policy(&self, passphrase: &str, policy: VaultPolicy) -> Result<()> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        if own_caller(vault, passphrase)?.is_none() {
+            return Err(DecryptionFailed.into());
+        }
+        vault.policy = policy;
+        save_metadata(vault)
+    }
+
+    pub fn settings(&self) -> Result<VaultSettings> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        Ok(vault.settings.clone())
+    }
+
+    pub fn set_settings(&self, settings: VaultSettings) -> Result<()> {
+        if settings.auto_lock_minutes == Some(0) {
+            return Err(anyhow!(Message::AutoLockTooShort));
+        }
+        let goals = settings.goals;
+        if goals.words_per_day == Some(0) || goals.entries_per_week == Some(0) {
+            return Err(anyhow!(Message::InvalidGoal));
+        }
+        let weather = &settings.weather;
+        if weather.enabled
+            && weather.provider == WeatherProvider::OpenWeatherMap
+            && weather.api_key.as_deref().is_none_or(|key| key.trim().is_empty())
+        {
+            return Err(anyhow!(Message::WeatherApiKeyMissing));
+        }
+        if settings
+            .timezone_offset_minutes
+            .is_some_and(|minutes| !(MIN_OFFSET_MINUTES..=MAX_OFFSET_MINUTES).contains(&minutes))
+        {
+            return Err(anyhow!(Message::InvalidTimezone));
+        }
+        let size = serde_json::to_vec(&settings)
+            .context("failed to serialize vault settings")?
+            .len();
+        if size > MAX_SETTINGS_BYTES {
+            return Err(QuotaExceeded(Message::SettingsTooLarge).into());
+        }
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        if settings.sentiment_analysis && !vault.settings.sentiment_analysis {
+            let scored =
+                fill_content_stats(&mut vault.metadata, &vault.entries_dir, &vault.key, true);
+            vault.dirty_years.extend(scored);
+        }
+        vault.settings = settings;
+        save_metadata(vault)?;
+        let timeout = auto_lock_duration(&vault.settings);
+        drop(guard);
+        self.set_auto_lock_after(timeout);
+        Ok(())
+    }
+
+    pub fn prompt_packs(&self) -> Result<Vec<PromptPackInfo>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        Ok(vault.prompts.packs())
+    }
+
+    pub fn save_prompt_pack(
+        &self,
+        id: Option<String>,
+        name: String,
+        prompts: Vec<String>,
+    ) -> Result<PromptPack> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        let pack = vault.prompts.save_pack(id, name, prompts)?;
+        save_metadata(vault)?;
+        Ok(pack)
+    }
+
+    pub fn delete_prompt_pack(&self, id: &str) -> Result<()> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        vault.prompts.delete_pack(id)?;
+        save_metadata(vault)
+    }
+
+    pub fn set_prompt_pack_enabled(&self, id: &str, enabled: bool) -> Result<()> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        vault.prompts.set_enabled(id, enabled)?;
+        save_metadata(vault)
+    }
+
+    // date 为本机时区的日期；所有题库都停用时返回 None
+    pub fn todays_prompt(&self, date: Date) -> Result<Option<TodaysPrompt>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let exists = |id: &Uuid| vault.metadata.iter().any(|entry| entry.id == *id);
+        Ok(vault.prompts.today(date, exists))
+    }
+
+    pub fn answer_prompt(
+        &self,
+        date: Date,
+        pack_id: &str,
+        index: usize,
+        entry_id: Uuid,
+    ) -> Result<Option<TodaysPrompt>> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        if !vault.metadata.iter().any(|entry| entry.id == entry_id) {
+            return Err(EntryNotFound.into());
+        }
+        vault.prompts.answer(date, pack_id, index, entry_id)?;
+        save_metadata(vault)?;
+        let exists = |id: &Uuid| vault.metadata.iter().any(|entry| entry.id == *id);
+        Ok(vault.prompts.today(date, exists))
+    }
+
+    pub fn habits(&self) -> Result<Vec<Habit>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        Ok(vault.habits.habits())
+    }
+
+    pub fn save_habit(
+        &self,
+        id: Option<String>,
+        name: String,
+        unit: Option<String>,
+        target: f64,
+    ) -> Result<Habit> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        let habit = vault.habits.save(id, name, unit, target)?;
+        save_metadata(vault)?;
+        Ok(habit)
+    }
+
+    // 个人词典按字母顺序返回
+    pub fn dictionary_words(&self) -> Result<Vec<String>> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        Ok(vault.dictionary.iter().cloned().collect())
+    }
+
+    // 已在词典中的词不重复添加
+    pub fn add_dictionary_word(&self, word: &str) -> Result<()> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        let word = word.trim();
+        if vault.dictionary.contains(word) {
+            return Ok(());
+        }
+        if vault.dictionary.len() >= MAX_DICTIONARY_WORDS {
+            let message = Message::TooManyDictionaryWords(MAX_DICTIONARY_WORDS);
+            return Err(QuotaExceeded(message).into());
+        }
+        vault.dictionary.insert(word.to_string());
+        save_metadata(vault)
+    }
+
+    pub fn remove_dictionary_word(&self, word: &str) -> Result<()> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        if vault.dictionary.remove(word.trim()) {
+            save_metadata(vault)?;
+        }
+        Ok(())
+    }
+
+    pub fn delete_habit(&self, id: &str) -> Result<()> {
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        vault.habits.delete(id)?;
+        save_metadata(vault)
+    }
+
+    // append_to_entry 时在当天最早的一篇日记末尾追加一行打卡记录，当天没有日记则新建一篇；
+    // 返回被修改或新建的日记。取消打卡（数值为 0）不改动日记
+    pub fn log_habit(
+        &self,
+        date: Date,
+        habit_id: &str,
+        value: f64,
+        append_to_entry: bool,
+    ) -> Result<Option<Entry>> {
+        self.touch();
+        let habit = {
+            let mut guard = self.inner.write();
+            let vault = guard.as_mut().ok_or(VaultLocked)?;
+            ensure_writable(vault)?;
+            let habit = vault.habits.log(date, habit_id, value)?;
+            save_metadata(vault)?;
+            habit
+        };
+        if !append_to_entry || value <= 0.0 {
+            return Ok(None);
+        }
+
+        let line = habits::entry_line(&habit, value);
+        let offset = self.now().offset();
+        let existing = {
+            let guard = self.inner.read();
+            let vault = guard.as_ref().ok_or(VaultLocked)?;
+            vault
+                .metadata
+                .iter()
+                .filter(|info| info.entry_day(offset) == date)
+                .min_by_key(|info| info.created_at)
+                .map(|info| info.id)
+        };
+        let entry = match existing {
+            Some(id) => {
+                let mut entry = self.load_entry(id)?;
+                if !entry.content.is_empty() && !entry.content.ends_with('\n') {
+                    entry.content.push('\n');
+                }
+                entry.content.push_str(&line);
+                entry.content.push('\n');
+                self.update_entry(entry)?
+            }
+            None => self.create_entry_from(NewEntry {
+                title: date.to_string(),
+                content: format!("{line}\n"),
+                entry_date: Some(date),
+                ..Default::default()
+            })?,
+        };
+        Ok(Some(entry))
+    }
+
+    pub fn habit_history(
+        &self,
+        habit_id: &str,
+        from: Date,
+        to: Date,
+        today: Date,
+    ) -> Result<HabitHistory> {
+        self.touch();
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        vault.habits.history(habit_id, from, to, today)
+    }
+
+    // 每天的汇总附加到当天最早的一篇日记，重复导入时覆盖之前的数据；当天没有日记则跳过
+    pub fn import_health(&self, source: HealthSource, path: &Path) -> Result<HealthImportReport> {
+        let offset = self.now().offset();
+        let days = health::read(source, path, offset)?;
+        self.touch();
+        let mut guard = self.inner.write();
+        let vault = guard.as_mut().ok_or(VaultLocked)?;
+        ensure_writable(vault)?;
+        let mut first: BTreeMap<Date, usize> = BTreeMap::new();
+        for (position, info) in vault.metadata.iter().enumerate() {
+            let earliest = first.entry(info.entry_day(offset)).or_insert(position);
+            if info.created_at < vault.metadata[*earliest].created_at {
+                *earliest = position;
+            }
+        }
+
+        let mut updated = Vec::new();
+        for (date, summary) in &days {
+            let Some(&position) = first.get(date) else {
+                continue;
+            };
+            let info = &mut vault.metadata[position];
+            info.health = Some(summary.clone());
+            vault.dirty_years.insert(shards::shard_year(info));
+            vault.pending_updates.insert(info.id);
+            updated.push(info.id);
+        }
+        let report = HealthImportReport {
+            days: days.len(),
+            attached: updated.len(),
+            skipped: days.len() - updated.len(),
+        };
+        if !updated.is_empty() {
+            vault.undo.clear_redo();
+            defer_metadata_save(vault);
+            drop(guard);
+            self.notify_entries(EntryChange::Updated, updated);
+        }
+        Ok(report)
+    }
+
+    pub fn verify_history_chain(&self) -> Result<HistoryReport> {
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let stored = load_vault(&vault.path)?;
+        let metadata = decrypt_metadata(&stored, &vault.key)?;
+        history::verify(
+            &history::history_file_path(&vault_root_of(vault)),
+            &vault.path,
+            &vault.journal_path,
+            metadata.history_head.as_deref(),
+        )
+    }
+
+    pub fn verify_vault_integrity(&self) -> Result<IntegrityReport> {
+        let history = self.verify_history_chain()?;
+
+        let guard = self.inner.read();
+        let vault = guard.as_ref().ok_or(VaultLocked)?;
+        let root = vault_root_of(vault);
+
+        // 只检查由日记管理的文件，所选目录中的其他文件不做要求
+        let mut world_readable = Vec::new();
+        for path in [
+            vault.path.clone(),
+            history::history_file_path(&root),
+            key_slots::key_slots_path(&root),
+            drafts::drafts_dir(&root),
+            vault.journal_path.clone(),
+            vault.search_path.clone(),
+            vault.entries_dir.clone(),
+            vault.attachments_dir.clone(),
+            vault.index_dir.clone(),
+            root.join("exports"),
+        ] {
+            if path.exists() {
+                world_readable.extend(
+                    permissions::find_world_readable(&path)?
+                        .iter()
+                        .map(|found| display_path(found)),
+                );
+            }
+        }
+
+        let unreadable_entries: Vec<EntryError> = vault
+            .metadata
+            .par_iter()
+            .filter_map(|info| {
+                load_entry_body(&vault.entries_dir, &vault.key, info.encryption, &info.id)
+                    .err()
+                    .map(|err| entry_error(info, err))
+            })
+            .collect();
+
+        Ok(IntegrityReport {
+            valid: history.valid && world_readable.is_empty() && unreadable_entries.is_empty(),
+            history,
+            world_readable,
+            unreadable_entries,
+        })
+    }
+}
+
+// 保存加密条目内容到指定路径
+fn save_entry_content_to_path(
+    path: &Path,
+    key: &[u8; 32],
+    method: TextEncryption,
+    entry: &Entry,
+) -> Result<()> {
+    // 普通日记仍按旧版本只保存正文，旧版程序可以继续读取
+    let typed;
+    let (version, plaintext) = match &entry.payload {
+        Some(payload) => {
+            typed = serde_json::to_vec(&TypedBody {
+                content: entry.content.clone(),
+                payload: payload.clone(),
+            })?;
+            (TYPED_ENTRY_VERSION, typed.as_slice())
+        }
+        None => (ENTRY_VERSION, entry.content.as_bytes()),
+    };
+    let (compression, payload) = compress(plaintext)?;
+    let aad = entry_header_aad(version, compression, method);
+    let (nonce_bytes, ciphertext) = seal(method, key, &aad, &payload)?;
+
+    let stored = StoredEntry {
+        version,
+        compression,
+        nonce: nonce_bytes.to_vec(),
+        ciphertext,
+        encryption: Some(method),
+        header_bound: true,
+    };
+
+    // 确保父目录存在
+    if let Some(parent) = path.parent() {
+        permissions::create_private_dir_all(parent).context("failed to create parent directory")?;
+    }
+
+    permissions::write_private(path, stored.to_bytes()).context("failed to store entry")
+}
+
+// 复制并重新加密图片目录（递归）
+fn copy_and_reencrypt_images_recursive(
+    src_dir: &Path,
+    dst_dir: &Path,
+    old_key: &[u8; 32],
+    new_key: &[u8; 32],
+    method: TextEncryption,
+) -> Result<()> {
+    if !src_dir.exists() || !src_dir.is_dir() {
+        return Ok(());
+    }
+
+    permissions::create_private_dir_all(dst_dir).context("failed to create destination directory")?;
+
+    let children = fs::read_dir(src_dir)
+        .context("failed to read source directory")?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .context("failed to read directory entry")?;
+
+    children.into_par_iter().try_for_each(|src_path| -> Result<()> {
+        let file_name = src_path
+            .file_name()
+            .ok_or_else(|| anyhow!("invalid file name"))?;
+        let dst_path = dst_dir.join(file_name);
+
+        if src_path.is_dir() {
+            copy_and_reencrypt_images_recursive(&src_path, &dst_path, old_key, new_key, method)?;
+        } else if src_path.is_file() {
+            // 读取文件
+            let encrypted = fs::read(&src_path)
+                .with_context(|| format!("failed to read file: {:?}", src_path))?;
+
+            // 检查是否是加密的图片文件
+            if is_encrypted_image(&encrypted) {
+                // 解密
+                let plaintext = decrypt_image_data(old_key, &encrypted)
+                    .with_context(|| format!("failed to decrypt image: {:?}", src_path))?;
+
+                // 用新密钥重新加密
+                let reencrypted = encrypt_image_data(new_key, method, &plaintext)
+                    .with_context(|| format!("failed to reencrypt image: {:?}", src_path))?;
+
+                // 写入目标位置
+                permissions::write_private(&dst_path, reencrypted)
+                    .with_context(|| format!("failed to write reencrypted image: {:?}", dst_path))?;
+            } else {
+                // 非加密文件，直接复制
+                permissions::copy_private(&src_path, &dst_path)?;
+            }
+        }
+        Ok(())
+    })
+}
+
+// 替换目录内容（递归删除目标目录并复制源目录）
+fn replace_directory_contents(src_dir: &Path, dst_dir: &Path) -> Result<()> {
+    if !src_dir.exists() {
+        return Ok(());
+    }
+
+    // 先删除目标目录中的所有内容
+    if dst_dir.exists() {
+        for entry in fs::read_dir(dst_dir).context("failed to read destination directory")? {
+            let entry = entry.context("failed to read directory entry")?;
+            let path = entry.path();
+            if path.is_dir() {
+                fs::remove_dir_all(&path)
+                    .with_context(|| format!("failed to remove directory: {:?}", path))?;
+            } else {
+                fs::remove_file(&path)
+                    .with_context(|| format!("failed to remove file: {:?}", path))?;
+            }
+        }
+    } else {
+        permissions::create_private_dir_all(dst_dir)
+            .context("failed to create destination directory")?;
+    }
+
+    // 复制源目录的所有内容到目标目录
+    copy_directory_recursive(src_dir, dst_dir)
+}
+
+// 递归复制目录
+fn copy_directory_recursive(src_dir: &Path, dst_dir: &Path) -> Result<()> {
+    permissions::create_private_dir_all(dst_dir).context("failed to create destination directory")?;
+
+    for entry in fs::read_dir(src_dir).context("failed to read source directory")? {
+        let entry = entry.context("failed to read directory entry")?;
+        let src_path = entry.path();
+        let file_name = src_path
+            .file_name()
+            .ok_or_else(|| anyhow!("invalid file name"))?;
+        let dst_path = dst_dir.join(file_name);
+
+        if src_path.is_dir() {
+            copy_directory_recursive(&src_path, &dst_path)?;
+        } else {
+            permissions::copy_private(&src_path, &dst_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+// 日记根目录中由本程序管理的文件和子目录，所选目录中的其他文件不属于日记
+fn managed_paths(root: &Path) -> Vec<PathBuf> {
+    vec![
+        vault_file_path(root.to_path_buf()),
+        history::history_file_path(root),
+        journal::journal_file_path(root),
+        search::index_file_path(root),
+        unlock_log::log_file_path(root),
+        profile::profile_file_path(root),
+        root.join("entries"),
+        root.join("attachments"),
+        root.join(shards::INDEX_DIR),
+        emergency::emergency_dir(root),
+        root.join("exports"),
+        root.join(MIGRATION_BACKUP_DIR),
+        root.join(QUARANTINE_DIR),
+        key_slots::key_slots_path(root),
+        drafts::drafts_dir(root),
+    ]
+}
+
+// 检查目标目录并返回各源路径在目标中的位置；目标不能与日记目录互相包含，也不能覆盖已有文件
+fn plan_copy(root: &Path, destination: &Path, sources: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    permissions::create_private_dir_all(destination).context(Message::CreateDestinationFailed)?;
+    let canonical_root = root
+        .canonicalize()
+        .context("failed to resolve vault directory")?;
+    let canonical_new = destination.canonicalize().context(Message::ResolveDestinationFailed)?;
+    if canonical_new.starts_with(&canonical_root) || canonical_root.starts_with(&canonical_new) {
+        return Err(anyhow!(Message::DestinationOverlapsVault));
+    }
+
+    let targets = sources
+        .iter()
+        .map(|source| {
+            source
+                .file_name()
+                .map(|name| destination.join(name))
+                .ok_or_else(|| anyhow!("invalid file name"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(existing) = targets.iter().find(|target| target.exists()) {
+        return Err(Conflict(Message::DestinationOccupied(display_path(existing))).into());
+    }
+    Ok(targets)
+}
+
+struct CopyStats {
+    files: usize,
+    bytes: u64,
+    verified_entries: usize,
+}
+
+// 复制后逐字节比对，再在新位置解密元数据、索引分片和抽样条目。失败时删除已复制的目标
+fn copy_and_verify(
+    vault: &UnlockedVault,
+    destination: &Path,
+    sources: &[PathBuf],
+    targets: &[PathBuf],
+) -> Result<CopyStats> {
+    let copied = (|| -> Result<CopyStats> {
+        let mut stats = CopyStats {
+            files: 0,
+            bytes: 0,
+            verified_entries: 0,
+        };
+        for (source, target) in sources.iter().zip(targets) {
+            if source.is_dir() {
+                copy_directory_recursive(source, target)?;
+            } else {
+                permissions::copy_private(source, target)?;
+            }
+            let files = verify_copy(source, target)?;
+            stats.files += files.len();
+            stats.bytes += files.iter().sum::<u64>();
+        }
+
+        let stored = load_vault(&vault_file_path(destination.to_path_buf()))?;
+        let metadata = decrypt_metadata(&stored, &vault.key)?;
+        shards::load_shards(
+            &destination.join(shards::INDEX_DIR),
+            &vault.key,
+            &metadata.shards,
+        )?;
+        let entries_dir = destination.join("entries");
+        let step = vault.metadata.len().div_ceil(COPY_SAMPLE_ENTRIES).max(1);
+        for meta in vault.metadata.iter().step_by(step) {
+            load_entry_content(&entries_dir, &vault.key, meta.encryption, &meta.id)?;
+            stats.verified_entries += 1;
+        }
+        Ok(stats)
+    })();
+    if copied.is_err() {
+        for target in targets {
+            let _ = remove_path(target);
+        }
+    }
+    copied
+}
+
+// 逐字节比对复制结果，返回每个文件的大小
+fn verify_copy(source: &Path, target: &Path) -> Result<Vec<u64>> {
+    let files = if source.is_dir() {
+        list_files_recursive(source)?
+    } else {
+        vec![source.to_path_buf()]
+    };
+    files
+        .par_iter()
+        .map(|file| -> Result<u64> {
+            let copy = match file.strip_prefix(source) {
+                Ok(relative) if !relative.as_os_str().is_empty() => target.join(relative),
+                _ => target.to_path_buf(),
+            };
+            let original = fs::read(file).context("failed to read source file")?;
+            let copied = fs::read(&copy).context("failed to read copied file")?;
+            if original != copied {
+                return Err(anyhow!("copied file does not match: {}", display_path(&copy)));
+            }
+            Ok(original.len() as u64)
+        })
+        .collect()
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+// 解锁记录写不进去（磁盘已满、权限不足）时只记警告，不能因此挡住正确的密码
+fn record_unlock(root: &Path) -> LastUnlockInfo {
+    unlock_log::record_success(root).unwrap_or_else(|err| {
+        tracing::warn!(error = %err, "failed to record unlock");
+        unlock_log::last_unlock(root)
+    })
+}
+
+// 整理：写入变化的分片并重写 vault.json，同时换用新的空日志
+fn save_metadata(vault: &mut UnlockedVault) -> Result<()> {
+    vault.last_saved = OffsetDateTime::now_utc();
+    vault.modified = true;
+    vault.pending_flush = None;
+    vault.pending_updates.clear();
+    // 先写分片再写 vault.json，元数据中的摘要总是指向已落盘的分片
+    let years = std::mem::take(&mut vault.dirty_years);
+    shards::write_years(&vault.index_dir, &vault.key, &vault.metadata, years, &mut vault.shards)?;
+    vault.journal_id = Uuid::new_v4();
+    let metadata = build_metadata(vault);
+    save_vault(
+        &vault.path,
+        &vault.salt,
+        &vault.kdf,
+        &vault.key,
+        &metadata,
+        vault.last_saved,
+    )?;
+    // vault.json 已指向新的分片与日志编号，旧分片和旧日志可以丢弃
+    shards::prune(&vault.index_dir, &vault.shards)?;
+    journal::reset(&vault.journal_path, &vault.journal_id)?;
+    vault.journal_len = 0;
+    if vault.search_dirty {
+        search::save(&vault.search_path, &vault.key, &vault.search)?;
+        vault.search_dirty = false;
+    }
+    record_history(vault)
+}
+
+// 口令所属的密钥槽；旧日记的主口令不占槽位，视为管理员
+struct Caller {
+    slot: Option<Uuid>,
+    admin: bool,
+}
+
+// 在全部槽位中查找口令，旧日记还要尝试主口令；解开的必须是当前的主密钥
+fn find_caller(vault: &UnlockedVault, passphrase: &str) -> Result<Option<Caller>> {
+    let root = vault_root_of(vault);
+    if !key_slots::has_admin(&root)?
+        && derive_key(passphrase, &vault.salt, &vault.kdf)? == vault.key
+    {
+        return Ok(Some(Caller {
+            slot: None,
+            admin: true,
+        }));
+    }
+    match key_slots::unwrap_key(&root, passphrase, &vault.kdf)? {
+        Some((id, key)) if key == vault.key => Ok(Some(Caller {
+            slot: Some(id),
+            admin: key_slots::is_admin(&root, id)?,
+        })),
+        _ => Ok(None),
+    }
+}
+
+// 知道解锁所用的槽位时只核对该槽位，不能借用其他用户的口令；
+// PIN 解锁时不知道槽位，在全部槽位中查找
+fn own_caller(vault: &UnlockedVault, passphrase: &str) -> Result<Option<Caller>> {
+    let Some(id) = vault.slot else {
+        return find_caller(vault, passphrase);
+    };
+    let root = vault_root_of(vault);
+    match key_slots::unwrap_key_with(&root, id, passphrase, &vault.kdf)? {
+        Some(key) if key == vault.key => Ok(Some(Caller {
+            slot: Some(id),
+            admin: key_slots::is_admin(&root, id)?,
+        })),
+        _ => Ok(None),
+    }
+}
+
+// 管理槽位和更换主密钥需要主口令或管理员口令，其他用户在场时也可以代为输入
+fn admin_caller(vault: &UnlockedVault, passphrase: &str) -> Result<Caller> {
+    match find_caller(vault, passphrase)? {
+        Some(caller) if caller.admin => Ok(caller),
+        Some(_) => {
+            Err(anyhow::Error::new(DecryptionFailed).context(Message::AdminPassphraseRequired))
+        }
+        None => Err(DecryptionFailed.into()),
+    }
+}
+
+// 换用新的随机主密钥重新加密全部文件，密钥槽只保留一个以给定口令包装的管理员槽位。
+// 返回作废的其他槽位数
+fn rekey_vault(
+    vault: &mut UnlockedVault,
+    label: &str,
+    passphrase: &str,
+    new_history: Vec<PassphraseVerifier>,
+) -> Result<usize> {
+    // 生成新的 salt 和 key
+    let mut new_salt = [0u8; 16];
+    OsRng.fill_bytes(&mut new_salt);
+    let mut new_key = [0u8; 32];
+    OsRng.fill_bytes(&mut new_key);
+    // 更换主密钥时顺带换用当前的默认参数
+    let new_kdf = kdf_params();
+
+    // 创建临时备份目录
+    let vault_root = vault.path.parent().ok_or_else(|| anyhow!("invalid vault path"))?;
+    let slot_count = key_slots::list(vault_root, &vault.key)?.len();
+    let voided = slot_count.saturating_sub(usize::from(vault.slot.is_some()));
+    let backup_root = vault_root.join(format!(".vault_backup_{}", Uuid::new_v4()));
+    let backup_entries = backup_root.join("entries");
+    let backup_attachments = backup_root.join("attachments");
+    let backup_index = backup_root.join(shards::INDEX_DIR);
+    let backup_metadata = backup_root.join("vault.json");
+
+    permissions::create_private_dir_all(&backup_entries)
+        .context("failed to create backup directory")?;
+    permissions::create_private_dir_all(&backup_attachments)
+        .context("failed to create backup directory")?;
+
+    // 执行备份和重新加密的操作，使用闭包包装以便统一错误处理
+    let mut new_shards = BTreeMap::new();
+    let new_journal_id = Uuid::new_v4();
+    let mut new_slot = None;
+    let result: Result<()> = (|| {
+        // 1. 备份并重新加密所有日记条目到临时目录（各条目互不依赖，并行处理）
+        vault.metadata.par_iter().try_for_each(|entry_info| -> Result<()> {
+            // 读取原内容
+            // 读不出的条目无法用新密钥重写，需先隔离，否则整体放弃
+            let (content, payload) = load_entry_body(
+                &vault.entries_dir,
+                &vault.key,
+                entry_info.encryption,
+                &entry_info.id,
+            )
+            .context(Message::EntryUnreadable(entry_info.title.clone()))?;
+
+            // 创建临时 Entry 对象
+            let temp_entry = entry_info.clone().with_body(content, payload);
+
+            // 先备份原文件
+            let original_path = entry_file_path(&vault.entries_dir, &entry_info.id);
+            let backup_path = entry_file_path(&backup_entries, &entry_info.id);
+            if original_path.exists() {
+                permissions::copy_private(&original_path, &backup_path)
+                    .context("failed to backup entry file")?;
+            }
+
+            // 使用新密钥重新加密并保存到临时位置
+            save_entry_content_to_path(
+                &backup_path,
+                &new_key,
+                entry_info.encryption,
+                &temp_entry,
+            )
+        })?;
+
+        // 2. 备份并重新加密所有图片附件到临时目录
+        let attachments_dir = &vault.attachments_dir;
+        if attachments_dir.exists() {
+            copy_and_reencrypt_images_recursive(
+                attachments_dir,
+                &backup_attachments,
+                &vault.key,
+                &new_key,
+                vault.text_encryption,
+            )?;
+        }
+
+        // 3. 用新密钥重写全部索引分片，并保存新的元数据到临时位置
+        let years: BTreeSet<i32> = vault.metadata.iter().map(shards::shard_year).collect();
+        shards::write_years(&backup_index, &new_key, &vault.metadata, years, &mut new_shards)?;
+        let new_metadata = VaultMetadata {
+            shards: new_shards.clone(),
+            journal_id: Some(new_journal_id),
+            passphrase_history: new_history.clone(),
+            ..build_metadata(vault)
+        };
+        save_vault(
+            &backup_metadata,
+            &new_salt,
+            &new_kdf,
+            &new_key,
+            &new_metadata,
+            OffsetDateTime::now_utc(),
+        )?;
+
+        // 4. 新的密钥槽文件只有一个管理员槽位，用给定口令包装新主密钥
+        new_slot = Some(key_slots::create(&backup_root, &new_key, &new_kdf, label, passphrase)?);
+
+        Ok(())
+    })();
+
+    // 如果加密失败，清理临时文件并返回错误
+    if let Err(e) = result {
+        let _ = fs::remove_dir_all(&backup_root);
+        return Err(e.context(Message::ChangePassphraseFailed));
+    }
+
+    // 所有文件都成功加密后，开始原子性替换
+    let replace_result: Result<()> = (|| {
+        // 5. 用新加密的文件替换原文件
+        // 5.1 替换日记条目
+        for entry_info in vault.metadata.iter() {
+            let backup_path = entry_file_path(&backup_entries, &entry_info.id);
+            let original_path = entry_file_path(&vault.entries_dir, &entry_info.id);
+            if backup_path.exists() {
+                permissions::copy_private(&backup_path, &original_path)
+                    .context("failed to replace entry file")?;
+            }
+        }
+
+        // 5.2 替换图片附件
+        if backup_attachments.exists() {
+            replace_directory_contents(&backup_attachments, &vault.attachments_dir)?;
+        }
+
+        // 5.3 替换索引分片
+        replace_directory_contents(&backup_index, &vault.index_dir)?;
+
+        // 5.4 替换元数据文件
+        permissions::copy_private(&backup_metadata, &vault.path)
+            .context("failed to replace metadata file")?;
+
+        // 5.5 替换密钥槽：其他槽位包装的是旧主密钥，随之作废
+        permissions::copy_private(
+            &key_slots::key_slots_path(&backup_root),
+            &key_slots::key_slots_path(vault_root),
+        )
+        .context("failed to replace key slots")?;
+
+        Ok(())
+    })();
+
+    // 清理临时备份目录
+    let _ = fs::remove_dir_all(&backup_root);
+
+    // 如果替换失败，返回错误（此时原文件可能已部分被修改）
+    replace_result.context(Message::ReplaceFilesFailed)?;
+
+    // 暂存内容不在备份范围内，单独换用新密钥；失败时只是无法恢复，不影响修改密码
+    if let Err(err) = drafts::rekey(&vault_root_of(vault), &vault.key, &new_key) {
+        tracing::warn!(error = %err, "failed to re-encrypt drafts");
+    }
+    // 升级格式前的备份仍用旧密钥加密，旧密码能解开其中的标题、标签和地点
+    if let Err(err) = remove_migration_backups(&vault_root_of(vault)) {
+        tracing::warn!(error = %err, "failed to remove migration backups");
+    }
+
+    // 6. 更新内存中的 vault 状态
+    vault.salt = new_salt;
+    vault.kdf = new_kdf;
+    vault.key = new_key;
+    vault.slot = new_slot.map(|slot| slot.id);
+    vault.shards = new_shards;
+    // 新元数据已包含内存中的全部改动，旧日志随之作废
+    vault.dirty_years.clear();
+    vault.pending_flush = None;
+    vault.pending_updates.clear();
+    vault.journal_id = new_journal_id;
+    vault.journal_len = 0;
+    journal::reset(&vault.journal_path, &vault.journal_id)?;
+    search::save(&vault.search_path, &vault.key, &vault.search)?;
+    vault.search_dirty = false;
+    vault.passphrase_history = new_history;
+    vault.last_saved = OffsetDateTime::now_utc();
+    record_history(vault)?;
+    Ok(voided)
+}
+
+// 条目改动只追加到日志，积累足够多后再整理
+fn append_journal(vault: &mut UnlockedVault, changes: &[JournalChange]) -> Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    journal::append(
+        &vault.journal_path,
+        &vault.key,
+        &vault.journal_id,
+        vault.journal_len,
+        changes,
+    )?;
+    vault.journal_len += changes.len() as u64;
+    vault.modified = true;
+    let history_path = history::history_file_path(&vault_root_of(vault));
+    let head =
+        history::append_journal(&history_path, &vault.journal_path, OffsetDateTime::now_utc())?;
+    vault.history_head = Some(head);
+    if vault.journal_len >= JOURNAL_COMPACT_THRESHOLD {
+        save_metadata(vault)?;
+    }
+    Ok(())
+}
+
+// 把推迟的条目更新写入日志
+fn flush_pending(vault: &mut UnlockedVault) -> Result<()> {
+    let changes: Vec<JournalChange> = vault
+        .metadata
+        .iter()
+        .filter(|info| vault.pending_updates.contains(&info.id))
+        .map(|info| JournalChange::Upsert {
+            entry: Box::new(info.clone()),
+        })
+        .collect();
+    append_journal(vault, &changes)?;
+    vault.pending_updates.clear();
+    vault.pending_flush = None;
+    Ok(())
+}
+
+fn compact_metadata(vault: &mut UnlockedVault) -> Result<()> {
+    if vault.pending_flush.is_some() || vault.journal_len > 0 || vault.search_dirty {
+        save_metadata(vault)?;
+    }
+    Ok(())
+}
+
+// 让检索索引与条目元数据一致，只为新增或修改过的条目解密建索引，返回索引是否有变化
+fn refresh_search_index(
+    index: &mut SearchIndex,
+    entries: &[EntryInfo],
+    entries_dir: &Path,
+    key: &[u8; 32],
+) -> bool {
+    let ids: BTreeSet<Uuid> = entries.iter().map(|info| info.id).collect();
+    let removed = index.retain(|id| ids.contains(id));
+
+    // 内容读不出来的条目暂不入索引，不影响解锁
+    let stale: Vec<(EntryInfo, String)> = entries
+        .par_iter()
+        .filter(|info| !index.is_current(&info.id, info.updated_at))
+        .filter_map(|info| {
+            load_entry_content(entries_dir, key, info.encryption, &info.id)
+                .ok()
+                .map(|content| (info.clone(), content))
+        })
+        .collect();
+    for (info, content) in stale.iter() {
+        index.upsert(info.id, info.updated_at, &info.title, content);
+    }
+    removed || !stale.is_empty()
+}
+
+// 按更新时间倒序分批解密并写出 Markdown；同一批内各条目独立解密，密钥只读，可并行。
+// 读不出的条目跳过，返回给调用方提示
+// 按条件筛选、排序并分页；调用方持有读锁，需要在同一把锁下继续处理结果时直接调用
+fn query_in(vault: &UnlockedVault, spec: QuerySpec) -> QueryPage {
+    let matched = spec
+        .text
+        .as_deref()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(|text| vault.search.search(text));
+    let mut entries: Vec<EntryInfo> = vault
+        .metadata
+        .iter()
+        .filter(|info| matched.as_ref().is_none_or(|ids| ids.contains(&info.id)))
+        .filter(|info| folder_matches(info, spec.folder.as_deref(), spec.include_subfolders))
+        .filter(|info| spec.tag.as_ref().is_none_or(|tag| info.tags.contains(tag)))
+        .filter(|info| spec.entry_type.is_none_or(|kind| info.entry_type == kind))
+        .filter(|info| {
+            if spec.min_mood.is_none() && spec.max_mood.is_none() {
+                return true;
+            }
+            info.mood.is_some_and(|mood| {
+                spec.min_mood.is_none_or(|min| mood >= min)
+                    && spec.max_mood.is_none_or(|max| mood <= max)
+            })
+        })
+        .filter(|info| {
+            let at = match spec.date_field {
+                QueryDateField::Created => info.created_at,
+                QueryDateField::Updated => info.updated_at,
+                QueryDateField::Entry => {
+                    return spec
+                        .from
+                        .is_none_or(|from| info.entry_day(from.offset()) >= from.date())
+                        && spec
+                            .to
+                            .is_none_or(|to| info.entry_day(to.offset()) <= to.date());
+                }
+            };
+            spec.from.is_none_or(|from| at >= from) && spec.to.is_none_or(|to| at <= to)
+        })
+        .cloned()
+        .collect();
+
+    if let Some(wanted) = spec.has_attachments {
+        let paths: Vec<&str> = vault
+            .attachments
+            .iter()
+            .map(|attachment| attachment.path.as_str())
+            .collect();
+        // 读不出正文的条目无法判断，两种筛选都不包含
+        let flags = entries
+            .par_iter()
+            .map(|info| {
+                load_entry_content(&vault.entries_dir, &vault.key, info.encryption, &info.id)
+                    .ok()
+                    .map(|content| references_attachment(&content, &paths))
+            })
+            .collect::<Vec<Option<bool>>>();
+        let mut flags = flags.into_iter();
+        entries.retain(|_| flags.next() == Some(Some(wanted)));
+    }
+
+    match spec.sort {
+        QuerySort::UpdatedDesc => entries.sort_by_key(|info| std::cmp::Reverse(info.updated_at)),
+        QuerySort::UpdatedAsc => entries.sort_by_key(|info| info.updated_at),
+        QuerySort::CreatedDesc => entries.sort_by_key(|info| std::cmp::Reverse(info.created_at)),
+        QuerySort::CreatedAsc => entries.sort_by_key(|info| info.created_at),
+        QuerySort::DateDesc => entries.sort_by_key(|info| {
+            std::cmp::Reverse((info.entry_day(info.created_at.offset()), info.created_at))
+        }),
+        QuerySort::DateAsc => {
+            entries.sort_by_key(|info| (info.entry_day(info.created_at.offset()), info.created_at))
+        }
+        QuerySort::Title => entries.sort_by_cached_key(|info| info.title.to_lowercase()),
+    }
+    let total = entries.len();
+    let entries = entries
+        .into_iter()
+        .skip(spec.offset)
+        .take(spec.limit.unwrap_or(usize::MAX))
+        .collect();
+    QueryPage { entries, total }
+}
+
+fn write_plaintext(vault: &UnlockedVault, writer: &mut impl Write) -> Result<Vec<EntryError>> {
+    let offset = export_offset(&vault.settings);
+    let mut entries = vault.metadata.clone();
+    entries.sort_by_key(|info| std::cmp::Reverse(info.updated_at));
+
+    let mut first = true;
+    let mut skipped = Vec::new();
+    for batch in entries.chunks(EXPORT_BATCH_SIZE) {
+        let sections: Vec<_> = batch
+            .par_iter()
+            .map(|info| {
+                load_entry_body(&vault.entries_dir, &vault.key, info.encryption, &info.id)
+                    .map(|(content, payload)| {
+                        info.clone().with_body(content, payload).to_markdown(offset)
+                    })
+                    .map_err(|err| entry_error(info, err))
+            })
+            .collect();
+        for section in sections {
+            let section = match section {
+                Ok(section) => section,
+                Err(error) => {
+                    skipped.push(error);
+                    continue;
+                }
+            };
+            if !first {
+                writer
+                    .write_all(b"\n---\n\n")
+                    .context("failed to write export")?;
+            }
+            writer
+                .write_all(section.as_bytes())
+                .context("failed to write export")?;
+            first = false;
+        }
+    }
+    Ok(skipped)
+}
+
+fn entry_error(info: &EntryInfo, err: anyhow::Error) -> EntryError {
+    EntryError {
+        id: info.id,
+        title: info.title.clone(),
+        error: format!("{err:#}"),
+    }
+}
+
+// 条目内容已写盘，只把元数据的写入推迟合并
+fn remove_entry(vault: &mut UnlockedVault, id: Uuid) -> Result<()> {
+    let Some(year) = vault
+        .metadata
+        .iter()
+        .find(|entry| entry.id == id)
+        .map(shards::shard_year)
+    else {
+        return Ok(());
+    };
+    vault.metadata.retain(|entry| entry.id != id);
+    vault.dirty_years.insert(year);
+
+    let content_path = entry_file_path(&vault.entries_dir, &id);
+    if content_path.exists() {
+        fs::remove_file(&content_path).context("failed to remove entry file")?;
+    }
+
+    vault.pending_updates.remove(&id);
+    vault.search.remove(&id);
+    vault.search_dirty = true;
+    append_journal(vault, &[JournalChange::Delete { id }])
+}
+
+// 依次执行撤销步骤，返回每个条目的变化类型用于通知前端。已不存在的条目直接跳过
+fn apply_undo_steps(
+    vault: &mut UnlockedVault,
+    steps: &[UndoStep],
+    now: OffsetDateTime,
+) -> Result<Vec<(EntryChange, Uuid)>> {
+    let mut changes = Vec::new();
+    for step in steps {
+        match step {
+            UndoStep::Restore(entry) => {
+                save_entry_content(&vault.entries_dir, &vault.key, entry.encryption, entry)?;
+                let info = entry.metadata(vault.settings.sentiment_analysis);
+                vault.dirty_years.insert(shards::shard_year(&info));
+                vault
+                    .search
+                    .upsert(entry.id, entry.updated_at, &entry.title, &entry.content);
+                vault.search_dirty = true;
+                let change = match vault.metadata.iter_mut().find(|item| item.id == entry.id) {
+                    Some(existing) => {
+                        *existing = info.clone();
+                        EntryChange::Updated
+                    }
+                    None => {
+                        vault.metadata.push(info.clone());
+                        EntryChange::Created
+                    }
+                };
+                append_journal(
+                    vault,
+                    &[JournalChange::Upsert {
+                        entry: Box::new(info),
+                    }],
+                )?;
+                changes.push((change, entry.id));
+            }
+            UndoStep::Remove(id) => {
+                if vault.metadata.iter().any(|entry| entry.id == *id) {
+                    remove_entry(vault, *id)?;
+                    changes.push((EntryChange::Deleted, *id));
+                }
+            }
+            UndoStep::SetFolder { id, .. } | UndoStep::SetTags { id, .. } => {
+                let Some(info) = vault.metadata.iter_mut().find(|entry| entry.id == *id) else {
+                    continue;
+                };
+                if let UndoStep::SetFolder { folder, .. } = step {
+                    info.folder = folder.clone();
+                }
+                if let UndoStep::SetTags { tags, .. } = step {
+                    info.tags = tags.clone();
+                }
+                info.touch(now);
+                let (year, updated_at) = (shards::shard_year(info), info.updated_at);
+                vault.dirty_years.insert(year);
+                vault.search.refresh(id, updated_at);
+                vault.search_dirty = true;
+                vault.pending_updates.insert(*id);
+                defer_metadata_save(vault);
+                changes.push((EntryChange::Updated, *id));
+            }
+        }
+    }
+    Ok(changes)
+}
+
+// 文件夹变化和标签被移除（包括合并、改名）记为可撤销的一步；其他修改只让重做失效
+fn record_reorganize(
+    undo: &mut UndoHistory,
+    (previous_folder, previous_tags): (Option<String>, Vec<String>),
+    current: &EntryInfo,
+) {
+    let id = current.id;
+    let mut record = UndoRecord {
+        kind: UndoKind::Move,
+        undo: Vec::new(),
+        redo: Vec::new(),
+    };
+    if current.folder != previous_folder {
+        record.undo.push(UndoStep::SetFolder {
+            id,
+            folder: previous_folder,
+        });
+        record.redo.push(UndoStep::SetFolder {
+            id,
+            folder: current.folder.clone(),
+        });
+    }
+    if previous_tags.iter().any(|tag| !current.tags.contains(tag)) {
+        if record.undo.is_empty() {
+            record.kind = UndoKind::Retag;
+        }
+        record.undo.push(UndoStep::SetTags {
+            id,
+            tags: previous_tags,
+        });
+        record.redo.push(UndoStep::SetTags {
+            id,
+            tags: current.tags.clone(),
+        });
+    }
+    if record.undo.is_empty() {
+        undo.clear_redo();
+    } else {
+        undo.record(record);
+    }
+}
+
+fn defer_metadata_save(vault: &mut UnlockedVault) {
+    let now = Instant::now();
+    vault.modified = true;
+    vault.pending_flush = Some(match vault.pending_flush {
+        Some(pending) => PendingFlush {
+            last_change: now,
+            ..pending
+        },
+        None => PendingFlush {
+            first_change: now,
+            last_change: now,
+        },
+    });
+}
+
+// 只读打开时拒绝一切修改，而不是等到写盘失败
+fn ensure_writable(vault: &UnlockedVault) -> Result<()> {
+    if vault.read_only {
+        return Err(anyhow!(Message::VaultReadOnly));
+    }
+    Ok(())
+}
+
+fn ensure_plaintext_export_allowed(vault: &UnlockedVault) -> Result<()> {
+    if vault.policy.disable_plaintext_export {
+        return Err(anyhow!(Message::PlaintextExportDisabled));
+    }
+    Ok(())
+}
+
+fn build_metadata(vault: &UnlockedVault) -> VaultMetadata {
+    VaultMetadata {
+        version: METADATA_VERSION,
+        entries: Vec::new(),
+        shards: vault.shards.clone(),
+        journal_id: Some(vault.journal_id),
+        text_encryption: vault.text_encryption,
+        history_head: vault.history_head.clone(),
+        passphrase_history: vault.passphrase_history.clone(),
+        passphrase_history_depth: vault.passphrase_history_depth,
+        emergency: vault.emergency.clone(),
+        policy: vault.policy,
+        settings: vault.settings.clone(),
+        prompts: vault.prompts.clone(),
+        habits: vault.habits.clone(),
+        recaps: vault.recaps.clone(),
+        day_summaries: vault.day_summaries.clone(),
+        dictionary: vault.dictionary.clone(),
+        attachments: Some(vault.attachments.clone()),
+    }
+}
+
+fn export_offset(settings: &VaultSettings) -> Option<UtcOffset> {
+    settings
+        .timezone_offset_minutes
+        .and_then(|minutes| UtcOffset::from_whole_seconds(i32::from(minutes) * 60).ok())
+}
+
+fn auto_lock_duration(settings: &VaultSettings) -> Option<Duration> {
+    settings
+        .auto_lock_minutes
+        .map(|minutes| Duration::from_secs(minutes.saturating_mul(60)))
+}
+
+// 解密全部条目与附件，作为紧急归档的内容
+fn recovery_payload(vault: &UnlockedVault) -> Result<RecoveryPayload> {
+    let entries = vault
+        .metadata
+        .par_iter()
+        .map(|info| {
+            let (content, payload) =
+                load_entry_body(&vault.entries_dir, &vault.key, info.encryption, &info.id)?;
+            Ok(info.clone().with_body(content, payload))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let root = vault_root_of(vault);
+    let attachments = list_files_recursive(&vault.attachments_dir)?
+        .into_par_iter()
+        .map(|path| {
+            let encrypted = fs::read(&path).context(Message::ReadImageFailed)?;
+            let relative = path.strip_prefix(&root).unwrap_or(&path);
+            Ok(RecoveryAttachment {
+                path: display_path(relative),
+                data: general_purpose::STANDARD_NO_PAD
+                    .encode(decrypt_image_data(&vault.key, &encrypted)?),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(RecoveryPayload {
+        created_at: OffsetDateTime::now_utc(),
+        entries,
+        attachments,
+    })
+}
+
+fn list_files_recursive(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if !dir.is_dir() {
+        return Ok(files);
+    }
+    for entry in fs::read_dir(dir).context("failed to read directory")? {
+        let path = entry.context("failed to read directory entry")?.path();
+        if path.is_dir() {
+            files.extend(list_files_recursive(&path)?);
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+fn record_attachment(vault: &mut UnlockedVault, attachment: AttachmentInfo) -> Result<()> {
+    vault.attachments.push(attachment.clone());
+    append_journal(vault, &[JournalChange::Attachment { attachment }])
+}
+
+// 旧版本日记没有附件缓存，解锁时逐个解密一次补齐；无法读取的文件跳过
+fn scan_attachments(
+    attachments_dir: &Path,
+    root: &Path,
+    key: &[u8; 32],
+) -> Result<Vec<AttachmentInfo>> {
+    let mut attachments: Vec<AttachmentInfo> = list_files_recursive(attachments_dir)?
+        .into_par_iter()
+        .filter_map(|path| {
+            let encrypted = fs::read(&path).ok()?;
+            let data = decrypt_image_data(key, &encrypted).ok()?;
+            let stored_at = fs::metadata(&path)
+                .and_then(|meta| meta.modified())
+                .map(OffsetDateTime::from)
+                .unwrap_or_else(|_| OffsetDateTime::now_utc());
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            Some(AttachmentInfo::describe(relative, &data, stored_at))
+        })
+        .collect();
+    attachments.sort_by_key(|attachment| attachment.stored_at);
+    Ok(attachments)
+}
+
+// 每次写入 vault.json 之后在哈希链上追加一环
+fn record_history(vault: &mut UnlockedVault) -> Result<()> {
+    let history_path = history::history_file_path(&vault_root_of(vault));
+    let head = history::append(&history_path, &vault.path, vault.last_saved)?;
+    vault.history_head = Some(head);
+    Ok(())
+}
+
+fn vault_root_of(vault: &UnlockedVault) -> PathBuf {
+    vault
+        .path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| vault.path.clone())
+}
+
+struct UnlockedVault {
+    // 解锁期间持有，随解锁状态一起释放
+    _lock: Option<vault_lock::VaultLock>,
+    key: [u8; 32],
+    salt: [u8; 16],
+    // 派生密钥所用的参数，与 salt 一起写入 vault.json 头部
+    kdf: KdfParams,
+    // 解锁所用的密钥槽；用旧日记的主口令或快速解锁 PIN 解锁时为空
+    slot: Option<Uuid>,
+    metadata: Vec<EntryInfo>,
+    path: PathBuf,
+    entries_dir: PathBuf,
+    attachments_dir: PathBuf,
+    attachments: Vec<AttachmentInfo>,
+    index_dir: PathBuf,
+    // 每个年份分片文件的 SHA-256，随加密元数据一起保存
+    shards: BTreeMap<i32, String>,
+    // 尚未写回的年份分片
+    dirty_years: BTreeSet<i32>,
+    journal_path: PathBuf,
+    journal_id: Uuid,
+    // 当前日志中的记录数，也是下一条记录的序号
+    journal_len: u64,
+    text_encryption: TextEncryption,
+    last_saved: OffsetDateTime,
+    history_head: Option<String>,
+    last_unlock: LastUnlockInfo,
+    passphrase_history: Vec<PassphraseVerifier>,
+    passphrase_history_depth: usize,
+    emergency: Option<EmergencyAccess>,
+    policy: VaultPolicy,
+    settings: VaultSettings,
+    prompts: PromptState,
+    habits: HabitState,
+    recaps: BTreeMap<String, Uuid>,
+    day_summaries: BTreeMap<String, String>,
+    dictionary: BTreeSet<String>,
+    // 本次解锁后是否写入过数据，用于锁定时决定是否刷新紧急归档
+    modified: bool,
+    // 日记目录不可写（光盘、写保护的存储卡等），只能查看和导出
+    read_only: bool,
+    pending_flush: Option<PendingFlush>,
+    // 推迟写入日志的条目更新
+    pending_updates: BTreeSet<Uuid>,
+    search: SearchIndex,
+    search_path: PathBuf,
+    // 检索索引有未保存的改动，随元数据整理一起写盘
+    search_dirty: bool,
+    // 删除和移动的撤销记录，删除时保存了完整正文，因此只放在内存中
+    undo: UndoHistory,
+}
+
+#[derive(Clone, Copy)]
+struct PendingFlush {
+    first_change: Instant,
+    last_change: Instant,
+}
+
+impl PendingFlush {
+    fn is_due(&self, now: Instant) -> bool {
+        now.duration_since(self.last_change) >= METADATA_FLUSH_DELAY
+            || now.duration_since(self.first_change) >= METADATA_FLUSH_MAX_DELAY
+    }
+}
+
+// 文件名沿用 vault.json 以兼容已有日记的路径，内容为二进制封装
+pub(crate) struct StoredVault {
+    pub(crate) version: u32,
+    pub(crate) salt: Vec<u8>,
+    pub(crate) nonce: Vec<u8>,
+    pub(crate) ciphertext: Vec<u8>,
+    pub(crate) updated_at: Option<OffsetDateTime>,
+    // 派生密钥所用的参数，写在明文头部，不解锁也能读出；旧文件没有该字段
+    pub(crate) kdf: Option<KdfParams>,
+}
+
+// 旧版 JSON + base64 格式，只读
+#[derive(Deserialize)]
+struct LegacyStoredVault {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+    updated_at: Option<OffsetDateTime>,
+}
+
+impl StoredVault {
+    fn to_bytes(&self) -> Vec<u8> {
+        let updated_at = self
+            .updated_at
+            .map(|ts| ts.unix_timestamp_nanos().to_le_bytes().to_vec())
+            .unwrap_or_default();
+        let kdf: Vec<u8> = self
+            .kdf
+            .as_ref()
+            .map(|kdf| {
+                [kdf.memory_kib, kdf.iterations, kdf.parallelism]
+                    .iter()
+                    .flat_map(|value| value.to_le_bytes())
+                    .collect()
+            })
+            .unwrap_or_default();
+        envelope::encode(
+            Kind::Vault,
+            &[
+                &self.version.to_le_bytes(),
+                &self.salt,
+                &self.nonce,
+                &updated_at,
+                &self.ciphertext,
+                &kdf,
+            ],
+        )
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if !envelope::is_envelope(bytes) {
+            let legacy: LegacyStoredVault =
+                serde_json::from_slice(bytes).context("failed to parse vault")?;
+            return Ok(StoredVault {
+                version: legacy.version,
+                salt: decode_base64(&legacy.salt).context("invalid salt encoding")?,
+                nonce: decode_base64(&legacy.nonce).context("invalid nonce encoding")?,
+                ciphertext: decode_base64(&legacy.ciphertext)
+                    .context("invalid ciphertext encoding")?,
+                updated_at: legacy.updated_at,
+                kdf: None,
+            });
+        }
+
+        // 第 6 个字段（密钥派生参数）是后来加的
+        let fields = envelope::decode_fields(bytes, Kind::Vault).context("failed to parse vault")?;
+        if !(5..=6).contains(&fields.len()) {
+            return Err(anyhow!("failed to parse vault: invalid storage envelope"));
+        }
+        let updated_at = match fields[3] {
+            [] => None,
+            raw => {
+                let nanos = i128::from_le_bytes(
+                    raw.try_into().map_err(|_| anyhow!("invalid vault timestamp"))?,
+                );
+                Some(
+                    OffsetDateTime::from_unix_timestamp_nanos(nanos)
+                        .context("invalid vault timestamp")?,
+                )
+            }
+        };
+        Ok(StoredVault {
+            version: read_u32(fields[0]).context("failed to parse vault")?,
+            salt: fields[1].to_vec(),
+            nonce: fields[2].to_vec(),
+            ciphertext: fields[4].to_vec(),
+            updated_at,
+            kdf: match fields.get(5).copied() {
+                None | Some([]) => None,
+                Some(raw) => Some(read_kdf_params(raw).context("failed to parse vault")?),
+            },
+        })
+    }
+}
+
+struct StoredEntry {
+    version: u32,
+    compression: Compression,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+    // 加密算法记录在每个文件中，算法迁移只完成一部分时也能逐个文件正确解密；
+    // 旧文件没有该字段，按元数据中记录的算法解密
+    encryption: Option<TextEncryption>,
+    // 版本、压缩方式和算法作为附加数据参与认证，改动任何一个都会解密失败；旧文件没有认证
+    header_bound: bool,
+}
+
+// 旧版 JSON + base64 格式，只读
+#[derive(Deserialize)]
+struct LegacyStoredEntry {
+    version: u32,
+    #[serde(default)]
+    compression: Compression,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl StoredEntry {
+    fn to_bytes(&self) -> Vec<u8> {
+        envelope::encode(
+            Kind::Entry,
+            &[
+                &self.version.to_le_bytes(),
+                &[self.compression.as_byte()],
+                &self.nonce,
+                &self.ciphertext,
+                &self
+                    .encryption
+                    .map(|method| vec![method.as_byte()])
+                    .unwrap_or_default(),
+                if self.header_bound { &[1] } else { &[] },
+            ],
+        )
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if !envelope::is_envelope(bytes) {
+            let legacy: LegacyStoredEntry =
+                serde_json::from_slice(bytes).context("failed to parse entry")?;
+            return Ok(StoredEntry {
+                version: legacy.version,
+                compression: legacy.compression,
+                nonce: decode_base64(&legacy.nonce).context("invalid nonce encoding")?,
+                ciphertext: decode_base64(&legacy.ciphertext)
+                    .context("invalid ciphertext encoding")?,
+                encryption: None,
+                header_bound: false,
+            });
+        }
+
+        let fields = envelope::decode_fields(bytes, Kind::Entry).context("failed to parse entry")?;
+        // 算法和头部认证标记都是后来追加的字段
+        let (encryption, header_bound) = match fields.get(4..) {
+            Some([]) | Some([[]]) => (None, false),
+            Some([[byte]]) => (Some(TextEncryption::from_byte(*byte)?), false),
+            Some([[byte], [1]]) => (Some(TextEncryption::from_byte(*byte)?), true),
+            _ => return Err(anyhow!("failed to parse entry")),
+        };
+        let compression = match fields[1] {
+            [byte] => Compression::from_byte(*byte)?,
+            _ => return Err(anyhow!("failed to parse entry")),
+        };
+        Ok(StoredEntry {
+            version: read_u32(fields[0]).context("failed to parse entry")?,
+            compression,
+            nonce: fields[2].to_vec(),
+            ciphertext: fields[3].to_vec(),
+            encryption,
+            header_bound,
+        })
+    }
+}
+
+fn decode_base64(value: &str) -> Result<Vec<u8>> {
+    Ok(general_purpose::STANDARD_NO_PAD.decode(value)?)
+}
+
+fn read_u32(field: &[u8]) -> Result<u32> {
+    let bytes: [u8; 4] = field.try_into().map_err(|_| anyhow!("invalid integer field"))?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn entry_header_aad(version: u32, compression: Compression, method: TextEncryption) -> Vec<u8> {
+    let mut aad = version.to_le_bytes().to_vec();
+    aad.push(compression.as_byte());
+    aad.push(method.as_byte());
+    aad
+}
+
+// 按所选算法认证加密，返回随机 nonce 和密文
+fn seal(
+    method: TextEncryption,
+    key: &[u8; 32],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<([u8; 12], Vec<u8>)> {
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let payload = Payload {
+        msg: plaintext,
+        aad,
+    };
+    let ciphertext = match method {
+        TextEncryption::Aes256Gcm => {
+            let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| anyhow!("invalid key"))?;
+            #[allow(deprecated)]
+            let nonce = Nonce::from_slice(&nonce_bytes);
+            cipher.encrypt(nonce, payload)
+        }
+        TextEncryption::ChaCha20Poly1305 => {
+            let cipher =
+                ChaCha20Poly1305::new_from_slice(key).map_err(|_| anyhow!("invalid key"))?;
+            #[allow(deprecated)]
+            let nonce = ChaChaNonce::from_slice(&nonce_bytes);
+            cipher.encrypt(nonce, payload)
+        }
+    }
+    .map_err(|_| anyhow!("encryption failed"))?;
+    Ok((nonce_bytes, ciphertext))
+}
+
+fn unseal(
+    method: TextEncryption,
+    key: &[u8; 32],
+    nonce_bytes: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>> {
+    if nonce_bytes.len() != 12 {
+        return Err(anyhow!("invalid nonce length"));
+    }
+    let payload = Payload {
+        msg: ciphertext,
+        aad,
+    };
+    match method {
+        TextEncryption::Aes256Gcm => {
+            let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| anyhow!("invalid key"))?;
+            #[allow(deprecated)]
+            let nonce = Nonce::from_slice(nonce_bytes);
+            cipher.decrypt(nonce, payload)
+        }
+        TextEncryption::ChaCha20Poly1305 => {
+            let cipher =
+                ChaCha20Poly1305::new_from_slice(key).map_err(|_| anyhow!("invalid key"))?;
+            #[allow(deprecated)]
+            let nonce = ChaChaNonce::from_slice(nonce_bytes);
+            cipher.decrypt(nonce, payload)
+        }
+    }
+    .map_err(|_| anyhow!("decryption failed"))
+}
+
+#[derive(Serialize, Deserialize)]
+struct TypedBody {
+    content: String,
+    payload: EntryPayload,
+}
+
+#[derive(Serialize, Deserialize)]
+struct VaultMetadata {
+    version: u32,
+    // 仅版本 1 使用，版本 2 起条目索引存放在 index/ 分片中
+    #[serde(default)]
+    entries: Vec<EntryInfo>,
+    #[serde(default)]
+    shards: BTreeMap<i32, String>,
+    #[serde(default)]
+    journal_id: Option<Uuid>,
+    #[serde(default)]
+    text_encryption: TextEncryption,
+    #[serde(default)]
+    history_head: Option<String>,
+    #[serde(default)]
+    passphrase_history: Vec<PassphraseVerifier>,
+    #[serde(default = "default_passphrase_history_depth")]
+    passphrase_history_depth: usize,
+    #[serde(default)]
+    emergency: Option<EmergencyAccess>,
+    #[serde(default)]
+    policy: VaultPolicy,
+    #[serde(default)]
+    settings: VaultSettings,
+    // 写作提示的自定义题库和作答记录
+    #[serde(default)]
+    prompts: PromptState,
+    // 习惯定义和打卡记录
+    #[serde(default)]
+    habits: HabitState,
+    // 各月份（YYYY-MM）生成的回顾日记，再次生成时更新而不是重复新建
+    #[serde(default)]
+    recaps: BTreeMap<String, Uuid>,
+    // 按日期（YYYY-MM-DD）保存的每日小结
+    #[serde(default)]
+    day_summaries: BTreeMap<String, String>,
+    // 拼写检查的个人词典，人名、地名等不写入系统词典
+    #[serde(default)]
+    dictionary: BTreeSet<String>,
+    // 为 None 表示旧版本尚未建立附件缓存
+    #[serde(default)]
+    attachments: Option<Vec<AttachmentInfo>>,
+}
+
+fn default_passphrase_history_depth() -> usize {
+    DEFAULT_PASSPHRASE_HISTORY_DEPTH
+}
+
+// 历史密码的加盐校验值，只保存在加密元数据中
+#[derive(Clone, Serialize, Deserialize)]
+struct PassphraseVerifier {
+    salt: String,
+    hash: String,
+}
+
+impl PassphraseVerifier {
+    fn new(passphrase: &str) -> Result<Self> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let hash = derive_key(passphrase, &salt, &original_kdf_params())?;
+        Ok(Self {
+            salt: general_purpose::STANDARD_NO_PAD.encode(salt),
+            hash: general_purpose::STANDARD_NO_PAD.encode(hash),
+        })
+    }
+
+    fn matches(&self, passphrase: &str) -> Result<bool> {
+        let salt_vec = general_purpose::STANDARD_NO_PAD
+            .decode(&self.salt)
+            .context("invalid verifier salt")?;
+        let salt: [u8; 16] = salt_vec
+            .try_into()
+            .map_err(|_| anyhow!("invalid verifier salt length"))?;
+        let hash = derive_key(passphrase, &salt, &original_kdf_params())?;
+        Ok(general_purpose::STANDARD_NO_PAD.encode(hash) == self.hash)
+    }
+}
+
+fn passphrase_in_history(passphrase: &str, history: &[PassphraseVerifier]) -> Result<bool> {
+    for verifier in history {
+        if verifier.matches(passphrase)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn stored_salt(stored: &StoredVault) -> Result<[u8; 16]> {
+    stored
+        .salt
+        .as_slice()
+        .try_into()
+        .map_err(|_| anyhow!("invalid salt length"))
+}
+
+pub(crate) fn derive_key(passphrase: &str, salt: &[u8; 16], kdf: &KdfParams) -> Result<[u8; 32]> {
+    let argon = Argon2::new(
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+            .context("invalid argon2 parameters")?,
+    );
+    let mut key = [0u8; 32];
+    argon
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .context("failed to derive key")?;
+    Ok(key)
+}
+
+fn save_vault(
+    path: &Path,
+    salt: &[u8; 16],
+    kdf: &KdfParams,
+    key: &[u8; 32],
+    metadata: &VaultMetadata,
+    timestamp: OffsetDateTime,
+) -> Result<()> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| anyhow!("invalid key"))?;
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(&nonce_bytes);
+
+    let payload = serde_json::to_vec(metadata).context("failed to serialize metadata")?;
+    let ciphertext = cipher
+        .encrypt(nonce, payload.as_ref())
+        .map_err(|_| anyhow!("encryption failed"))?;
+
+    let stored = StoredVault {
+        version: VAULT_VERSION,
+        salt: salt.to_vec(),
+        nonce: nonce_bytes.to_vec(),
+        ciphertext,
+        updated_at: Some(timestamp),
+        kdf: Some(*kdf),
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("failed to create vault directory")?;
+    }
+    permissions::write_private(path, stored.to_bytes()).context("failed to write vault")
+}
+
+pub fn probe_vault(path: &Path) -> VaultProbe {
+    let mut probe = VaultProbe {
+        exists: path.is_file(),
+        compatible: false,
+        version: None,
+        legacy_format: false,
+        updated_at: None,
+        kdf: None,
+        profile: None,
+        error: None,
+    };
+    if !probe.exists {
+        return probe;
+    }
+    if let Some(root) = path.parent() {
+        probe.profile = Some(profile::read(root));
+    }
+
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            probe.error = Some(format!("failed to read vault: {err}"));
+            return probe;
+        }
+    };
+    probe.legacy_format = !envelope::is_envelope(&bytes);
+    let stored = match StoredVault::from_bytes(&bytes) {
+        Ok(stored) => stored,
+        Err(err) => {
+            probe.error = Some(err.to_string());
+            return probe;
+        }
+    };
+
+    probe.version = Some(stored.version);
+    probe.updated_at = stored.updated_at.and_then(|ts| ts.format(&Rfc3339).ok());
+    probe.compatible = vault_file_steps(stored.version).is_ok() && stored_salt(&stored).is_ok();
+    if probe.compatible {
+        probe.kdf = Some(stored_kdf(&stored));
+    } else if stored.version > VAULT_VERSION {
+        probe.error = Some(Message::VaultTooNew.to_string());
+    } else {
+        probe.error = Some("unsupported vault version".to_string());
+    }
+    probe
+}
+
+fn kdf_params() -> KdfParams {
+    KdfParams {
+        algorithm: "argon2id",
+        memory_kib: KDF_MEMORY_KIB,
+        iterations: KDF_ITERATIONS,
+        parallelism: KDF_PARALLELISM,
+    }
+}
+
+pub(crate) fn original_kdf_params() -> KdfParams {
+    let [memory_kib, iterations, parallelism] = ORIGINAL_KDF;
+    KdfParams {
+        algorithm: "argon2id",
+        memory_kib,
+        iterations,
+        parallelism,
+    }
+}
+
+fn stored_kdf(stored: &StoredVault) -> KdfParams {
+    stored.kdf.unwrap_or_else(original_kdf_params)
+}
+
+fn read_kdf_params(raw: &[u8]) -> Result<KdfParams> {
+    if raw.len() != 12 {
+        return Err(anyhow!("invalid key derivation parameters"));
+    }
+    Ok(KdfParams {
+        algorithm: "argon2id",
+        memory_kib: read_u32(&raw[0..4])?,
+        iterations: read_u32(&raw[4..8])?,
+        parallelism: read_u32(&raw[8..12])?,
+    })
+}
+
+// 删除整个日记库：先用密码解锁确认归属，可选地先复制一份加密备份，
+// 再逐个覆盖并删除日记文件，最后删除已空的目录。目录中不属于日记的文件保留
+pub fn delete_vault(
+    metadata_path: PathBuf,
+    passphrase: &str,
+    backup_dir: Option<&Path>,
+) -> Result<DeleteReport> {
+    let manager = VaultManager::default();
+    manager.unlock(passphrase, metadata_path)?;
+    let root = manager.vault_root()?;
+    // 备份失败时不删除任何文件
+    let backup = backup_dir.map(|dir| manager.clone_vault(dir)).transpose()?;
+    // 直接丢弃解锁状态，不再整理元数据或生成紧急归档
+    manager.inner.write().take();
+
+    let mut removed_files = 0;
+    let mut leftover = Vec::new();
+    for path in managed_paths(&root).into_iter().filter(|path| path.exists()) {
+        let files = if path.is_dir() {
+            list_files_recursive(&path)?
+        } else {
+            vec![path.clone()]
+        };
+        for file in files {
+            match shred_file(&file) {
+                Ok(()) => removed_files += 1,
+                Err(_) => leftover.push(display_path(&file)),
+            }
+        }
+        if path.is_dir() && fs::remove_dir_all(&path).is_err() {
+            leftover.push(display_path(&path));
+        }
+    }
+    let _ = fs::remove_file(vault_lock::lock_file_path(&root));
+    let directory_removed = fs::remove_dir(&root).is_ok();
+
+    Ok(DeleteReport {
+        backup,
+        removed_files,
+        leftover,
+        directory_removed,
+    })
+}
+
+// 删除前用随机数据覆盖文件内容。闪存和写时复制文件系统上不保证覆盖到原来的物理块
+fn shred_file(path: &Path) -> Result<()> {
+    let len = fs::metadata(path).context("failed to read file metadata")?.len();
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .context("failed to open file for overwrite")?;
+    let mut buffer = vec![0u8; SHRED_CHUNK_BYTES];
+    let mut remaining = len;
+    while remaining > 0 {
+        let chunk = remaining.min(SHRED_CHUNK_BYTES as u64) as usize;
+        OsRng.fill_bytes(&mut buffer[..chunk]);
+        file.write_all(&buffer[..chunk]).context("failed to overwrite file")?;
+        remaining -= chunk as u64;
+    }
+    file.sync_all().context("failed to flush overwritten file")?;
+    drop(file);
+    fs::remove_file(path).context("failed to remove file")
+}
+
+// 用随机密钥和合成数据在 scratch_dir 中测量各项耗时，结束后删除该目录，不接触真实日记
+pub fn run_benchmarks(scratch_dir: &Path) -> Result<BenchmarkReport> {
+    permissions::create_private_dir_all(scratch_dir)
+        .context("failed to prepare benchmark directory")?;
+    let report = benchmark_in(scratch_dir);
+    let _ = fs::remove_dir_all(scratch_dir);
+    report
+}
+
+fn benchmark_in(scratch_dir: &Path) -> Result<BenchmarkReport> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let kdf = kdf_params();
+    let started = Instant::now();
+    let key = derive_key("benchmark", &salt, &kdf)?;
+    let kdf_ms = elapsed_ms(started.elapsed());
+
+    let samples: Vec<Entry> = (0..BENCHMARK_ENTRY_COUNT)
+        .map(|index| {
+            Entry::new(
+                format!("Benchmark {index}"),
+                sample_text(BENCHMARK_ENTRY_BYTES),
+                TextEncryption::default(),
+            )
+        })
+        .collect();
+    let entries_dir = scratch_dir.join("entries");
+    let entries = SUPPORTED_TEXT_ENCRYPTIONS
+        .iter()
+        .map(|method| benchmark_entries(&entries_dir, &key, *method, &samples))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut attachment = vec![0u8; BENCHMARK_ATTACHMENT_BYTES];
+    OsRng.fill_bytes(&mut attachment);
+    let started = Instant::now();
+    let encrypted = encrypt_image_data(&key, TextEncryption::default(), &attachment)?;
+    let encrypt_elapsed = started.elapsed();
+    let started = Instant::now();
+    decrypt_image_data(&key, &encrypted)?;
+    let decrypt_elapsed = started.elapsed();
+
+    // 整理元数据：写入全部分片和 vault.json
+    let infos: Vec<EntryInfo> = samples.iter().map(|entry| entry.metadata(false)).collect();
+    let years: BTreeSet<i32> = infos.iter().map(shards::shard_year).collect();
+    let mut hashes = BTreeMap::new();
+    let started = Instant::now();
+    shards::write_years(
+        &scratch_dir.join(shards::INDEX_DIR),
+        &key,
+        &infos,
+        years,
+        &mut hashes,
+    )?;
+    let metadata = VaultMetadata {
+        version: METADATA_VERSION,
+        entries: Vec::new(),
+        shards: hashes,
+        journal_id: Some(Uuid::new_v4()),
+        text_encryption: TextEncryption::default(),
+        history_head: None,
+        passphrase_history: Vec::new(),
+        passphrase_history_depth: DEFAULT_PASSPHRASE_HISTORY_DEPTH,
+        emergency: None,
+        policy: VaultPolicy::default(),
+        settings: VaultSettings::default(),
+        prompts: PromptState::default(),
+        habits: HabitState::default(),
+        recaps: BTreeMap::new(),
+        day_summaries: BTreeMap::new(),
+        dictionary: BTreeSet::new(),
+        attachments: Some(Vec::new()),
+    };
+    save_vault(
+        &vault_file_path(scratch_dir.to_path_buf()),
+        &salt,
+        &kdf,
+        &key,
+        &metadata,
+        OffsetDateTime::now_utc(),
+    )?;
+    let metadata_save_ms = elapsed_ms(started.elapsed());
+
+    // 单条改动追加到日志，即日常保存的开销
+    let journal_path = journal::journal_file_path(scratch_dir);
+    let journal_id = Uuid::new_v4();
+    journal::reset(&journal_path, &journal_id)?;
+    let started = Instant::now();
+    for (seq, info) in infos.iter().enumerate() {
+        journal::append(
+            &journal_path,
+            &key,
+            &journal_id,
+            seq as u64,
+            &[JournalChange::Upsert {
+                entry: Box::new(info.clone()),
+            }],
+        )?;
+    }
+    let journal_append_ms = elapsed_ms(started.elapsed()) / infos.len() as f64;
+
+    Ok(BenchmarkReport {
+        platform: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+        threads: rayon::current_num_threads(),
+        kdf,
+        kdf_ms,
+        entries,
+        attachment: AttachmentBenchmark {
+            bytes: attachment.len(),
+            encrypt_mb_per_sec: throughput(attachment.len(), encrypt_elapsed),
+            decrypt_mb_per_sec: throughput(attachment.len(), decrypt_elapsed),
+        },
+        metadata_entries: infos.len(),
+        metadata_save_ms,
+        journal_append_ms,
+    })
+}
+
+// 逐条写入再逐条读回，包含压缩与文件读写，接近实际保存和打开条目的开销
+fn benchmark_entries(
+    entries_dir: &Path,
+    key: &[u8; 32],
+    method: TextEncryption,
+    samples: &[Entry],
+) -> Result<EntryBenchmark> {
+    let total_bytes: usize = samples.iter().map(|entry| entry.content.len()).sum();
+    let started = Instant::now();
+    for entry in samples {
+        save_entry_content(entries_dir, key, method, entry)?;
+    }
+    let encrypt_elapsed = started.elapsed();
+    let started = Instant::now();
+    for entry in samples {
+        load_entry_content(entries_dir, key, method, &entry.id)?;
+    }
+    let decrypt_elapsed = started.elapsed();
+
+    let count = samples.len().max(1) as f64;
+    Ok(EntryBenchmark {
+        encryption: method,
+        entry_count: samples.len(),
+        entry_bytes: BENCHMARK_ENTRY_BYTES,
+        encrypt_ms_per_entry: elapsed_ms(encrypt_elapsed) / count,
+        decrypt_ms_per_entry: elapsed_ms(decrypt_elapsed) / count,
+        encrypt_mb_per_sec: throughput(total_bytes, encrypt_elapsed),
+        decrypt_mb_per_sec: throughput(total_bytes, decrypt_elapsed),
+    })
+}
+
+// 随机拼接中英文词语，压缩率接近真实日记
+fn sample_text(target_bytes: usize) -> String {
+    let mut text = String::with_capacity(target_bytes + 16);
+    while text.len() < target_bytes {
+        let word = BENCHMARK_WORDS[OsRng.next_u32() as usize % BENCHMARK_WORDS.len()];
+        text.push_str(word);
+        text.push(' ');
+    }
+    text
+}
+
+// 在 scratch_dir 中用临时日记库和随机密钥逐项检查，结束后删除该目录。
+// vault_dir 是用户选择的日记目录，只检查能否写入和权限，不读取其中的日记
+pub fn run_self_test(scratch_dir: &Path, vault_dir: Option<&Path>) -> Result<SelfTestReport> {
+    permissions::create_private_dir_all(scratch_dir)
+        .context("failed to prepare self-test directory")?;
+    let entries_dir = scratch_dir.join("entries");
+    let mut key = [0u8; 32];
+    OsRng.fill_bytes(&mut key);
+
+    let mut checks = vec![self_test_check(SelfTestKind::Random, None, check_random)];
+    for method in SUPPORTED_TEXT_ENCRYPTIONS {
+        checks.push(self_test_check(SelfTestKind::TextEncryption, Some(method), || {
+            check_text_roundtrip(&entries_dir, &key, method)
+        }));
+    }
+    checks.push(self_test_check(SelfTestKind::Attachment, None, || {
+        check_attachment_roundtrip(&key)
+    }));
+    checks.push(self_test_check(SelfTestKind::WrongKeyRejected, None, || {
+        check_wrong_key_rejected(&entries_dir, &key)
+    }));
+    checks.push(self_test_check(SelfTestKind::VaultRoundtrip, None, || {
+        check_vault_roundtrip(&scratch_dir.join("vault"))
+    }));
+    if let Some(dir) = vault_dir {
+        checks.push(self_test_check(SelfTestKind::VaultDirectory, None, || {
+            check_vault_directory(dir)
+        }));
+    }
+    let _ = fs::remove_dir_all(scratch_dir);
+
+    Ok(SelfTestReport {
+        platform: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+        passed: checks.iter().all(|check| check.passed),
+        checks,
+    })
+}
+
+fn self_test_check(
+    kind: SelfTestKind,
+    encryption: Option<TextEncryption>,
+    check: impl FnOnce() -> Result<Option<String>>,
+) -> SelfTestCheck {
+    let started = Instant::now();
+    let result = check();
+    let duration_ms = elapsed_ms(started.elapsed());
+    let (passed, detail) = match result {
+        Ok(detail) => (true, detail),
+        Err(err) => (false, Some(format!("{err:#}"))),
+    };
+    SelfTestCheck {
+        kind,
+        encryption,
+        passed,
+        detail,
+        duration_ms,
+    }
+}
+
+// 粗略检查系统随机数：两次取样不同、覆盖几乎所有字节值，且比特 1 的比例接近一半
+fn check_random() -> Result<Option<String>> {
+    let mut first = vec![0u8; SELF_TEST_RANDOM_BYTES];
+    let mut second = vec![0u8; SELF_TEST_RANDOM_BYTES];
+    OsRng
+        .try_fill_bytes(&mut first)
+        .and_then(|_| OsRng.try_fill_bytes(&mut second))
+        .map_err(|err| anyhow!("system random number generator unavailable: {err}"))?;
+    if first == second {
+        return Err(anyhow!("random samples repeat"));
+    }
+    let distinct = first.iter().collect::<BTreeSet<_>>().len();
+    if distinct < 200 {
+        return Err(anyhow!("random bytes cover only {distinct} of 256 values"));
+    }
+    let ones: u32 = first.iter().map(|byte| byte.count_ones()).sum();
+    let ratio = f64::from(ones) / (first.len() * 8) as f64;
+    if !(0.45..=0.55).contains(&ratio) {
+        return Err(anyhow!("random bits are biased: {ratio:.3} ones"));
+    }
+    Ok(Some(format!("{distinct} distinct bytes, {ratio:.3} ones")))
+}
+
+fn check_text_roundtrip(
+    entries_dir: &Path,
+    key: &[u8; 32],
+    method: TextEncryption,
+) -> Result<Option<String>> {
+    let entry = Entry::new("Self-test", sample_text(SELF_TEST_ENTRY_BYTES), method);
+    save_entry_content(entries_dir, key, method, &entry)?;
+    let content = load_entry_content(entries_dir, key, method, &entry.id)?;
+    if content != entry.content {
+        return Err(anyhow!("decrypted content does not match"));
+    }
+    Ok(None)
+}
+
+fn check_attachment_roundtrip(key: &[u8; 32]) -> Result<Option<String>> {
+    let mut data = vec![0u8; SELF_TEST_ATTACHMENT_BYTES];
+    OsRng.fill_bytes(&mut data);
+    for method in SUPPORTED_TEXT_ENCRYPTIONS {
+        let encrypted = encrypt_image_data(key, method, &data)?;
+        if decrypt_image_data(key, &encrypted)? != data {
+            return Err(anyhow!("decrypted attachment does not match"));
+        }
+    }
+    Ok(None)
+}
+
+// 用另一把密钥解密必须失败，否则认证加密没有生效
+fn check_wrong_key_rejected(entries_dir: &Path, key: &[u8; 32]) -> Result<Option<String>> {
+    let mut other = [0u8; 32];
+    OsRng.fill_bytes(&mut other);
+    for method in SUPPORTED_TEXT_ENCRYPTIONS {
+        let entry = Entry::new("Self-test", "wrong key", method);
+        save_entry_content(entries_dir, key, method, &entry)?;
+        if load_entry_content(entries_dir, &other, method, &entry.id).is_ok() {
+            return Err(anyhow!("{method:?} accepted a wrong key"));
+        }
+    }
+    Ok(None)
+}
+
+// 完整走一遍新建、写入、锁定、解锁、读回，包含密钥派生
+fn check_vault_roundtrip(dir: &Path) -> Result<Option<String>> {
+    let mut passphrase = [0u8; 16];
+    OsRng.fill_bytes(&mut passphrase);
+    let passphrase = general_purpose::STANDARD_NO_PAD.encode(passphrase);
+    let path = vault_file_path(dir.to_path_buf());
+
+    let manager = VaultManager::default();
+    manager.create(&passphrase, path.clone(), None, None)?;
+    let entry = manager.create_entry("Self-test", "vault roundtrip", None)?;
+    manager.lock()?;
+    manager.unlock(&passphrase, path)?;
+    let loaded = manager.load_entry(entry.id)?;
+    let report = manager.verify_vault_integrity()?;
+    manager.lock()?;
+    if loaded.content != entry.content {
+        return Err(anyhow!("entry changed after unlocking again"));
+    }
+    if !report.history.valid {
+        return Err(anyhow!("history chain is invalid"));
+    }
+    Ok(None)
+}
+
+fn check_vault_directory(dir: &Path) -> Result<Option<String>> {
+    if !dir.is_dir() {
+        return Err(anyhow!("{} is not a directory", display_path(dir)));
+    }
+    let probe = dir.join(format!(".self-test-{}", Uuid::new_v4()));
+    let written = permissions::write_private(&probe, b"self-test")
+        .and_then(|_| fs::read(&probe).context("failed to read probe file"));
+    let _ = fs::remove_file(&probe);
+    if written? != b"self-test" {
+        return Err(anyhow!("probe file content changed"));
+    }
+
+    let mut world_readable = Vec::new();
+    for path in managed_paths(dir) {
+        if path.exists() {
+            world_readable.extend(permissions::find_world_readable(&path)?);
+        }
+    }
+    if let Some(first) = world_readable.first() {
+        return Err(anyhow!(
+            "{} files are readable by other users, e.g. {}",
+            world_readable.len(),
+            display_path(first)
+        ));
+    }
+    Ok(Some(display_path(dir)))
+}
+
+fn elapsed_ms(elapsed: Duration) -> f64 {
+    elapsed.as_secs_f64() * 1000.0
+}
+
+fn throughput(bytes: usize, elapsed: Duration) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64().max(f64::EPSILON)
+}
+
+fn load_vault(path: &PathBuf) -> Result<StoredVault> {
+    let bytes = fs::read(path).context("failed to read vault")?;
+    let mut stored = StoredVault::from_bytes(&bytes)?;
+    // version 仍是文件中的版本，解锁时据此报告升级；保存时总是写入当前版本
+    let steps = vault_file_steps(stored.version)?;
+    migrations::apply(&mut stored, &steps)?;
+    Ok(stored)
+}
+
+fn vault_file_steps(version: u32) -> Result<Vec<&'static migrations::Step<StoredVault>>> {
+    migrations::plan(
+        migrations::VAULT_FILE_STEPS,
+        version,
+        VAULT_VERSION,
+        "unsupported vault version",
+    )
+}
+
+fn step_descriptions<A, B>(
+    file_steps: &[&migrations::Step<A>],
+    steps: &[&migrations::Step<B>],
+) -> Vec<String> {
+    file_steps
+        .iter()
+        .map(|step| step.description)
+        .chain(steps.iter().map(|step| step.description))
+        .map(str::to_string)
+        .collect()
+}
+
+fn decrypt_metadata(stored: &StoredVault, key: &[u8; 32]) -> Result<VaultMetadata> {
+    let metadata = decrypt_metadata_value(stored, key)?;
+    serde_json::from_value(metadata).context("invalid metadata")
+}
+
+// 升级格式前需要按原始 JSON 处理，不能直接解析为当前结构
+fn decrypt_metadata_value(stored: &StoredVault, key: &[u8; 32]) -> Result<serde_json::Value> {
+    if stored.nonce.len() != 12 {
+        return Err(anyhow!("invalid nonce length"));
+    }
+
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| anyhow!("invalid key"))?;
+    #[allow(deprecated)]
+    let nonce = Nonce::from_slice(&stored.nonce);
+    let plaintext = cipher
+        .decrypt(nonce, stored.ciphertext.as_ref())
+        .map_err(|_| DecryptionFailed)?;
+    serde_json::from_slice(&plaintext).context("invalid metadata")
+}
+
+// 元数据升级只改写 vault.json、索引分片和日志，升级前把它们复制一份
+fn backup_before_migration(root: &Path, version: u32) -> Result<PathBuf> {
+    let stamp = OffsetDateTime::now_utc().unix_timestamp();
+    let backup = root
+        .join(MIGRATION_BACKUP_DIR)
+        .join(format!("v{version}-{stamp}"));
+    permissions::create_private_dir_all(&backup).context("failed to create migration backup")?;
+    let files = [
+        vault_file_path(root.to_path_buf()),
+        journal::journal_file_path(root),
+    ];
+    for file in files.iter().filter(|file| file.exists()) {
+        if let Some(name) = file.file_name() {
+            permissions::copy_private(file, &backup.join(name))
+                .context("failed to create migration backup")?;
+        }
+    }
+    let index_dir = root.join(shards::INDEX_DIR);
+    if index_dir.exists() {
+        copy_directory_recursive(&index_dir, &backup.join(shards::INDEX_DIR))?;
+    }
+    if let Err(err) = prune_migration_backups(&root.join(MIGRATION_BACKUP_DIR)) {
+        tracing::warn!(error = %err, "failed to prune migration backups");
+    }
+    Ok(backup)
+}
+
+fn remove_migration_backups(root: &Path) -> Result<()> {
+    let dir = root.join(MIGRATION_BACKUP_DIR);
+    if !dir.exists() {
+        return Ok(());
+    }
+    fs::remove_dir_all(&dir).context("failed to remove migration backups")
+}
+
+// 备份目录名为 v<版本>-<时间戳>，按时间戳删掉较早的备份
+fn prune_migration_backups(dir: &Path) -> Result<()> {
+    let mut backups = fs::read_dir(dir)
+        .context("failed to read migration backups")?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?;
+            let stamp: i64 = name.rsplit_once('-')?.1.parse().ok()?;
+            Some((stamp, path))
+        })
+        .collect::<Vec<_>>();
+    backups.sort();
+    let excess = backups.len().saturating_sub(MIGRATION_BACKUPS_KEPT);
+    for (_, path) in backups.drain(..excess) {
+        fs::remove_dir_all(&path).context("failed to remove migration backup")?;
+    }
+    Ok(())
+}
+
+// 旧日记先按主口令直接派生主密钥，解不开元数据时再依次尝试各用户的密钥槽；
+// 有管理员槽位时主密钥是随机的，只能通过槽位解开。同时返回解开主密钥的槽位
+fn master_key(
+    root: &Path,
+    stored: &StoredVault,
+    passphrase: &str,
+) -> Result<([u8; 32], Option<Uuid>)> {
+    let kdf = stored_kdf(stored);
+    if !key_slots::has_admin(root)? {
+        let key = derive_key(passphrase, &stored_salt(stored)?, &kdf)?;
+        match decrypt_metadata_value(stored, &key) {
+            Ok(_) => return Ok((key, None)),
+            Err(err) if !err.is::<DecryptionFailed>() => return Err(err),
+            Err(_) => {}
+        }
+    }
+    match key_slots::unwrap_key(root, passphrase, &kdf)? {
+        Some((id, key)) => Ok((key, Some(id))),
+        None => Err(DecryptionFailed.into()),
+    }
+}
+
+// 预检：用密码解密元数据，列出解锁时将要执行的升级步骤，不修改任何文件
+pub fn plan_migration(metadata_path: &Path, passphrase: &str) -> Result<MigrationReport> {
+    if !metadata_path.exists() {
+        return Err(VaultNotFound.into());
+    }
+    let stored = load_vault(&metadata_path.to_path_buf())?;
+    let root = metadata_path.parent().unwrap_or(metadata_path);
+    let (key, _) = master_key(root, &stored, passphrase)?;
+    let metadata = decrypt_metadata_value(&stored, &key)?;
+    let version = migrations::version(&metadata)?;
+    let steps = migrations::plan_metadata(version, METADATA_VERSION)?;
+    Ok(MigrationReport {
+        from_version: version,
+        to_version: METADATA_VERSION,
+        steps: step_descriptions(&vault_file_steps(stored.version)?, &steps),
+        backup: None,
+    })
+}
+
+fn save_entry_content(
+    entries_dir: &Path,
+    key: &[u8; 32],
+    method: TextEncryption,
+    entry: &Entry,
+) -> Result<()> {
+    let path = entry_file_path(entries_dir, &entry.id);
+    save_entry_content_to_path(&path, key, method, entry)
+}
+
+fn load_entry_content(
+    entries_dir: &Path,
+    key: &[u8; 32],
+    method: TextEncryption,
+    id: &Uuid,
+) -> Result<String> {
+    load_entry_body(entries_dir, key, method, id).map(|(content, _)| content)
+}
+
+// 同时取出结构化内容；需要重写正文文件的地方用它，避免丢失结构化内容
+fn load_entry_body(
+    entries_dir: &Path,
+    key: &[u8; 32],
+    method: TextEncryption,
+    id: &Uuid,
+) -> Result<(String, Option<EntryPayload>)> {
+    let path = entry_file_path(entries_dir, id);
+    if !path.exists() {
+        return Err(anyhow!("entry content missing"));
+    }
+    let bytes = fs::read(&path).context("failed to read entry")?;
+    let stored = StoredEntry::from_bytes(&bytes)?;
+    // 版本 1 与 2 都是当前格式，更早的版本解密后逐级升级到版本 1
+    let steps = if stored.version == TYPED_ENTRY_VERSION {
+        Vec::new()
+    } else {
+        migrations::plan(
+            migrations::ENTRY_FILE_STEPS,
+            stored.version,
+            ENTRY_VERSION,
+            "unsupported entry version",
+        )?
+    };
+
+    let nonce_bytes = stored.nonce;
+    if nonce_bytes.len() != 12 {
+        return Err(anyhow!("invalid nonce length"));
+    }
+    let ciphertext = stored.ciphertext;
+
+    let method = stored.encryption.unwrap_or(method);
+    let aad = if stored.header_bound {
+        entry_header_aad(stored.version, stored.compression, method)
+    } else {
+        Vec::new()
+    };
+    let plaintext = unseal(method, key, &nonce_bytes, &aad, &ciphertext)?;
+
+    let mut plaintext = decompress(stored.compression, plaintext)?;
+    migrations::apply(&mut plaintext, &steps)?;
+    if stored.version == TYPED_ENTRY_VERSION {
+        let body: TypedBody =
+            serde_json::from_slice(&plaintext).context("invalid entry content")?;
+        return Ok((body.content, Some(body.payload)));
+    }
+    let content = String::from_utf8(plaintext).context("invalid entry content")?;
+    Ok((content, None))
+}
+
+fn entry_file_path(entries_dir: &Path, id: &Uuid) -> PathBuf {
+    let mut path = entries_dir.to_path_buf();
+    path.push(format!("{id}.bin", id = id));
+    path
+}
+
+fn display_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+pub fn vault_file_path(mut base: PathBuf) -> PathBuf {
+    base.push("vault.json");
+    base
+}
+
+fn fill_content_stats(
+    entries: &mut [EntryInfo],
+    entries_dir: &Path,
+    key: &[u8; 32],
+    sentiment: bool,
+) -> BTreeSet<i32> {
+    // 内容读不出来的条目保持未统计，下次解锁再试
+    entries
+        .par_iter_mut()
+        .filter(|info| {
+            info.word_count.is_none()
+                || info.preview.is_none()
+                || (sentiment && info.sentiment.is_none())
+        })
+        .filter_map(|info| {
+            let content = load_entry_content(entries_dir, key, info.encryption, &info.id).ok()?;
+            info.word_count = Some(search::word_count(&content));
+            info.preview = Some(search::preview(&content));
+            if sentiment {
+                info.sentiment = Some(sentiment::score(&content));
+            }
+            Some(shards::shard_year(info))
+        })
+        .collect()
+}
+
+// 未开启情感分析时不保留旧分数，正文已经变了
+fn score_sentiment(settings: &VaultSettings, content: &str) -> Option<f64> {
+    settings.sentiment_analysis.then(|| sentiment::score(content))
+}
+
+fn bucket_start(day: Date, bucket: TrendBucket) -> Date {
+    match bucket {
+        TrendBucket::Day => day,
+        TrendBucket::Week => week_start(day),
+        TrendBucket::Month => day.replace_day(1).unwrap_or(day),
+    }
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().sum::<f64>() / values.len() as f64)
+}
+
+// 皮尔逊相关系数
+fn correlation(pairs: &[(f64, f64)]) -> Option<f64> {
+    if pairs.len() < 2 {
+        return None;
+    }
+    let xs: Vec<f64> = pairs.iter().map(|(x, _)| *x).collect();
+    let ys: Vec<f64> = pairs.iter().map(|(_, y)| *y).collect();
+    let (mean_x, mean_y) = (mean(&xs)?, mean(&ys)?);
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (x, y) in pairs {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_x * variance_y).sqrt())
+}
+
+fn month_summary(
+    vault: &UnlockedVault,
+    year: i32,
+    month: Month,
+    offset: UtcOffset,
+) -> MonthRecap {
+    let in_month = |date: Date| date.year() == year && date.month() == month;
+    let entries: Vec<&EntryInfo> = vault
+        .metadata
+        .iter()
+        .filter(|info| !vault.recaps.values().any(|id| *id == info.id))
+        .filter(|info| in_month(info.entry_day(offset)))
+        .collect();
+
+    let days: BTreeSet<Date> = entries.iter().map(|info| info.entry_day(offset)).collect();
+    let moods: Vec<f64> = entries
+        .iter()
+        .filter_map(|info| info.mood.map(f64::from))
+        .collect();
+    let mut tags: BTreeMap<&str, usize> = BTreeMap::new();
+    for tag in entries.iter().flat_map(|info| &info.tags) {
+        *tags.entry(tag).or_default() += 1;
+    }
+    let mut tags: Vec<(&str, usize)> = tags.into_iter().collect();
+    tags.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+    let mut photos: Vec<&AttachmentInfo> = vault
+        .attachments
+        .iter()
+        .filter(|attachment| attachment.width.is_some())
+        .filter(|attachment| in_month(attachment.stored_at.to_offset(offset).date()))
+        .collect();
+    photos.sort_by_key(|attachment| attachment.stored_at);
+
+    let mut highlights: Vec<&EntryInfo> = entries
+        .iter()
+        .copied()
+        .filter(|info| info.preview.as_deref().is_some_and(|preview| !preview.is_empty()))
+        .collect();
+    highlights.sort_by_key(|info| std::cmp::Reverse(info.word_count));
+    highlights.truncate(recap::MAX_RECAP_LINES);
+    highlights.sort_by_key(|info| (info.entry_day(offset), info.created_at));
+
+    MonthRecap {
+        year,
+        month: month as u8,
+        entries: entries.len() as u32,
+        words: entries
+            .iter()
+            .map(|info| u64::from(info.word_count.unwrap_or_default()))
+            .sum(),
+        active_days: days.len() as u32,
+        average_mood: mean(&moods),
+        top_tags: tags
+            .into_iter()
+            .take(recap::MAX_RECAP_TAGS)
+            .map(|(tag, count)| TagCount {
+                tag: tag.to_string(),
+                count,
+            })
+            .collect(),
+        photos: photos
+            .into_iter()
+            .take(recap::MAX_RECAP_PHOTOS)
+            .map(|attachment| attachment.path.clone())
+            .collect(),
+        highlights: highlights
+            .into_iter()
+            .map(|info| RecapLine {
+                id: info.id,
+                date: info.entry_day(offset).to_string(),
+                title: info.title.clone(),
+                preview: info.preview.clone().unwrap_or_default(),
+            })
+            .collect(),
+        entry: None,
+    }
+}
+
+// on_this_day 与 anniversaries 共用，year_matches 按日记所在的年份筛选
+fn same_day_entries(
+    vault: &UnlockedVault,
+    today: Date,
+    offset: UtcOffset,
+    year_matches: impl Fn(i32) -> bool,
+) -> Vec<EntryInfo> {
+    let leap_day_included = today.month() == time::Month::February
+        && today.day() == 28
+        && !time::util::is_leap_year(today.year());
+    let mut entries: Vec<EntryInfo> = vault
+        .metadata
+        .iter()
+        .filter(|info| {
+            let day = info.entry_day(offset);
+            day.year() < today.year()
+                && year_matches(day.year())
+                && day.month() == today.month()
+                && (day.day() == today.day() || leap_day_included && day.day() == 29)
+        })
+        .cloned()
+        .collect();
+    entries.sort_by_key(|info| std::cmp::Reverse((info.entry_day(offset), info.created_at)));
+    entries
+}
+
+fn week_start(date: Date) -> Date {
+    date - time::Duration::days(date.weekday().number_days_from_monday().into())
+}
+
+fn streak(
+    counts: &BTreeMap<Date, u32>,
+    current: Date,
+    goal: u32,
+    previous: impl Fn(Date) -> Option<Date>,
+) -> u32 {
+    let reached = |date: &Date| counts.get(date).is_some_and(|count| *count >= goal);
+    let mut next = if reached(&current) {
+        Some(current)
+    } else {
+        previous(current)
+    };
+    let mut streak = 0;
+    while let Some(date) = next.filter(reached) {
+        streak += 1;
+        next = previous(date);
+    }
+    streak
+}
+
+// 新建与批量新建共用，created_at 由调用方提供时同时作为更新时间
+fn build_entry(
+    item: NewEntry,
+    default_method: TextEncryption,
+    now: OffsetDateTime,
+) -> Result<Entry> {
+    let method = item.encryption.unwrap_or(default_method);
+    if !SUPPORTED_TEXT_ENCRYPTIONS.contains(&method) {
+        return Err(anyhow!("unsupported text encryption method"));
+    }
+    let (title, content) = match &item.payload {
+        Some(payload) if item.title.trim().is_empty() => {
+            (payload.default_title(), payload.to_markdown())
+        }
+        Some(payload) => (item.title, payload.to_markdown()),
+        None => (item.title, item.content),
+    };
+    let mut entry = Entry::new(title, content, method).at(now);
+    entry.folder = item.folder;
+    entry.tags = unique_tags(item.tags);
+    entry.mood = item.mood;
+    entry.entry_date = item.entry_date;
+    entry.location = item.location;
+    entry.weather = item.weather;
+    entry.payload = item.payload;
+    if let Some(created_at) = item.created_at {
+        entry.created_at = created_at;
+        entry.updated_at = created_at;
+    }
+    Ok(entry)
+}
+
+// 去掉重复标签，保留首次出现的顺序
+fn unique_tags(tags: Vec<String>) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    tags.into_iter()
+        .filter(|tag| seen.insert(tag.clone()))
+        .collect()
+}
+
+fn folder_matches(info: &EntryInfo, folder: Option<&str>, include_subfolders: bool) -> bool {
+    let Some(folder) = folder else {
+        return true;
+    };
+    match info.folder.as_deref() {
+        Some(current) if current == folder => true,
+        Some(current) => {
+            include_subfolders
+                && current
+                    .strip_prefix(folder)
+                    .is_some_and(|rest| rest.starts_with('/'))
+        }
+        None => false,
+    }
+}
+
+// 附件路径在正文里可能以任一种分隔符出现
+fn references_attachment(content: &str, paths: &[&str]) -> bool {
+    paths.iter().any(|path| {
+        content.contains(path) || content.contains(&path.replace('\\', "/"))
+    })
+}
+
+fn attachment_target(vault: &UnlockedVault, extension: &str) -> Result<(PathBuf, PathBuf)> {
+    let now = OffsetDateTime::now_utc();
+    let year = now.year();
+    let month: u8 = now.month().into();
+
+    let mut target_dir = vault.attachments_dir.clone();
+    target_dir.push(year.to_string());
+    target_dir.push(format!("{:02}", month));
+    permissions::create_private_dir_all(&target_dir)
+        .context("failed to prepare attachment directory")?;
+
+    let ext = if extension.is_empty() {
+        "bin"
+    } else {
+        extension
+    };
+    let filename = format!("{id}.{ext}", id = Uuid::new_v4(), ext = ext);
+    let target_path = target_dir.join(filename);
+
+    let root = vault
+        .path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| vault.path.clone());
+    let relative = target_path
+        .strip_prefix(&root)
+        .unwrap_or(&target_path)
+        .to_path_buf();
+
+    Ok((target_path, relative))
+}
+
+fn infer_image_extension(name: Option<&str>, mime: Option<&str>) -> String {
+    if let Some(name) = name {
+        if let Some(ext) = Path::new(name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.trim_start_matches('.'))
+        {
+            let lowered = ext.to_ascii_lowercase();
+            if !lowered.is_empty() {
+                return lowered;
+            }
+        }
+    }
+
+    if let Some(mime) = mime {
+        match mime.to_ascii_lowercase().as_str() {
+            "image/png" => return "png".into(),
+            "image/jpeg" | "image/jpg" => return "jpg".into(),
+            "image/gif" => return "gif".into(),
+            "image/webp" => return "webp".into(),
+            "image/bmp" => return "bmp".into(),
+            "image/svg+xml" => return "svg".into(),
+            _ => {}
+        }
+    }
+
+    "bin".into()
+}
+
+// 图片用 AES-256-GCM 加密，封装字段为 [压缩方式, nonce, 密文]，压缩方式作为附加数据一并认证
+// 信封字段：算法、压缩方式、nonce、密文，前两个字节作为附加数据参与认证
+fn encrypt_image_data(key: &[u8; 32], method: TextEncryption, data: &[u8]) -> Result<Vec<u8>> {
+    // 常见图片格式本身已压缩，只有压缩后确实更小（如 SVG、BMP）才使用
+    let (compression, buffer) = compress(data)?;
+    let header = [method.as_byte(), compression.as_byte()];
+    let (nonce_bytes, ciphertext) = seal(method, key, &header, &buffer)?;
+    Ok(envelope::encode(
+        Kind::Image,
+        &[&header[..1], &header[1..], &nonce_bytes, &ciphertext],
+    ))
+}
+
+fn is_encrypted_image(bytes: &[u8]) -> bool {
+    envelope::is_kind(bytes, Kind::Image) || bytes.starts_with(IMAGE_MAGIC_PREFIX)
+}
+
+fn decrypt_image_data(key: &[u8; 32], encrypted: &[u8]) -> Result<Vec<u8>> {
+    if envelope::is_kind(encrypted, Kind::Image) {
+        let fields = envelope::decode_fields(encrypted, Kind::Image)
+            .context("invalid encrypted image")?;
+        // 早先的信封没有算法字段，固定为 AES-256-GCM，只认证压缩方式
+        let (method, compression, nonce, ciphertext, aad) = match fields.as_slice() {
+            [[method], [compression], nonce, ciphertext] => (
+                TextEncryption::from_byte(*method)?,
+                *compression,
+                *nonce,
+                *ciphertext,
+                vec![*method, *compression],
+            ),
+            [[compression], nonce, ciphertext] => (
+                TextEncryption::Aes256Gcm,
+                *compression,
+                *nonce,
+                *ciphertext,
+                vec![*compression],
+            ),
+            _ => return Err(anyhow!("invalid encrypted image")),
+        };
+        let compression = Compression::from_byte(compression)?;
+        let plaintext = unseal(method, key, nonce, &aad, ciphertext)?;
+        return decompress(compression, plaintext);
+    }
+
+    // 以下为旧版 AES-256-CTR 格式，密文没有认证，只能靠解压上限挡住异常数据
+    // 检查最小长度：魔数(8) + IV(16)
+    if encrypted.len() < IMAGE_MAGIC_PREFIX.len() + 16 {
+        return Err(anyhow!("invalid encrypted image: too short"));
+    }
+
+    // 检查魔数
+    if &encrypted[..IMAGE_MAGIC_PREFIX.len()] != IMAGE_MAGIC_PREFIX {
+        // 不是加密图片，可能是旧版本的明文图片
+        return Ok(encrypted.to_vec());
+    }
+
+    let mut offset = IMAGE_MAGIC_PREFIX.len();
+    let (method, marker_len) = ImageEncryption::detect(&encrypted[offset..])
+        .unwrap_or((ImageEncryption::Aes256Ctr, 0));
+    offset += marker_len;
+    let compression = if marker_len > 0 && encrypted[offset..].starts_with(IMAGE_ZSTD_MARKER) {
+        offset += IMAGE_ZSTD_MARKER.len();
+        Compression::Zstd
+    } else {
+        Compression::None
+    };
+
+    if encrypted.len() < offset + 16 {
+        return Err(anyhow!("invalid encrypted image: missing iv"));
+    }
+
+    // 提取 IV
+    let iv_end = offset + 16;
+    let mut iv = [0u8; 16];
+    iv.copy_from_slice(&encrypted[offset..iv_end]);
+
+    // 提取密文
+    let ciphertext = &encrypted[iv_end..];
+    let mut buffer = ciphertext.to_vec();
+
+    // 按照标记的算法解密
+    match method {
+        ImageEncryption::Aes256Ctr => {
+            let mut cipher = Aes256Ctr::new(key.into(), &iv.into());
+            cipher.apply_keystream(&mut buffer);
+        }
+    }
+
+    decompress(compression, buffer)
+}
+
+// 压缩后不比原文小（例如很短的条目）时保留原文
+fn compress(data: &[u8]) -> Result<(Compression, Vec<u8>)> {
+    let compressed = zstd::encode_all(data, ZSTD_LEVEL).context("failed to compress data")?;
+    if compressed.len() < data.len() {
+        Ok((Compression::Zstd, compressed))
+    } else {
+        Ok((Compression::None, data.to_vec()))
+    }
+}
+
+fn decompress(compression: Compression, data: Vec<u8>) -> Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(data),
+        Compression::Zstd => {
+            let decoder =
+                zstd::stream::Decoder::new(data.as_slice()).context("failed to decompress data")?;
+            let mut output = Vec::new();
+            decoder
+                .take(MAX_DECOMPRESSED_BYTES + 1)
+                .read_to_end(&mut output)
+                .context("failed to decompress data")?;
+            if output.len() as u64 > MAX_DECOMPRESSED_BYTES {
+                return Err(anyhow!("decompressed data is too large"));
+            }
+            Ok(output)
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use anyhow::{Context, Result};
+use std::fs::{File, TryLockError};
+use std::path::{Path, PathBuf};
+
+use crate::i18n::Message;
+use crate::permissions;
+use crate::vault::Conflict;
+
+// 解锁期间持有的咨询锁，防止另一个进程（例如命令行工具）同时写同一个日记库。
+// 进程退出时由系统释放，残留的锁文件不影响下次打开
+const LOCK_FILE: &str = "vault.lock";
+
+pub fn lock_file_path(root: &Path) -> PathBuf {
+    root.join(LOCK_FILE)
+}
+
+// 句柄关闭即释放锁
+#[derive(Debug)]
+pub struct VaultLock {
+    _file: File,
+}
+
+// 已被其他句柄持有时拒绝；文件系统不支持加锁时只记录警告，照常打开
+pub fn acquire(root: &Path) -> Result<Option<VaultLock>> {
+    let file = permissions::open_private_append(&lock_file_path(root))
+        .context("failed to open vault lock")?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(VaultLock { _file: file })),
+        Err(TryLockError::WouldBlock) => Err(Conflict(Message::VaultInUse).into()),
+        Err(TryLockError::Error(err)) => {
+            tracing::warn!(error = %err, "file locking is unsupported, opened without a lock");
+            Ok(None)
+        }
+    }
+}
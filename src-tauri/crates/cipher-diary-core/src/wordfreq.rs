@@ -0,0 +1,101 @@
+use serde::Serialize;
+use specta::Type;
+use std::collections::HashMap;
+
+use crate::i18n::Locale;
+use crate::search;
+
+// 词云数据：正文只在后端解密和分词，返回给前端的只有高频词和次数
+pub const DEFAULT_TERMS: usize = 100;
+pub const MAX_TERMS: usize = 500;
+
+#[derive(Debug, Serialize, Type)]
+pub struct WordFrequency {
+    pub term: String,
+    pub count: u32,
+}
+
+const EN_STOPWORDS: &[&str] = &[
+    "a", "about", "after", "again", "all", "also", "am", "an", "and", "any", "are", "as", "at",
+    "be", "because", "been", "before", "being", "but", "by", "can", "could", "did", "do", "does",
+    "doing", "don", "down", "for", "from", "get", "got", "had", "has", "have", "he", "her",
+    "here", "him", "his", "how", "i", "if", "in", "into", "is", "it", "its", "just", "me", "more",
+    "my", "no", "not", "now", "of", "off", "on", "one", "only", "or", "our", "out", "over",
+    "really", "she", "so", "some", "than", "that", "the", "their", "them", "then", "there",
+    "these", "they", "this", "to", "too", "up", "very", "was", "we", "were", "what", "when",
+    "which", "who", "will", "with", "would", "you", "your",
+];
+
+// 常见虚词和代词，中文词频在这些字处断开
+const ZH_STOP_CHARS: &[char] = &[
+    '的', '了', '是', '我', '你', '他', '她', '它', '们', '在', '和', '就', '也', '都', '而', '与',
+    '着', '或', '这', '那', '有', '很', '吗', '呢', '吧', '啊', '把', '被', '让', '给', '对', '从',
+    '到', '说', '要', '会', '还', '又', '再', '只', '才', '得', '地', '之', '为', '于', '个', '一',
+    '不', '没', '去', '来', '上', '下', '里', '些', '么', '什', '怎',
+];
+
+// 同一份统计可以依次加入多篇正文，最后按次数取前几名
+pub struct WordCounter {
+    english: bool,
+    chinese: bool,
+    counts: HashMap<String, u32>,
+}
+
+impl WordCounter {
+    // stopwords 为 None 时同时去掉中英文停用词，适合混用两种语言的日记
+    pub fn new(stopwords: Option<Locale>) -> Self {
+        WordCounter {
+            english: stopwords != Some(Locale::ZhCn),
+            chinese: stopwords != Some(Locale::En),
+            counts: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, text: &str) {
+        let stop_chars = if self.chinese { ZH_STOP_CHARS } else { &[] };
+        for term in search::frequency_terms(&strip_link_targets(text), stop_chars) {
+            // 单个字母和纯数字不计
+            if term.chars().count() < 2 || term.chars().all(|ch| ch.is_ascii_digit()) {
+                continue;
+            }
+            if self.english && EN_STOPWORDS.contains(&term.as_str()) {
+                continue;
+            }
+            *self.counts.entry(term).or_default() += 1;
+        }
+    }
+
+    pub fn merge(mut self, other: WordCounter) -> Self {
+        for (term, count) in other.counts {
+            *self.counts.entry(term).or_default() += count;
+        }
+        self
+    }
+
+    // 次数相同按词排序，保证结果稳定
+    pub fn top(self, limit: usize) -> Vec<WordFrequency> {
+        let mut terms: Vec<(String, u32)> = self.counts.into_iter().collect();
+        terms.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        terms
+            .into_iter()
+            .take(limit)
+            .map(|(term, count)| WordFrequency { term, count })
+            .collect()
+    }
+}
+
+// 去掉 Markdown 链接和图片的地址部分，附件路径不计入词频
+fn strip_link_targets(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("](") {
+        result.push_str(&rest[..start]);
+        rest = &rest[start + 2..];
+        match rest.find(')') {
+            Some(end) => rest = &rest[end + 1..],
+            None => rest = "",
+        }
+    }
+    result.push_str(rest);
+    result
+}
@@ -0,0 +1,193 @@
+package com.xumin.diary
+
+import android.app.Activity
+import android.content.Intent
+import android.net.Uri
+import android.provider.DocumentsContract
+import android.provider.DocumentsContract.Document
+import android.util.Base64
+import androidx.activity.result.ActivityResult
+import app.tauri.annotation.ActivityCallback
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSArray
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+import java.io.IOException
+
+// 通过 SAF 读写用户选择的文件夹，供 src/saf.rs 中的 SafTree 使用。
+// 路径相对于所选文件夹、以 / 分隔，文件内容以 Base64 传递
+@InvokeArg
+class TreeArgs {
+    lateinit var uri: String
+    var path: String? = null
+    var data: String? = null
+}
+
+private data class Child(
+    val id: String,
+    val name: String,
+    val isDir: Boolean,
+    val size: Long,
+    val modified: Long
+)
+
+@TauriPlugin
+class SafPlugin(private val activity: Activity) : Plugin(activity) {
+    private val resolver get() = activity.contentResolver
+
+    @Command
+    fun pickTree(invoke: Invoke) {
+        val intent = Intent(Intent.ACTION_OPEN_DOCUMENT_TREE)
+        startActivityForResult(invoke, intent, "pickTreeResult")
+    }
+
+    @ActivityCallback
+    fun pickTreeResult(invoke: Invoke, result: ActivityResult) {
+        val ret = JSObject()
+        val uri = result.data?.data
+        if (result.resultCode == Activity.RESULT_OK && uri != null) {
+            // 保留授权，重启应用后仍能访问同一文件夹
+            resolver.takePersistableUriPermission(
+                uri,
+                Intent.FLAG_GRANT_READ_URI_PERMISSION or Intent.FLAG_GRANT_WRITE_URI_PERMISSION
+            )
+            ret.put("uri", uri.toString())
+        }
+        invoke.resolve(ret)
+    }
+
+    @Command
+    fun list(invoke: Invoke) = respond(invoke) { tree, _ ->
+        val files = JSArray()
+        collect(tree, DocumentsContract.getTreeDocumentId(tree), "", files)
+        JSObject().apply { put("files", files) }
+    }
+
+    @Command
+    fun read(invoke: Invoke) = respond(invoke) { tree, args ->
+        val file = findFile(tree, args.path!!) ?: throw IOException("not found: ${args.path}")
+        val bytes = resolver.openInputStream(docUri(tree, file.id))?.use { it.readBytes() }
+            ?: throw IOException("cannot open ${args.path}")
+        JSObject().apply { put("data", Base64.encodeToString(bytes, Base64.NO_WRAP)) }
+    }
+
+    @Command
+    fun write(invoke: Invoke) = respond(invoke) { tree, args ->
+        val path = args.path!!
+        val parts = path.split('/')
+        val dir = findDir(tree, parts.dropLast(1), true)
+            ?: throw IOException("cannot create directory for $path")
+        val existing = children(tree, dir).firstOrNull { !it.isDir && it.name == parts.last() }
+        val target = existing?.let { docUri(tree, it.id) }
+            ?: DocumentsContract.createDocument(
+                resolver, docUri(tree, dir), "application/octet-stream", parts.last()
+            )
+            ?: throw IOException("cannot create $path")
+        // "wt" 截断原有内容，部分文档提供方默认的 "w" 不会截断
+        resolver.openOutputStream(target, "wt")?.use {
+            it.write(Base64.decode(args.data ?: "", Base64.NO_WRAP))
+        } ?: throw IOException("cannot open $path")
+        val written = stat(target)
+        JSObject().apply {
+            put("path", path)
+            put("size", written.first)
+            put("modified", written.second)
+        }
+    }
+
+    @Command
+    fun remove(invoke: Invoke) = respond(invoke) { tree, args ->
+        findFile(tree, args.path!!)?.let {
+            DocumentsContract.deleteDocument(resolver, docUri(tree, it.id))
+        }
+        JSObject()
+    }
+
+    private fun respond(invoke: Invoke, body: (Uri, TreeArgs) -> JSObject) {
+        try {
+            val args = invoke.parseArgs(TreeArgs::class.java)
+            invoke.resolve(body(Uri.parse(args.uri), args))
+        } catch (err: Exception) {
+            invoke.reject(err.message ?: err.toString())
+        }
+    }
+
+    private fun docUri(tree: Uri, id: String): Uri =
+        DocumentsContract.buildDocumentUriUsingTree(tree, id)
+
+    private fun children(tree: Uri, parentId: String): List<Child> {
+        val uri = DocumentsContract.buildChildDocumentsUriUsingTree(tree, parentId)
+        val columns = arrayOf(
+            Document.COLUMN_DOCUMENT_ID,
+            Document.COLUMN_DISPLAY_NAME,
+            Document.COLUMN_MIME_TYPE,
+            Document.COLUMN_SIZE,
+            Document.COLUMN_LAST_MODIFIED
+        )
+        val result = mutableListOf<Child>()
+        resolver.query(uri, columns, null, null, null)?.use { cursor ->
+            while (cursor.moveToNext()) {
+                result.add(
+                    Child(
+                        cursor.getString(0),
+                        cursor.getString(1),
+                        cursor.getString(2) == Document.MIME_TYPE_DIR,
+                        cursor.getLong(3),
+                        cursor.getLong(4)
+                    )
+                )
+            }
+        }
+        return result
+    }
+
+    private fun collect(tree: Uri, dirId: String, prefix: String, files: JSArray) {
+        for (child in children(tree, dirId)) {
+            val path = prefix + child.name
+            if (child.isDir) {
+                collect(tree, child.id, "$path/", files)
+            } else {
+                files.put(JSObject().apply {
+                    put("path", path)
+                    put("size", child.size)
+                    put("modified", child.modified)
+                })
+            }
+        }
+    }
+
+    // 逐级查找目录；create 为 true 时创建缺少的目录
+    private fun findDir(tree: Uri, parts: List<String>, create: Boolean): String? {
+        var id = DocumentsContract.getTreeDocumentId(tree)
+        for (name in parts) {
+            val child = children(tree, id).firstOrNull { it.isDir && it.name == name }
+            id = when {
+                child != null -> child.id
+                create -> DocumentsContract.createDocument(
+                    resolver, docUri(tree, id), Document.MIME_TYPE_DIR, name
+                )?.let { DocumentsContract.getDocumentId(it) } ?: return null
+                else -> return null
+            }
+        }
+        return id
+    }
+
+    private fun findFile(tree: Uri, path: String): Child? {
+        val parts = path.split('/')
+        val dir = findDir(tree, parts.dropLast(1), false) ?: return null
+        return children(tree, dir).firstOrNull { !it.isDir && it.name == parts.last() }
+    }
+
+    private fun stat(uri: Uri): Pair<Long, Long> {
+        val columns = arrayOf(Document.COLUMN_SIZE, Document.COLUMN_LAST_MODIFIED)
+        resolver.query(uri, columns, null, null, null)?.use { cursor ->
+            if (cursor.moveToFirst()) {
+                return Pair(cursor.getLong(0), cursor.getLong(1))
+            }
+        }
+        throw IOException("cannot stat $uri")
+    }
+}
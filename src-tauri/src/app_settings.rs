@@ -0,0 +1,125 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use cipher_diary_core::error::VaultError;
+use cipher_diary_core::i18n::{Locale, Message};
+use cipher_diary_core::permissions;
+
+use crate::logging::LogLevel;
+use crate::reminders::{self, MemorySchedule, ReminderSchedule};
+
+// 解锁前就需要的偏好设置，保存在应用数据目录中。这里不放任何与日记内容相关的信息
+const APP_SETTINGS_FILE: &str = "app_settings.json";
+const APP_SETTINGS_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct AppSettings {
+    #[serde(default)]
+    pub theme: Theme,
+    // 未设置时使用后端默认语言
+    #[serde(default)]
+    pub locale: Option<Locale>,
+    // 日记库自身没有设置自动锁定时使用
+    #[serde(default)]
+    pub default_auto_lock_minutes: Option<u64>,
+    // 关闭后不再记录最近打开的日记库
+    #[serde(default = "default_true")]
+    pub remember_recent_vaults: bool,
+    #[serde(default)]
+    pub sidebar_collapsed: bool,
+    // 写入应用数据目录下诊断日志的级别
+    #[serde(default)]
+    pub log_level: LogLevel,
+    #[serde(default)]
+    pub reminders: ReminderSchedule,
+    #[serde(default)]
+    pub memories: MemorySchedule,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            locale: None,
+            default_auto_lock_minutes: None,
+            remember_recent_vaults: true,
+            sidebar_collapsed: false,
+            log_level: LogLevel::default(),
+            reminders: ReminderSchedule::default(),
+            memories: MemorySchedule::default(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredAppSettings {
+    // 没有版本号的文件视为第 1 版
+    #[serde(default = "first_version")]
+    version: u32,
+    #[serde(flatten)]
+    settings: serde_json::Value,
+}
+
+fn first_version() -> u32 {
+    1
+}
+
+pub fn app_settings_path(app_dir: &Path) -> PathBuf {
+    app_dir.join(APP_SETTINGS_FILE)
+}
+
+// 文件缺失、损坏或来自更新版本时使用默认值，不影响启动
+pub fn read(path: &Path) -> AppSettings {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<StoredAppSettings>(&content).ok())
+        .and_then(|stored| migrate(stored.version, stored.settings).ok())
+        .unwrap_or_default()
+}
+
+pub fn validate(settings: &AppSettings) -> Result<(), VaultError> {
+    if settings.default_auto_lock_minutes == Some(0) {
+        return Err(VaultError::invalid(Message::AutoLockTooShort));
+    }
+    reminders::validate(&settings.reminders)?;
+    reminders::validate_memories(&settings.memories)
+}
+
+pub fn write(path: &Path, settings: &AppSettings) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        permissions::create_private_dir_all(parent)?;
+    }
+    let stored = StoredAppSettings {
+        version: APP_SETTINGS_VERSION,
+        settings: serde_json::to_value(settings).context("failed to serialize app settings")?,
+    };
+    let serialized =
+        serde_json::to_string_pretty(&stored).context("failed to serialize app settings")?;
+    permissions::write_private(path, serialized).context("failed to write app settings")
+}
+
+// 以后修改字段时在这里按版本逐步升级旧数据
+fn migrate(version: u32, settings: serde_json::Value) -> Result<AppSettings> {
+    match version {
+        APP_SETTINGS_VERSION => {
+            serde_json::from_value(settings).context("failed to parse app settings")
+        }
+        _ => Err(anyhow!("unsupported app settings version {version}")),
+    }
+}
@@ -0,0 +1,166 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use cipher_diary_core::error::VaultError;
+use cipher_diary_core::i18n;
+use cipher_diary_core::validation;
+use cipher_diary_core::vault::{
+    Entry, EntryInfo, IntegrityReport, PatchFields, QueryPage, QuerySpec, TextEncryption,
+    UnlockResponse, VaultManager, VaultPolicy,
+};
+use uuid::Uuid;
+
+use crate::app_settings::{self, AppSettings};
+use crate::{
+    apply_app_settings, check_new_entry, create_vault_root, local_now, register_vault,
+    resolve_vault_path, vault_root_for, AppState, OpenedVault,
+};
+
+// 不创建窗口、不经过 generate_context! 的命令层，与 IPC 命令共用校验和登记逻辑。
+// 集成测试和格式模糊测试用它在临时目录中走完整的解锁、编辑、锁定流程
+pub struct HeadlessApp {
+    state: AppState,
+    data_dir: PathBuf,
+}
+
+impl HeadlessApp {
+    // data_dir 代替应用数据目录，存放应用设置、最近列表以及未指定目录时的日记库
+    pub fn new(data_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            state: AppState::default(),
+            data_dir: data_dir.into(),
+        }
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    pub fn create_vault(
+        &self,
+        passphrase: &str,
+        directory: Option<String>,
+        encryption: Option<TextEncryption>,
+        policy: Option<VaultPolicy>,
+    ) -> Result<OpenedVault, VaultError> {
+        let path = self.vault_path(directory)?;
+        create_vault_root(&path)?;
+        self.open(path, |manager, path| {
+            manager.create(passphrase, path, encryption, policy)
+        })
+    }
+
+    pub fn unlock_vault(
+        &self,
+        passphrase: &str,
+        directory: Option<String>,
+    ) -> Result<OpenedVault, VaultError> {
+        let path = self.vault_path(directory)?;
+        self.open(path, |manager, path| manager.unlock(passphrase, path))
+    }
+
+    pub fn lock_vault(&self, vault_id: Uuid) -> Result<(), VaultError> {
+        self.state.manager(&vault_id)?.lock()?;
+        self.state.vaults.write().remove(&vault_id);
+        Ok(())
+    }
+
+    // 其余操作直接取句柄调用，与 with_manager 中的调用一致
+    pub fn manager(&self, vault_id: Uuid) -> Result<Arc<VaultManager>, VaultError> {
+        self.state.manager(&vault_id)
+    }
+
+    pub fn list_entries(&self, vault_id: Uuid) -> Result<Vec<EntryInfo>, VaultError> {
+        Ok(self.manager(vault_id)?.list()?)
+    }
+
+    pub fn search_entries(
+        &self,
+        vault_id: Uuid,
+        query: &str,
+    ) -> Result<Vec<EntryInfo>, VaultError> {
+        Ok(self.manager(vault_id)?.search(query)?)
+    }
+
+    pub fn query_entries(&self, vault_id: Uuid, spec: QuerySpec) -> Result<QueryPage, VaultError> {
+        Ok(self.manager(vault_id)?.query(spec)?)
+    }
+
+    pub fn load_entry(&self, vault_id: Uuid, id: Uuid) -> Result<Entry, VaultError> {
+        Ok(self.manager(vault_id)?.load_entry(id)?)
+    }
+
+    pub fn create_entry(
+        &self,
+        vault_id: Uuid,
+        title: &str,
+        content: &str,
+        encryption: Option<TextEncryption>,
+    ) -> Result<Entry, VaultError> {
+        check_new_entry(title, content, None)?;
+        Ok(self
+            .manager(vault_id)?
+            .create_entry(title, content, encryption)?)
+    }
+
+    pub fn update_entry(&self, vault_id: Uuid, entry: Entry) -> Result<Entry, VaultError> {
+        validation::entry(&entry)?;
+        Ok(self.manager(vault_id)?.update_entry(entry)?)
+    }
+
+    pub fn patch_entry(
+        &self,
+        vault_id: Uuid,
+        id: Uuid,
+        patch: PatchFields,
+    ) -> Result<EntryInfo, VaultError> {
+        validation::patch(&patch)?;
+        Ok(self.manager(vault_id)?.patch_entry(id, patch)?)
+    }
+
+    pub fn delete_entry(&self, vault_id: Uuid, id: Uuid) -> Result<(), VaultError> {
+        Ok(self.manager(vault_id)?.delete_entry(id)?)
+    }
+
+    pub fn export_plaintext(&self, vault_id: Uuid) -> Result<String, VaultError> {
+        Ok(self.manager(vault_id)?.export_plaintext()?)
+    }
+
+    pub fn verify_vault_integrity(&self, vault_id: Uuid) -> Result<IntegrityReport, VaultError> {
+        Ok(self.manager(vault_id)?.verify_vault_integrity()?)
+    }
+
+    pub fn app_settings(&self) -> AppSettings {
+        app_settings::read(&app_settings::app_settings_path(&self.data_dir))
+    }
+
+    pub fn set_app_settings(&self, settings: AppSettings) -> Result<(), VaultError> {
+        apply_app_settings(&settings)?;
+        let path = app_settings::app_settings_path(&self.data_dir);
+        Ok(app_settings::write(&path, &settings)?)
+    }
+
+    fn vault_path(&self, directory: Option<String>) -> Result<PathBuf, VaultError> {
+        resolve_vault_path(directory, || Ok(self.data_dir.clone()))
+    }
+
+    // 与 open_vault 相同，只是在当前线程执行，也不挂事件监听
+    fn open<F>(&self, path: PathBuf, task: F) -> Result<OpenedVault, VaultError>
+    where
+        F: FnOnce(&VaultManager, PathBuf) -> anyhow::Result<UnlockResponse>,
+    {
+        let root = vault_root_for(&path);
+        let _reservation = self.state.reserve(&root, i18n::Message::VaultAlreadyOpen)?;
+        let manager = Arc::new(VaultManager::default());
+        manager.set_local_clock(local_now);
+        let response = task(&manager, path)?;
+        Ok(register_vault(
+            &self.state,
+            Some(&self.data_dir),
+            &root,
+            Uuid::new_v4(),
+            manager,
+            response,
+        ))
+    }
+}
@@ -1,212 +1,2358 @@
-mod vault;
+// headless 构建没有 run()，IPC 命令照常编译但不会注册
+#![cfg_attr(feature = "headless", allow(dead_code, unused_imports))]
 
+mod app_settings;
+#[cfg(feature = "headless")]
+pub mod headless;
+mod logging;
+mod open_request;
+mod recent_vaults;
+mod reminders;
+mod saf;
+mod weather;
+
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
-use tauri::Manager;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use specta::Type;
+use specta_typescript::{BigIntExportBehavior, Typescript};
+use tauri::ipc::Response;
 use tauri::{AppHandle, State};
+use tauri::{Emitter, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_notification::NotificationExt;
 use time::macros::format_description;
-use time::OffsetDateTime;
+use time::{Month, OffsetDateTime, UtcOffset};
 use uuid::Uuid;
 
-use crate::vault::{
-    vault_file_path, Entry, EntryInfo, TextEncryption, UnlockResponse, VaultManager,
+use cipher_diary_core::drafts::Draft;
+use cipher_diary_core::emergency::{self, EmergencyAccess, EmergencyKeypair, EmergencyStatus};
+use cipher_diary_core::entry_types::EntryPayload;
+use cipher_diary_core::error::VaultError;
+use cipher_diary_core::frontmatter::{self, FrontmatterMapping};
+use cipher_diary_core::habits::{Habit, HabitHistory};
+use cipher_diary_core::health::{HealthImportReport, HealthSource};
+use cipher_diary_core::history::HistoryReport;
+use cipher_diary_core::i18n::{self, Locale, Message};
+use cipher_diary_core::key_slots::UserSlot;
+use cipher_diary_core::profile::{self, VaultProfile};
+use cipher_diary_core::prompts::{PromptPack, PromptPackInfo, TodaysPrompt};
+use cipher_diary_core::quick_unlock::{self, QuickUnlockStatus};
+use cipher_diary_core::recap::MonthRecap;
+use cipher_diary_core::share::{self, EntryShareReport, ShareReport};
+use cipher_diary_core::storage::{self, Mirror};
+use cipher_diary_core::undo::UndoOutcome;
+use cipher_diary_core::unlock_log::LastUnlockInfo;
+use cipher_diary_core::validation;
+use cipher_diary_core::vault::{
+    self, vault_file_path, AttachmentInfo, BenchmarkReport, CalendarDay, CloneReport, DateRange,
+    DayView, DeleteReport, EntriesChanged, Entry, EntryInfo, ExportReport, FolderCount,
+    GoalProgress, ImportReport, IntegrityReport, Location, MediaDay, MigrationReport, MoodTrends,
+    MoveReport, NewEntry, PatchFields, PlaceCount, QueryPage, QuerySpec, SelfTestReport,
+    SentimentTrends, StatsGroup, TagCount, TextEncryption, TimelineYear, TrendBucket,
+    UnlockProgress, UnlockResponse, VaultEvent, VaultInfo, VaultManager, VaultPolicy, VaultProbe,
+    VaultSettings, Weather, WritingStats, ENTRIES_CHANGED_EVENT, UNLOCK_PROGRESS_EVENT, VAULT_EVENT,
 };
+use cipher_diary_core::wordfreq::{self, WordFrequency};
+
+use crate::app_settings::AppSettings;
+use crate::logging::LogLevel;
+use crate::open_request::{OpenRequest, OPEN_REQUEST_EVENT};
+use crate::recent_vaults::RecentVault;
+use crate::reminders::{MemorySchedule, ReminderClock, ReminderSchedule};
 
+const AUTO_LOCK_POLL_INTERVAL: Duration = Duration::from_secs(15);
+const METADATA_FLUSH_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const REMINDER_POLL_INTERVAL: Duration = Duration::from_secs(30);
+const MIRROR_SYNC_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const MIN_PASSPHRASE_LENGTH: usize = 6;
+
+// 每个已解锁的日记库对应一个句柄，各自独立计时自动锁定
 #[derive(Default)]
 struct AppState {
-    manager: VaultManager,
+    vaults: RwLock<HashMap<Uuid, Arc<VaultManager>>>,
+    // 通过文件关联或链接请求打开、前端尚未取走的日记库目录
+    open_request: Mutex<Option<String>>,
+    // Android 上以 content:// 地址打开过的文件夹及其工作副本，按地址区分
+    mirrors: Mutex<HashMap<String, Arc<Mirror>>>,
+    // 正在解锁、删除或作为导入来源的目录，操作完成前其他操作不能再使用
+    busy_roots: Mutex<Vec<PathBuf>>,
+}
+
+// 占用一个目录，离开作用域时释放
+struct RootReservation<'a> {
+    state: &'a AppState,
+    root: PathBuf,
+}
+
+impl Drop for RootReservation<'_> {
+    fn drop(&mut self) {
+        let mut busy = self.state.busy_roots.lock();
+        if let Some(index) = busy.iter().position(|root| *root == self.root) {
+            busy.remove(index);
+        }
+    }
+}
+
+impl AppState {
+    fn manager(&self, vault_id: &Uuid) -> Result<Arc<VaultManager>, VaultError> {
+        self.vaults
+            .read()
+            .get(vault_id)
+            .cloned()
+            .ok_or_else(|| VaultError::Locked(Message::VaultLocked.to_string()))
+    }
+
+    fn managers(&self) -> Vec<(Uuid, Arc<VaultManager>)> {
+        self.vaults
+            .read()
+            .iter()
+            .map(|(vault_id, manager)| (*vault_id, Arc::clone(manager)))
+            .collect()
+    }
+
+    // 工作副本目录对应的远端地址，普通目录返回 None
+    fn mirror_location(&self, root: &Path) -> Option<String> {
+        self.mirrors
+            .lock()
+            .values()
+            .find(|mirror| same_directory(mirror.local_root(), root))
+            .map(|mirror| mirror.location())
+    }
+
+    // 检查目录没有打开也没有被占用，并在同一把写锁下占用它，
+    // 并发的解锁、删除和导入不会同时通过检查
+    fn reserve(&self, root: &Path, message: Message) -> Result<RootReservation<'_>, VaultError> {
+        let vaults = self.vaults.write();
+        let mut busy = self.busy_roots.lock();
+        let open = vaults.values().any(|manager| {
+            manager
+                .vault_root()
+                .is_ok_and(|open_root| same_directory(&open_root, root))
+        });
+        if open || busy.iter().any(|other| same_directory(other, root)) {
+            return Err(VaultError::conflict(message));
+        }
+        busy.push(root.to_path_buf());
+        Ok(RootReservation {
+            state: self,
+            root: root.to_path_buf(),
+        })
+    }
+
+    fn is_open(&self, root: &Path) -> bool {
+        self.managers().iter().any(|(_, manager)| {
+            manager
+                .vault_root()
+                .is_ok_and(|open_root| same_directory(&open_root, root))
+        })
+    }
+}
+
+#[derive(Serialize, Type)]
+pub struct OpenedVault {
+    pub vault_id: Uuid,
+    #[serde(flatten)]
+    pub response: UnlockResponse,
+}
+
+// 事件附带句柄编号，前端据此区分来自哪个日记库
+#[derive(Clone, Serialize, Type)]
+struct VaultScoped<T> {
+    vault_id: Uuid,
+    #[serde(flatten)]
+    payload: T,
+}
+
+// 在阻塞线程池中执行，避免 Argon2 派生和大批量加解密卡住 IPC 线程
+async fn blocking<T, F>(task: F) -> Result<T, VaultError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> anyhow::Result<T> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|err| VaultError::other(err.to_string()))?
+        .map_err(|err| {
+            let error = VaultError::from(err);
+            tracing::debug!(code = error.code(), "command failed");
+            error
+        })
+}
+
+async fn with_manager<T, F>(state: &AppState, vault_id: Uuid, task: F) -> Result<T, VaultError>
+where
+    T: Send + 'static,
+    F: FnOnce(&VaultManager) -> anyhow::Result<T> + Send + 'static,
+{
+    let manager = state.manager(&vault_id)?;
+    blocking(move || task(&manager)).await
+}
+
+fn new_manager(app: &AppHandle, vault_id: Uuid) -> Arc<VaultManager> {
+    let manager = VaultManager::default();
+    manager.set_local_clock(local_now);
+    let handle = app.clone();
+    manager.set_event_listener(move |event| {
+        let _ = handle.emit(
+            VAULT_EVENT,
+            VaultScoped {
+                vault_id,
+                payload: event.clone(),
+            },
+        );
+    });
+    let handle = app.clone();
+    manager.set_progress_listener(move |stage| {
+        let _ = handle.emit(
+            UNLOCK_PROGRESS_EVENT,
+            VaultScoped {
+                vault_id,
+                payload: stage,
+            },
+        );
+    });
+    let handle = app.clone();
+    manager.set_entries_listener(move |changed| {
+        let _ = handle.emit(
+            ENTRIES_CHANGED_EVENT,
+            VaultScoped {
+                vault_id,
+                payload: changed.clone(),
+            },
+        );
+    });
+    Arc::new(manager)
 }
 
-fn resolve_vault_path(app: &AppHandle, directory: Option<String>) -> Result<PathBuf, String> {
+fn same_directory(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// 登记解锁成功的句柄，并应用保存在应用数据目录中的偏好
+fn register_vault(
+    state: &AppState,
+    data_dir: Option<&Path>,
+    root: &Path,
+    vault_id: Uuid,
+    manager: Arc<VaultManager>,
+    response: UnlockResponse,
+) -> OpenedVault {
+    let app_settings = data_dir
+        .map(|dir| app_settings::read(&app_settings::app_settings_path(dir)))
+        .unwrap_or_default();
+    // 日记库自己没有设置自动锁定时使用应用默认值
+    if response.settings.auto_lock_minutes.is_none() {
+        if let Some(minutes) = app_settings.default_auto_lock_minutes {
+            manager.set_auto_lock_after(Some(Duration::from_secs(minutes.saturating_mul(60))));
+        }
+    }
+    state.vaults.write().insert(vault_id, manager);
+    tracing::info!(%vault_id, entries = response.entries.len(), "vault opened");
+    // 最近列表只是便利功能，写入失败不影响解锁
+    if let (true, Some(dir)) = (app_settings.remember_recent_vaults, data_dir) {
+        let recent_path = recent_vaults::recent_vaults_path(dir);
+        let _ = recent_vaults::record(&recent_path, root, Some(response.text_encryption));
+    }
+    OpenedVault { vault_id, response }
+}
+
+// 为解锁创建新句柄，成功后登记
+async fn open_vault<F>(
+    app: &AppHandle,
+    state: &AppState,
+    path: PathBuf,
+    task: F,
+) -> Result<OpenedVault, VaultError>
+where
+    F: FnOnce(&VaultManager, PathBuf) -> anyhow::Result<UnlockResponse> + Send + 'static,
+{
+    let root = vault_root_for(&path);
+    // 同一目录只能打开一次，两个句柄同时写入会破坏日志；解锁完成登记前一直占用
+    let _reservation = state.reserve(&root, Message::VaultAlreadyOpen)?;
+
+    let vault_id = Uuid::new_v4();
+    let manager = new_manager(app, vault_id);
+    let mut response = {
+        let manager = Arc::clone(&manager);
+        blocking(move || task(&manager, path)).await?
+    };
+    // 工作副本对前端不可见，界面显示和最近列表都使用远端地址
+    let root = match state.mirror_location(&root) {
+        Some(location) => {
+            response.vault_root = location.clone();
+            PathBuf::from(location)
+        }
+        None => root,
+    };
+    let data_dir = app_data_dir(app).ok();
+    Ok(register_vault(state, data_dir.as_deref(), &root, vault_id, manager, response))
+}
+
+// 只解析路径，不创建目录；新建日记库由 create_vault 负责。未指定目录时使用 default_base
+fn resolve_vault_path(
+    directory: Option<String>,
+    default_base: impl FnOnce() -> Result<PathBuf, VaultError>,
+) -> Result<PathBuf, VaultError> {
     let base = if let Some(dir) = directory {
         let trimmed = dir.trim();
         if trimmed.is_empty() {
-            return Err("所选目录无效".to_string());
+            return Err(VaultError::invalid(Message::InvalidDirectory));
         }
         let path = PathBuf::from(trimmed);
         if path.is_file() {
-            return Err("所选路径不是文件夹".to_string());
+            return Err(VaultError::invalid(Message::NotADirectory));
         }
-        fs::create_dir_all(&path).map_err(|err| format!("无法创建所选目录: {err}"))?;
         path
     } else {
-        app.path()
-            .app_local_data_dir()
-            .map_err(|err| format!("failed to resolve app data dir: {err}"))?
+        default_base()?
     };
 
     Ok(vault_file_path(base))
 }
 
+// Android 分区存储中用户选择的文件夹只有 content:// 地址：先同步到应用私有目录中的
+// 工作副本，之后按普通目录处理，改动由后台线程写回。已打开的日记库以工作副本为准
+async fn resolve_vault_location(
+    app: &AppHandle,
+    state: &AppState,
+    directory: Option<String>,
+) -> Result<PathBuf, VaultError> {
+    let uri = match directory {
+        Some(dir) if storage::is_document_uri(&dir) => dir,
+        directory => return resolve_vault_path(directory, || app_data_dir(app)),
+    };
+    let mirror = document_mirror(app, state, &uri)?;
+    let root = mirror.local_root().to_path_buf();
+    if !state.is_open(&root) {
+        // 上次没来得及写回的改动先写回，pull 不会覆盖尚未同步的本地文件
+        blocking(move || {
+            mirror
+                .push()
+                .and_then(|_| mirror.pull())
+                .map_err(|err| err.context(Message::DocumentTreeSyncFailed))
+        })
+        .await?;
+    }
+    Ok(vault_file_path(root))
+}
+
+fn document_mirror(
+    app: &AppHandle,
+    state: &AppState,
+    uri: &str,
+) -> Result<Arc<Mirror>, VaultError> {
+    let uri = uri.trim();
+    if let Some(mirror) = state.mirrors.lock().get(uri) {
+        return Ok(Arc::clone(mirror));
+    }
+    let tree = saf::open_tree(app, uri)?;
+    let local = storage::mirror_path(&app_data_dir(app)?.join("document-trees"), uri);
+    let mirror = Arc::new(Mirror::new(tree, local));
+    state
+        .mirrors
+        .lock()
+        .insert(uri.to_string(), Arc::clone(&mirror));
+    Ok(mirror)
+}
+
+// 把各工作副本的改动写回；失败时保留改动，下一轮重试
+fn push_mirrors(state: &AppState) {
+    let mirrors: Vec<Arc<Mirror>> = state.mirrors.lock().values().cloned().collect();
+    for mirror in mirrors {
+        if let Err(err) = mirror.push() {
+            tracing::warn!(error = %err, "failed to sync document tree");
+        }
+    }
+}
+
+fn create_vault_root(path: &Path) -> Result<(), VaultError> {
+    fs::create_dir_all(vault_root_for(path))
+        .map_err(|err| VaultError::Io(format!("{}: {err}", Message::CreateDirectoryFailed)))
+}
+
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf, VaultError> {
+    app.path()
+        .app_local_data_dir()
+        .map_err(|err| VaultError::other(format!("failed to resolve app data dir: {err}")))
+}
+
+// Android 上用系统选择器选取文件夹，返回 content:// 地址；用户取消时返回 None。
+// 桌面平台使用对话框插件选择普通目录
+#[tauri::command]
+#[specta::specta]
+async fn pick_document_tree(app: AppHandle) -> Result<Option<String>, VaultError> {
+    tauri::async_runtime::spawn_blocking(move || saf::pick_tree(&app))
+        .await
+        .map_err(|err| VaultError::other(err.to_string()))?
+}
+
+// 只读探测，不创建目录，也不运行密钥派生
 #[tauri::command]
-fn unlock_vault(
+#[specta::specta]
+async fn probe_vault(
+    directory: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<VaultProbe, VaultError> {
+    let path = resolve_vault_location(&app, &state, directory).await?;
+    blocking(move || Ok(vault::probe_vault(&path))).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn create_vault(
     passphrase: String,
     directory: Option<String>,
     encryption: Option<TextEncryption>,
+    policy: Option<VaultPolicy>,
     app: AppHandle,
-    state: State<AppState>,
-) -> Result<UnlockResponse, String> {
-    let path = resolve_vault_path(&app, directory)?;
-    state
-        .manager
-        .unlock(&passphrase, path, encryption)
-        .map_err(|err| err.to_string())
+    state: State<'_, AppState>,
+) -> Result<OpenedVault, VaultError> {
+    let path = resolve_vault_location(&app, &state, directory).await?;
+    create_vault_root(&path)?;
+    open_vault(&app, &state, path, move |manager, path| {
+        manager.create(&passphrase, path, encryption, policy)
+    })
+    .await
+}
+
+// 目录中没有日记库时返回 "vault not found"，不再隐式新建
+#[tauri::command]
+#[specta::specta]
+async fn unlock_vault(
+    passphrase: String,
+    directory: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<OpenedVault, VaultError> {
+    let path = resolve_vault_location(&app, &state, directory).await?;
+    open_vault(&app, &state, path, move |manager, path| manager.unlock(&passphrase, path)).await
+}
+
+// 解锁前预检格式升级：需要密码解密元数据，但不修改任何文件。
+// 实际升级在解锁时进行，升级前会在日记目录中备份元数据
+#[tauri::command]
+#[specta::specta]
+async fn plan_vault_migration(
+    passphrase: String,
+    directory: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<MigrationReport, VaultError> {
+    let path = resolve_vault_location(&app, &state, directory).await?;
+    blocking(move || vault::plan_migration(&path, &passphrase)).await
+}
+
+fn recent_vaults_file(app: &AppHandle) -> Result<PathBuf, VaultError> {
+    app_data_dir(app).map(|dir| recent_vaults::recent_vaults_path(&dir))
+}
+
+fn app_settings_file(app: &AppHandle) -> Result<PathBuf, VaultError> {
+    app_data_dir(app).map(|dir| app_settings::app_settings_path(&dir))
+}
+
+// 解锁前即可读取，不需要 vault_id
+#[tauri::command]
+#[specta::specta]
+async fn get_app_settings(app: AppHandle) -> Result<AppSettings, VaultError> {
+    let path = app_settings_file(&app)?;
+    blocking(move || Ok(app_settings::read(&path))).await
+}
+
+// 校验应用设置并立即应用语言和日志级别，IPC 命令与 headless 共用
+fn apply_app_settings(settings: &AppSettings) -> Result<(), VaultError> {
+    app_settings::validate(settings)?;
+    if let Some(locale) = settings.locale {
+        i18n::set_locale(locale);
+    }
+    logging::set_level(settings.log_level);
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_app_settings(settings: AppSettings, app: AppHandle) -> Result<(), VaultError> {
+    let path = app_settings_file(&app)?;
+    apply_app_settings(&settings)?;
+    blocking(move || app_settings::write(&path, &settings)).await
+}
+
+// 立即生效并写入应用设置，下次启动沿用
+#[tauri::command]
+#[specta::specta]
+async fn set_log_level(level: LogLevel, app: AppHandle) -> Result<(), VaultError> {
+    logging::set_level(level);
+    let path = app_settings_file(&app)?;
+    blocking(move || {
+        let mut settings = app_settings::read(&path);
+        settings.log_level = level;
+        app_settings::write(&path, &settings)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_reminder_schedule(app: AppHandle) -> Result<ReminderSchedule, VaultError> {
+    let path = app_settings_file(&app)?;
+    blocking(move || Ok(app_settings::read(&path).reminders)).await
+}
+
+// 保存在应用设置中，提醒线程下一轮检查时生效
+#[tauri::command]
+#[specta::specta]
+async fn set_reminder_schedule(
+    mut schedule: ReminderSchedule,
+    app: AppHandle,
+) -> Result<(), VaultError> {
+    reminders::validate(&schedule)?;
+    schedule.times.sort();
+    let path = app_settings_file(&app)?;
+    blocking(move || {
+        let mut settings = app_settings::read(&path);
+        settings.reminders = schedule;
+        app_settings::write(&path, &settings)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_memory_schedule(app: AppHandle) -> Result<MemorySchedule, VaultError> {
+    let path = app_settings_file(&app)?;
+    blocking(move || Ok(app_settings::read(&path).memories)).await
+}
+
+// 默认关闭；开启后每天在设定时刻检查一次
+#[tauri::command]
+#[specta::specta]
+async fn set_memory_schedule(
+    mut schedule: MemorySchedule,
+    app: AppHandle,
+) -> Result<(), VaultError> {
+    reminders::validate_memories(&schedule)?;
+    schedule.years.sort();
+    let path = app_settings_file(&app)?;
+    blocking(move || {
+        let mut settings = app_settings::read(&path);
+        settings.memories = schedule;
+        app_settings::write(&path, &settings)
+    })
+    .await
+}
+
+// time 在多线程进程中无法安全读取本地时区，这里借用 chrono 取当前偏移
+fn local_now() -> OffsetDateTime {
+    let seconds = chrono::Local::now().offset().local_minus_utc();
+    let offset = UtcOffset::from_whole_seconds(seconds).unwrap_or(UtcOffset::UTC);
+    OffsetDateTime::now_utc().to_offset(offset)
+}
+
+// 任一已打开的日记库今天有新建或修改过的日记
+fn wrote_today(state: &AppState, now: OffsetDateTime) -> bool {
+    state.managers().iter().any(|(_, manager)| {
+        manager
+            .has_entry_on(now.date(), now.offset())
+            .unwrap_or(false)
+    })
+}
+
+// 在已打开的日记库中找最久远的那年今日；锁定的日记库无法读取，不参与
+fn find_memory(
+    state: &AppState,
+    now: OffsetDateTime,
+    schedule: &MemorySchedule,
+) -> Option<Message> {
+    let mut oldest: Option<(u32, Vec<EntryInfo>)> = None;
+    for (_, manager) in state.managers() {
+        let Ok(entries) = manager.anniversaries(now.date(), now.offset(), &schedule.years) else {
+            continue;
+        };
+        for info in entries {
+            let years = (now.year() - info.entry_day(now.offset()).year()) as u32;
+            match &mut oldest {
+                Some((oldest_years, list)) if *oldest_years == years => list.push(info),
+                Some((oldest_years, _)) if *oldest_years > years => {}
+                _ => oldest = Some((years, vec![info])),
+            }
+        }
+    }
+    let (years, entries) = oldest?;
+    Some(match entries.as_slice() {
+        [entry] if schedule.show_titles && !entry.title.trim().is_empty() => {
+            Message::MemoryEntry(years, entry.title.clone())
+        }
+        _ => Message::MemoryEntries(years, entries.len()),
+    })
+}
+
+fn show_memory(app: &AppHandle, body: Message) {
+    let result = app
+        .notification()
+        .builder()
+        .title(Message::MemoryTitle.to_string())
+        .body(body.to_string())
+        .show();
+    if let Err(err) = result {
+        tracing::warn!(error = %err, "failed to show memory");
+    }
+}
+
+fn show_reminder(app: &AppHandle) {
+    let result = app
+        .notification()
+        .builder()
+        .title(Message::ReminderTitle.to_string())
+        .body(Message::ReminderBody.to_string())
+        .show();
+    if let Err(err) = result {
+        tracing::warn!(error = %err, "failed to show reminder");
+    }
 }
 
+// 仅影响之后返回的提示文字，错误码保持不变
 #[tauri::command]
-fn lock_vault(state: State<AppState>) -> Result<(), String> {
-    state.manager.lock();
+#[specta::specta]
+async fn set_backend_locale(locale: Locale) -> Result<(), VaultError> {
+    i18n::set_locale(locale);
     Ok(())
 }
 
 #[tauri::command]
-fn list_entries(state: State<AppState>) -> Result<Vec<EntryInfo>, String> {
-    state.manager.list().map_err(|err| err.to_string())
+#[specta::specta]
+async fn list_recent_vaults(app: AppHandle) -> Result<Vec<RecentVault>, VaultError> {
+    let path = recent_vaults_file(&app)?;
+    blocking(move || Ok(recent_vaults::list(&path))).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_startup_vault(app: AppHandle) -> Result<Option<RecentVault>, VaultError> {
+    let path = recent_vaults_file(&app)?;
+    blocking(move || Ok(recent_vaults::startup(&path))).await
+}
+
+// 取走启动时或运行中收到的打开请求，前端据此选中对应的日记库
+#[tauri::command]
+#[specta::specta]
+async fn take_open_request(state: State<'_, AppState>) -> Result<Option<String>, VaultError> {
+    Ok(state.open_request.lock().take())
+}
+
+fn request_open(app: &AppHandle, directory: Option<PathBuf>) {
+    let Some(directory) = directory else {
+        return;
+    };
+    let directory = directory.to_string_lossy().into_owned();
+    tracing::info!(%directory, "open request received");
+    *app.state::<AppState>().open_request.lock() = Some(directory.clone());
+    let _ = app.emit(OPEN_REQUEST_EVENT, OpenRequest { directory });
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn forget_recent_vault(directory: String, app: AppHandle) -> Result<(), VaultError> {
+    let path = recent_vaults_file(&app)?;
+    let root = PathBuf::from(directory.trim());
+    blocking(move || recent_vaults::forget(&path, &root)).await
+}
+
+fn quick_unlock_dir(app: &AppHandle) -> Result<PathBuf, VaultError> {
+    app_data_dir(app).map(|dir| dir.join("quick_unlock"))
+}
+
+fn vault_root_for(path: &Path) -> PathBuf {
+    path.parent()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn unlock_vault_with_pin(
+    pin: String,
+    directory: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<OpenedVault, VaultError> {
+    let path = resolve_vault_location(&app, &state, directory).await?;
+    let store_dir = quick_unlock_dir(&app)?;
+    open_vault(&app, &state, path, move |manager, path| {
+        manager.unlock_with_pin(&store_dir, &pin, path)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn enable_quick_unlock(
+    vault_id: Uuid,
+    pin: String,
+    max_attempts: Option<u32>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    let store_dir = quick_unlock_dir(&app)?;
+    with_manager(&state, vault_id, move |manager| {
+        manager.enable_quick_unlock(&store_dir, &pin, max_attempts.unwrap_or_default())
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn disable_quick_unlock(
+    directory: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    let path = resolve_vault_location(&app, &state, directory).await?;
+    let store_dir = quick_unlock_dir(&app)?;
+    blocking(move || quick_unlock::disable(&store_dir, &vault_root_for(&path))).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_quick_unlock_status(
+    directory: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<QuickUnlockStatus, VaultError> {
+    let path = resolve_vault_location(&app, &state, directory).await?;
+    let store_dir = quick_unlock_dir(&app)?;
+    blocking(move || quick_unlock::status(&store_dir, &vault_root_for(&path))).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn lock_vault(
+    vault_id: Uuid,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    with_manager(&state, vault_id, |manager| manager.lock()).await?;
+    state.vaults.write().remove(&vault_id);
+    tracing::info!(%vault_id, "vault locked");
+    // 锁定时写入的最后改动立即同步，不等后台线程
+    if !state.mirrors.lock().is_empty() {
+        let handle = app.clone();
+        blocking(move || {
+            push_mirrors(&handle.state::<AppState>());
+            Ok(())
+        })
+        .await?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn flush_vault(vault_id: Uuid, state: State<'_, AppState>) -> Result<bool, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.flush()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_last_unlock_info(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<LastUnlockInfo, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.last_unlock_info()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn list_entries(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Vec<EntryInfo>, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.list()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn search_entries(
+    vault_id: Uuid,
+    query: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<EntryInfo>, VaultError> {
+    with_manager(&state, vault_id, move |manager| manager.search(&query)).await
+}
+
+// month 为 1-12；未设置日记日期的条目按本机时区的新建日期计
+#[tauri::command]
+#[specta::specta]
+async fn get_calendar_month(
+    vault_id: Uuid,
+    year: i32,
+    month: u8,
+    state: State<'_, AppState>,
+) -> Result<Vec<CalendarDay>, VaultError> {
+    let offset = local_now().offset();
+    with_manager(&state, vault_id, move |manager| {
+        manager.calendar_month(year, month, offset)
+    })
+    .await
+}
+
+// date 为 YYYY-MM-DD；未设置日记日期的条目按本机时区的新建日期计
+#[tauri::command]
+#[specta::specta]
+async fn get_day(
+    vault_id: Uuid,
+    date: String,
+    state: State<'_, AppState>,
+) -> Result<DayView, VaultError> {
+    let date = validation::date(&date)?;
+    let offset = local_now().offset();
+    with_manager(&state, vault_id, move |manager| manager.day(date, offset)).await
+}
+
+// summary 为空时清除当天的小结
+#[tauri::command]
+#[specta::specta]
+async fn set_day_summary(
+    vault_id: Uuid,
+    date: String,
+    summary: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    let date = validation::date(&date)?;
+    if let Some(summary) = &summary {
+        validation::day_summary(summary)?;
+    }
+    with_manager(&state, vault_id, move |manager| {
+        manager.set_day_summary(date, summary)
+    })
+    .await
+}
+
+// 按本机时区的日记日期分组，年、月和条目都是新的在前
+#[tauri::command]
+#[specta::specta]
+async fn get_timeline(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Vec<TimelineYear>, VaultError> {
+    let offset = local_now().offset();
+    with_manager(&state, vault_id, move |manager| manager.timeline(offset)).await
+}
+
+// 按本机时区划分月份；create_entry 为 true 时写成回顾日记，同月再次生成会更新原日记
+#[tauri::command]
+#[specta::specta]
+async fn generate_month_recap(
+    vault_id: Uuid,
+    year: i32,
+    month: u8,
+    create_entry: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<MonthRecap, VaultError> {
+    let month = Month::try_from(month).map_err(|_| VaultError::invalid(Message::InvalidDate))?;
+    let offset = local_now().offset();
+    with_manager(&state, vault_id, move |manager| {
+        manager.month_recap(year, month, offset, create_entry.unwrap_or_default())
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_on_this_day(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Vec<EntryInfo>, VaultError> {
+    let now = local_now();
+    with_manager(&state, vault_id, move |manager| {
+        manager.on_this_day(now.date(), now.offset())
+    })
+    .await
+}
+
+// 按本机时区划分日期
+#[tauri::command]
+#[specta::specta]
+async fn get_mood_trends(
+    vault_id: Uuid,
+    range: DateRange,
+    bucket: TrendBucket,
+    state: State<'_, AppState>,
+) -> Result<MoodTrends, VaultError> {
+    let offset = local_now().offset();
+    with_manager(&state, vault_id, move |manager| {
+        manager.mood_trends(range, bucket, offset)
+    })
+    .await
+}
+
+// 需先在日记库设置中开启情感分析；日期按本机时区划分
+#[tauri::command]
+#[specta::specta]
+async fn get_sentiment_trends(
+    vault_id: Uuid,
+    range: DateRange,
+    bucket: TrendBucket,
+    state: State<'_, AppState>,
+) -> Result<SentimentTrends, VaultError> {
+    let offset = local_now().offset();
+    with_manager(&state, vault_id, move |manager| {
+        manager.sentiment_trends(range, bucket, offset)
+    })
+    .await
+}
+
+// 按本机时区统计今天和本周的进度；目标通过 set_vault_settings 保存
+#[tauri::command]
+#[specta::specta]
+async fn get_goal_progress(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<GoalProgress, VaultError> {
+    let now = local_now();
+    with_manager(&state, vault_id, move |manager| manager.goal_progress(now)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn list_prompt_packs(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Vec<PromptPackInfo>, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.prompt_packs()).await
+}
+
+// id 为空时新建自定义题库
+#[tauri::command]
+#[specta::specta]
+async fn save_prompt_pack(
+    vault_id: Uuid,
+    id: Option<String>,
+    name: String,
+    prompts: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<PromptPack, VaultError> {
+    validation::prompt_pack(&name, &prompts)?;
+    with_manager(&state, vault_id, move |manager| {
+        manager.save_prompt_pack(id, name, prompts)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn delete_prompt_pack(
+    vault_id: Uuid,
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    with_manager(&state, vault_id, move |manager| manager.delete_prompt_pack(&id)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_prompt_pack_enabled(
+    vault_id: Uuid,
+    id: String,
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    with_manager(&state, vault_id, move |manager| {
+        manager.set_prompt_pack_enabled(&id, enabled)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_todays_prompt(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Option<TodaysPrompt>, VaultError> {
+    let today = local_now().date();
+    with_manager(&state, vault_id, move |manager| manager.todays_prompt(today)).await
+}
+
+// 写完当天的题目后关联日记，之后轮换会跳过这道题
+#[tauri::command]
+#[specta::specta]
+async fn answer_prompt(
+    vault_id: Uuid,
+    pack_id: String,
+    index: u32,
+    entry_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Option<TodaysPrompt>, VaultError> {
+    let today = local_now().date();
+    with_manager(&state, vault_id, move |manager| {
+        manager.answer_prompt(today, &pack_id, index as usize, entry_id)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn list_habits(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Vec<Habit>, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.habits()).await
+}
+
+// id 为空时新建习惯；unit 为空表示只记录是否完成，此时 target 应为 1
+#[tauri::command]
+#[specta::specta]
+async fn save_habit(
+    vault_id: Uuid,
+    id: Option<String>,
+    name: String,
+    unit: Option<String>,
+    target: f64,
+    state: State<'_, AppState>,
+) -> Result<Habit, VaultError> {
+    validation::habit(&name, unit.as_deref(), target)?;
+    with_manager(&state, vault_id, move |manager| {
+        manager.save_habit(id, name, unit, target)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn delete_habit(
+    vault_id: Uuid,
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    with_manager(&state, vault_id, move |manager| manager.delete_habit(&id)).await
+}
+
+// 个人词典保存在日记库中，按字母顺序返回
+#[tauri::command]
+#[specta::specta]
+async fn list_dictionary_words(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.dictionary_words()).await
 }
 
 #[tauri::command]
-fn load_entry(id: Uuid, state: State<AppState>) -> Result<Entry, String> {
-    state.manager.load_entry(id).map_err(|err| err.to_string())
+#[specta::specta]
+async fn add_dictionary_word(
+    vault_id: Uuid,
+    word: String,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    validation::dictionary_word(&word)?;
+    with_manager(&state, vault_id, move |manager| manager.add_dictionary_word(&word)).await
 }
 
 #[tauri::command]
-fn create_entry(
+#[specta::specta]
+async fn remove_dictionary_word(
+    vault_id: Uuid,
+    word: String,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    with_manager(&state, vault_id, move |manager| {
+        manager.remove_dictionary_word(&word)
+    })
+    .await
+}
+
+// date 为 YYYY-MM-DD；value 为 0 时取消当天的打卡
+#[tauri::command]
+#[specta::specta]
+async fn log_habit(
+    vault_id: Uuid,
+    date: String,
+    habit_id: String,
+    value: f64,
+    append_to_entry: bool,
+    state: State<'_, AppState>,
+) -> Result<Option<Entry>, VaultError> {
+    let date = validation::date(&date)?;
+    validation::habit_value(value)?;
+    with_manager(&state, vault_id, move |manager| {
+        manager.log_habit(date, &habit_id, value, append_to_entry)
+    })
+    .await
+}
+
+// from/to 为 YYYY-MM-DD，包含两端；连续天数按本机时区的今天计算
+#[tauri::command]
+#[specta::specta]
+async fn get_habit_history(
+    vault_id: Uuid,
+    habit_id: String,
+    from: String,
+    to: String,
+    state: State<'_, AppState>,
+) -> Result<HabitHistory, VaultError> {
+    let from = validation::date(&from)?;
+    let to = validation::date(&to)?;
+    let today = local_now().date();
+    with_manager(&state, vault_id, move |manager| {
+        manager.habit_history(&habit_id, from, to, today)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn list_folders(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Vec<FolderCount>, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.list_folders()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn list_tags(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Vec<TagCount>, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.list_tags()).await
+}
+
+// range 按日记日期筛选，两端都可省略
+#[tauri::command]
+#[specta::specta]
+async fn get_writing_stats(
+    vault_id: Uuid,
+    group_by: StatsGroup,
+    range: DateRange,
+    state: State<'_, AppState>,
+) -> Result<Vec<WritingStats>, VaultError> {
+    with_manager(&state, vault_id, move |manager| manager.writing_stats(group_by, range)).await
+}
+
+// 词云数据；stopword_lang 为空时同时去掉中英文停用词，limit 缺省取 100 个词
+#[tauri::command]
+#[specta::specta]
+async fn get_word_frequencies(
+    vault_id: Uuid,
+    range: DateRange,
+    stopword_lang: Option<Locale>,
+    limit: Option<u32>,
+    state: State<'_, AppState>,
+) -> Result<Vec<WordFrequency>, VaultError> {
+    let limit = limit.map_or(wordfreq::DEFAULT_TERMS, |limit| limit as usize);
+    let limit = limit.min(wordfreq::MAX_TERMS);
+    with_manager(&state, vault_id, move |manager| {
+        manager.word_frequencies(range, stopword_lang, limit)
+    })
+    .await
+}
+
+// radius_km 为搜索半径（公里），结果按距离由近到远
+#[tauri::command]
+#[specta::specta]
+async fn list_entries_near(
+    vault_id: Uuid,
+    latitude: f64,
+    longitude: f64,
+    radius_km: f64,
+    state: State<'_, AppState>,
+) -> Result<Vec<EntryInfo>, VaultError> {
+    validation::coordinates(latitude, longitude)?;
+    validation::radius(radius_km)?;
+    with_manager(&state, vault_id, move |manager| {
+        manager.list_entries_near(latitude, longitude, radius_km)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn list_places(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Vec<PlaceCount>, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.list_places()).await
+}
+
+// 组合条件查询，供列表、筛选和搜索共用
+#[tauri::command]
+#[specta::specta]
+async fn query_entries(
+    vault_id: Uuid,
+    spec: QuerySpec,
+    state: State<'_, AppState>,
+) -> Result<QueryPage, VaultError> {
+    with_manager(&state, vault_id, move |manager| manager.query(spec)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_entry_info(
+    vault_id: Uuid,
+    id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Option<EntryInfo>, VaultError> {
+    with_manager(&state, vault_id, move |manager| manager.entry_info(id)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn load_entry(
+    vault_id: Uuid,
+    id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Entry, VaultError> {
+    with_manager(&state, vault_id, move |manager| manager.load_entry(id)).await
+}
+
+// 新建日记的参数校验，IPC 命令与 headless 共用
+fn check_new_entry(
+    title: &str,
+    content: &str,
+    location: Option<&Location>,
+) -> Result<(), VaultError> {
+    validation::title(title)?;
+    validation::content(content)?;
+    validation::location(location)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn create_entry(
+    vault_id: Uuid,
     title: Option<String>,
     content: Option<String>,
     encryption: Option<TextEncryption>,
-    state: State<AppState>,
-) -> Result<Entry, String> {
+    location: Option<Location>,
+    state: State<'_, AppState>,
+) -> Result<Entry, VaultError> {
     let title = title.unwrap_or_else(|| "Untitled entry".to_string());
     let content = content.unwrap_or_default();
-    state
-        .manager
-        .create_entry(&title, &content, encryption)
-        .map_err(|err| err.to_string())
+    check_new_entry(&title, &content, location.as_ref())?;
+    let manager = state.manager(&vault_id)?;
+    let weather = match &location {
+        Some(location) => current_weather(&manager, location).await,
+        None => None,
+    };
+    let item = NewEntry {
+        title,
+        content,
+        encryption,
+        location,
+        weather,
+        ..Default::default()
+    };
+    blocking(move || manager.create_entry_from(item)).await
 }
 
+// 引导式日记：正文由结构化内容生成，标题为空时按类型取默认标题
 #[tauri::command]
-fn update_entry(entry: Entry, state: State<AppState>) -> Result<Entry, String> {
-    state
-        .manager
-        .update_entry(entry)
-        .map_err(|err| err.to_string())
+#[specta::specta]
+async fn create_typed_entry(
+    vault_id: Uuid,
+    payload: EntryPayload,
+    title: Option<String>,
+    entry_date: Option<String>,
+    location: Option<Location>,
+    state: State<'_, AppState>,
+) -> Result<Entry, VaultError> {
+    let title = title.unwrap_or_default();
+    validation::title(&title)?;
+    validation::entry_payload(Some(&payload))?;
+    validation::location(location.as_ref())?;
+    let entry_date = entry_date.as_deref().map(validation::date).transpose()?;
+    let manager = state.manager(&vault_id)?;
+    let weather = match &location {
+        Some(location) => current_weather(&manager, location).await,
+        None => None,
+    };
+    let item = NewEntry {
+        title,
+        payload: Some(payload),
+        entry_date,
+        location,
+        weather,
+        ..Default::default()
+    };
+    blocking(move || manager.create_entry_from(item)).await
+}
+
+// 未开启天气或获取失败（离线、超时、密钥无效）时返回 None，日记照常新建
+async fn current_weather(manager: &VaultManager, location: &Location) -> Option<Weather> {
+    let settings = manager.settings().ok()?.weather;
+    if !settings.enabled {
+        return None;
+    }
+    match weather::fetch(&settings, location).await {
+        Ok(weather) => Some(weather),
+        Err(err) => {
+            tracing::warn!(error = %err, "weather fetch failed");
+            None
+        }
+    }
+}
+
+// 批量新建的单条结果，顺序与输入一致
+#[derive(Serialize, Type)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum NewEntryResult {
+    Created { entry: Box<EntryInfo> },
+    Failed { error: VaultError },
 }
 
 #[tauri::command]
-fn delete_entry(id: Uuid, state: State<AppState>) -> Result<(), String> {
-    state
-        .manager
-        .delete_entry(id)
-        .map_err(|err| err.to_string())
+#[specta::specta]
+async fn create_entries(
+    vault_id: Uuid,
+    entries: Vec<NewEntry>,
+    state: State<'_, AppState>,
+) -> Result<Vec<NewEntryResult>, VaultError> {
+    create_checked(&state, vault_id, entries.into_iter().map(Ok).collect()).await
 }
 
+// 每个文件新建一篇日记，frontmatter 中的日期、标题、标签、心情和位置写入对应字段；
+// mapping 为其他应用使用的非标准键名。结果顺序与 paths 一致
 #[tauri::command]
-fn export_plaintext(state: State<AppState>) -> Result<String, String> {
-    state
-        .manager
-        .export_plaintext()
-        .map_err(|err| err.to_string())
+#[specta::specta]
+async fn import_markdown_files(
+    vault_id: Uuid,
+    paths: Vec<String>,
+    mapping: Option<FrontmatterMapping>,
+    state: State<'_, AppState>,
+) -> Result<Vec<NewEntryResult>, VaultError> {
+    let mapping = mapping.unwrap_or_default();
+    // 逐个读文件，放到阻塞线程池中，不占用 IPC 线程
+    let entries = blocking(move || {
+        Ok(paths
+            .iter()
+            .map(|path| frontmatter::read(Path::new(path), &mapping).map_err(VaultError::from))
+            .collect())
+    })
+    .await?;
+    create_checked(&state, vault_id, entries).await
+}
+
+// 读取或检查失败的条目直接记为失败，其余交给一次批量写入
+async fn create_checked(
+    state: &AppState,
+    vault_id: Uuid,
+    entries: Vec<Result<NewEntry, VaultError>>,
+) -> Result<Vec<NewEntryResult>, VaultError> {
+    let mut results = Vec::with_capacity(entries.len());
+    let mut accepted = Vec::new();
+    for entry in entries {
+        match entry.and_then(|entry| validation::new_entry(&entry).map(|()| entry)) {
+            Ok(entry) => {
+                results.push(None);
+                accepted.push(entry);
+            }
+            Err(error) => results.push(Some(NewEntryResult::Failed { error })),
+        }
+    }
+    let mut created = with_manager(state, vault_id, move |manager| {
+        manager.create_entries(accepted)
+    })
+    .await?
+    .into_iter();
+    Ok(results
+        .into_iter()
+        .filter_map(|result| {
+            result.or_else(|| {
+                created.next().map(|outcome| match outcome {
+                    Ok(entry) => NewEntryResult::Created {
+                        entry: Box::new(entry),
+                    },
+                    Err(err) => NewEntryResult::Failed { error: err.into() },
+                })
+            })
+        })
+        .collect())
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn update_entry(
+    vault_id: Uuid,
+    entry: Entry,
+    state: State<'_, AppState>,
+) -> Result<Entry, VaultError> {
+    validation::entry(&entry)?;
+    with_manager(&state, vault_id, move |manager| manager.update_entry(entry)).await
 }
 
 #[tauri::command]
-fn store_image(path: String, state: State<AppState>) -> Result<String, String> {
+#[specta::specta]
+async fn update_entry_payload(
+    vault_id: Uuid,
+    id: Uuid,
+    payload: EntryPayload,
+    state: State<'_, AppState>,
+) -> Result<Entry, VaultError> {
+    validation::entry_payload(Some(&payload))?;
+    with_manager(&state, vault_id, move |manager| manager.update_entry_payload(id, payload)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn patch_entry(
+    vault_id: Uuid,
+    id: Uuid,
+    patch: PatchFields,
+    state: State<'_, AppState>,
+) -> Result<EntryInfo, VaultError> {
+    validation::patch(&patch)?;
+    with_manager(&state, vault_id, move |manager| manager.patch_entry(id, patch)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn delete_entry(
+    vault_id: Uuid,
+    id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    with_manager(&state, vault_id, move |manager| manager.delete_entry(id)).await
+}
+
+// 把读不出的日记文件移到隔离目录并从列表中去掉，返回文件的新位置
+#[tauri::command]
+#[specta::specta]
+async fn quarantine_entry(
+    vault_id: Uuid,
+    id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<String, VaultError> {
+    with_manager(&state, vault_id, move |manager| manager.quarantine_entry(id)).await
+}
+
+// 编辑器的暂存内容，比自动保存更频繁；崩溃后下次解锁时由 recover_unsaved_changes 取回
+#[tauri::command]
+#[specta::specta]
+async fn save_draft(
+    vault_id: Uuid,
+    entry_id: Uuid,
+    title: String,
+    content: String,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    with_manager(&state, vault_id, move |manager| {
+        manager.save_draft(entry_id, title, content)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn discard_draft(
+    vault_id: Uuid,
+    entry_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    with_manager(&state, vault_id, move |manager| manager.discard_draft(entry_id)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn recover_unsaved_changes(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Vec<Draft>, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.recover_unsaved_changes()).await
+}
+
+// 撤销最近一次删除或移动；没有可撤销的操作时返回 null
+#[tauri::command]
+#[specta::specta]
+async fn undo_last(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Option<UndoOutcome>, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.undo_last()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn redo_last(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Option<UndoOutcome>, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.redo_last()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn export_plaintext(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<String, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.export_plaintext()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn store_image(
+    vault_id: Uuid,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<String, VaultError> {
     let trimmed = path.trim();
     if trimmed.is_empty() {
-        return Err("未选择任何文件".to_string());
+        return Err(VaultError::invalid(Message::NoFilesSelected));
     }
 
     let source = PathBuf::from(trimmed);
-    state
-        .manager
-        .store_image(source)
-        .map_err(|err| err.to_string())
+    validation::attachment_file(&source)?;
+    with_manager(&state, vault_id, move |manager| manager.store_image(source)).await
 }
 
 #[tauri::command]
-fn store_image_from_bytes(
+#[specta::specta]
+async fn store_image_from_bytes(
+    vault_id: Uuid,
     name: Option<String>,
     mime: Option<String>,
     data: Vec<u8>,
-    state: State<AppState>,
-) -> Result<String, String> {
-    state
-        .manager
-        .store_image_bytes(name, mime, data)
-        .map_err(|err| err.to_string())
+    state: State<'_, AppState>,
+) -> Result<String, VaultError> {
+    validation::attachment_size(data.len() as u64)?;
+    with_manager(&state, vault_id, move |manager| {
+        manager.store_image_bytes(name, mime, data)
+    })
+    .await
 }
 
 #[tauri::command]
-fn export_plaintext_file(state: State<AppState>) -> Result<String, String> {
-    let content = state
-        .manager
-        .export_plaintext()
-        .map_err(|err| err.to_string())?;
+#[specta::specta]
+async fn export_plaintext_file(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<ExportReport, VaultError> {
+    with_manager(&state, vault_id, |manager| {
+        let date_fmt = format_description!("[year]-[month]-[day]");
+        let now = local_now();
+        let suggested = format!(
+            "diary-{}.md",
+            now.format(&date_fmt).unwrap_or_else(|_| "today".into())
+        );
 
-    let date_fmt = format_description!("[year]-[month]-[day]");
-    let now = OffsetDateTime::now_utc();
-    let suggested = format!(
-        "diary-{}.md",
-        now.format(&date_fmt).unwrap_or_else(|_| "today".into())
-    );
+        let mut export_dir = manager.vault_root()?;
+        export_dir.push("exports");
+        export_dir.push(suggested);
+        let skipped = manager.export_plaintext_to(&export_dir)?;
 
-    let mut export_dir = state.manager.vault_root().map_err(|err| err.to_string())?;
-    export_dir.push("exports");
-    fs::create_dir_all(&export_dir).map_err(|err| err.to_string())?;
+        Ok(ExportReport {
+            path: export_dir.to_string_lossy().into_owned(),
+            skipped,
+        })
+    })
+    .await
+}
 
-    export_dir.push(suggested);
-    fs::write(&export_dir, content).map_err(|err| err.to_string())?;
+// 按 filters 选出日记写成加密的 HTML 分享包，保存在日记库的 exports 目录中；
+// 对方用浏览器打开并输入 password 即可查看，不需要安装应用
+#[tauri::command]
+#[specta::specta]
+async fn export_share_bundle(
+    vault_id: Uuid,
+    filters: QuerySpec,
+    password: String,
+    state: State<'_, AppState>,
+) -> Result<ShareReport, VaultError> {
+    validation::share_password(&password)?;
+    with_manager(&state, vault_id, move |manager| {
+        let date_fmt = format_description!("[year]-[month]-[day]-[hour][minute][second]");
+        let now = local_now();
+        let name = format!(
+            "share-{}.html",
+            now.format(&date_fmt).unwrap_or_else(|_| "today".into())
+        );
+        let mut path = manager.vault_root()?;
+        path.push("exports");
+        path.push(name);
+        manager.export_share_bundle(filters, &password, &path)
+    })
+    .await
+}
 
-    Ok(export_dir.to_string_lossy().into_owned())
+// 单篇日记连同附件写成一次性分享文件，保存在 exports 目录中；
+// 返回的 passcode 只显示这一次，需要另行告诉对方
+#[tauri::command]
+#[specta::specta]
+async fn share_entry(
+    vault_id: Uuid,
+    id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<EntryShareReport, VaultError> {
+    with_manager(&state, vault_id, move |manager| {
+        let date_fmt = format_description!("[year]-[month]-[day]-[hour][minute][second]");
+        let now = local_now();
+        let name = format!(
+            "entry-{}.{}",
+            now.format(&date_fmt).unwrap_or_else(|_| "today".into()),
+            share::ENTRY_SHARE_EXTENSION
+        );
+        let mut path = manager.vault_root()?;
+        path.push("exports");
+        path.push(name);
+        manager.share_entry(id, &path)
+    })
+    .await
 }
 
 #[tauri::command]
-fn decrypt_image(path: String, state: State<AppState>) -> Result<Vec<u8>, String> {
-    state
-        .manager
-        .decrypt_image(&path)
-        .map_err(|err| err.to_string())
+#[specta::specta]
+async fn import_shared_entry(
+    vault_id: Uuid,
+    path: String,
+    passcode: String,
+    state: State<'_, AppState>,
+) -> Result<Entry, VaultError> {
+    let path = PathBuf::from(path.trim());
+    if passcode.trim().is_empty() {
+        return Err(VaultError::invalid(Message::ShareWrongPassword));
+    }
+    with_manager(&state, vault_id, move |manager| {
+        manager.import_shared_entry(&path, &passcode)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_vault_info(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<VaultInfo, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.info()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn list_attachments(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Vec<AttachmentInfo>, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.list_attachments()).await
+}
+
+// 按本机时区的保存日期分组，旧日期在前；图片内容仍通过 decrypt_image 按 path 读取
+#[tauri::command]
+#[specta::specta]
+async fn get_media_timeline(
+    vault_id: Uuid,
+    range: DateRange,
+    state: State<'_, AppState>,
+) -> Result<Vec<MediaDay>, VaultError> {
+    let offset = local_now().offset();
+    with_manager(&state, vault_id, move |manager| {
+        manager.media_timeline(range, offset)
+    })
+    .await
+}
+
+// 以原始二进制返回，避免把每个字节编码成 JSON 数组
+#[tauri::command]
+async fn decrypt_image(
+    vault_id: Uuid,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Response, VaultError> {
+    with_manager(&state, vault_id, move |manager| manager.decrypt_image(&path))
+        .await
+        .map(Response::new)
 }
 
 #[tauri::command]
-fn change_vault_passphrase(
+#[specta::specta]
+async fn change_vault_passphrase(
+    vault_id: Uuid,
     old_passphrase: String,
     new_passphrase: String,
-    state: State<AppState>,
-) -> Result<(), String> {
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
     if new_passphrase.trim().is_empty() {
-        return Err("新密码不能为空".to_string());
+        return Err(VaultError::invalid(Message::EmptyPassphrase));
     }
-    if new_passphrase.len() < 6 {
-        return Err("新密码长度至少需要 6 个字符".to_string());
+    if new_passphrase.len() < MIN_PASSPHRASE_LENGTH {
+        return Err(VaultError::invalid(Message::PassphraseTooShort(MIN_PASSPHRASE_LENGTH)));
+    }
+    let store_dir = quick_unlock_dir(&app)?;
+    with_manager(&state, vault_id, move |manager| {
+        manager.change_passphrase(&old_passphrase, &new_passphrase)?;
+
+        // 修改口令后之前保存的快速解锁副本一并作废，需用新口令重新设置
+        quick_unlock::disable(&store_dir, &manager.vault_root()?)
+    })
+    .await
+}
+
+// 彻底撤销已移除的用户：换用新的主密钥重新加密全部文件，其他用户需重新添加
+#[tauri::command]
+#[specta::specta]
+async fn rotate_master_key(
+    vault_id: Uuid,
+    passphrase: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<usize, VaultError> {
+    let store_dir = quick_unlock_dir(&app)?;
+    with_manager(&state, vault_id, move |manager| {
+        let voided = manager.rotate_master_key(&passphrase)?;
+
+        // 主密钥已更换，之前保存的快速解锁副本随之作废
+        quick_unlock::disable(&store_dir, &manager.vault_root()?)?;
+        Ok(voided)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn list_user_slots(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<Vec<UserSlot>, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.user_slots()).await
+}
+
+// 为另一位用户添加独立的口令，解锁后与主口令看到同一本日记
+#[tauri::command]
+#[specta::specta]
+async fn add_user_slot(
+    vault_id: Uuid,
+    admin_passphrase: String,
+    label: String,
+    passphrase: String,
+    admin: bool,
+    state: State<'_, AppState>,
+) -> Result<UserSlot, VaultError> {
+    validation::key_slot_label(&label)?;
+    if passphrase.trim().is_empty() {
+        return Err(VaultError::invalid(Message::EmptyPassphrase));
+    }
+    if passphrase.len() < MIN_PASSPHRASE_LENGTH {
+        return Err(VaultError::invalid(Message::PassphraseTooShort(MIN_PASSPHRASE_LENGTH)));
+    }
+    with_manager(&state, vault_id, move |manager| {
+        manager.add_user_slot(&admin_passphrase, label.trim(), &passphrase, admin)
+    })
+    .await
+}
+
+// 撤销某位用户的口令；要让对方彻底无法解密已有文件，还需更换主密钥
+#[tauri::command]
+#[specta::specta]
+async fn remove_user_slot(
+    vault_id: Uuid,
+    admin_passphrase: String,
+    id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    with_manager(&state, vault_id, move |manager| {
+        manager.remove_user_slot(&admin_passphrase, id)
+    })
+    .await
+}
+
+fn destination_dir(directory: &str) -> Result<PathBuf, VaultError> {
+    let trimmed = directory.trim();
+    if trimmed.is_empty() {
+        return Err(VaultError::invalid(Message::InvalidDirectory));
+    }
+    if storage::is_document_uri(trimmed) {
+        return Err(VaultError::invalid(Message::DocumentTreeUnsupported));
+    }
+    let destination = PathBuf::from(trimmed);
+    if destination.is_file() {
+        return Err(VaultError::invalid(Message::NotADirectory));
+    }
+    Ok(destination)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn clone_vault(
+    vault_id: Uuid,
+    destination: String,
+    state: State<'_, AppState>,
+) -> Result<CloneReport, VaultError> {
+    let destination = destination_dir(&destination)?;
+    with_manager(&state, vault_id, move |manager| manager.clone_vault(&destination)).await
+}
+
+// 删除前必须锁定；成功后清理快速解锁记录和最近列表中的该目录
+#[tauri::command]
+#[specta::specta]
+async fn delete_vault(
+    directory: Option<String>,
+    passphrase: String,
+    backup_directory: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DeleteReport, VaultError> {
+    let path = resolve_vault_location(&app, &state, directory).await?;
+    let root = vault_root_for(&path);
+    let _reservation = state.reserve(&root, Message::LockVaultFirst)?;
+    let backup_dir = backup_directory
+        .as_deref()
+        .map(destination_dir)
+        .transpose()?;
+    let store_dir = quick_unlock_dir(&app)?;
+    let recent_path = recent_vaults_file(&app)?;
+    blocking(move || {
+        let canonical_root = root.canonicalize().unwrap_or_else(|_| root.clone());
+        let report = vault::delete_vault(path, &passphrase, backup_dir.as_deref())?;
+        let _ = quick_unlock::disable(&store_dir, &canonical_root);
+        let _ = recent_vaults::forget(&recent_path, &root);
+        Ok(report)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn import_vault(
+    vault_id: Uuid,
+    other_path: String,
+    other_passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<ImportReport, VaultError> {
+    let other_root = destination_dir(&other_path)?;
+    // 已打开的日记库（包括当前这个）不能再被临时解锁一次，否则两个句柄会同时写日志；
+    // 导入期间来源目录也不能被打开或删除
+    let _reservation = state.reserve(&other_root, Message::ImportSourceOpen)?;
+    with_manager(&state, vault_id, move |manager| {
+        manager.import_vault(vault_file_path(other_root), &other_passphrase)
+    })
+    .await
+}
+
+// Apple Health 传入解压后的 export.xml，Google Fit 传入 Takeout 中的 Fit 目录
+#[tauri::command]
+#[specta::specta]
+async fn import_health_data(
+    vault_id: Uuid,
+    source: HealthSource,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<HealthImportReport, VaultError> {
+    with_manager(&state, vault_id, move |manager| {
+        manager.import_health(source, Path::new(&path))
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn move_vault(
+    vault_id: Uuid,
+    new_directory: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<MoveReport, VaultError> {
+    let destination = destination_dir(&new_directory)?;
+    let store_dir = quick_unlock_dir(&app)?;
+    let recent_path = recent_vaults_file(&app)?;
+    with_manager(&state, vault_id, move |manager| {
+        let old_root = manager.vault_root()?;
+        // 旧目录可能随移动一起删除，先取得规范路径，快速解锁以此登记
+        let canonical_old = old_root.canonicalize().unwrap_or_else(|_| old_root.clone());
+        let report = manager.move_vault(&destination)?;
+
+        // 快速解锁按目录登记，移动后旧副本作废
+        let _ = quick_unlock::disable(&store_dir, &canonical_old);
+        let _ = recent_vaults::forget(&recent_path, &old_root);
+        let _ = recent_vaults::record(&recent_path, &destination, None);
+        Ok(report)
+    })
+    .await
+}
+
+// 按目录读取，不需要解锁，供选择器和窗口标题使用
+#[tauri::command]
+#[specta::specta]
+async fn get_vault_profile(
+    directory: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<VaultProfile, VaultError> {
+    let path = resolve_vault_location(&app, &state, directory).await?;
+    blocking(move || Ok(profile::read(&vault_root_for(&path)))).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_vault_profile(
+    vault_id: Uuid,
+    profile: VaultProfile,
+    state: State<'_, AppState>,
+) -> Result<VaultProfile, VaultError> {
+    with_manager(&state, vault_id, move |manager| manager.set_profile(profile)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_passphrase_history_depth(
+    vault_id: Uuid,
+    passphrase: String,
+    depth: usize,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    with_manager(&state, vault_id, move |manager| {
+        manager.set_passphrase_history_depth(&passphrase, depth)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_vault_policy(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<VaultPolicy, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.policy()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_vault_policy(
+    vault_id: Uuid,
+    passphrase: String,
+    policy: VaultPolicy,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    with_manager(&state, vault_id, move |manager| {
+        manager.set_policy(&passphrase, policy)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_vault_settings(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<VaultSettings, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.settings()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_vault_settings(
+    vault_id: Uuid,
+    settings: VaultSettings,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    with_manager(&state, vault_id, move |manager| manager.set_settings(settings)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn verify_history_chain(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<HistoryReport, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.verify_history_chain()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn verify_vault_integrity(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<IntegrityReport, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.verify_vault_integrity()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_auto_lock_timeout(
+    vault_id: Uuid,
+    minutes: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    if minutes == Some(0) {
+        return Err(VaultError::invalid(Message::AutoLockTooShort));
     }
     state
-        .manager
-        .change_passphrase(&old_passphrase, &new_passphrase)
-        .map_err(|err| err.to_string())
+        .manager(&vault_id)?
+        .set_auto_lock_after(minutes.map(|value| Duration::from_secs(value * 60)));
+    Ok(())
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_dialog::init())
-        .manage(AppState::default())
-        .invoke_handler(tauri::generate_handler![
+#[tauri::command]
+#[specta::specta]
+async fn configure_emergency_access(
+    vault_id: Uuid,
+    recipient_public_key: String,
+    delay_days: u32,
+    destination: String,
+    state: State<'_, AppState>,
+) -> Result<EmergencyStatus, VaultError> {
+    let access = EmergencyAccess::new(&recipient_public_key, delay_days, &destination)
+        .map_err(VaultError::from)?;
+    with_manager(&state, vault_id, move |manager| {
+        manager.configure_emergency_access(access)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn cancel_emergency_access(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<(), VaultError> {
+    with_manager(&state, vault_id, |manager| manager.cancel_emergency_access()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_emergency_access_status(
+    vault_id: Uuid,
+    state: State<'_, AppState>,
+) -> Result<EmergencyStatus, VaultError> {
+    with_manager(&state, vault_id, |manager| manager.emergency_access_status()).await
+}
+
+// 启动时由前端调用，不需要解锁：到期则发布归档
+#[tauri::command]
+#[specta::specta]
+async fn process_emergency_access(
+    directory: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<EmergencyStatus, VaultError> {
+    let path = resolve_vault_location(&app, &state, directory).await?;
+    blocking(move || emergency::process(&vault_root_for(&path))).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn generate_emergency_keypair() -> EmergencyKeypair {
+    emergency::generate_keypair()
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn open_emergency_archive(
+    archive_path: String,
+    secret_key: String,
+    output_dir: String,
+) -> Result<String, VaultError> {
+    blocking(move || {
+        emergency::open_archive(
+            &PathBuf::from(archive_path.trim()),
+            &secret_key,
+            &PathBuf::from(output_dir.trim()),
+        )
+        .map(|path| path.to_string_lossy().into_owned())
+    })
+    .await
+}
+
+// 不需要解锁，使用临时目录和随机密钥
+#[tauri::command]
+#[specta::specta]
+async fn run_benchmarks() -> Result<BenchmarkReport, VaultError> {
+    let scratch_dir =
+        std::env::temp_dir().join(format!("cipher-diary-benchmark-{}", Uuid::new_v4()));
+    blocking(move || vault::run_benchmarks(&scratch_dir)).await
+}
+
+// 不需要解锁；未指定目录时检查默认日记目录
+#[tauri::command]
+#[specta::specta]
+async fn run_self_test(
+    directory: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<SelfTestReport, VaultError> {
+    let vault_dir = vault_root_for(&resolve_vault_location(&app, &state, directory).await?);
+    let scratch_dir =
+        std::env::temp_dir().join(format!("cipher-diary-self-test-{}", Uuid::new_v4()));
+    blocking(move || vault::run_self_test(&scratch_dir, Some(&vault_dir))).await
+}
+
+// 命令和类型的 TypeScript 定义由 tauri-specta 生成，调试运行时写入前端目录
+fn specta_builder() -> tauri_specta::Builder<tauri::Wry> {
+    tauri_specta::Builder::<tauri::Wry>::new()
+        .commands(tauri_specta::collect_commands![
+            set_backend_locale,
+            probe_vault,
+            pick_document_tree,
+            create_vault,
             unlock_vault,
+            plan_vault_migration,
+            unlock_vault_with_pin,
+            enable_quick_unlock,
+            disable_quick_unlock,
+            get_quick_unlock_status,
+            get_app_settings,
+            set_app_settings,
+            set_log_level,
+            get_reminder_schedule,
+            set_reminder_schedule,
+            get_memory_schedule,
+            set_memory_schedule,
+            list_recent_vaults,
+            get_startup_vault,
+            forget_recent_vault,
+            take_open_request,
             lock_vault,
+            flush_vault,
+            get_last_unlock_info,
             list_entries,
+            search_entries,
+            query_entries,
+            list_folders,
+            list_tags,
+            get_writing_stats,
+            get_word_frequencies,
+            list_entries_near,
+            list_places,
+            get_calendar_month,
+            get_day,
+            set_day_summary,
+            get_timeline,
+            generate_month_recap,
+            get_on_this_day,
+            get_goal_progress,
+            get_mood_trends,
+            get_sentiment_trends,
+            list_prompt_packs,
+            save_prompt_pack,
+            delete_prompt_pack,
+            set_prompt_pack_enabled,
+            get_todays_prompt,
+            answer_prompt,
+            list_habits,
+            save_habit,
+            delete_habit,
+            log_habit,
+            list_dictionary_words,
+            add_dictionary_word,
+            remove_dictionary_word,
+            get_habit_history,
+            get_entry_info,
             load_entry,
             create_entry,
+            create_typed_entry,
+            create_entries,
+            import_markdown_files,
             update_entry,
+            update_entry_payload,
+            patch_entry,
             delete_entry,
+            quarantine_entry,
+            save_draft,
+            discard_draft,
+            recover_unsaved_changes,
+            undo_last,
+            redo_last,
             export_plaintext,
             store_image,
             store_image_from_bytes,
             export_plaintext_file,
-            decrypt_image,
-            change_vault_passphrase
+            export_share_bundle,
+            share_entry,
+            import_shared_entry,
+            get_vault_info,
+            list_attachments,
+            get_media_timeline,
+            change_vault_passphrase,
+            rotate_master_key,
+            list_user_slots,
+            add_user_slot,
+            remove_user_slot,
+            clone_vault,
+            import_vault,
+            import_health_data,
+            delete_vault,
+            move_vault,
+            get_vault_profile,
+            set_vault_profile,
+            set_passphrase_history_depth,
+            get_vault_policy,
+            set_vault_policy,
+            get_vault_settings,
+            set_vault_settings,
+            verify_history_chain,
+            verify_vault_integrity,
+            set_auto_lock_timeout,
+            configure_emergency_access,
+            cancel_emergency_access,
+            get_emergency_access_status,
+            process_emergency_access,
+            generate_emergency_keypair,
+            open_emergency_archive,
+            run_benchmarks,
+            run_self_test,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .typ::<VaultScoped<VaultEvent>>()
+        .typ::<VaultScoped<UnlockProgress>>()
+        .typ::<VaultScoped<EntriesChanged>>()
+}
+
+// headless 构建不打包前端资源，也就没有窗口入口，改用 headless::HeadlessApp
+#[cfg(not(feature = "headless"))]
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    let builder = specta_builder();
+    #[cfg(debug_assertions)]
+    builder
+        .export(
+            Typescript::default().bigint(BigIntExportBehavior::Number),
+            "../src/lib/bindings.ts",
+        )
+        .expect("failed to export TypeScript bindings");
+    let typed = builder.invoke_handler();
+    // 图片以原始二进制返回，specta 无法描述，单独注册
+    let binary = tauri::generate_handler![decrypt_image];
+
+    let builder = tauri::Builder::default();
+    // 已在运行时再次双击日记文件或打开链接，参数转交给已有的窗口；必须最先注册
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+        request_open(app, open_request::from_args(argv, Path::new(&cwd)));
+    }));
+
+    #[cfg(target_os = "android")]
+    let builder = builder.plugin(saf::init());
+
+    builder
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
+        .manage(AppState::default())
+        .setup(|app| {
+            // 在前端第一次调用命令之前应用保存的语言和日志级别
+            if let Ok(dir) = app_data_dir(app.handle()) {
+                let settings = app_settings::read(&app_settings::app_settings_path(&dir));
+                if let Some(locale) = settings.locale {
+                    i18n::set_locale(locale);
+                }
+                // 日志不可用时照常启动
+                if let Err(err) = logging::init(&logging::log_dir(&dir), settings.log_level) {
+                    eprintln!("failed to initialize logging: {err:#}");
+                }
+            }
+            tracing::info!(version = env!("CARGO_PKG_VERSION"), "app started");
+
+            // 由文件关联或链接启动时先记下，前端加载后通过 take_open_request 取走
+            let cwd = std::env::current_dir().unwrap_or_default();
+            request_open(
+                app.handle(),
+                open_request::from_args(std::env::args().skip(1), &cwd),
+            );
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                let directory = event
+                    .urls()
+                    .iter()
+                    .find_map(|url| open_request::from_url(url.as_str()));
+                request_open(&handle, directory);
+            });
+            // 安装包之外（开发或便携版）运行时也注册链接协议
+            #[cfg(any(windows, target_os = "linux"))]
+            if let Err(err) = app.deep_link().register_all() {
+                tracing::warn!(error = %err, "failed to register deep link scheme");
+            }
+
+            let handle = app.handle().clone();
+            thread::spawn(move || loop {
+                thread::sleep(AUTO_LOCK_POLL_INTERVAL);
+                let state = handle.state::<AppState>();
+                for (vault_id, manager) in state.managers() {
+                    if manager.lock_if_idle() {
+                        state.vaults.write().remove(&vault_id);
+                        tracing::info!(%vault_id, "vault auto-locked");
+                    }
+                }
+            });
+
+            let handle = app.handle().clone();
+            thread::spawn(move || loop {
+                thread::sleep(METADATA_FLUSH_POLL_INTERVAL);
+                for (vault_id, manager) in handle.state::<AppState>().managers() {
+                    // 写入失败时保留改动，下一轮重试
+                    if let Err(err) = manager.flush_if_due() {
+                        tracing::warn!(%vault_id, error = %err, "metadata flush failed");
+                    }
+                }
+            });
+
+            let handle = app.handle().clone();
+            thread::spawn(move || loop {
+                thread::sleep(MIRROR_SYNC_POLL_INTERVAL);
+                push_mirrors(&handle.state::<AppState>());
+            });
+
+            let handle = app.handle().clone();
+            thread::spawn(move || {
+                let mut clock = ReminderClock::new(local_now());
+                let mut memory_clock = ReminderClock::new(local_now());
+                loop {
+                    thread::sleep(REMINDER_POLL_INTERVAL);
+                    let Ok(path) = app_settings_file(&handle) else {
+                        continue;
+                    };
+                    let settings = app_settings::read(&path);
+                    let schedule = settings.reminders;
+                    let now = local_now();
+                    if memory_clock.memories_due(now, &settings.memories) {
+                        let state = handle.state::<AppState>();
+                        if let Some(body) = find_memory(&state, now, &settings.memories) {
+                            show_memory(&handle, body);
+                        }
+                    }
+                    if !clock.due(now, &schedule) {
+                        continue;
+                    }
+                    if schedule.skip_if_written && wrote_today(&handle.state::<AppState>(), now) {
+                        tracing::debug!("reminder skipped: already wrote today");
+                        continue;
+                    }
+                    show_reminder(&handle);
+                }
+            });
+            Ok(())
+        })
+        .invoke_handler(move |invoke| match invoke.message.command() {
+            "decrypt_image" => binary(invoke),
+            _ => typed(invoke),
+        })
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            // macOS 通过文件关联打开时不经过启动参数
+            #[cfg(target_os = "macos")]
+            if let tauri::RunEvent::Opened { urls } = &event {
+                let directory = urls
+                    .iter()
+                    .find_map(|url| open_request::from_url(url.as_str()));
+                request_open(app, directory);
+            }
+            // 退出前写回尚未落盘的元数据
+            if let tauri::RunEvent::Exit = event {
+                for (_, manager) in app.state::<AppState>().managers() {
+                    let _ = manager.flush();
+                }
+            }
+        });
 }
@@ -0,0 +1,254 @@
+use anyhow::{anyhow, Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fmt::{self, Write as _};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Metadata, Subscriber};
+
+use cipher_diary_core::permissions;
+
+// 诊断日志写入应用数据目录下的 logs/diary.log，超过上限后轮转，只保留最近几份
+const LOG_DIR: &str = "logs";
+const LOG_FILE: &str = "diary.log";
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+const MAX_LOG_FILES: usize = 3;
+const MAX_VALUE_CHARS: usize = 200;
+
+// 字段名包含这些词时只写 [redacted]，不论值的类型。口令、密钥和解密后的内容
+// 只能以字段形式出现在日志调用中，消息文字必须是固定文本
+const REDACTED_FIELDS: [&str; 10] = [
+    "passphrase",
+    "password",
+    "pin",
+    "key",
+    "secret",
+    "token",
+    "content",
+    "plaintext",
+    "title",
+    "salt",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Off => LevelFilter::OFF,
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => LogLevel::Off,
+            1 => LogLevel::Error,
+            2 => LogLevel::Warn,
+            3 => LogLevel::Info,
+            4 => LogLevel::Debug,
+            _ => LogLevel::Trace,
+        }
+    }
+}
+
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+}
+
+impl LogFile {
+    fn open(path: PathBuf) -> Result<Self> {
+        let file = permissions::open_private_append(&path)?;
+        let written = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        Ok(Self {
+            path,
+            file,
+            written,
+        })
+    }
+
+    fn append(&mut self, line: &str) -> Result<()> {
+        if self.written + line.len() as u64 > MAX_LOG_BYTES {
+            self.rotate()?;
+        }
+        self.file
+            .write_all(line.as_bytes())
+            .context("failed to write log")?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    // diary.log -> diary.log.1 -> diary.log.2 ...，最旧的一份被覆盖
+    fn rotate(&mut self) -> Result<()> {
+        for index in (1..MAX_LOG_FILES).rev() {
+            let from = rotated_path(&self.path, index);
+            if from.exists() {
+                fs::rename(&from, rotated_path(&self.path, index + 1))
+                    .context("failed to rotate log")?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1)).context("failed to rotate log")?;
+        *self = Self::open(self.path.clone())?;
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+struct Logger {
+    level: AtomicU8,
+    file: Mutex<LogFile>,
+}
+
+impl Logger {
+    fn level(&self) -> LogLevel {
+        LogLevel::from_u8(self.level.load(Ordering::Relaxed))
+    }
+
+    fn write(&self, event: &Event<'_>) {
+        let metadata = event.metadata();
+        let mut visitor = RedactingVisitor::default();
+        event.record(&mut visitor);
+        let timestamp = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default();
+        let line = format!(
+            "{timestamp} {:>5} {}: {}{}\n",
+            metadata.level(),
+            metadata.target(),
+            visitor.message,
+            visitor.fields
+        );
+        // 日志写入失败不能影响正常使用
+        let _ = self.file.lock().append(&line);
+    }
+}
+
+#[derive(Default)]
+struct RedactingVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for RedactingVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = truncate(format!("{value:?}").replace('\n', "\\n"));
+            return;
+        }
+        let value = if is_redacted(field.name()) {
+            "[redacted]".to_string()
+        } else {
+            truncate(format!("{value:?}"))
+        };
+        let _ = write!(self.fields, " {}={value}", field.name());
+    }
+}
+
+fn is_redacted(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    REDACTED_FIELDS.iter().any(|word| name.contains(word))
+}
+
+fn truncate(mut value: String) -> String {
+    if let Some((index, _)) = value.char_indices().nth(MAX_VALUE_CHARS) {
+        value.truncate(index);
+        value.push('…');
+    }
+    value
+}
+
+// 不记录 span，只处理事件
+struct LogSubscriber(&'static Logger);
+
+impl Subscriber for LogSubscriber {
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= self.0.level().filter()
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.0.level().filter())
+    }
+
+    fn new_span(&self, _span: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        self.0.write(event);
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+pub fn log_dir(app_dir: &Path) -> PathBuf {
+    app_dir.join(LOG_DIR)
+}
+
+// 只能初始化一次；之后通过 set_level 调整级别
+pub fn init(log_dir: &Path, level: LogLevel) -> Result<()> {
+    permissions::create_private_dir_all(log_dir)?;
+    let file = LogFile::open(log_dir.join(LOG_FILE))?;
+    let logger = Logger {
+        level: AtomicU8::new(level as u8),
+        file: Mutex::new(file),
+    };
+    LOGGER
+        .set(logger)
+        .map_err(|_| anyhow!("logging already initialized"))?;
+    let logger = LOGGER
+        .get()
+        .ok_or_else(|| anyhow!("logging not initialized"))?;
+    tracing::subscriber::set_global_default(LogSubscriber(logger))
+        .context("failed to install log subscriber")
+}
+
+pub fn set_level(level: LogLevel) {
+    if let Some(logger) = LOGGER.get() {
+        logger.level.store(level as u8, Ordering::Relaxed);
+        // 各调用点缓存了是否启用，需要重新计算
+        tracing::callsite::rebuild_interest_cache();
+    }
+}
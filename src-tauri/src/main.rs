@@ -2,5 +2,8 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
-    diary_lib::run()
+    #[cfg(not(feature = "headless"))]
+    diary_lib::run();
+    #[cfg(feature = "headless")]
+    eprintln!("built with the headless feature; drive the app through diary_lib::headless");
 }
@@ -0,0 +1,58 @@
+use serde::Serialize;
+use specta::Type;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+use cipher_diary_core::vault::vault_file_path;
+
+// 双击日记文件、以 vault.json 为参数启动或打开 cipherdiary://open?path=... 链接时，
+// 通知前端切换到对应日记库的解锁界面。只预先选中目录，仍需输入密码
+pub const OPEN_REQUEST_EVENT: &str = "vault://open-request";
+pub const DEEP_LINK_SCHEME: &str = "cipherdiary";
+// 放在日记库目录中的快捷文件；macOS 上也可以是以此为扩展名的日记库目录本身
+pub const BUNDLE_EXTENSION: &str = "cipherdiary";
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct OpenRequest {
+    pub directory: String,
+}
+
+// 启动参数中第一个指向日记库的路径或链接；相对路径按启动时的工作目录解析
+pub fn from_args<I>(args: I, cwd: &Path) -> Option<PathBuf>
+where
+    I: IntoIterator<Item = String>,
+{
+    args.into_iter()
+        .find_map(|arg| from_url(&arg).or_else(|| vault_dir(&cwd.join(arg))))
+}
+
+pub fn from_url(text: &str) -> Option<PathBuf> {
+    let url = Url::parse(text).ok()?;
+    match url.scheme() {
+        DEEP_LINK_SCHEME if url.host_str() == Some("open") => {
+            let (_, path) = url.query_pairs().find(|(key, _)| key == "path")?;
+            vault_dir(Path::new(path.as_ref()))
+        }
+        // macOS 通过文件关联打开时传入 file:// 地址
+        "file" => vault_dir(&url.to_file_path().ok()?),
+        _ => None,
+    }
+}
+
+// 接受日记库目录、其中的 vault.json 或 .cipherdiary 文件，返回日记库目录；
+// 目录中没有日记库时忽略，链接不能用来触发新建
+fn vault_dir(path: &Path) -> Option<PathBuf> {
+    let dir = if path.is_dir() {
+        path.to_path_buf()
+    } else {
+        let parent = path.parent()?.to_path_buf();
+        let bundle = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(BUNDLE_EXTENSION));
+        if !bundle && vault_file_path(parent.clone()) != path {
+            return None;
+        }
+        parent
+    };
+    vault_file_path(dir.clone()).is_file().then_some(dir)
+}
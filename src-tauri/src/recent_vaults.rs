@@ -0,0 +1,106 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+use cipher_diary_core::permissions;
+use cipher_diary_core::profile::{self, VaultProfile};
+use cipher_diary_core::vault::{vault_file_path, TextEncryption};
+
+// 最近打开过的日记目录，保存在应用数据目录中，只有路径，不含任何密钥
+const RECENT_VAULTS_FILE: &str = "recent_vaults.json";
+const MAX_RECENT_VAULTS: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct RecentVault {
+    pub directory: String,
+    pub last_opened_at: OffsetDateTime,
+    // 上次在该日记库中使用的文本加密算法，启动时预先选中
+    #[serde(default)]
+    pub text_encryption: Option<TextEncryption>,
+    // 只在返回给前端时填写，目录被移走或删除后为 false
+    #[serde(default, skip_deserializing)]
+    pub available: bool,
+    // 同上，用于在列表中显示名称和图标而不是路径
+    #[serde(default, skip_deserializing)]
+    pub profile: Option<VaultProfile>,
+}
+
+pub fn recent_vaults_path(app_dir: &Path) -> PathBuf {
+    app_dir.join(RECENT_VAULTS_FILE)
+}
+
+fn same_directory(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn read_list(path: &Path) -> Vec<RecentVault> {
+    // 列表损坏时从空列表开始，不影响解锁
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_list(path: &Path, list: &[RecentVault]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        permissions::create_private_dir_all(parent)?;
+    }
+    let serialized =
+        serde_json::to_string_pretty(list).context("failed to serialize recent vaults")?;
+    permissions::write_private(path, serialized).context("failed to write recent vaults")
+}
+
+// 解锁成功后把目录移到列表最前；未给出加密算法时沿用原记录
+pub fn record(path: &Path, root: &Path, text_encryption: Option<TextEncryption>) -> Result<()> {
+    let mut list = read_list(path);
+    let previous = list
+        .iter()
+        .find(|recent| same_directory(Path::new(&recent.directory), root))
+        .and_then(|recent| recent.text_encryption);
+    list.retain(|recent| !same_directory(Path::new(&recent.directory), root));
+    list.insert(
+        0,
+        RecentVault {
+            directory: root.to_string_lossy().into_owned(),
+            last_opened_at: OffsetDateTime::now_utc(),
+            text_encryption: text_encryption.or(previous),
+            available: true,
+            profile: None,
+        },
+    );
+    list.truncate(MAX_RECENT_VAULTS);
+    write_list(path, &list)
+}
+
+pub fn list(path: &Path) -> Vec<RecentVault> {
+    let mut list = read_list(path);
+    for recent in list.iter_mut() {
+        let root = PathBuf::from(&recent.directory);
+        recent.available = vault_file_path(root.clone()).is_file();
+        if recent.available {
+            recent.profile = Some(profile::read(&root));
+        }
+    }
+    list
+}
+
+// 启动时直接进入上次解锁的日记库；目录已不可用时返回 None，由用户重新选择
+pub fn startup(path: &Path) -> Option<RecentVault> {
+    list(path).into_iter().next().filter(|recent| recent.available)
+}
+
+pub fn forget(path: &Path, root: &Path) -> Result<()> {
+    let mut list = read_list(path);
+    let before = list.len();
+    list.retain(|recent| !same_directory(Path::new(&recent.directory), root));
+    if list.len() == before {
+        return Ok(());
+    }
+    write_list(path, &list)
+}
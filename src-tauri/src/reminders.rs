@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::BTreeSet;
+use time::{OffsetDateTime, Time};
+
+use cipher_diary_core::error::VaultError;
+use cipher_diary_core::i18n::Message;
+
+pub const MAX_REMINDERS: usize = 8;
+pub const MAX_MEMORY_YEARS: u32 = 100;
+
+// 按本机时区的每日提醒时刻
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Type)]
+pub struct ReminderTime {
+    pub hour: u8,
+    pub minute: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct ReminderSchedule {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub times: Vec<ReminderTime>,
+    // 已打开的日记库今天已有日记时不提醒；日记库都已锁定时无法判断，照常提醒
+    #[serde(default = "default_true")]
+    pub skip_if_written: bool,
+}
+
+impl Default for ReminderSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            times: Vec::new(),
+            skip_if_written: true,
+        }
+    }
+}
+
+// 那年今日：每天在固定时刻检查已打开的日记库，若有 years 年前同一天的日记则通知
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct MemorySchedule {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_memory_time")]
+    pub time: ReminderTime,
+    #[serde(default = "default_memory_years")]
+    pub years: Vec<u32>,
+    // 标题可能出现在锁屏上，默认只显示篇数
+    #[serde(default)]
+    pub show_titles: bool,
+}
+
+impl Default for MemorySchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time: default_memory_time(),
+            years: default_memory_years(),
+            show_titles: false,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_memory_time() -> ReminderTime {
+    ReminderTime { hour: 9, minute: 0 }
+}
+
+fn default_memory_years() -> Vec<u32> {
+    vec![1, 5, 10]
+}
+
+pub fn validate(schedule: &ReminderSchedule) -> Result<(), VaultError> {
+    if schedule.times.len() > MAX_REMINDERS {
+        return Err(VaultError::invalid(Message::TooManyReminders(
+            MAX_REMINDERS,
+        )));
+    }
+    let unique: BTreeSet<_> = schedule.times.iter().collect();
+    let valid = unique.len() == schedule.times.len()
+        && schedule
+            .times
+            .iter()
+            .all(|time| time.hour < 24 && time.minute < 60);
+    if !valid {
+        return Err(VaultError::invalid(Message::InvalidReminderTime));
+    }
+    Ok(())
+}
+
+pub fn validate_memories(schedule: &MemorySchedule) -> Result<(), VaultError> {
+    if schedule.time.hour >= 24 || schedule.time.minute >= 60 {
+        return Err(VaultError::invalid(Message::InvalidReminderTime));
+    }
+    let unique: BTreeSet<_> = schedule.years.iter().collect();
+    let valid = !schedule.years.is_empty()
+        && unique.len() == schedule.years.len()
+        && schedule
+            .years
+            .iter()
+            .all(|years| (1..=MAX_MEMORY_YEARS).contains(years));
+    if !valid {
+        return Err(VaultError::invalid(Message::InvalidMemoryYears(
+            MAX_MEMORY_YEARS,
+        )));
+    }
+    Ok(())
+}
+
+// 记住上次检查的时间，两次检查之间经过的提醒时刻视为到期；
+// 启动之前错过的提醒不补发，休眠醒来后最多提醒一次
+pub struct ReminderClock {
+    last: OffsetDateTime,
+}
+
+impl ReminderClock {
+    pub fn new(now: OffsetDateTime) -> Self {
+        Self { last: now }
+    }
+
+    pub fn due(&mut self, now: OffsetDateTime, schedule: &ReminderSchedule) -> bool {
+        self.due_at(now, schedule.enabled, &schedule.times)
+    }
+
+    pub fn memories_due(&mut self, now: OffsetDateTime, schedule: &MemorySchedule) -> bool {
+        self.due_at(now, schedule.enabled, &[schedule.time])
+    }
+
+    fn due_at(&mut self, now: OffsetDateTime, enabled: bool, times: &[ReminderTime]) -> bool {
+        let last = std::mem::replace(&mut self.last, now);
+        if !enabled || now <= last {
+            return false;
+        }
+        times.iter().any(|reminder| {
+            let Ok(time) = Time::from_hms(reminder.hour, reminder.minute, 0) else {
+                return false;
+            };
+            [last, now].iter().any(|day| {
+                let at = day.replace_time(time);
+                last < at && at <= now
+            })
+        })
+    }
+}
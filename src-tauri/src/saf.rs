@@ -0,0 +1,167 @@
+use cipher_diary_core::error::VaultError;
+use cipher_diary_core::i18n::Message;
+use cipher_diary_core::storage::DocumentTree;
+use tauri::AppHandle;
+
+// Android 分区存储中用户选择的文件夹只能通过 SAF 访问，由 SafPlugin.kt 实现；
+// 其他平台的文件夹都有普通路径，不需要这一层
+#[cfg(target_os = "android")]
+pub use android::init;
+
+#[cfg(target_os = "android")]
+pub fn open_tree(app: &AppHandle, uri: &str) -> Result<Box<dyn DocumentTree>, VaultError> {
+    android::open_tree(app, uri)
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn open_tree(_app: &AppHandle, _uri: &str) -> Result<Box<dyn DocumentTree>, VaultError> {
+    Err(VaultError::invalid(Message::DocumentTreeUnsupported))
+}
+
+// 打开系统的文件夹选择器并保留读写授权；用户取消时返回 None
+#[cfg(target_os = "android")]
+pub fn pick_tree(app: &AppHandle) -> Result<Option<String>, VaultError> {
+    android::pick_tree(app)
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn pick_tree(_app: &AppHandle) -> Result<Option<String>, VaultError> {
+    Err(VaultError::invalid(Message::DocumentTreeUnsupported))
+}
+
+#[cfg(target_os = "android")]
+mod android {
+    use anyhow::anyhow;
+    use base64::{engine::general_purpose, Engine as _};
+    use serde::de::DeserializeOwned;
+    use serde::{Deserialize, Serialize};
+    use tauri::plugin::{Builder, PluginHandle, TauriPlugin};
+    use tauri::{AppHandle, Manager, Wry};
+
+    use cipher_diary_core::error::VaultError;
+    use cipher_diary_core::storage::{DocumentTree, TreeFile};
+
+    const PLUGIN_NAME: &str = "saf";
+    const PLUGIN_PACKAGE: &str = "com.xumin.diary";
+
+    struct Saf(PluginHandle<Wry>);
+
+    pub fn init() -> TauriPlugin<Wry> {
+        Builder::new(PLUGIN_NAME)
+            .setup(|app, api| {
+                let handle = api.register_android_plugin(PLUGIN_PACKAGE, "SafPlugin")?;
+                app.manage(Saf(handle));
+                Ok(())
+            })
+            .build()
+    }
+
+    #[derive(Serialize)]
+    struct TreeArgs<'a> {
+        uri: &'a str,
+        path: Option<&'a str>,
+        data: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct RemoteFile {
+        path: String,
+        size: u64,
+        modified: i64,
+    }
+
+    impl From<RemoteFile> for TreeFile {
+        fn from(file: RemoteFile) -> Self {
+            TreeFile {
+                path: file.path,
+                size: file.size,
+                modified: file.modified,
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct Listing {
+        files: Vec<RemoteFile>,
+    }
+
+    #[derive(Deserialize)]
+    struct Contents {
+        data: String,
+    }
+
+    #[derive(Deserialize)]
+    struct Picked {
+        uri: Option<String>,
+    }
+
+    struct SafTree {
+        handle: PluginHandle<Wry>,
+        uri: String,
+    }
+
+    impl SafTree {
+        fn call<T: DeserializeOwned>(
+            &self,
+            command: &str,
+            path: Option<&str>,
+            data: Option<String>,
+        ) -> anyhow::Result<T> {
+            let args = TreeArgs {
+                uri: &self.uri,
+                path,
+                data,
+            };
+            self.handle
+                .run_mobile_plugin(command, args)
+                .map_err(|err| anyhow!("{command} failed: {err}"))
+        }
+    }
+
+    impl DocumentTree for SafTree {
+        fn location(&self) -> String {
+            self.uri.clone()
+        }
+
+        fn list(&self) -> anyhow::Result<Vec<TreeFile>> {
+            let listing: Listing = self.call("list", None, None)?;
+            Ok(listing.files.into_iter().map(TreeFile::from).collect())
+        }
+
+        fn read(&self, path: &str) -> anyhow::Result<Vec<u8>> {
+            let contents: Contents = self.call("read", Some(path), None)?;
+            general_purpose::STANDARD
+                .decode(contents.data)
+                .map_err(|_| anyhow!("invalid data for {path}"))
+        }
+
+        fn write(&self, path: &str, data: &[u8]) -> anyhow::Result<TreeFile> {
+            let encoded = general_purpose::STANDARD.encode(data);
+            let file: RemoteFile = self.call("write", Some(path), Some(encoded))?;
+            Ok(file.into())
+        }
+
+        fn remove(&self, path: &str) -> anyhow::Result<()> {
+            let _: serde_json::Value = self.call("remove", Some(path), None)?;
+            Ok(())
+        }
+    }
+
+    fn plugin(app: &AppHandle) -> PluginHandle<Wry> {
+        app.state::<Saf>().0.clone()
+    }
+
+    pub fn open_tree(app: &AppHandle, uri: &str) -> Result<Box<dyn DocumentTree>, VaultError> {
+        Ok(Box::new(SafTree {
+            handle: plugin(app),
+            uri: uri.trim().to_string(),
+        }))
+    }
+
+    pub fn pick_tree(app: &AppHandle) -> Result<Option<String>, VaultError> {
+        let picked: Picked = plugin(app)
+            .run_mobile_plugin("pickTree", ())
+            .map_err(|err| VaultError::other(err.to_string()))?;
+        Ok(picked.uri)
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::time::Duration;
+
+use cipher_diary_core::vault::{
+    Location, Weather, WeatherCondition, WeatherProvider, WeatherSettings,
+};
+
+// 离线或服务缓慢时不能拖住新建日记
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const OPEN_METEO_URL: &str = "https://api.open-meteo.com/v1/forecast";
+const OPEN_WEATHER_MAP_URL: &str = "https://api.openweathermap.org/data/2.5/weather";
+
+pub async fn fetch(settings: &WeatherSettings, location: &Location) -> Result<Weather> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .context("failed to create weather client")?;
+    let latitude = location.latitude.to_string();
+    let longitude = location.longitude.to_string();
+    let request = match settings.provider {
+        WeatherProvider::OpenMeteo => client.get(OPEN_METEO_URL).query(&[
+            ("latitude", latitude.as_str()),
+            ("longitude", longitude.as_str()),
+            (
+                "current",
+                "temperature_2m,relative_humidity_2m,wind_speed_10m,weather_code",
+            ),
+        ]),
+        WeatherProvider::OpenWeatherMap => {
+            let key = settings
+                .api_key
+                .as_deref()
+                .ok_or_else(|| anyhow!("weather api key missing"))?;
+            client.get(OPEN_WEATHER_MAP_URL).query(&[
+                ("lat", latitude.as_str()),
+                ("lon", longitude.as_str()),
+                ("units", "metric"),
+                ("appid", key),
+            ])
+        }
+    };
+    let body: Value = request
+        .send()
+        .await
+        .context("weather request failed")?
+        .error_for_status()
+        .context("weather provider returned an error")?
+        .json()
+        .await
+        .context("invalid weather response")?;
+    parse(settings.provider, &body)
+}
+
+pub fn parse(provider: WeatherProvider, body: &Value) -> Result<Weather> {
+    match provider {
+        WeatherProvider::OpenMeteo => {
+            let current = &body["current"];
+            Ok(Weather {
+                condition: current["weather_code"]
+                    .as_u64()
+                    .map_or(WeatherCondition::Unknown, wmo_condition),
+                temperature_c: current["temperature_2m"]
+                    .as_f64()
+                    .ok_or_else(|| anyhow!("weather response has no temperature"))?,
+                humidity: current["relative_humidity_2m"]
+                    .as_f64()
+                    .map(|value| value.round() as u8),
+                wind_kph: current["wind_speed_10m"].as_f64(),
+                provider,
+            })
+        }
+        WeatherProvider::OpenWeatherMap => Ok(Weather {
+            condition: body["weather"][0]["main"]
+                .as_str()
+                .map_or(WeatherCondition::Unknown, owm_condition),
+            temperature_c: body["main"]["temp"]
+                .as_f64()
+                .ok_or_else(|| anyhow!("weather response has no temperature"))?,
+            humidity: body["main"]["humidity"]
+                .as_f64()
+                .map(|value| value.round() as u8),
+            // OpenWeatherMap 的公制风速单位为米每秒
+            wind_kph: body["wind"]["speed"].as_f64().map(|speed| speed * 3.6),
+            provider,
+        }),
+    }
+}
+
+// WMO 天气代码，见 Open-Meteo 文档
+fn wmo_condition(code: u64) -> WeatherCondition {
+    match code {
+        0 | 1 => WeatherCondition::Clear,
+        2 | 3 => WeatherCondition::Cloudy,
+        45 | 48 => WeatherCondition::Fog,
+        51..=57 => WeatherCondition::Drizzle,
+        61..=67 | 80..=82 => WeatherCondition::Rain,
+        71..=77 | 85 | 86 => WeatherCondition::Snow,
+        95..=99 => WeatherCondition::Thunderstorm,
+        _ => WeatherCondition::Unknown,
+    }
+}
+
+fn owm_condition(main: &str) -> WeatherCondition {
+    match main {
+        "Clear" => WeatherCondition::Clear,
+        "Clouds" => WeatherCondition::Cloudy,
+        "Mist" | "Fog" | "Haze" | "Smoke" | "Dust" | "Sand" => WeatherCondition::Fog,
+        "Drizzle" => WeatherCondition::Drizzle,
+        "Rain" | "Squall" => WeatherCondition::Rain,
+        "Snow" => WeatherCondition::Snow,
+        "Thunderstorm" => WeatherCondition::Thunderstorm,
+        _ => WeatherCondition::Unknown,
+    }
+}
@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::PathBuf;
+
+use cipher_diary_core::error::VaultError;
+use cipher_diary_core::vault::PatchFields;
+use diary_lib::headless::HeadlessApp;
+use uuid::Uuid;
+
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("diary-headless-{}", Uuid::new_v4()));
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    fn child(&self, name: &str) -> String {
+        self.0.join(name).to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn unlock_edit_lock_roundtrip() {
+    let dir = TempDir::new();
+    let app = HeadlessApp::new(dir.child("app"));
+    let vault_dir = dir.child("vault");
+
+    let opened = app
+        .create_vault("correct horse", Some(vault_dir.clone()), None, None)
+        .unwrap();
+    assert!(opened.response.created);
+    let entry = app
+        .create_entry(opened.vault_id, "First day", "hello headless", None)
+        .unwrap();
+    let patch = PatchFields {
+        content: Some("edited content".into()),
+        folder: Some(Some("notes".into())),
+        ..Default::default()
+    };
+    app.patch_entry(opened.vault_id, entry.id, patch).unwrap();
+    app.lock_vault(opened.vault_id).unwrap();
+    assert!(matches!(
+        app.list_entries(opened.vault_id),
+        Err(VaultError::Locked(_))
+    ));
+
+    let reopened = app.unlock_vault("correct horse", Some(vault_dir)).unwrap();
+    let entries = app.list_entries(reopened.vault_id).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].folder.as_deref(), Some("notes"));
+    let loaded = app.load_entry(reopened.vault_id, entry.id).unwrap();
+    assert_eq!(loaded.content, "edited content");
+    assert_eq!(
+        app.search_entries(reopened.vault_id, "first")
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(app.verify_vault_integrity(reopened.vault_id).unwrap().valid);
+
+    app.delete_entry(reopened.vault_id, entry.id).unwrap();
+    assert!(app.list_entries(reopened.vault_id).unwrap().is_empty());
+    app.lock_vault(reopened.vault_id).unwrap();
+}
+
+#[test]
+fn rejects_wrong_passphrase_and_double_open() {
+    let dir = TempDir::new();
+    let app = HeadlessApp::new(dir.child("app"));
+    let vault_dir = dir.child("vault");
+
+    let opened = app
+        .create_vault("correct horse", Some(vault_dir.clone()), None, None)
+        .unwrap();
+    assert!(matches!(
+        app.unlock_vault("correct horse", Some(vault_dir.clone())),
+        Err(VaultError::Conflict(_))
+    ));
+    app.lock_vault(opened.vault_id).unwrap();
+
+    assert!(matches!(
+        app.unlock_vault("wrong horse", Some(vault_dir)),
+        Err(VaultError::WrongPassphrase(_))
+    ));
+    assert!(matches!(
+        app.unlock_vault("correct horse", Some(dir.child("missing"))),
+        Err(VaultError::VaultNotFound(_))
+    ));
+}
+
+#[test]
+fn validates_input_like_commands() {
+    let dir = TempDir::new();
+    let app = HeadlessApp::new(dir.child("app"));
+    let opened = app.create_vault("correct horse", None, None, None).unwrap();
+
+    let long_title = "x".repeat(1000);
+    assert!(matches!(
+        app.create_entry(opened.vault_id, &long_title, "", None),
+        Err(VaultError::InvalidInput(_))
+    ));
+    assert!(app.list_entries(opened.vault_id).unwrap().is_empty());
+    app.lock_vault(opened.vault_id).unwrap();
+}
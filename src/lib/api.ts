@@ -1,76 +1,843 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { open } from '@tauri-apps/plugin-dialog';
-import type { EntryDetail, EntrySummary, TextEncryption, UnlockResponse } from './types';
+import type {
+  AppSettings,
+  AttachmentInfo,
+  BackendLocale,
+  BenchmarkReport,
+  CalendarDay,
+  CloneReport,
+  DateRange,
+  DayView,
+  DeleteReport,
+  Draft,
+  EmergencyKeypair,
+  EntriesChanged,
+  EmergencyStatus,
+  EntryDetail,
+  EntryLocation,
+  EntryPatch,
+  EntryPayload,
+  EntryShareReport,
+  EntrySummary,
+  ExportReport,
+  FolderCount,
+  FrontmatterMapping,
+  GoalProgress,
+  Habit,
+  HabitHistory,
+  HealthImportReport,
+  HealthSource,
+  HistoryReport,
+  ImportReport,
+  IntegrityReport,
+  LastUnlockInfo,
+  LogLevel,
+  MediaDay,
+  MemorySchedule,
+  MigrationReport,
+  MonthRecap,
+  MoodTrends,
+  MoveReport,
+  NewEntry,
+  NewEntryResult,
+  OpenRequest,
+  PlaceCount,
+  PromptPack,
+  PromptPackInfo,
+  QueryPage,
+  QuerySpec,
+  QuickUnlockStatus,
+  RecentVault,
+  ReminderSchedule,
+  SelfTestReport,
+  SentimentTrends,
+  ShareReport,
+  StatsGroup,
+  TagCount,
+  TextEncryption,
+  TimelineYear,
+  TodaysPrompt,
+  TrendBucket,
+  UndoOutcome,
+  UnlockProgress,
+  UnlockResponse,
+  UserSlot,
+  VaultError,
+  VaultErrorCode,
+  VaultEvent,
+  VaultInfo,
+  VaultPolicy,
+  VaultProbe,
+  VaultProfile,
+  VaultSettings,
+  WordFrequency,
+  WritingStats
+} from './types';
 
-export async function unlockVault(
+function isVaultError(err: unknown): err is VaultError {
+  return typeof err === 'object' && err !== null && 'code' in err && 'message' in err;
+}
+
+export function errorCode(err: unknown): VaultErrorCode | null {
+  return isVaultError(err) ? err.code : null;
+}
+
+export function errorMessage(err: unknown, fallback: string): string {
+  if (isVaultError(err)) return err.message;
+  if (err instanceof Error) return err.message;
+  if (typeof err === 'string' && err) return err;
+  return fallback;
+}
+
+export async function setBackendLocale(locale: BackendLocale): Promise<void> {
+  await invoke('set_backend_locale', { locale });
+}
+
+export async function getAppSettings(): Promise<AppSettings> {
+  return invoke<AppSettings>('get_app_settings');
+}
+
+export async function setAppSettings(settings: AppSettings): Promise<void> {
+  await invoke('set_app_settings', { settings });
+}
+
+// 诊断日志写入应用数据目录下的 logs/diary.log，不会包含口令、密钥或日记内容
+export async function setLogLevel(level: LogLevel): Promise<void> {
+  await invoke('set_log_level', { level });
+}
+
+export async function getReminderSchedule(): Promise<ReminderSchedule> {
+  return invoke<ReminderSchedule>('get_reminder_schedule');
+}
+
+// 提醒通过系统通知发出，时刻按本机时区
+export async function setReminderSchedule(schedule: ReminderSchedule): Promise<void> {
+  await invoke('set_reminder_schedule', { schedule });
+}
+
+export async function getMemorySchedule(): Promise<MemorySchedule> {
+  return invoke<MemorySchedule>('get_memory_schedule');
+}
+
+// 默认关闭；只检查已解锁的日记库
+export async function setMemorySchedule(schedule: MemorySchedule): Promise<void> {
+  await invoke('set_memory_schedule', { schedule });
+}
+
+export async function probeVault(directory: string | null | undefined): Promise<VaultProbe> {
+  return invoke<VaultProbe>('probe_vault', { directory: directory ?? undefined });
+}
+
+export async function createVault(
   passphrase: string,
   directory: string | null | undefined,
-  encryption: TextEncryption
+  encryption: TextEncryption,
+  policy?: VaultPolicy
 ): Promise<UnlockResponse> {
-  return invoke<UnlockResponse>('unlock_vault', {
+  return invoke<UnlockResponse>('create_vault', {
     passphrase,
     directory: directory ?? undefined,
-    encryption
+    encryption,
+    policy
+  });
+}
+
+export async function unlockVault(
+  passphrase: string,
+  directory: string | null | undefined
+): Promise<UnlockResponse> {
+  return invoke<UnlockResponse>('unlock_vault', {
+    passphrase,
+    directory: directory ?? undefined
+  });
+}
+
+// 解锁前预检需要执行的格式升级，不修改任何文件
+export async function planVaultMigration(
+  passphrase: string,
+  directory: string | null | undefined
+): Promise<MigrationReport> {
+  return invoke<MigrationReport>('plan_vault_migration', {
+    passphrase,
+    directory: directory ?? undefined
+  });
+}
+
+export async function unlockVaultWithPin(
+  pin: string,
+  directory: string | null | undefined
+): Promise<UnlockResponse> {
+  return invoke<UnlockResponse>('unlock_vault_with_pin', {
+    pin,
+    directory: directory ?? undefined
+  });
+}
+
+export async function enableQuickUnlock(
+  vaultId: string,
+  pin: string,
+  maxAttempts?: number
+): Promise<void> {
+  await invoke('enable_quick_unlock', { vaultId, pin, maxAttempts });
+}
+
+export async function disableQuickUnlock(directory: string | null | undefined): Promise<void> {
+  await invoke('disable_quick_unlock', { directory: directory ?? undefined });
+}
+
+export async function getQuickUnlockStatus(
+  directory: string | null | undefined
+): Promise<QuickUnlockStatus> {
+  return invoke<QuickUnlockStatus>('get_quick_unlock_status', {
+    directory: directory ?? undefined
   });
 }
 
-export async function lockVault(): Promise<void> {
-  await invoke('lock_vault');
+export async function listRecentVaults(): Promise<RecentVault[]> {
+  return invoke<RecentVault[]>('list_recent_vaults');
+}
+
+export async function getStartupVault(): Promise<RecentVault | null> {
+  return invoke<RecentVault | null>('get_startup_vault');
+}
+
+export async function forgetRecentVault(directory: string): Promise<void> {
+  await invoke('forget_recent_vault', { directory });
+}
+
+// 通过文件关联或 cipherdiary:// 链接请求打开的日记库目录，取走后清空
+export async function takeOpenRequest(): Promise<string | null> {
+  return invoke<string | null>('take_open_request');
+}
+
+export async function onOpenRequest(
+  handler: (request: OpenRequest) => void
+): Promise<UnlistenFn> {
+  return listen<OpenRequest>('vault://open-request', (event) => handler(event.payload));
+}
+
+export async function lockVault(vaultId: string): Promise<void> {
+  await invoke('lock_vault', { vaultId });
+}
+
+export async function flushVault(vaultId: string): Promise<boolean> {
+  return invoke<boolean>('flush_vault', { vaultId });
 }
 
-export async function fetchEntries(): Promise<EntrySummary[]> {
-  return invoke<EntrySummary[]>('list_entries');
+export async function getLastUnlockInfo(vaultId: string): Promise<LastUnlockInfo> {
+  return invoke<LastUnlockInfo>('get_last_unlock_info', { vaultId });
 }
 
-export async function loadVaultEntry(id: string): Promise<EntryDetail> {
-  return invoke<EntryDetail>('load_entry', { id });
+export async function setAutoLockTimeout(vaultId: string, minutes: number | null): Promise<void> {
+  await invoke('set_auto_lock_timeout', { vaultId, minutes });
 }
 
+export async function onVaultEvent(handler: (event: VaultEvent) => void): Promise<UnlistenFn> {
+  return listen<VaultEvent>('vault://lifecycle', (event) => handler(event.payload));
+}
+
+export async function onUnlockProgress(
+  handler: (progress: UnlockProgress) => void
+): Promise<UnlistenFn> {
+  return listen<UnlockProgress>('unlock://progress', (event) => handler(event.payload));
+}
+
+export async function onEntriesChanged(
+  handler: (changed: EntriesChanged) => void
+): Promise<UnlistenFn> {
+  return listen<EntriesChanged>('entries://changed', (event) => handler(event.payload));
+}
+
+export async function fetchEntries(vaultId: string): Promise<EntrySummary[]> {
+  return invoke<EntrySummary[]>('list_entries', { vaultId });
+}
+
+export async function searchEntries(vaultId: string, query: string): Promise<EntrySummary[]> {
+  return invoke<EntrySummary[]>('search_entries', { vaultId, query });
+}
+
+export async function queryVaultEntries(vaultId: string, spec: QuerySpec): Promise<QueryPage> {
+  return invoke<QueryPage>('query_entries', { vaultId, spec });
+}
+
+// 选择器和自动补全使用，按名称排序
+export async function listFolders(vaultId: string): Promise<FolderCount[]> {
+  return invoke<FolderCount[]>('list_folders', { vaultId });
+}
+
+export async function listTags(vaultId: string): Promise<TagCount[]> {
+  return invoke<TagCount[]>('list_tags', { vaultId });
+}
+
+// range 按日记日期筛选，结果按分组名排序
+export async function getWritingStats(
+  vaultId: string,
+  groupBy: StatsGroup,
+  range: DateRange
+): Promise<WritingStats[]> {
+  return invoke<WritingStats[]>('get_writing_stats', { vaultId, groupBy, range });
+}
+
+// 词云数据，正文在后端解密分词；stopwordLang 为空时同时去掉中英文停用词
+export async function getWordFrequencies(
+  vaultId: string,
+  range: DateRange,
+  stopwordLang?: BackendLocale,
+  limit?: number
+): Promise<WordFrequency[]> {
+  return invoke<WordFrequency[]>('get_word_frequencies', {
+    vaultId,
+    range,
+    stopwordLang,
+    limit
+  });
+}
+
+// 按距离由近到远，radiusKm 单位为公里
+export async function listEntriesNear(
+  vaultId: string,
+  latitude: number,
+  longitude: number,
+  radiusKm: number
+): Promise<EntrySummary[]> {
+  return invoke<EntrySummary[]>('list_entries_near', {
+    vaultId,
+    latitude,
+    longitude,
+    radiusKm
+  });
+}
+
+export async function listPlaces(vaultId: string): Promise<PlaceCount[]> {
+  return invoke<PlaceCount[]>('list_places', { vaultId });
+}
+
+// month 为 1-12，只返回有日记的日子
+export async function getCalendarMonth(
+  vaultId: string,
+  year: number,
+  month: number
+): Promise<CalendarDay[]> {
+  return invoke<CalendarDay[]>('get_calendar_month', { vaultId, year, month });
+}
+
+// date 为 YYYY-MM-DD，返回当天的全部日记、统计、引用的附件和小结
+export async function getDay(vaultId: string, date: string): Promise<DayView> {
+  return invoke<DayView>('get_day', { vaultId, date });
+}
+
+// summary 为 null 或空白时清除当天的小结
+export async function setDaySummary(
+  vaultId: string,
+  date: string,
+  summary: string | null
+): Promise<void> {
+  return invoke<void>('set_day_summary', { vaultId, date, summary });
+}
+
+export async function getTimeline(vaultId: string): Promise<TimelineYear[]> {
+  return invoke<TimelineYear[]>('get_timeline', { vaultId });
+}
+
+// month 为 1-12；createEntry 为 true 时写成回顾日记，同月再次生成会更新原日记
+export async function generateMonthRecap(
+  vaultId: string,
+  year: number,
+  month: number,
+  createEntry = false
+): Promise<MonthRecap> {
+  return invoke<MonthRecap>('generate_month_recap', { vaultId, year, month, createEntry });
+}
+
+export async function getOnThisDay(vaultId: string): Promise<EntrySummary[]> {
+  return invoke<EntrySummary[]>('get_on_this_day', { vaultId });
+}
+
+// 只统计填写了心情的条目，日期按本机时区划分
+export async function getMoodTrends(
+  vaultId: string,
+  range: DateRange,
+  bucket: TrendBucket
+): Promise<MoodTrends> {
+  return invoke<MoodTrends>('get_mood_trends', { vaultId, range, bucket });
+}
+
+// 需先开启情感分析，只统计已打分的日记
+export async function getSentimentTrends(
+  vaultId: string,
+  range: DateRange,
+  bucket: TrendBucket
+): Promise<SentimentTrends> {
+  return invoke<SentimentTrends>('get_sentiment_trends', { vaultId, range, bucket });
+}
+
+export async function getEntryInfo(vaultId: string, id: string): Promise<EntrySummary | null> {
+  return invoke<EntrySummary | null>('get_entry_info', { vaultId, id });
+}
+
+export async function loadVaultEntry(vaultId: string, id: string): Promise<EntryDetail> {
+  return invoke<EntryDetail>('load_entry', { vaultId, id });
+}
+
+// 提供位置且开启了天气时会顺带记录当前天气，获取失败不影响新建
 export async function createVaultEntry(
+  vaultId: string,
   title?: string,
   content?: string,
-  encryption?: TextEncryption
+  encryption?: TextEncryption,
+  location?: EntryLocation
 ): Promise<EntryDetail> {
-  return invoke<EntryDetail>('create_entry', { title, content, encryption });
+  return invoke<EntryDetail>('create_entry', {
+    vaultId,
+    title,
+    content,
+    encryption,
+    location
+  });
+}
+
+// 引导式日记；title 为空时按类型取默认标题，entryDate 为 YYYY-MM-DD
+export async function createTypedEntry(
+  vaultId: string,
+  payload: EntryPayload,
+  title?: string,
+  entryDate?: string,
+  location?: EntryLocation
+): Promise<EntryDetail> {
+  return invoke<EntryDetail>('create_typed_entry', {
+    vaultId,
+    payload,
+    title,
+    entryDate,
+    location
+  });
+}
+
+// 导入器批量新建，只保存一次元数据；结果与输入顺序一致
+export async function createVaultEntries(
+  vaultId: string,
+  entries: NewEntry[]
+): Promise<NewEntryResult[]> {
+  return invoke<NewEntryResult[]>('create_entries', { vaultId, entries });
+}
+
+// 每个 Markdown 文件新建一篇日记，frontmatter 写入对应字段；结果与 paths 顺序一致
+export async function importMarkdownFiles(
+  vaultId: string,
+  paths: string[],
+  mapping: FrontmatterMapping | null = null
+): Promise<NewEntryResult[]> {
+  return invoke<NewEntryResult[]>('import_markdown_files', { vaultId, paths, mapping });
+}
+
+export async function updateVaultEntry(vaultId: string, entry: EntryDetail): Promise<EntryDetail> {
+  return invoke<EntryDetail>('update_entry', { vaultId, entry });
+}
+
+// 按结构化内容重新生成正文
+export async function updateEntryPayload(
+  vaultId: string,
+  id: string,
+  payload: EntryPayload
+): Promise<EntryDetail> {
+  return invoke<EntryDetail>('update_entry_payload', { vaultId, id, payload });
+}
+
+export async function patchVaultEntry(
+  vaultId: string,
+  id: string,
+  patch: EntryPatch
+): Promise<EntrySummary> {
+  return invoke<EntrySummary>('patch_entry', { vaultId, id, patch });
 }
 
-export async function updateVaultEntry(entry: EntryDetail): Promise<EntryDetail> {
-  return invoke<EntryDetail>('update_entry', { entry });
+export async function deleteVaultEntry(vaultId: string, id: string): Promise<void> {
+  await invoke('delete_entry', { vaultId, id });
 }
 
-export async function deleteVaultEntry(id: string): Promise<void> {
-  await invoke('delete_entry', { id });
+// 读不出的日记移到隔离目录并从列表去掉，返回文件的新位置
+export async function quarantineEntry(vaultId: string, id: string): Promise<string> {
+  return invoke<string>('quarantine_entry', { vaultId, id });
+}
+
+// 编辑器暂存：未保存的修改先加密写入日记库，崩溃后下次解锁时可恢复
+export async function saveDraft(
+  vaultId: string,
+  entryId: string,
+  title: string,
+  content: string
+): Promise<void> {
+  await invoke('save_draft', { vaultId, entryId, title, content });
+}
+
+export async function discardDraft(vaultId: string, entryId: string): Promise<void> {
+  await invoke('discard_draft', { vaultId, entryId });
+}
+
+export async function recoverUnsavedChanges(vaultId: string): Promise<Draft[]> {
+  return invoke<Draft[]>('recover_unsaved_changes', { vaultId });
+}
+
+// 撤销或重做最近一次删除、移动；栈为空时返回 null
+export async function undoVaultChange(vaultId: string): Promise<UndoOutcome | null> {
+  return invoke<UndoOutcome | null>('undo_last', { vaultId });
+}
+
+export async function redoVaultChange(vaultId: string): Promise<UndoOutcome | null> {
+  return invoke<UndoOutcome | null>('redo_last', { vaultId });
 }
 
 export async function changeVaultPassphrase(
+  vaultId: string,
   oldPassphrase: string,
   newPassphrase: string
 ): Promise<void> {
-  await invoke('change_vault_passphrase', { oldPassphrase, newPassphrase });
+  await invoke('change_vault_passphrase', { vaultId, oldPassphrase, newPassphrase });
+}
+
+// 更换主密钥并重新加密全部文件，返回作废的其他用户槽位数
+export async function rotateMasterKey(vaultId: string, passphrase: string): Promise<number> {
+  return invoke<number>('rotate_master_key', { vaultId, passphrase });
+}
+
+export async function listUserSlots(vaultId: string): Promise<UserSlot[]> {
+  return invoke<UserSlot[]>('list_user_slots', { vaultId });
+}
+
+// 为另一位用户添加独立口令，解锁后看到同一本日记；需要主口令或管理员口令
+export async function addUserSlot(
+  vaultId: string,
+  adminPassphrase: string,
+  label: string,
+  passphrase: string,
+  admin = false
+): Promise<UserSlot> {
+  return invoke<UserSlot>('add_user_slot', {
+    vaultId,
+    adminPassphrase,
+    label,
+    passphrase,
+    admin
+  });
+}
+
+export async function removeUserSlot(
+  vaultId: string,
+  adminPassphrase: string,
+  id: string
+): Promise<void> {
+  await invoke('remove_user_slot', { vaultId, adminPassphrase, id });
+}
+
+export async function cloneVault(vaultId: string, destination: string): Promise<CloneReport> {
+  return invoke<CloneReport>('clone_vault', { vaultId, destination });
+}
+
+export async function deleteVault(
+  directory: string | null | undefined,
+  passphrase: string,
+  backupDirectory?: string
+): Promise<DeleteReport> {
+  return invoke<DeleteReport>('delete_vault', {
+    directory: directory ?? undefined,
+    passphrase,
+    backupDirectory
+  });
+}
+
+export async function importVault(
+  vaultId: string,
+  otherPath: string,
+  otherPassphrase: string
+): Promise<ImportReport> {
+  return invoke<ImportReport>('import_vault', { vaultId, otherPath, otherPassphrase });
+}
+
+// apple_health 传入解压后的 export.xml，google_fit 传入 Takeout 中的 Fit 目录
+export async function importHealthData(
+  vaultId: string,
+  source: HealthSource,
+  path: string
+): Promise<HealthImportReport> {
+  return invoke<HealthImportReport>('import_health_data', { vaultId, source, path });
+}
+
+export async function moveVault(vaultId: string, newDirectory: string): Promise<MoveReport> {
+  return invoke<MoveReport>('move_vault', { vaultId, newDirectory });
+}
+
+export async function getVaultProfile(
+  directory: string | null | undefined
+): Promise<VaultProfile> {
+  return invoke<VaultProfile>('get_vault_profile', { directory: directory ?? undefined });
+}
+
+export async function setVaultProfile(
+  vaultId: string,
+  profile: VaultProfile
+): Promise<VaultProfile> {
+  return invoke<VaultProfile>('set_vault_profile', { vaultId, profile });
+}
+
+export async function setPassphraseHistoryDepth(
+  vaultId: string,
+  passphrase: string,
+  depth: number
+): Promise<void> {
+  await invoke('set_passphrase_history_depth', { vaultId, passphrase, depth });
+}
+
+export async function getVaultPolicy(vaultId: string): Promise<VaultPolicy> {
+  return invoke<VaultPolicy>('get_vault_policy', { vaultId });
+}
+
+export async function setVaultPolicy(
+  vaultId: string,
+  passphrase: string,
+  policy: VaultPolicy
+): Promise<void> {
+  await invoke('set_vault_policy', { vaultId, passphrase, policy });
+}
+
+export async function getVaultSettings(vaultId: string): Promise<VaultSettings> {
+  return invoke<VaultSettings>('get_vault_settings', { vaultId });
+}
+
+export async function setVaultSettings(vaultId: string, settings: VaultSettings): Promise<void> {
+  await invoke('set_vault_settings', { vaultId, settings });
+}
+
+// 目标保存在 VaultSettings.goals 中
+export async function getGoalProgress(vaultId: string): Promise<GoalProgress> {
+  return invoke<GoalProgress>('get_goal_progress', { vaultId });
+}
+
+export async function listPromptPacks(vaultId: string): Promise<PromptPackInfo[]> {
+  return invoke<PromptPackInfo[]>('list_prompt_packs', { vaultId });
+}
+
+// id 为 null 时新建自定义题库
+export async function savePromptPack(
+  vaultId: string,
+  id: string | null,
+  name: string,
+  prompts: string[]
+): Promise<PromptPack> {
+  return invoke<PromptPack>('save_prompt_pack', { vaultId, id, name, prompts });
+}
+
+export async function deletePromptPack(vaultId: string, id: string): Promise<void> {
+  await invoke('delete_prompt_pack', { vaultId, id });
+}
+
+export async function setPromptPackEnabled(
+  vaultId: string,
+  id: string,
+  enabled: boolean
+): Promise<void> {
+  await invoke('set_prompt_pack_enabled', { vaultId, id, enabled });
+}
+
+// 所有题库都停用时返回 null
+export async function getTodaysPrompt(vaultId: string): Promise<TodaysPrompt | null> {
+  return invoke<TodaysPrompt | null>('get_todays_prompt', { vaultId });
+}
+
+export async function answerPrompt(
+  vaultId: string,
+  packId: string,
+  index: number,
+  entryId: string
+): Promise<TodaysPrompt | null> {
+  return invoke<TodaysPrompt | null>('answer_prompt', { vaultId, packId, index, entryId });
+}
+
+export async function listHabits(vaultId: string): Promise<Habit[]> {
+  return invoke<Habit[]>('list_habits', { vaultId });
+}
+
+// id 为 null 时新建习惯
+export async function saveHabit(
+  vaultId: string,
+  id: string | null,
+  name: string,
+  unit: string | null,
+  target: number
+): Promise<Habit> {
+  return invoke<Habit>('save_habit', { vaultId, id, name, unit, target });
+}
+
+export async function deleteHabit(vaultId: string, id: string): Promise<void> {
+  await invoke('delete_habit', { vaultId, id });
+}
+
+// 拼写检查的个人词典随日记库加密保存，不写入系统词典
+export async function listDictionaryWords(vaultId: string): Promise<string[]> {
+  return invoke<string[]>('list_dictionary_words', { vaultId });
+}
+
+export async function addDictionaryWord(vaultId: string, word: string): Promise<void> {
+  return invoke<void>('add_dictionary_word', { vaultId, word });
+}
+
+export async function removeDictionaryWord(vaultId: string, word: string): Promise<void> {
+  return invoke<void>('remove_dictionary_word', { vaultId, word });
+}
+
+// value 为 0 时取消打卡；appendToEntry 时返回追加了打卡记录的当天日记
+export async function logHabit(
+  vaultId: string,
+  date: string,
+  habitId: string,
+  value: number,
+  appendToEntry = false
+): Promise<EntryDetail | null> {
+  return invoke<EntryDetail | null>('log_habit', {
+    vaultId,
+    date,
+    habitId,
+    value,
+    appendToEntry
+  });
+}
+
+// from、to 为 YYYY-MM-DD，包含两端
+export async function getHabitHistory(
+  vaultId: string,
+  habitId: string,
+  from: string,
+  to: string
+): Promise<HabitHistory> {
+  return invoke<HabitHistory>('get_habit_history', { vaultId, habitId, from, to });
+}
+
+export async function verifyHistoryChain(vaultId: string): Promise<HistoryReport> {
+  return invoke<HistoryReport>('verify_history_chain', { vaultId });
+}
+
+export async function verifyVaultIntegrity(vaultId: string): Promise<IntegrityReport> {
+  return invoke<IntegrityReport>('verify_vault_integrity', { vaultId });
+}
+
+export async function configureEmergencyAccess(
+  vaultId: string,
+  recipientPublicKey: string,
+  delayDays: number,
+  destination: string
+): Promise<EmergencyStatus> {
+  return invoke<EmergencyStatus>('configure_emergency_access', {
+    vaultId,
+    recipientPublicKey,
+    delayDays,
+    destination
+  });
 }
 
-export async function exportVaultToFile(): Promise<string> {
-  return invoke<string>('export_plaintext_file');
+export async function cancelEmergencyAccess(vaultId: string): Promise<void> {
+  await invoke('cancel_emergency_access', { vaultId });
 }
 
-export async function importVaultImage(path: string): Promise<string> {
-  return invoke<string>('store_image', { path });
+export async function getEmergencyAccessStatus(vaultId: string): Promise<EmergencyStatus> {
+  return invoke<EmergencyStatus>('get_emergency_access_status', { vaultId });
 }
 
-export async function importClipboardImage(options: {
-  name?: string | null;
-  mime?: string | null;
-  data: Uint8Array;
-}): Promise<string> {
+export async function processEmergencyAccess(
+  directory: string | null | undefined
+): Promise<EmergencyStatus> {
+  return invoke<EmergencyStatus>('process_emergency_access', {
+    directory: directory ?? undefined
+  });
+}
+
+export async function generateEmergencyKeypair(): Promise<EmergencyKeypair> {
+  return invoke<EmergencyKeypair>('generate_emergency_keypair');
+}
+
+export async function openEmergencyArchive(
+  archivePath: string,
+  secretKey: string,
+  outputDir: string
+): Promise<string> {
+  return invoke<string>('open_emergency_archive', { archivePath, secretKey, outputDir });
+}
+
+export async function getVaultInfo(vaultId: string): Promise<VaultInfo> {
+  return invoke<VaultInfo>('get_vault_info', { vaultId });
+}
+
+export async function listAttachments(vaultId: string): Promise<AttachmentInfo[]> {
+  return invoke<AttachmentInfo[]>('list_attachments', { vaultId });
+}
+
+// 图片内容仍按 path 通过 decryptImage 读取
+export async function getMediaTimeline(vaultId: string, range: DateRange): Promise<MediaDay[]> {
+  return invoke<MediaDay[]>('get_media_timeline', { vaultId, range });
+}
+
+export async function runBenchmarks(): Promise<BenchmarkReport> {
+  return invoke<BenchmarkReport>('run_benchmarks');
+}
+
+export async function runSelfTest(directory: string | null | undefined): Promise<SelfTestReport> {
+  return invoke<SelfTestReport>('run_self_test', { directory: directory ?? undefined });
+}
+
+export async function exportVaultToFile(vaultId: string): Promise<ExportReport> {
+  return invoke<ExportReport>('export_plaintext_file', { vaultId });
+}
+
+// filters 选出的日记加密写成独立的 HTML 文件，对方在浏览器中输入 password 查看
+export async function exportShareBundle(
+  vaultId: string,
+  filters: QuerySpec,
+  password: string
+): Promise<ShareReport> {
+  return invoke<ShareReport>('export_share_bundle', { vaultId, filters, password });
+}
+
+// 单篇日记的一次性分享文件；passcode 只在返回值中出现一次
+export async function shareEntry(vaultId: string, id: string): Promise<EntryShareReport> {
+  return invoke<EntryShareReport>('share_entry', { vaultId, id });
+}
+
+export async function importSharedEntry(
+  vaultId: string,
+  path: string,
+  passcode: string
+): Promise<EntryDetail> {
+  return invoke<EntryDetail>('import_shared_entry', { vaultId, path, passcode });
+}
+
+export async function importVaultImage(vaultId: string, path: string): Promise<string> {
+  return invoke<string>('store_image', { vaultId, path });
+}
+
+export async function importClipboardImage(
+  vaultId: string,
+  options: {
+    name?: string | null;
+    mime?: string | null;
+    data: Uint8Array;
+  }
+): Promise<string> {
   const { name = null, mime = null, data } = options;
   return invoke<string>('store_image_from_bytes', {
+    vaultId,
     name,
     mime,
     data: Array.from(data)
   });
 }
 
+// Android 的分区存储中文件夹只有 content:// 地址，用系统选择器选取
+const isAndroid = typeof navigator !== 'undefined' && /android/i.test(navigator.userAgent);
+
 export async function pickVaultDirectory(): Promise<string | null> {
+  if (isAndroid) {
+    return invoke<string | null>('pick_document_tree');
+  }
   const selection = await open({
     directory: true,
     multiple: false,
@@ -101,6 +868,8 @@ export async function pickImageFile(): Promise<string | null> {
   return typeof selection === 'string' ? selection : null;
 }
 
-export async function decryptImage(path: string): Promise<Uint8Array> {
-  return invoke<number[]>('decrypt_image', { path }).then(arr => new Uint8Array(arr));
+export async function decryptImage(vaultId: string, path: string): Promise<Uint8Array> {
+  return invoke<ArrayBuffer>('decrypt_image', { vaultId, path }).then(
+    (buffer) => new Uint8Array(buffer)
+  );
 }
@@ -2,6 +2,8 @@ import { derived, writable } from 'svelte/store';
 import type { EntryDetail, EntrySummary, TextEncryption } from '../types';
 
 export const unlocked = writable(false);
+// 当前窗口操作的日记库句柄，由 unlock_vault / create_vault 返回
+export const vaultId = writable<string | null>(null);
 export const entries = writable<EntrySummary[]>([]);
 export const activeEntryId = writable<string | null>(null);
 export const searchTerm = writable('');
@@ -5,19 +5,712 @@ export interface EntrySummary {
   title: string;
   created_at: string;
   updated_at: string;
+  // 日记记述的日期（YYYY-MM-DD），为 null 时取新建日期
+  entry_date?: string | null;
   folder?: string | null;
+  tags: string[];
+  // 心情评分 1-5
+  mood?: number | null;
+  location?: EntryLocation | null;
+  weather?: EntryWeather | null;
+  // 从健康数据导入的当天汇总
+  health?: HealthSummary | null;
+  // 旧数据在解锁时补算，正文读取失败的条目为 null
+  word_count?: number | null;
+  // 正文首行摘要，补算方式同字数
+  preview?: string | null;
+  // 本机情感打分，-1 到 1；未开启情感分析时为 null
+  sentiment?: number | null;
+  entry_type?: EntryType;
   encryption: TextEncryption;
 }
 
+// 引导式日记的类型，note 为普通日记
+export type EntryType = 'note' | 'gratitude' | 'dream' | 'review';
+
+export type ReviewPeriod = 'day' | 'week' | 'month' | 'year';
+
+// 结构化内容与正文一起加密保存；正文由它生成
+export type EntryPayload =
+  | { type: 'gratitude'; items: string[] }
+  | {
+      type: 'dream';
+      story?: string;
+      lucid?: boolean;
+      recurring?: boolean;
+      symbols?: string[];
+    }
+  | {
+      type: 'review';
+      period: ReviewPeriod;
+      went_well?: string[];
+      to_improve?: string[];
+      next_steps?: string[];
+    };
+
+// 写日记时所在的位置，随元数据加密保存
+export interface EntryLocation {
+  latitude: number;
+  longitude: number;
+  place?: string | null;
+}
+
+export type WeatherCondition =
+  | 'clear'
+  | 'cloudy'
+  | 'fog'
+  | 'drizzle'
+  | 'rain'
+  | 'snow'
+  | 'thunderstorm'
+  | 'unknown';
+
+export type WeatherProvider = 'open_meteo' | 'open_weather_map';
+
+// 新建日记时记录的天气，风速单位为公里每小时
+export interface EntryWeather {
+  condition: WeatherCondition;
+  temperature_c: number;
+  humidity?: number | null;
+  wind_kph?: number | null;
+  provider: WeatherProvider;
+}
+
 export interface EntryDetail extends EntrySummary {
   content: string;
+  payload?: EntryPayload | null;
+}
+
+export interface NewEntry {
+  title?: string;
+  content?: string;
+  folder?: string | null;
+  tags?: string[];
+  mood?: number | null;
+  location?: EntryLocation | null;
+  weather?: EntryWeather | null;
+  // 提供时忽略 content，正文由结构化内容生成
+  payload?: EntryPayload | null;
+  encryption?: TextEncryption;
+  // RFC 3339，缺省为当前时间
+  created_at?: string;
+  // YYYY-MM-DD，补记过去的日记时使用
+  entry_date?: string | null;
+}
+
+export type NewEntryResult =
+  | { status: 'created'; entry: EntrySummary }
+  | { status: 'failed'; error: VaultError };
+
+// 在内置键名之外额外识别的 frontmatter 键名，不区分大小写
+export interface FrontmatterMapping {
+  title?: string[];
+  date?: string[];
+  tags?: string[];
+  mood?: string[];
+  location?: string[];
+}
+
+// 只发送需要修改的字段；folder、mood、location、entry_date 传 null 表示清除，tags 整体替换
+export interface EntryPatch {
+  title?: string;
+  content?: string;
+  folder?: string | null;
+  tags?: string[];
+  mood?: number | null;
+  location?: EntryLocation | null;
+  weather?: EntryWeather | null;
+  entry_date?: string | null;
+  encryption?: TextEncryption;
+}
+
+// 组合查询条件；时间为 RFC 3339 字符串，省略的字段不参与筛选
+export interface QuerySpec {
+  text?: string;
+  folder?: string;
+  include_subfolders?: boolean;
+  tag?: string;
+  entry_type?: EntryType;
+  // 心情评分范围（含两端）；设置任一端时不包含未打分的条目
+  min_mood?: number;
+  max_mood?: number;
+  from?: string;
+  to?: string;
+  // entry 按日记日期筛选
+  date_field?: 'created' | 'updated' | 'entry';
+  has_attachments?: boolean;
+  sort?:
+    | 'updated_desc'
+    | 'updated_asc'
+    | 'created_desc'
+    | 'created_asc'
+    | 'date_desc'
+    | 'date_asc'
+    | 'title';
+  offset?: number;
+  limit?: number;
+}
+
+export interface QueryPage {
+  entries: EntrySummary[];
+  total: number;
+}
+
+// count 只统计直接位于该文件夹中的条目
+export interface FolderCount {
+  folder: string;
+  count: number;
+}
+
+export interface TagCount {
+  tag: string;
+  count: number;
+}
+
+export type StatsGroup = 'folder' | 'tag';
+
+export interface WordFrequency {
+  term: string;
+  count: number;
+}
+
+// group 为 null 表示没有文件夹或标签的日记；按标签统计时一篇日记计入它的每个标签
+export interface WritingStats {
+  group: string | null;
+  entries: number;
+  words: number;
+  average_words: number;
+}
+
+// 坐标为该地点各条目的平均值
+export interface PlaceCount {
+  place: string;
+  latitude: number;
+  longitude: number;
+  count: number;
+}
+
+export interface CalendarDay {
+  date: string;
+  count: number;
+}
+
+// 条目按新建时间排列；attachments 为这些日记正文引用的附件
+export interface DayView {
+  date: string;
+  entries: EntrySummary[];
+  words: number;
+  average_mood: number | null;
+  attachments: AttachmentInfo[];
+  summary: string | null;
+}
+
+// 按日记日期分组，年、月和条目都是新的在前；month 为 1-12
+export interface TimelineMonth {
+  month: number;
+  count: number;
+  entries: EntrySummary[];
+}
+
+export interface TimelineYear {
+  year: number;
+  count: number;
+  months: TimelineMonth[];
+}
+
+// date 为 YYYY-MM-DD，preview 为正文首行摘要
+export interface RecapLine {
+  id: string;
+  date: string;
+  title: string;
+  preview: string;
+}
+
+// photos 为当月保存的图片附件路径；highlights 取字数最多的几篇，按日期排列
+export interface MonthRecap {
+  year: number;
+  month: number;
+  entries: number;
+  words: number;
+  active_days: number;
+  average_mood: number | null;
+  top_tags: TagCount[];
+  photos: string[];
+  highlights: RecapLine[];
+  // 写成回顾日记时为该日记
+  entry: EntrySummary | null;
+}
+
+export type TrendBucket = 'day' | 'week' | 'month';
+
+// RFC 3339，按日记日期筛选
+export interface DateRange {
+  from?: string;
+  to?: string;
+}
+
+// start 为时间段第一天（YYYY-MM-DD），周从周一开始
+export interface MoodPoint {
+  start: string;
+  average: number;
+  entries: number;
+}
+
+export interface TagMood {
+  tag: string;
+  average: number;
+  entries: number;
+}
+
+export interface MoodTrends {
+  average: number | null;
+  points: MoodPoint[];
+  tags: TagMood[];
+  word_count_correlation: number | null;
+}
+
+// 只统计已打分的日记；mood_correlation 为情感分与心情评分的相关系数
+export interface SentimentTrends {
+  average: number | null;
+  points: MoodPoint[];
+  mood_correlation: number | null;
+}
+
+export interface UndoOutcome {
+  kind: 'delete' | 'move' | 'retag';
+  ids: string[];
+  can_undo: boolean;
+  can_redo: boolean;
+}
+
+export interface AttachmentInfo {
+  path: string;
+  size: number;
+  width?: number | null;
+  height?: number | null;
+  stored_at: string;
+}
+
+// date 为本机时区的保存日期（YYYY-MM-DD）；cover 为当天像素最多的图片路径
+export interface MediaDay {
+  date: string;
+  cover?: string | null;
+  attachments: AttachmentInfo[];
 }
 
 export interface UnlockResponse {
+  vault_id: string;
   entries: EntrySummary[];
   created: boolean;
   last_saved?: string | null;
   vault_root: string;
   text_encryption: TextEncryption;
   available_text_encryptions: TextEncryption[];
+  policy: VaultPolicy;
+  settings: VaultSettings;
+  // 本次解锁时升级了旧版本格式
+  migration?: MigrationReport | null;
+  // 日记目录不可写，已自动以只读方式打开
+  read_only?: boolean;
+}
+
+// backup 为升级前的元数据备份目录，预检时为 null
+export interface MigrationReport {
+  from_version: number;
+  to_version: number;
+  steps: string[];
+  backup: string | null;
+}
+
+export interface VaultProfile {
+  name?: string | null;
+  description?: string | null;
+  icon?: string | null;
+}
+
+export interface RecentVault {
+  directory: string;
+  last_opened_at: string;
+  text_encryption?: TextEncryption | null;
+  available: boolean;
+  profile?: VaultProfile | null;
+}
+
+// 用户的口令槽位（含主口令），标签只在解锁后可见
+export interface UserSlot {
+  id: string;
+  label: string;
+  admin: boolean;
+  created_at: string;
+}
+
+// 双击日记文件或打开 cipherdiary:// 链接时请求打开的日记库
+export interface OpenRequest {
+  directory: string;
+}
+
+export interface KdfParams {
+  algorithm: string;
+  memory_kib: number;
+  iterations: number;
+  parallelism: number;
+}
+
+export interface VaultProbe {
+  exists: boolean;
+  compatible: boolean;
+  version?: number | null;
+  legacy_format: boolean;
+  updated_at?: string | null;
+  kdf?: KdfParams | null;
+  profile?: VaultProfile | null;
+  error?: string | null;
+}
+
+export interface EntryBenchmark {
+  encryption: TextEncryption;
+  entry_count: number;
+  entry_bytes: number;
+  encrypt_ms_per_entry: number;
+  decrypt_ms_per_entry: number;
+  encrypt_mb_per_sec: number;
+  decrypt_mb_per_sec: number;
+}
+
+export interface AttachmentBenchmark {
+  bytes: number;
+  encrypt_mb_per_sec: number;
+  decrypt_mb_per_sec: number;
+}
+
+export interface BenchmarkReport {
+  platform: string;
+  threads: number;
+  kdf: KdfParams;
+  kdf_ms: number;
+  entries: EntryBenchmark[];
+  attachment: AttachmentBenchmark;
+  metadata_entries: number;
+  metadata_save_ms: number;
+  journal_append_ms: number;
+}
+
+export type SelfTestKind =
+  | 'random'
+  | 'text_encryption'
+  | 'attachment'
+  | 'wrong_key_rejected'
+  | 'vault_roundtrip'
+  | 'vault_directory';
+
+export interface SelfTestCheck {
+  kind: SelfTestKind;
+  encryption: TextEncryption | null;
+  passed: boolean;
+  detail: string | null;
+  duration_ms: number;
+}
+
+// 自检结果，可直接附在问题反馈中
+export interface SelfTestReport {
+  platform: string;
+  passed: boolean;
+  checks: SelfTestCheck[];
+}
+
+export interface VaultPolicy {
+  disable_plaintext_export: boolean;
+}
+
+// 除 auto_lock_minutes 外的键由前端自行约定，后端原样加密保存
+export interface WritingGoals {
+  words_per_day?: number | null;
+  entries_per_week?: number | null;
+}
+
+// open_weather_map 需要 api_key；新建日记时带上位置才会查询天气
+export interface WeatherSettings {
+  enabled: boolean;
+  provider: WeatherProvider;
+  api_key?: string | null;
+}
+
+export interface VaultSettings {
+  auto_lock_minutes?: number | null;
+  goals?: WritingGoals;
+  // 导出时使用的时区（相对 UTC 的分钟数），为空时保留写入时的时区
+  timezone_offset_minutes?: number | null;
+  weather?: WeatherSettings;
+  // 保存时在本机用内置词典给正文打情感分，不联网
+  sentiment_analysis?: boolean;
+  [key: string]: unknown;
+}
+
+// 按新建时间和本机时区统计，周从周一开始
+export interface GoalProgress {
+  goals: WritingGoals;
+  words_today: number;
+  entries_this_week: number;
+  day_streak: number;
+  week_streak: number;
+}
+
+export interface PromptPack {
+  id: string;
+  name: string;
+  prompts: string[];
+}
+
+// 内置题库按后端语言显示，不能修改或删除
+export interface PromptPackInfo extends PromptPack {
+  builtin: boolean;
+  enabled: boolean;
+}
+
+// date 为本机时区的日期（YYYY-MM-DD），entry_id 不为空表示今天已作答
+export interface TodaysPrompt {
+  date: string;
+  pack_id: string;
+  pack_name: string;
+  index: number;
+  text: string;
+  entry_id?: string | null;
+}
+
+// unit 为空表示只记录是否完成，此时 target 为 1
+export interface Habit {
+  id: string;
+  name: string;
+  unit?: string | null;
+  target: number;
+}
+
+// date 为 YYYY-MM-DD，只包含有打卡记录的日子
+export interface HabitDay {
+  date: string;
+  value: number;
+  done: boolean;
+}
+
+// 连续天数按全部记录计算，不受查询范围影响
+export interface HabitHistory {
+  habit: Habit;
+  days: HabitDay[];
+  current_streak: number;
+  longest_streak: number;
+}
+
+export interface HistoryReport {
+  valid: boolean;
+  length: number;
+  head?: string | null;
+  broken_at?: number | null;
+  issues: string[];
+}
+
+export interface CloneReport {
+  vault_root: string;
+  files: number;
+  bytes: number;
+  verified_entries: number;
+}
+
+export interface DeleteReport {
+  backup?: CloneReport | null;
+  removed_files: number;
+  leftover: string[];
+  directory_removed: boolean;
+}
+
+// 分享包只含文字，附件不会打包
+export interface ShareReport {
+  path: string;
+  entries: number;
+  // 读不出而未打包的日记
+  skipped: EntryError[];
+}
+
+// 编辑器中尚未保存的修改，崩溃或断电后解锁时返回
+export interface Draft {
+  entry_id: string;
+  title: string;
+  content: string;
+  saved_at: string;
+}
+
+// 单篇日记的一次性分享文件，附件一并打包
+export interface EntryShareReport {
+  path: string;
+  passcode: string;
+  attachments: number;
+}
+
+export interface ImportReport {
+  entries: number;
+  attachments: number;
+  renamed: number;
+  folders: string[];
+}
+
+export type HealthSource = 'apple_health' | 'google_fit';
+
+// activity 为导出文件中的运动类型（小写下划线形式）
+export interface Workout {
+  activity: string;
+  minutes: number;
+}
+
+export interface HealthSummary {
+  steps?: number | null;
+  sleep_minutes?: number | null;
+  workouts: Workout[];
+}
+
+// skipped 为当天没有日记、未能附加的天数
+export interface HealthImportReport {
+  days: number;
+  attached: number;
+  skipped: number;
+}
+
+export interface MoveReport {
+  vault_root: string;
+  leftover: string[];
+}
+
+export interface VaultInfo {
+  vault_root: string;
+  created_at?: string | null;
+  entry_count: number;
+  attachment_count: number;
+  attachment_bytes: number;
+  last_saved?: string | null;
+  text_encryption: TextEncryption;
+  kdf: KdfParams;
+  auto_lock_minutes?: number | null;
+  auto_lock_in_secs?: number | null;
+}
+
+export interface IntegrityReport {
+  valid: boolean;
+  history: HistoryReport;
+  world_readable: string[];
+  unreadable_entries: EntryError[];
+}
+
+// 无法解密或解析的日记
+export interface EntryError {
+  id: string;
+  title: string;
+  error: string;
+}
+
+export interface ExportReport {
+  path: string;
+  skipped: EntryError[];
+}
+
+export type VaultEvent = { vault_id: string } & (
+  | { kind: 'unlocked'; vault_root: string }
+  | { kind: 'moved'; vault_root: string }
+  | { kind: 'profile_changed'; profile: VaultProfile }
+  | { kind: 'locked' }
+  | { kind: 'auto_locked' }
+  | { kind: 'passphrase_changed' }
+);
+
+export interface EntriesChanged {
+  vault_id: string;
+  change: 'created' | 'updated' | 'deleted';
+  ids: string[];
+}
+
+// 后端提示文字的语言，默认 zh-CN
+export type BackendLocale = 'zh-CN' | 'en';
+
+export type AppTheme = 'system' | 'light' | 'dark';
+
+export type LogLevel = 'off' | 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+// 本机时区的每日提醒时刻
+export interface ReminderTime {
+  hour: number;
+  minute: number;
+}
+
+export interface ReminderSchedule {
+  enabled: boolean;
+  times: ReminderTime[];
+  // 已打开的日记库今天已有日记时不提醒
+  skip_if_written: boolean;
+}
+
+// 那年今日：每天在 time 检查已打开的日记库，有 years 年前同一天的日记时通知。
+// show_titles 为 false 时只显示篇数，避免标题出现在锁屏上
+export interface MemorySchedule {
+  enabled: boolean;
+  time: ReminderTime;
+  years: number[];
+  show_titles: boolean;
+}
+
+// 解锁前即可读取的应用偏好，保存在应用数据目录而不是 localStorage
+export interface AppSettings {
+  theme: AppTheme;
+  locale: BackendLocale | null;
+  default_auto_lock_minutes: number | null;
+  remember_recent_vaults: boolean;
+  sidebar_collapsed: boolean;
+  log_level: LogLevel;
+  reminders: ReminderSchedule;
+  memories: MemorySchedule;
+}
+
+export type VaultErrorCode =
+  | 'locked'
+  | 'wrong_passphrase'
+  | 'vault_not_found'
+  | 'entry_not_found'
+  | 'io'
+  | 'corrupted'
+  | 'conflict'
+  | 'quota_exceeded'
+  | 'invalid_input'
+  | 'other';
+
+// 后端命令失败时返回的错误
+export interface VaultError {
+  code: VaultErrorCode;
+  message: string;
+}
+
+export interface UnlockProgress {
+  vault_id: string;
+  stage: 'started' | 'deriving' | 'decrypting' | 'loading_entries';
+}
+
+export interface LastUnlockInfo {
+  failed_attempts: number;
+  last_failed_at?: string | null;
+  previous_unlock_at?: string | null;
+}
+
+export interface EmergencyStatus {
+  configured: boolean;
+  delay_days?: number | null;
+  destination?: string | null;
+  last_check_in?: string | null;
+  release_due_at?: string | null;
+  released_at?: string | null;
+  released_path?: string | null;
+}
+
+export interface EmergencyKeypair {
+  public_key: string;
+  secret_key: string;
+}
+
+export interface QuickUnlockStatus {
+  enabled: boolean;
+  remaining_attempts?: number | null;
 }